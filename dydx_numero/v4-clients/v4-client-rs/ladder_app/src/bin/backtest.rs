@@ -0,0 +1,482 @@
+// ladder_app/src/bin/backtest.rs
+//
+// Headless backtest of a Rhai bot script over recorded data. No GUI, no
+// network: the ticker's book is replayed with ReplayEngine and the script
+// runs once per TF step against the paper-trading TradingState.
+//
+//   cargo run -p ladder_app --bin backtest -- --ticker ETH-USD --script bot.rhai --tf 60
+//
+// Options:
+//   --data-dir <path>     where orderbook_/trades_ files live (default data/)
+//   --margin <usdc>       starting sim margin (default 100)
+//   --leverage <x>        sim leverage (default 5)
+//   --trades-out <path>   write every closed round trip as CSV (with --sweep,
+//                         the best run's)
+//   --sweep <name>=<start>:<end>:<step>
+//                         run once per value, set as scope variable <name>;
+//                         repeat the flag to search the grid of all of them
+//
+// A sweep runs the combinations on all cores, each one a fresh replay and a
+// fresh sim, and prints them as a table sorted by net PnL, best first.
+//
+// The script sees the same scope full_gui_x15 feeds it (see ladder_app::bot)
+// with mode "replay", and a signal is traded the way full_gui_x15's auto
+// trade sends it: a market order of bot_size, once per change of signal.
+// A position still open at the end is closed at the last mid.
+
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use rhai::{Engine, Scope, AST};
+
+use ladder_app::bot::{
+    feed_scope, read_bot_from_scope, BotInputs, BotOutput, DEFAULT_LIQUIDITY_BPS,
+    DEFAULT_WALL_SIGMA,
+};
+use ladder_app::candle_agg::resample;
+use ladder_app::csv_io::{arg_value, arg_values, data_dir};
+use ladder_app::replay::{ReplayData, ReplayEngine};
+use ladder_app::sim::{Depth, ExitReason, PositionSide, TradingState};
+use ladder_app::snapshot::simple_mid;
+use ladder_app::time_fmt::{format_ts, TimeDisplayMode};
+
+const DEFAULT_TICKER: &str = "ETH-USD";
+const DEFAULT_TF_SECS: u64 = 60;
+// candles the script's rsi_14 looks back over, full_gui_x15's chart default
+const HISTORY_CANDLES: usize = 200;
+
+// values a script reads from scope instead of hardcoding, one run's worth
+type Params = Vec<(String, f64)>;
+
+struct Summary {
+    first_ts: u64,
+    last_ts: u64,
+    steps: usize,
+    sim: TradingState,
+}
+
+fn compile_script(text: &str) -> Result<(Engine, AST), String> {
+    let mut engine = Engine::new();
+    engine.set_max_expr_depths(64, 64);
+    let ast = engine.compile(text).map_err(|e| e.to_string())?;
+    Ok((engine, ast))
+}
+
+/// One `--sweep` flag: every value from start to end (inclusive) by step.
+#[derive(Clone, Debug, PartialEq)]
+struct Sweep {
+    name: String,
+    values: Vec<f64>,
+}
+
+impl Sweep {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let bad = || format!("--sweep: expected name=start:end:step, got {spec:?}");
+        let (name, range) = spec.split_once('=').ok_or_else(bad)?;
+        let nums: Vec<f64> = range
+            .split(':')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| bad())?;
+        let [start, end, step] = nums[..] else {
+            return Err(bad());
+        };
+        if name.trim().is_empty() || step <= 0.0 || end < start {
+            return Err(bad());
+        }
+        // index-based so the float step doesn't drift, with slack for an end
+        // that is a whole number of steps away
+        let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+        Ok(Self {
+            name: name.trim().to_string(),
+            values: (0..count).map(|i| start + i as f64 * step).collect(),
+        })
+    }
+}
+
+/// Every combination of the sweeps' values, the first sweep varying slowest.
+fn grid(sweeps: &[Sweep]) -> Vec<Params> {
+    let mut out: Vec<Params> = vec![Vec::new()];
+    for sw in sweeps {
+        out = out
+            .into_iter()
+            .flat_map(|p| {
+                sw.values.iter().map(move |v| {
+                    let mut p = p.clone();
+                    p.push((sw.name.clone(), *v));
+                    p
+                })
+            })
+            .collect();
+    }
+    out
+}
+
+/// Replay `data` in `tf_secs` steps, running the script at the end of each.
+fn run_backtest(
+    data: &ReplayData,
+    engine: &Engine,
+    ast: &AST,
+    tf_secs: u64,
+    mut sim: TradingState,
+    params: &[(String, f64)],
+) -> Result<Summary, String> {
+    let (Some(first), Some(last)) = (data.book_events.first(), data.book_events.last()) else {
+        return Err(format!("no book rows for {}", data.ticker));
+    };
+    let (first_ts, last_ts) = (first.ts, last.ts);
+    let tf_secs = tf_secs.max(1);
+
+    let mut replay = ReplayEngine::new(data);
+    let mut scope = Scope::new();
+    let mut out = BotOutput::default();
+    let mut last_executed = String::new();
+    let mut last_mark = None;
+    let mut steps = 0;
+
+    // the end of every TF bucket the data touches, the last one cut at last_ts
+    let mut ts = first_ts;
+    loop {
+        replay.seek(ts);
+        let depth = Depth {
+            bids: replay.bids(),
+            asks: replay.asks(),
+            scale: data.price_scale,
+        };
+        if let Some(mark) = simple_mid(depth.bids, depth.asks, depth.scale) {
+            steps += 1;
+            last_mark = Some(mark);
+            sim.accrue_funding_at(mark, ts);
+            sim.check_tp_sl(&depth, mark, ts);
+            sim.check_liquidation(mark, ts);
+
+            let candles = resample(replay.candles(), tf_secs);
+            let start = candles.len().saturating_sub(HISTORY_CANDLES);
+            let closes: Vec<f64> = candles[start..].iter().map(|c| c.close).collect();
+            let inputs = BotInputs {
+                ticker: &data.ticker,
+                mode: "replay",
+                bids: depth.bids,
+                asks: depth.asks,
+                price_scale: depth.scale,
+                closes: &closes,
+                tf_secs,
+                history_candles: HISTORY_CANDLES,
+                liquidity_bps: DEFAULT_LIQUIDITY_BPS,
+                wall_sigma: DEFAULT_WALL_SIGMA,
+            };
+            feed_scope(&mut scope, &inputs, &out);
+            for (name, v) in params {
+                scope.set_value(name.clone(), *v);
+            }
+            engine
+                .run_ast_with_scope(&mut scope, ast)
+                .map_err(|e| format!("script failed at {ts}: {e}"))?;
+            out = read_bot_from_scope(&scope);
+
+            let side = match out.signal.as_str() {
+                "buy" => Some(PositionSide::Long),
+                "sell" => Some(PositionSide::Short),
+                _ => None,
+            };
+            if let Some(side) = side {
+                if out.signal != last_executed && out.size > 0.0 {
+                    sim.market_order(&depth, side, out.size, mark, ts);
+                    last_executed = out.signal.clone();
+                }
+            }
+            sim.record_equity(ts, mark);
+        }
+
+        if ts >= last_ts {
+            break;
+        }
+        ts = ((ts / tf_secs + 1) * tf_secs).min(last_ts);
+    }
+
+    if let Some(mark) = last_mark {
+        let depth = Depth {
+            bids: replay.bids(),
+            asks: replay.asks(),
+            scale: data.price_scale,
+        };
+        sim.close_at(&depth, mark, last_ts, ExitReason::Manual);
+    }
+
+    Ok(Summary {
+        first_ts,
+        last_ts,
+        steps,
+        sim,
+    })
+}
+
+/// A backtest per combination, spread over the available cores. Each thread
+/// compiles its own copy of the script (a rhai AST isn't Send); every run
+/// starts from a fresh replay and a clone of `sim`. Results in `grid` order.
+fn run_sweep(
+    data: &ReplayData,
+    script: &str,
+    tf_secs: u64,
+    sim: &TradingState,
+    grid: &[Params],
+) -> Result<Vec<Summary>, String> {
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(grid.len())
+        .max(1);
+
+    let per_thread = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| -> Result<Vec<(usize, Summary)>, String> {
+                    let (engine, ast) = compile_script(script)?;
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(params) = grid.get(i) else {
+                            return Ok(done);
+                        };
+                        let summary =
+                            run_backtest(data, &engine, &ast, tf_secs, sim.clone(), params)?;
+                        done.push((i, summary));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("sweep thread panicked"))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let mut all: Vec<(usize, Summary)> = per_thread.into_iter().flatten().collect();
+    all.sort_by_key(|(i, _)| *i);
+    Ok(all.into_iter().map(|(_, s)| s).collect())
+}
+
+fn fmt_params(params: &[(String, f64)]) -> String {
+    params
+        .iter()
+        .map(|(name, v)| format!("{name}={v}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs sorted by net PnL, best first, as (params, summary) rows.
+fn print_sweep_table(rows: &[(&Params, &Summary)]) {
+    println!(
+        "{:<32} {:>7} {:>12} {:>9} {:>12}",
+        "params", "trades", "net pnl", "win rate", "drawdown"
+    );
+    for (params, s) in rows {
+        let (net, win_rate, dd) = s.sim.log_totals();
+        println!(
+            "{:<32} {:>7} {:>12.4} {:>8.1}% {:>12.4}",
+            fmt_params(params),
+            s.sim.trade_log.len(),
+            net,
+            win_rate * 100.0,
+            dd
+        );
+    }
+}
+
+fn trades_csv(sim: &TradingState) -> String {
+    let mut out =
+        String::from("open_ts,close_ts,side,size,entry_price,exit_price,fees,pnl,reason\n");
+    for t in &sim.trade_log {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            t.open_ts,
+            t.close_ts,
+            t.side.label(),
+            t.size,
+            t.entry_price,
+            t.exit_price,
+            t.fees,
+            t.pnl,
+            t.reason.label()
+        );
+    }
+    out
+}
+
+fn print_summary(ticker: &str, tf_secs: u64, s: &Summary) {
+    let (net, win_rate, realized_dd) = s.sim.log_totals();
+    println!(
+        "{ticker} tf {tf_secs}s: {} steps, {} .. {}",
+        s.steps,
+        format_ts(TimeDisplayMode::Local, s.first_ts),
+        format_ts(TimeDisplayMode::Local, s.last_ts)
+    );
+    println!("trades      {}", s.sim.trade_log.len());
+    println!(
+        "net pnl     {net:.4} (fees {:.4}, funding {:.4})",
+        s.sim.fees_paid, s.sim.funding_paid
+    );
+    println!("win rate    {:.1}%", win_rate * 100.0);
+    println!(
+        "drawdown    {realized_dd:.4} realized, {:.4} equity",
+        s.sim.max_drawdown()
+    );
+    if s.sim.liquidated_flag {
+        println!("liquidated at {:?}", s.sim.last_liq_time);
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], flag: &str, default: T) -> Result<T, String> {
+    match arg_value(args.iter().cloned(), flag) {
+        Some(v) => v.parse().map_err(|_| format!("{flag}: bad value {v:?}")),
+        None => Ok(default),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let ticker = arg_value(args.iter().cloned(), "--ticker").unwrap_or(DEFAULT_TICKER.to_string());
+    let script =
+        arg_value(args.iter().cloned(), "--script").ok_or("--script <file.rhai> is required")?;
+    let tf_secs = parse_arg(args, "--tf", DEFAULT_TF_SECS)?;
+
+    let mut sim = TradingState::new();
+    sim.margin = parse_arg(args, "--margin", sim.margin)?;
+    sim.leverage = parse_arg(args, "--leverage", sim.leverage)?;
+
+    let sweeps = arg_values(args.iter().cloned(), "--sweep")
+        .iter()
+        .map(|spec| Sweep::parse(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let text = fs::read_to_string(&script).map_err(|e| format!("{script}: {e}"))?;
+    let (engine, ast) = compile_script(&text).map_err(|e| format!("{script}: {e}"))?;
+    let data = ReplayData::load(data_dir(), &ticker)
+        .ok_or_else(|| format!("no data for {ticker} in {}", data_dir().display()))?;
+    if data.load_stats.skipped > 0 {
+        eprintln!(
+            "skipped {} malformed lines: {}",
+            data.load_stats.skipped,
+            data.load_stats.samples.join("; ")
+        );
+    }
+
+    let summary = if sweeps.is_empty() {
+        run_backtest(&data, &engine, &ast, tf_secs, sim, &[])?
+    } else {
+        let grid = grid(&sweeps);
+        println!("sweeping {} combinations", grid.len());
+        let mut results: Vec<(Params, Summary)> = grid
+            .iter()
+            .cloned()
+            .zip(run_sweep(&data, &text, tf_secs, &sim, &grid)?)
+            .collect();
+        results.sort_by(|a, b| b.1.sim.log_totals().0.total_cmp(&a.1.sim.log_totals().0));
+        let rows: Vec<(&Params, &Summary)> = results.iter().map(|(p, s)| (p, s)).collect();
+        print_sweep_table(&rows);
+        let (best_params, best) = results.swap_remove(0);
+        println!("\nbest: {}", fmt_params(&best_params));
+        best
+    };
+    print_summary(&ticker, tf_secs, &summary);
+
+    if let Some(path) = arg_value(args.iter().cloned(), "--trades-out") {
+        fs::write(&path, trades_csv(&summary.sim)).map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {} trades to {path}", summary.sim.trade_log.len());
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("backtest: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn buy_then_sell_script_logs_one_round_trip() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay");
+        let data = ReplayData::load(&dir, "ETH-USD").unwrap();
+        let engine = Engine::new();
+        let ast = engine
+            .compile(
+                r#"
+                bot_size = 0.5;
+                bot_signal = if best_ask > 100.75 { "buy" } else { "sell" };
+                "#,
+            )
+            .unwrap();
+
+        let s = run_backtest(&data, &engine, &ast, 5, TradingState::new(), &[]).unwrap();
+
+        // steps at 0, 5, 10, 12: buys at 101 until the 100.5 ask shows up,
+        // then sells the half unit back at the 100 bid
+        assert_eq!(s.steps, 4);
+        assert_eq!(s.sim.trade_log.len(), 1);
+        let t = &s.sim.trade_log[0];
+        assert_eq!((t.entry_price, t.exit_price), (101.0, 100.0));
+        assert!(!s.sim.is_open());
+        assert!(trades_csv(&s.sim)
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",manual"));
+    }
+
+    #[test]
+    fn sweeps_expand_to_a_grid() {
+        let a = Sweep::parse("imbalance_threshold=1:2:0.25").unwrap();
+        assert_eq!(a.values, [1.0, 1.25, 1.5, 1.75, 2.0]);
+        assert!(Sweep::parse("x=2:1:0.5").is_err());
+        assert!(Sweep::parse("x=1:2").is_err());
+
+        let b = Sweep::parse("size=0.1:0.2:0.1").unwrap();
+        let g = grid(&[a, b]);
+        assert_eq!(g.len(), 10);
+        assert_eq!(
+            g[1],
+            [
+                ("imbalance_threshold".to_string(), 1.0),
+                ("size".to_string(), 0.2)
+            ]
+        );
+    }
+
+    #[test]
+    fn sweep_runs_each_value_from_a_fresh_start() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay");
+        let data = ReplayData::load(&dir, "ETH-USD").unwrap();
+        let script = r#"
+            bot_size = 0.5;
+            bot_signal = if best_ask > threshold { "buy" } else { "sell" };
+        "#;
+        let grid = grid(&[Sweep::parse("threshold=100:102:1").unwrap()]);
+
+        let runs = run_sweep(&data, script, 5, &TradingState::new(), &grid).unwrap();
+
+        // only 100 is under the opening 101 ask, so only that run goes long;
+        // each run opens at the first step and is closed at the end
+        let sides: Vec<&str> = runs
+            .iter()
+            .map(|s| s.sim.trade_log[0].side.label())
+            .collect();
+        assert_eq!(sides, ["LONG", "SHORT", "SHORT"]);
+        assert!(runs
+            .iter()
+            .all(|s| s.steps == 4 && s.sim.trade_log[0].open_ts == 1_700_000_000));
+    }
+}
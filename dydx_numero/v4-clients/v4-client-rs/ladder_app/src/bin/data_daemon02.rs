@@ -5,12 +5,14 @@
 // - Subscribes to L2 orders feed for multiple tickers
 // - Maintains an in-memory orderbook per ticker
 // - Derives mid-price candles (30s/1m/3m/5m)
-// - Appends everything to CSVs under ./data:
+// - Appends everything to CSVs under ./data (override with --data-dir <path>):
 //     data/orderbook_{TICKER}.csv
 //         ts,ticker,kind,side,price,size
 //         kind ∈ {book_init,delta}
 //     data/candles_{TICKER}_{TF}.csv
 //         open_ts,ticker,tf_secs,open,high,low,close,volume
+// - `--data-format jsonl` writes the book as orderbook_{TICKER}.jsonl instead,
+//   one JSON object per row with the same field names (candles stay CSV)
 //
 // This is meant to run 24/7 (via launchd), while your GUI only *reads* the data.
 
@@ -19,12 +21,13 @@ mod candle_agg;
 use candle_agg::{Candle, CandleAgg};
 
 use std::collections::BTreeMap;
-use std::fs::{create_dir_all, OpenOptions};
-use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use tokio::time::sleep;
+use tracing::{error, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
 
 // dYdX client config + indexer
 use dydx_client::config::ClientConfig;
@@ -32,6 +35,8 @@ use dydx_client::indexer::{
     Feed as DxFeed, Feeds, IndexerClient, IndexerConfig, OrderbookResponsePriceLevel,
     OrdersMessage, Ticker,
 };
+use ladder_app::csv_io::{data_format, BookRow, BookSide};
+use ladder_app::csv_sink;
 
 // ---------- basic helpers ----------
 
@@ -53,43 +58,63 @@ fn key_to_price(key: PriceKey) -> f64 {
     key as f64 / 10_000.0
 }
 
-// ---------- CSV writers (compatible with full_gui / replay) ----------
+// ---------- data dir ----------
 
-fn ensure_data_dir() {
-    let _ = create_dir_all(Path::new("data"));
-}
+const DEFAULT_DATA_DIR: &str = "data";
 
-fn append_book_csv(ticker: &str, kind: &str, side: &str, price: f64, size: f64) {
-    ensure_data_dir();
-    let ts = now_unix();
-    let path = Path::new("data").join(format!("orderbook_{ticker}.csv"));
-
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
-        let _ = writeln!(f, "{ts},{ticker},{kind},{side},{price},{size}");
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// `--data-dir <path>` or `--data-dir=<path>`; `data` when absent
+fn data_dir_from_args(args: impl IntoIterator<Item = String>) -> PathBuf {
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == "--data-dir" {
+            if let Some(v) = args.next() {
+                return PathBuf::from(v);
+            }
+        } else if let Some(v) = a.strip_prefix("--data-dir=") {
+            return PathBuf::from(v);
+        }
     }
+    PathBuf::from(DEFAULT_DATA_DIR)
+}
+
+/// Base dir for every CSV read and write, taken from the command line once.
+fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| data_dir_from_args(std::env::args().skip(1)))
+}
+
+// ---------- CSV writers (compatible with full_gui / replay) ----------
+//
+// buffered in csv_sink, on disk within csv_sink::FLUSH_INTERVAL
+
+fn append_book_csv(ticker: &str, kind: &str, side: BookSide, price: f64, size: f64) {
+    let format = data_format();
+    let row = BookRow {
+        ts: now_unix(),
+        ticker,
+        kind,
+        side,
+        price,
+        size,
+        seq: None,
+    };
+    let path = data_dir().join(format!("orderbook_{ticker}.{}", format.ext()));
+    let _ = csv_sink::append_row(&path, &row.encode(format));
 }
 
 fn append_candle_csv(ticker: &str, tf_secs: u64, c: &Candle) {
-    ensure_data_dir();
-    let path =
-        Path::new("data").join(format!("candles_{}_{}s.csv", ticker.replace('-', "_"), tf_secs));
+    let path = data_dir().join(format!(
+        "candles_{}_{}s.csv",
+        ticker.replace('-', "_"),
+        tf_secs
+    ));
     let open_ts = c.t;
-
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
-        let _ = writeln!(
-            f,
-            "{open_ts},{ticker},{tf_secs},{},{},{},{},{}",
-            c.open, c.high, c.low, c.close, c.volume
-        );
-    }
+    let row = format!(
+        "{open_ts},{ticker},{tf_secs},{},{},{},{},{}",
+        c.open, c.high, c.low, c.close, c.volume
+    );
+    let _ = csv_sink::append_row(&path, &row);
 }
 
 // ---------- orderbook state ----------
@@ -111,65 +136,35 @@ impl DaemonBook {
         self.asks.clear();
 
         for lvl in bids {
-            let p = lvl
-                .price
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let s = lvl
-                .size
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let p = lvl.price.0.to_string().parse::<f64>().unwrap_or(0.0);
+            let s = lvl.size.0.to_string().parse::<f64>().unwrap_or(0.0);
             let key = price_to_key(p);
             if s != 0.0 {
                 self.bids.insert(key, s);
             }
-            append_book_csv(ticker, "book_init", "bid", p, s);
+            append_book_csv(ticker, "book_init", BookSide::Bid, p, s);
         }
 
         for lvl in asks {
-            let p = lvl
-                .price
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let s = lvl
-                .size
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let p = lvl.price.0.to_string().parse::<f64>().unwrap_or(0.0);
+            let s = lvl.size.0.to_string().parse::<f64>().unwrap_or(0.0);
             let key = price_to_key(p);
             if s != 0.0 {
                 self.asks.insert(key, s);
             }
-            append_book_csv(ticker, "book_init", "ask", p, s);
+            append_book_csv(ticker, "book_init", BookSide::Ask, p, s);
         }
     }
 
     fn apply_levels(
         map: &mut BTreeMap<PriceKey, f64>,
         levels: Vec<OrderbookResponsePriceLevel>,
-        side: &str,
+        side: BookSide,
         ticker: &str,
     ) {
         for lvl in levels {
-            let p = lvl
-                .price
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let s = lvl
-                .size
-                .0
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
+            let p = lvl.price.0.to_string().parse::<f64>().unwrap_or(0.0);
+            let s = lvl.size.0.to_string().parse::<f64>().unwrap_or(0.0);
             let key = price_to_key(p);
 
             if s == 0.0 {
@@ -189,10 +184,10 @@ impl DaemonBook {
         ticker: &str,
     ) {
         if let Some(b) = bids {
-            Self::apply_levels(&mut self.bids, b, "bid", ticker);
+            Self::apply_levels(&mut self.bids, b, BookSide::Bid, ticker);
         }
         if let Some(a) = asks {
-            Self::apply_levels(&mut self.asks, a, "ask", ticker);
+            Self::apply_levels(&mut self.asks, a, BookSide::Ask, ticker);
         }
     }
 
@@ -216,13 +211,40 @@ fn init_crypto_provider() {
     let _ = rustls::crypto::ring::default_provider().install_default();
 }
 
+// ---------- logging ----------
+
+// fmt subscriber on stderr. RUST_LOG filters as usual (default `info`, e.g.
+// `RUST_LOG=data_daemon02=debug,dydx=debug`); `json` emits one object per line,
+// which is what launchd log shipping wants.
+fn init_tracing(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        fmt.json().init();
+    } else {
+        fmt.init();
+    }
+}
+
+// `--log-json` or LOG_FORMAT=json
+fn log_json_from_args(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--log-json")
+        || std::env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
 // ---------- per-ticker daemon ----------
 
+// candles kept in memory per TF; everything is already on disk, and only the
+// last candle is ever read back for the CSV flush
+const MAX_CANDLES_PER_TF: usize = 5000;
+
 async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
     let mut indexer = IndexerClient::new(indexer_cfg);
 
     loop {
-        eprintln!("[daemon] subscribing orders for {ticker_str}");
+        info!("subscribing orders feed");
 
         let mut feeds: Feeds<'_> = indexer.feed();
         let ticker = Ticker(ticker_str.clone());
@@ -230,9 +252,7 @@ async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
         let mut feed: DxFeed<OrdersMessage> = match feeds.orders(&ticker, false).await {
             Ok(f) => f,
             Err(e) => {
-                eprintln!(
-                    "[daemon] orders feed error for {ticker_str}: {e}; retrying in 5s"
-                );
+                warn!(error = %e, "orders feed subscribe failed; retrying in 5s");
                 sleep(Duration::from_secs(5)).await;
                 continue;
             }
@@ -240,10 +260,10 @@ async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
 
         let mut book = DaemonBook::default();
 
-        let mut tf_30s = CandleAgg::new(30);
-        let mut tf_1m = CandleAgg::new(60);
-        let mut tf_3m = CandleAgg::new(180);
-        let mut tf_5m = CandleAgg::new(300);
+        let mut tf_30s = CandleAgg::with_capacity(30, MAX_CANDLES_PER_TF);
+        let mut tf_1m = CandleAgg::with_capacity(60, MAX_CANDLES_PER_TF);
+        let mut tf_3m = CandleAgg::with_capacity(180, MAX_CANDLES_PER_TF);
+        let mut tf_5m = CandleAgg::with_capacity(300, MAX_CANDLES_PER_TF);
 
         // last written candle open_ts per TF to avoid duplicates
         let mut last_30s_written: u64 = 0;
@@ -254,18 +274,10 @@ async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
         while let Some(msg) = feed.recv().await {
             match msg {
                 OrdersMessage::Initial(init) => {
-                    book.apply_initial(
-                        init.contents.bids,
-                        init.contents.asks,
-                        &ticker_str,
-                    );
+                    book.apply_initial(init.contents.bids, init.contents.asks, &ticker_str);
                 }
                 OrdersMessage::Update(upd) => {
-                    book.apply_update(
-                        upd.contents.bids,
-                        upd.contents.asks,
-                        &ticker_str,
-                    );
+                    book.apply_update(upd.contents.bids, upd.contents.asks, &ticker_str);
                 }
             }
 
@@ -308,9 +320,7 @@ async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
             }
         }
 
-        eprintln!(
-            "[daemon] feed ended for {ticker_str}; reconnecting in 5s..."
-        );
+        warn!("feed ended; reconnecting in 5s");
         sleep(Duration::from_secs(5)).await;
     }
 }
@@ -320,11 +330,13 @@ async fn run_market_daemon(indexer_cfg: IndexerConfig, ticker_str: String) {
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     init_crypto_provider();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    init_tracing(log_json_from_args(&args));
 
     let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[daemon] failed to load client/tests/testnet.toml: {e}");
+            error!(error = %e, "failed to load client/tests/testnet.toml");
             return;
         }
     };
@@ -333,22 +345,39 @@ async fn main() {
 
     let tickers = ["ETH-USD", "BTC-USD", "SOL-USD"];
 
+    let mut daemons = Vec::new();
     for tk in tickers {
         let cfg = indexer_cfg.clone();
         let t = tk.to_string();
-        tokio::spawn(async move {
-            run_market_daemon(cfg, t).await;
-        });
+        daemons.push(tokio::spawn(async move {
+            let span = info_span!("daemon", ticker = %t);
+            run_market_daemon(cfg, t).instrument(span).await;
+        }));
     }
+    tokio::spawn(csv_sink::flush_periodically());
 
-    eprintln!(
-        "[daemon] started for tickers: {}",
-        tickers.join(", ")
+    info!(
+        tickers = %tickers.join(","),
+        data_dir = %data_dir().display(),
+        "daemon started"
     );
-    eprintln!("[daemon] writing CSVs under ./data/ ...");
 
-    // Just park the main task forever; the market tasks do all the work.
-    loop {
-        sleep(Duration::from_secs(3600)).await;
+    // The market tasks do all the work; main just waits for Ctrl-C / SIGINT.
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!(error = %e, "cannot listen for Ctrl-C; running until killed");
+        loop {
+            sleep(Duration::from_secs(3600)).await;
+        }
+    }
+    info!("SIGINT, stopping daemon");
+
+    // stop the writers first so the final flush really is the last word;
+    // rows are buffered whole, so none is left half-written
+    for d in &daemons {
+        d.abort();
+    }
+    for d in daemons {
+        let _ = d.await;
     }
+    csv_sink::flush_all();
 }
@@ -6,18 +6,27 @@
 //   - Connects to dYdX indexer testnet
 //   - Streams orderbook deltas for selected ticker
 //   - Builds live orderbook depth + ladders
+//   - Freshly updated ladder levels flash and fade (toggle + fade time in Layout)
+//   - Optional cumulative size / notional ladder columns (Layout)
 //   - Builds mid-price candles on many TFs (1s .. 1d)
 //   - Mouse drag/zoom on both axes
 //   - Shift + mouse wheel over candles/volume => Y-only zoom
 //   - Real testnet market BUY/SELL buttons with CSV logging
+//   - Clicking a live ladder price switches the form to Limit at that price,
+//     or with one-click orders on (and hotkeys armed) places that limit order
+//     right away; there is no paper mode here, every order is a testnet order
+//   - My resting limit orders marked on the live ladders, with a cancel button
 //   - Preloads candles from existing CSV history for current ticker
 //
 // Replay mode:
-//   - Reads CSVs from ./data:
+//   - Reads CSVs from ./data (or --data-dir <path>):
 //       data/orderbook_{TICKER}.csv
 //       data/trades_{TICKER}.csv
+//     (or the .jsonl versions; the format is picked by extension)
 //   - Reconstructs book + candles + volume + recent trades
 //   - Same candle engine as live mode (all TFs)
+//   - Clock gaps (collector offline) marked on a timeline strip, with "Skip gap"
+//   - Bookmarks (label + ts) with Prev/Next seek, marked on the chart
 //
 // Shared:
 //   - Ticker dropdown: ETH-USD / BTC-USD / SOL-USD
@@ -26,69 +35,132 @@
 //   - Y-axis: auto vs manual (plus vertical zoom via Shift+scroll)
 //   - Layout & appearance controls (ratios, colors, body width)
 //   - Current candle kept roughly centered horizontally
+//   - Ctrl+Z / Ctrl+Shift+Z: undo / redo layout, overlay toggles and drawings
+//   - Per-ticker workspace: drawings, bookmarks and live mid price alerts in
+//     data/workspace_{TICKER}.json, saved shortly after each change
 //
 // Run:
 //   # for GUI only (no real trades needed):
 //   cargo run -p ladder_app --bin full_gui11
 //
+//   # against another dataset (all CSV reads and writes):
+//   cargo run -p ladder_app --bin full_gui11 -- --data-dir /path/to/archive
+//
+//   # repaint every 33ms while busy, every second once idle (0 = never slow down):
+//   cargo run -p ladder_app --bin full_gui11 -- --repaint-ms 33 --idle-repaint-ms 1000
+//
+//   # record JSON Lines instead of CSV:
+//   cargo run -p ladder_app --bin full_gui11 -- --data-format jsonl
+//
+//   # full-book snapshot rows every 5 minutes instead of every minute (0 = off):
+//   cargo run -p ladder_app --bin full_gui11 -- --headless --snapshot-secs 300
+//
+//   # collector only, no window (Ctrl-C to stop):
+//   cargo run -p ladder_app --bin full_gui11 -- --headless --tickers ETH-USD,BTC-USD
+//
+//   # optional Prometheus endpoint (GUI or headless), on 127.0.0.1 unless
+//   # --metrics-addr says otherwise:
+//   cargo run -p ladder_app --bin full_gui11 -- --headless --metrics-port 9898
+//   cargo run -p ladder_app --bin full_gui11 -- --headless --metrics-port 9898 --metrics-addr 0.0.0.0
+//
 //   # to enable real trades from the buttons:
 //   export DYDX_TESTNET_MNEMONIC='...'
 //   cargo run -p ladder_app --bin full_gui11
 //
-
-mod candle_agg;
-
-use candle_agg::{Candle, CandleAgg};
+//   # market orders (and FLATTEN / KILL closes) fill at most 0.5% past the
+//   # oracle price instead of the default 1%:
+//   cargo run -p ladder_app --bin full_gui11 -- --max-slippage-pct 0.5
+//
 
 use eframe::egui;
-use egui::Color32;
-use egui_plot::{Line, Plot, PlotBounds, PlotPoints, VLine};
+use egui::{Color32, Stroke};
+use egui_plot::{
+    HLine, Line, LineStyle, MarkerShape, Plot, PlotBounds, PlotPoint, PlotPoints, PlotUi, Points,
+    Polygon, Text, VLine,
+};
 
-use chrono::{Local, TimeZone};
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
+use memmap2::Mmap;
 
 use std::cmp::{max, min};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::env;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::Path;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::fs::File;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+
+use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use serde::{Deserialize, Serialize};
 
 // dYdX client
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use std::str::FromStr;
 
 use dydx_client::config::ClientConfig;
 use dydx_client::indexer::{
-    Feed as DxFeed, Feeds, IndexerClient, OrderbookResponsePriceLevel, OrdersMessage, Ticker,
+    ApiOrderStatus, Feed as DxFeed, Feeds, Height, IndexerClient, ListOrdersOpts,
+    OrderResponseObject, OrderSide as IndexerSide, OrderStatus, OrdersMessage, PerpetualMarket,
+    PositionSide, Subaccount, SubaccountResponseObject, Ticker, TradesMessage,
+};
+use dydx_client::node::{
+    Account, NodeClient, NodeError, OrderBuilder, OrderGoodUntil, OrderId, OrderSide, Wallet,
 };
-use dydx_client::node::{NodeClient, OrderBuilder, OrderSide, Wallet};
 use dydx_proto::dydxprotocol::clob::order::TimeInForce;
 
+use ladder_app::book::{
+    fmt_price, fmt_size, key_to_price, price_to_key, scale_from_tick, LiveBook, PriceKey,
+    DEFAULT_PRICE_SCALE,
+};
+use ladder_app::candle_agg::{Candle, CandleAgg};
+use ladder_app::candle_render::{draw_candles, CandleBody, CandleColorBasis, CandleRenderStyle};
+use ladder_app::csv_io::{
+    arg_value, data_dir, data_file, data_format, load_book_csv, load_trades_csv,
+    merge_book_sources, merge_sources_from_args, merge_trade_sources, parse_book_fields,
+    BookCsvEvent, BookRow, BookSide, DataFormat, DataSource, LoadStats, TradeCsvEvent, TradeRow,
+    TradeSide,
+};
+use ladder_app::csv_sink;
+use ladder_app::footprint::{footprint, FootprintRow};
+use ladder_app::pairs::{aligned, rolling_zscore, PairMetric};
+use ladder_app::pnl_hud::{self, HudCorner, PnlHud};
+use ladder_app::repaint::{RepaintThrottle, DEFAULT_IDLE_INTERVAL};
+use ladder_app::replay::{MidFn, ReplayEngine};
+use ladder_app::sim::{liquidation_price, DEFAULT_MAINT_RATE};
+use ladder_app::snapshot::{
+    apply_level, book_spread, compute_snapshot_for, SnapshotBlocks, TfSnapshot, SNAPSHOT_KIND,
+};
+use ladder_app::snapshot_worker;
+use ladder_app::time_fmt::{format_ts, now_unix, TimeDisplayMode};
+
 // ------------- timeframe config -------------
 
 // "Every timeframe from 1 sec to one day" interpreted as a rich discrete set
 // of useful TFs from 1s up to 1d.
 const TF_CHOICES: &[u64] = &[
     // seconds
-    1, 5, 10, 15, 30,
-    // minutes
-    60,        // 1m
-    120,       // 2m
-    180,       // 3m
-    300,       // 5m
-    600,       // 10m
-    900,       // 15m
-    1800,      // 30m
+    1, 5, 10, 15, 30, // minutes
+    60,   // 1m
+    120,  // 2m
+    180,  // 3m
+    300,  // 5m
+    600,  // 10m
+    900,  // 15m
+    1800, // 30m
     // hours
-    3600,      // 1h
-    7200,      // 2h
-    14400,     // 4h
-    28800,     // 8h
-    43200,     // 12h
-    86400,     // 1d
+    3600,  // 1h
+    7200,  // 2h
+    14400, // 4h
+    28800, // 8h
+    43200, // 12h
+    86400, // 1d
 ];
 
 fn tf_label(tf: u64) -> &'static str {
@@ -115,55 +187,22 @@ fn tf_label(tf: u64) -> &'static str {
     }
 }
 
-// ------------- basic helpers -------------
-
-fn now_unix() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0))
-        .as_secs()
-}
-
-// integer keys so BTreeMap ordering is nice
-type PriceKey = i64;
-
-fn price_to_key(price: f64) -> PriceKey {
-    (price * 10_000.0).round() as PriceKey
-}
-
-fn key_to_price(key: PriceKey) -> f64 {
-    key as f64 / 10_000.0
-}
-
-// ------------- time formatting -------------
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TimeDisplayMode {
-    Unix,
-    Local,
+// `steps` places along TF_CHOICES from `tf` (negative = finer), stopping at
+// either end; a TF off the list counts from the next choice up
+fn step_tf(tf: u64, steps: i32) -> u64 {
+    let last = TF_CHOICES.len() - 1;
+    let i = TF_CHOICES.iter().position(|&c| c >= tf).unwrap_or(last);
+    let j = (i as i64 + steps as i64).clamp(0, last as i64);
+    TF_CHOICES[j as usize]
 }
 
-impl TimeDisplayMode {
-    fn label(self) -> &'static str {
-        match self {
-            TimeDisplayMode::Unix => "Unix",
-            TimeDisplayMode::Local => "Local",
-        }
-    }
-}
+// "History candles" slider range
+const SHOW_CANDLES_MIN: usize = 20;
+const SHOW_CANDLES_MAX: usize = 1000;
 
-fn format_ts(mode: TimeDisplayMode, ts: u64) -> String {
-    match mode {
-        TimeDisplayMode::Unix => format!("{ts}"),
-        TimeDisplayMode::Local => {
-            let dt = Local
-                .timestamp_opt(ts as i64, 0)
-                .single()
-                .unwrap_or_else(|| Local.timestamp_opt(0, 0).single().unwrap());
-            dt.format("%Y-%m-%d %H:%M:%S").to_string()
-        }
-    }
-}
+// Ctrl + scroll over the candles: accumulated ln(zoom) per TF step; one wheel
+// notch is about 0.25
+const TF_SCROLL_STEP: f32 = 0.2;
 
 // ------------- chart + layout settings -------------
 
@@ -176,6 +215,43 @@ struct ChartSettings {
     x_zoom: f64,
     x_pan_secs: f64,
     selected_tf: u64,
+    show_trade_markers: bool,
+    scale_trade_markers: bool,
+    show_patterns: bool,
+    show_spread: bool,
+    // shade replay clock gaps and stop lines from joining across them
+    break_at_gaps: bool,
+    // quiet spans at least this long count as gaps (>= GAP_FLOOR_SECS)
+    gap_secs: u64,
+    show_volume_profile: bool,
+    profile_bucket: f64,
+    // buy / sell volume per price row inside each candle, once zoomed in
+    show_footprint: bool,
+    footprint_row_ticks: i64,
+    show_session_lines: bool,
+    session_local_day: bool, // false = UTC day
+    // live position box over the candles
+    show_pnl_hud: bool,
+    pnl_hud_corner: HudCorner,
+    y_mode: YAxisMode,
+    // reference candle (open ts) for Percent mode; None = first visible candle
+    pct_ref_t: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum YAxisMode {
+    Price,
+    // (price / ref_open - 1) * 100
+    Percent,
+}
+
+impl YAxisMode {
+    fn label(&self) -> &'static str {
+        match self {
+            YAxisMode::Price => "Price",
+            YAxisMode::Percent => "Percent",
+        }
+    }
 }
 
 impl Default for ChartSettings {
@@ -188,16 +264,57 @@ impl Default for ChartSettings {
             x_zoom: 1.0,
             x_pan_secs: 0.0,
             selected_tf: 60, // default 1m
+            show_trade_markers: false,
+            scale_trade_markers: false,
+            show_patterns: false,
+            show_spread: false,
+            break_at_gaps: false,
+            gap_secs: 300,
+            show_volume_profile: false,
+            profile_bucket: 1.0,
+            show_footprint: false,
+            footprint_row_ticks: 10,
+            show_session_lines: true,
+            session_local_day: false,
+            show_pnl_hud: true,
+            pnl_hud_corner: HudCorner::TopLeft,
+            y_mode: YAxisMode::Price,
+            pct_ref_t: None,
         }
     }
 }
 
-#[derive(Clone)]
+impl ChartSettings {
+    // switches TF keeping about the same time span on screen
+    fn set_tf(&mut self, tf: u64) {
+        if tf == self.selected_tf || tf == 0 {
+            return;
+        }
+        let span = (self.show_candles as u64 * self.selected_tf) as f64;
+        self.show_candles =
+            ((span / tf as f64).round() as usize).clamp(SHOW_CANDLES_MIN, SHOW_CANDLES_MAX);
+        self.selected_tf = tf;
+    }
+
+    // "home": latest candles framed at the default window, y back on auto
+    fn fit(&mut self) {
+        let d = Self::default();
+        self.x_zoom = d.x_zoom;
+        self.x_pan_secs = d.x_pan_secs;
+        self.show_candles = d.show_candles;
+        self.auto_y = true;
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct LayoutSettings {
-    ladders_height_ratio: f32,     // fraction of central height for ladders+trading
-    depth_width_ratio: f32,        // fraction of width for depth plot
-    volume_height_ratio: f32,      // fraction of candles+volume height for volume
+    ladders_height_ratio: f32, // fraction of central height for ladders+trading
+    depth_width_ratio: f32,    // fraction of width for depth plot
+    volume_height_ratio: f32,  // fraction of candles+volume height for volume
     candle_body_width_factor: f32, // 0.3..1.0 of TF bucket width
+    candle_wick_width: f32,
+    candle_body: CandleBody,
+    candle_color_basis: CandleColorBasis,
 }
 
 impl Default for LayoutSettings {
@@ -207,15 +324,31 @@ impl Default for LayoutSettings {
             depth_width_ratio: 0.45,
             volume_height_ratio: 0.3,
             candle_body_width_factor: 0.7,
+            candle_wick_width: 1.5,
+            candle_body: CandleBody::Outline,
+            candle_color_basis: CandleColorBasis::BodyOpenClose,
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct AppearanceSettings {
     bull_color: Color32,
     bear_color: Color32,
     volume_color: Color32,
+    // shaded bid/ask areas under the depth curves instead of bare lines
+    depth_filled: bool,
+    // ladder levels this many stddevs above their neighbours show as walls
+    wall_sigma: f64,
+    // extra live ladder columns binned at these multiples of the tick
+    multi_res_ladder: bool,
+    ladder_multiples: Vec<u32>,
+    // live ladder levels flash on update, fading over flash_secs
+    flash_updates: bool,
+    flash_secs: f32,
+    // running totals from the best price in the live/replay ladders
+    ladder_cum_size: bool,
+    ladder_cum_notional: bool,
 }
 
 impl Default for AppearanceSettings {
@@ -224,6 +357,14 @@ impl Default for AppearanceSettings {
             bull_color: Color32::from_rgb(0, 200, 0),
             bear_color: Color32::from_rgb(220, 50, 50),
             volume_color: Color32::from_rgb(120, 170, 240),
+            depth_filled: false,
+            wall_sigma: 2.0,
+            multi_res_ladder: false,
+            ladder_multiples: vec![1, 10, 100],
+            flash_updates: true,
+            flash_secs: 1.0,
+            ladder_cum_size: true,
+            ladder_cum_notional: false,
         }
     }
 }
@@ -245,6 +386,60 @@ impl UiOrderType {
     }
 }
 
+// time in force picked in the order form
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UiTif {
+    Gtc,
+    Ioc,
+    Fok,
+    PostOnly,
+}
+
+impl UiTif {
+    const ALL: [UiTif; 4] = [UiTif::Gtc, UiTif::Ioc, UiTif::Fok, UiTif::PostOnly];
+
+    fn label(self) -> &'static str {
+        match self {
+            UiTif::Gtc => "GTC",
+            UiTif::Ioc => "IOC",
+            UiTif::Fok => "FOK",
+            UiTif::PostOnly => "Post-Only",
+        }
+    }
+
+    fn time_in_force(self) -> TimeInForce {
+        match self {
+            // rests until filled, cancelled or its good-til expiry
+            UiTif::Gtc => TimeInForce::Unspecified,
+            UiTif::Ioc => TimeInForce::Ioc,
+            UiTif::Fok => TimeInForce::FillOrKill,
+            UiTif::PostOnly => TimeInForce::PostOnly,
+        }
+    }
+
+    // a market order takes liquidity right away or not at all
+    fn applies_to(self, ot: UiOrderType) -> bool {
+        match ot {
+            UiOrderType::Market => matches!(self, UiTif::Ioc | UiTif::Fok),
+            UiOrderType::Limit => true,
+        }
+    }
+
+    // IOC / FOK never rest, which a long-term order has to
+    fn rests(self) -> bool {
+        matches!(self, UiTif::Gtc | UiTif::PostOnly)
+    }
+
+    // this, or IOC where it doesn't apply to `ot`
+    fn for_order(self, ot: UiOrderType) -> UiTif {
+        if self.applies_to(ot) {
+            self
+        } else {
+            UiTif::Ioc
+        }
+    }
+}
+
 // ------------- tabs + modes -------------
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -257,291 +452,619 @@ enum ReplayTab {
 enum Mode {
     Live,
     Replay,
+    // compact candle + ladder cell per ticker, all live at once
+    Multi,
 }
 
-// ------------- live book -------------
-
-#[derive(Clone, Debug, Default)]
-struct LiveBook {
-    bids: BTreeMap<PriceKey, f64>,
-    asks: BTreeMap<PriceKey, f64>,
+// ------------- multi mode -------------
+
+const MULTI_TF: u64 = 60;
+const MULTI_PANE_CANDLES: usize = 90;
+const MULTI_LADDER_ROWS: usize = 8;
+// kept per ticker so the pair panel's z-score has a day of MULTI_TF history
+const MULTI_HISTORY_CANDLES: usize = 1440;
+
+const DEFAULT_PAIR_Z_WINDOW: usize = 60;
+const DEFAULT_PAIR_Z_BAND: f64 = 2.0;
+
+// Multi mode's pair panel: `a` against `b` on the shared MULTI_TF buckets
+struct PairSettings {
+    show: bool,
+    a: String,
+    b: String,
+    spread: bool,
+    hedge: f64,
+    z_window: usize,
+    z_band: f64,
 }
 
-impl LiveBook {
-    fn apply_levels(
-        map: &mut BTreeMap<PriceKey, f64>,
-        levels: Vec<OrderbookResponsePriceLevel>,
-        side: &str,
-        ticker: &str,
-    ) {
-        for lvl in levels {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-
-            if s == 0.0 {
-                map.remove(&key);
-            } else {
-                map.insert(key, s);
-            }
+impl PairSettings {
+    // the first two tickers, or the only one against itself
+    fn new(tickers: &[String]) -> Self {
+        let a = tickers.first().cloned().unwrap_or_default();
+        let b = tickers.get(1).cloned().unwrap_or_else(|| a.clone());
+        Self {
+            show: false,
+            a,
+            b,
+            spread: false,
+            hedge: 1.0,
+            z_window: DEFAULT_PAIR_Z_WINDOW,
+            z_band: DEFAULT_PAIR_Z_BAND,
+        }
+    }
 
-            append_book_csv(ticker, "delta", side, p, s);
+    fn metric(&self) -> PairMetric {
+        if self.spread {
+            PairMetric::Spread { hedge: self.hedge }
+        } else {
+            PairMetric::Ratio
         }
     }
+}
 
-    fn apply_initial(
-        &mut self,
-        bids: Vec<OrderbookResponsePriceLevel>,
-        asks: Vec<OrderbookResponsePriceLevel>,
-        ticker: &str,
-    ) {
-        self.bids.clear();
-        self.asks.clear();
+// ------------- mid price -------------
 
-        for lvl in bids {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-            if s != 0.0 {
-                self.bids.insert(key, s);
-            }
-            append_book_csv(ticker, "book_init", "bid", p, s);
-        }
+// which mid the candles are built from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum MidMode {
+    #[default]
+    Simple,
+    // size-weighted top of book, steadier on one-tick markets
+    Weighted,
+}
 
-        for lvl in asks {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-            if s != 0.0 {
-                self.asks.insert(key, s);
-            }
-            append_book_csv(ticker, "book_init", "ask", p, s);
+impl MidMode {
+    fn label(&self) -> &'static str {
+        match self {
+            MidMode::Simple => "Simple mid",
+            MidMode::Weighted => "Weighted mid",
         }
     }
 
-    fn apply_update(
-        &mut self,
-        bids: Option<Vec<OrderbookResponsePriceLevel>>,
-        asks: Option<Vec<OrderbookResponsePriceLevel>>,
-        ticker: &str,
-    ) {
-        if let Some(b) = bids {
-            Self::apply_levels(&mut self.bids, b, "bid", ticker);
-        }
-        if let Some(a) = asks {
-            Self::apply_levels(&mut self.asks, a, "ask", ticker);
+    fn mid_fn(self) -> MidFn {
+        match self {
+            MidMode::Simple => |b, a, s| book_mid(b, a, s, MidMode::Simple),
+            MidMode::Weighted => |b, a, s| book_mid(b, a, s, MidMode::Weighted),
         }
     }
+}
 
-    fn mid(&self) -> Option<f64> {
-        let bp = self.bids.iter().next_back();
-        let ap = self.asks.iter().next();
-        match (bp, ap) {
-            (Some((b, _)), Some((a, _))) => {
-                let pb = key_to_price(*b);
-                let pa = key_to_price(*a);
-                Some((pb + pa) * 0.5)
-            }
-            _ => None,
-        }
+fn book_mid(
+    bids: &BTreeMap<PriceKey, f64>,
+    asks: &BTreeMap<PriceKey, f64>,
+    scale: f64,
+    mode: MidMode,
+) -> Option<f64> {
+    let (bk, bs) = bids.iter().next_back()?;
+    let (ak, as_) = asks.iter().next()?;
+    let (pb, pa) = (key_to_price(*bk, scale), key_to_price(*ak, scale));
+    match mode {
+        MidMode::Weighted if bs + as_ > 0.0 => Some((pb * as_ + pa * bs) / (bs + as_)),
+        _ => Some((pb + pa) * 0.5),
     }
 }
 
 // ------------- CSV + replay structures -------------
 
-#[derive(Clone, Debug)]
-struct BookCsvEvent {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BookIndexEntry {
     ts: u64,
-    ticker: String,
-    kind: String,
-    side: String,
-    price: f64,
-    size: f64,
+    event: usize,
+    // line start in book_mmap; 0 for in-memory events
+    offset: usize,
 }
 
-#[derive(Clone, Debug)]
-struct TradeCsvEvent {
-    ts: u64,
-    ticker: String,
-    source: String,
-    side: String,
-    size_str: String,
-}
+// one index entry per this many book rows
+const BOOK_INDEX_STRIDE: usize = 4096;
 
 #[derive(Clone, Debug)]
 struct TickerData {
     ticker: String,
     book_events: Vec<BookCsvEvent>,
+    // set instead of book_events for files over STREAM_THRESHOLD_BYTES
+    book_mmap: Option<Arc<Mmap>>,
+    // every BOOK_INDEX_STRIDE rows: ts + event index (+ byte offset when streamed)
+    book_index: Vec<BookIndexEntry>,
     trade_events: Vec<TradeCsvEvent>,
     min_ts: u64,
     max_ts: u64,
+    // CSVs carry no tick size, so replay uses DEFAULT_PRICE_SCALE
+    price_scale: f64,
+    // book + trades files combined
+    load_stats: LoadStats,
+    // (last ts before, first ts after) for every quiet span >= GAP_FLOOR_SECS
+    gaps: Vec<(u64, u64)>,
+    // venue labels when merged with --merge-source dirs, empty for one dir
+    venues: Vec<String>,
+}
+
+impl TickerData {
+    /// Book events with `e.ts <= ts`, found by binary search (events are sorted by ts).
+    fn events_upto(&self, ts: u64) -> &[BookCsvEvent] {
+        // narrow to one stride via the index, then search inside it
+        let after = self.book_index.partition_point(|ix| ix.ts <= ts);
+        let lo = after
+            .checked_sub(1)
+            .map(|i| self.book_index[i].event)
+            .unwrap_or(0);
+        let hi = self
+            .book_index
+            .get(after)
+            .map(|ix| ix.event)
+            .unwrap_or(self.book_events.len());
+        let end = lo + self.book_events[lo..hi].partition_point(|e| e.ts <= ts);
+        &self.book_events[..end]
+    }
+
+    /// Gaps of at least `min_secs`, oldest first.
+    fn gaps_over(&self, min_secs: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.gaps
+            .iter()
+            .copied()
+            .filter(move |(a, b)| b - a >= min_secs)
+    }
+
+    /// End of the first gap of at least `min_secs` that ends after `ts`
+    /// (skips the one `ts` sits in, or the next one ahead).
+    fn next_gap_end(&self, ts: u64, min_secs: u64) -> Option<u64> {
+        self.gaps_over(min_secs).map(|(_, b)| b).find(|b| *b > ts)
+    }
+
+    /// Streaming mode: the mapped bytes that can hold rows with ts <= `ts`,
+    /// cut at the first index entry past it.
+    fn mapped_upto(&self, ts: u64) -> Option<&[u8]> {
+        let mmap = self.book_mmap.as_ref()?;
+        let after = self.book_index.partition_point(|ix| ix.ts <= ts);
+        let end = self
+            .book_index
+            .get(after)
+            .map(|ix| ix.offset)
+            .unwrap_or(mmap.len());
+        Some(&mmap[..end])
+    }
 }
 
-#[derive(Clone, Debug, Default)]
-struct Snapshot {
-    bids: BTreeMap<PriceKey, f64>,
-    asks: BTreeMap<PriceKey, f64>,
-    candles_by_tf: HashMap<u64, Vec<Candle>>,
-    last_mid: f64,
-    last_vol: f64,
-    trades: Vec<TradeCsvEvent>,
+fn build_book_index(events: &[BookCsvEvent]) -> Vec<BookIndexEntry> {
+    events
+        .iter()
+        .enumerate()
+        .step_by(BOOK_INDEX_STRIDE)
+        .map(|(i, e)| BookIndexEntry {
+            ts: e.ts,
+            event: i,
+            offset: 0,
+        })
+        .collect()
 }
 
-// --- CSV IO ---
+// ------------- clock gaps -------------
 
-fn append_book_csv(ticker: &str, kind: &str, side: &str, price: f64, size: f64) {
-    let ts = now_unix();
-    let dir = Path::new("data");
-    let _ = std::fs::create_dir_all(dir);
-    let path = dir.join(format!("orderbook_{ticker}.csv"));
+// shortest gap recorded at load; the settings threshold only filters above it
+const GAP_FLOOR_SECS: u64 = 60;
 
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
-        let _ = writeln!(f, "{ts},{ticker},{kind},{side},{price},{size}");
+// Quiet spans of one event stream, fed sorted timestamps. The open ends before
+// the first and after the last event are kept as (0, first) and (last, MAX) so
+// two streams can be intersected by `data_gaps`, which trims them off again.
+struct QuietSpans {
+    prev: Option<u64>,
+    spans: Vec<(u64, u64)>,
+}
+
+impl QuietSpans {
+    fn new() -> Self {
+        Self {
+            prev: None,
+            spans: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, ts: u64) {
+        match self.prev {
+            None => self.spans.push((0, ts)),
+            Some(p) if ts.saturating_sub(p) >= GAP_FLOOR_SECS => self.spans.push((p, ts)),
+            _ => {}
+        }
+        self.prev = Some(ts);
+    }
+
+    fn finish(mut self) -> Vec<(u64, u64)> {
+        self.spans.push((self.prev.unwrap_or(0), u64::MAX));
+        self.spans
     }
 }
 
-fn append_trade_csv(ticker: &str, source: &str, side: &str, size_str: &str) {
-    let ts = now_unix();
-    let dir = Path::new("data");
-    let _ = std::fs::create_dir_all(dir);
-    let path = dir.join(format!("trades_{ticker}.csv"));
+fn quiet_spans(ts: impl IntoIterator<Item = u64>) -> Vec<(u64, u64)> {
+    let mut q = QuietSpans::new();
+    for t in ts {
+        q.push(t);
+    }
+    q.finish()
+}
 
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
-        let _ = writeln!(f, "{ts},{ticker},{source},{side},{size_str}");
+// spans where neither stream had an event, inside the data range only
+fn data_gaps(book: &[(u64, u64)], trades: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < book.len() && j < trades.len() {
+        let lo = book[i].0.max(trades[j].0);
+        let hi = book[i].1.min(trades[j].1);
+        if lo != 0 && hi != u64::MAX && hi.saturating_sub(lo) >= GAP_FLOOR_SECS {
+            out.push((lo, hi));
+        }
+        if book[i].1 < trades[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
     }
+    out
+}
+
+// true when a gap starts in [from, to), i.e. points at `from` and `to` sit on
+// either side of it (`gaps` sorted)
+fn crosses_gap(gaps: &[(u64, u64)], from: u64, to: u64) -> bool {
+    let i = gaps.partition_point(|g| g.0 < from);
+    gaps.get(i).is_some_and(|g| g.0 < to)
 }
 
-fn load_book_csv(path: &Path, ticker: &str) -> Vec<BookCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
+// thin min..max strip under the replay slider: gaps in red, `ts` as a white
+// tick. Clicking or dragging on it returns the ts under the pointer.
+fn replay_timeline(
+    ui: &mut egui::Ui,
+    min_ts: u64,
+    max_ts: u64,
+    ts: u64,
+    gaps: &[(u64, u64)],
+) -> Option<u64> {
+    let (rect, resp) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), 8.0),
+        egui::Sense::click_and_drag(),
+    );
+    let span = max_ts.saturating_sub(min_ts).max(1) as f32;
+    let x_of = |t: u64| rect.left() + rect.width() * (t.saturating_sub(min_ts) as f32 / span);
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_gray(50));
+    for (a, b) in gaps {
+        let gap =
+            egui::Rect::from_x_y_ranges(x_of(*a)..=x_of(*b).max(x_of(*a) + 1.0), rect.y_range());
+        painter.rect_filled(gap, 0.0, Color32::from_rgb(200, 60, 60));
     }
-    let f = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
+    let x = x_of(ts);
+    painter.line_segment(
+        [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+        Stroke::new(2.0, Color32::WHITE),
+    );
+
+    let pos = resp.interact_pointer_pos()?;
+    let frac = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0) as f64;
+    Some(min_ts + (frac * span as f64) as u64)
+}
+
+// --- CSV IO ---
+
+// rows go through csv_sink: buffered, on disk within csv_sink::FLUSH_INTERVAL.
+// CSV or JSON Lines per --data-format, the extension following suit.
+
+fn data_write_path(stem: &str) -> PathBuf {
+    data_dir().join(format!("{stem}.{}", data_format().ext()))
+}
+
+fn append_book_csv(ticker: &str, kind: &str, side: BookSide, price: f64, size: f64, seq: u64) {
+    let row = BookRow {
+        ts: now_unix(),
+        ticker,
+        kind,
+        side,
+        price,
+        size,
+        seq: Some(seq),
     };
-    let reader = BufReader::new(f);
-    let mut out = Vec::new();
+    let path = data_write_path(&format!("orderbook_{ticker}"));
+    let _ = csv_sink::append_row(&path, &row.encode(data_format()));
+}
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 6 {
-                continue;
-            }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            let kind = parts[2].to_string();
-            let side = parts[3].to_string();
-            let price = match parts[4].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let size = match parts[5].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+// market trades from the trades channel; price rides in an extra 6th column
+// that the 5-field readers ignore
+fn append_market_trade_csv(t: &TapeTrade) {
+    let row = TradeRow {
+        ts: t.ts,
+        ticker: &t.ticker,
+        source: MARKET_SOURCE,
+        side: t.side,
+        size: t.size,
+        price: Some(t.price.unwrap_or(0.0)),
+    };
+    let path = data_write_path(&format!("trades_{}", t.ticker));
+    let _ = csv_sink::append_row(&path, &row.encode(data_format()));
+}
 
-            if tk != ticker {
-                continue;
-            }
+fn append_trade_csv(ticker: &str, source: &str, side: TradeSide, size: f64) {
+    let row = TradeRow {
+        ts: now_unix(),
+        ticker,
+        source,
+        side,
+        size,
+        price: None,
+    };
+    let path = data_write_path(&format!("trades_{ticker}"));
+    let _ = csv_sink::append_row(&path, &row.encode(data_format()));
+}
 
-            out.push(BookCsvEvent {
-                ts,
-                ticker: tk,
-                kind,
-                side,
-                price,
-                size,
-            });
-        }
+// 1234 -> "1234", 1_234_567 -> "1.2M"
+fn fmt_count(n: usize) -> String {
+    if n >= 1_000_000 {
+        format!("{:.1}M", n as f64 / 1_000_000.0)
+    } else if n >= 10_000 {
+        format!("{:.1}k", n as f64 / 1_000.0)
+    } else {
+        n.to_string()
     }
+}
 
-    out.sort_by_key(|e| e.ts);
-    out
+// above this the book CSV is read through a memory map instead of BufReader
+const MMAP_LOAD_THRESHOLD_BYTES: u64 = 64 * 1024 * 1024;
+// above this the events are not materialized at all; replay scans the map
+const STREAM_THRESHOLD_BYTES: u64 = 1024 * 1024 * 1024;
+
+fn map_file(path: &Path) -> std::io::Result<Mmap> {
+    let f = File::open(path)?;
+    // SAFETY: the daemon only ever appends to these files; bytes appended after
+    // mapping are simply not visible. Truncating a mapped file is not supported.
+    unsafe { Mmap::map(&f) }
 }
 
-fn load_trades_csv(path: &Path, ticker: &str) -> Vec<TradeCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
+// Walk the mapped CSV line by line; `f` gets (line_no, byte offset, line)
+// and returns false to stop early.
+fn for_each_mapped_line(bytes: &[u8], mut f: impl FnMut(usize, usize, &str) -> bool) {
+    let mut offset = 0;
+    for (idx, raw) in bytes.split(|b| *b == b'\n').enumerate() {
+        let start = offset;
+        offset += raw.len() + 1;
+        let line = match std::str::from_utf8(raw) {
+            Ok(l) => l.trim(),
+            Err(_) => "\u{fffd}", // counted as malformed by the parser
+        };
+        if line.is_empty() || (idx == 0 && line.starts_with("ts,")) {
+            continue;
+        }
+        if !f(idx + 1, start, line) {
+            break;
+        }
     }
-    let f = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
+}
+
+// Same result as load_book_csv, but parses straight out of a memory map.
+fn load_book_csv_mmap(path: &Path, ticker: &str) -> (Vec<BookCsvEvent>, LoadStats) {
+    let mut stats = LoadStats::default();
+    let mmap = match map_file(path) {
+        Ok(m) => m,
+        Err(e) => {
+            stats.skip(0, format!("cannot map {}: {e}", path.display()));
+            return (Vec::new(), stats);
+        }
     };
-    let reader = BufReader::new(f);
-    let mut out = Vec::new();
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 5 {
-                continue;
+    let mut out = Vec::new();
+    for_each_mapped_line(&mmap, |line_no, _, line| {
+        stats.total_lines += 1;
+        match parse_book_fields(line) {
+            Ok((ts, tk, kind, side, price, size, seq)) => {
+                if tk == ticker {
+                    stats.parsed += 1;
+                    out.push(BookCsvEvent {
+                        ts,
+                        ticker: tk.to_string(),
+                        kind: kind.to_string(),
+                        side,
+                        price,
+                        size,
+                        seq,
+                        venue: String::new(),
+                    });
+                }
             }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            let source = parts[2].to_string();
-            let side = parts[3].to_string();
-            let size_str = parts[4].to_string();
+            Err(reason) => stats.skip(line_no, reason),
+        }
+        true
+    });
 
-            if tk != ticker {
-                continue;
+    out.sort_by_key(|e| e.ts);
+    (out, stats)
+}
+
+// Streaming mode: stats, ts range, byte-offset index and quiet spans (see
+// `QuietSpans`) in one pass, without keeping any events.
+fn scan_book_mmap(
+    mmap: &Mmap,
+    ticker: &str,
+) -> (LoadStats, u64, u64, Vec<BookIndexEntry>, Vec<(u64, u64)>) {
+    let mut stats = LoadStats::default();
+    let mut min_ts = u64::MAX;
+    let mut max_ts = 0u64;
+    let mut index = Vec::new();
+    let mut quiet = QuietSpans::new();
+    for_each_mapped_line(mmap, |line_no, offset, line| {
+        stats.total_lines += 1;
+        match parse_book_fields(line) {
+            Ok((ts, tk, ..)) if tk == ticker => {
+                if stats.parsed % BOOK_INDEX_STRIDE == 0 {
+                    index.push(BookIndexEntry {
+                        ts,
+                        event: stats.parsed,
+                        offset,
+                    });
+                }
+                stats.parsed += 1;
+                min_ts = min(min_ts, ts);
+                max_ts = max(max_ts, ts);
+                quiet.push(ts);
             }
+            Ok(_) => {}
+            Err(reason) => stats.skip(line_no, reason),
+        }
+        true
+    });
+    (stats, min_ts, max_ts, index, quiet.finish())
+}
 
-            out.push(TradeCsvEvent {
-                ts,
-                ticker: tk,
-                source,
-                side,
-                size_str,
-            });
+// Drop book rows that would not change the reconstructed book: same side +
+// price level already holding exactly this size. Removals (size 0) and
+// snapshot rows are always kept. Note the candle volume pulse comes from
+// these rows, so it shrinks too.
+fn dedup_book_events(events: Vec<BookCsvEvent>, scale: f64) -> (Vec<BookCsvEvent>, usize) {
+    let mut state: HashMap<(bool, PriceKey), f64> = HashMap::new();
+    let mut blocks = SnapshotBlocks::default();
+    let mut out = Vec::with_capacity(events.len());
+    let mut collapsed = 0usize;
+
+    for e in events {
+        let key = (e.side == BookSide::Bid, price_to_key(e.price, scale));
+
+        if blocks.starts_block(&e.kind, e.seq) {
+            state.clear();
+        }
+        if e.kind == SNAPSHOT_KIND {
+            state.insert(key, e.size);
+        } else if e.size == 0.0 {
+            state.remove(&key);
+        } else if state.get(&key) == Some(&e.size) {
+            collapsed += 1;
+            continue;
+        } else {
+            state.insert(key, e.size);
         }
+        out.push(e);
     }
 
-    out.sort_by_key(|e| e.ts);
-    out
+    (out, collapsed)
+}
+
+// the --data-dir venue when others are merged in
+const PRIMARY_VENUE: &str = "dydx";
+
+// the mmap fast paths only parse CSV
+fn is_csv(path: &Path) -> bool {
+    DataFormat::from_path(path) == DataFormat::Csv
+}
+
+fn load_book_file(path: &Path, ticker: &str) -> (Vec<BookCsvEvent>, LoadStats) {
+    let len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if len >= MMAP_LOAD_THRESHOLD_BYTES && is_csv(path) {
+        load_book_csv_mmap(path, ticker)
+    } else {
+        load_book_csv(path, ticker)
+    }
+}
+
+// Fold the --merge-source dirs into the primary dir's events: one combined
+// book with same-price levels summed across venues, trades interleaved by ts
+// and tagged with their venue.
+fn merge_venues(
+    book_events: Vec<BookCsvEvent>,
+    trade_events: Vec<TradeCsvEvent>,
+    extra: &[DataSource],
+    ticker: &str,
+    load_stats: &mut LoadStats,
+) -> (Vec<BookCsvEvent>, Vec<TradeCsvEvent>, Vec<String>) {
+    let mut books = vec![(PRIMARY_VENUE.to_string(), book_events)];
+    let mut trades = vec![(PRIMARY_VENUE.to_string(), trade_events)];
+    for src in extra {
+        let ob_path = data_file(&src.dir, &format!("orderbook_{ticker}"));
+        let tr_path = data_file(&src.dir, &format!("trades_{ticker}"));
+        let (b, b_stats) = load_book_file(&ob_path, ticker);
+        let (t, t_stats) = load_trades_csv(&tr_path, ticker);
+        load_stats.merge(&b_stats);
+        load_stats.merge(&t_stats);
+        info!(
+            %ticker,
+            venue = %src.label,
+            book_rows = b.len(),
+            trades = t.len(),
+            "merging venue"
+        );
+        books.push((src.label.clone(), b));
+        trades.push((src.label.clone(), t));
+    }
+    let venues = books.iter().map(|(label, _)| label.clone()).collect();
+    (
+        merge_book_sources(books, DEFAULT_PRICE_SCALE),
+        merge_trade_sources(trades),
+        venues,
+    )
 }
 
-fn load_ticker_data(base_dir: &str, ticker: &str) -> Option<TickerData> {
-    let ob_path = Path::new(base_dir).join(format!("orderbook_{ticker}.csv"));
-    let tr_path = Path::new(base_dir).join(format!("trades_{ticker}.csv"));
+fn load_ticker_data(base_dir: &Path, extra: &[DataSource], ticker: &str) -> Option<TickerData> {
+    let ob_path = data_file(base_dir, &format!("orderbook_{ticker}"));
+    let tr_path = data_file(base_dir, &format!("trades_{ticker}"));
+
+    let ob_len = std::fs::metadata(&ob_path).map(|m| m.len()).unwrap_or(0);
+
+    // huge CSV files: keep the map, only validate and find the ts range.
+    // Merging needs every row in memory, so it never streams.
+    let mut book_mmap = None;
+    let mut stream_index = Vec::new();
+    let mut stream_range = None;
+    let mut stream_quiet = None;
+    let streamable = extra.is_empty() && is_csv(&ob_path);
+    let (book_events, book_stats) = if ob_len >= STREAM_THRESHOLD_BYTES && streamable {
+        match map_file(&ob_path) {
+            Ok(m) => {
+                let (stats, lo, hi, index, quiet) = scan_book_mmap(&m, ticker);
+                stream_index = index;
+                stream_quiet = Some(quiet);
+                info!(
+                    %ticker,
+                    mib = ob_len / (1024 * 1024),
+                    "streaming book file from disk"
+                );
+                if stats.parsed > 0 {
+                    stream_range = Some((lo, hi));
+                }
+                book_mmap = Some(Arc::new(m));
+                (Vec::new(), stats)
+            }
+            Err(e) => {
+                warn!(%ticker, error = %e, "mmap failed, reading normally");
+                load_book_csv(&ob_path, ticker)
+            }
+        }
+    } else {
+        load_book_file(&ob_path, ticker)
+    };
+    let (trade_events, trade_stats) = load_trades_csv(&tr_path, ticker);
+
+    let mut load_stats = book_stats;
+    load_stats.merge(&trade_stats);
+
+    let (book_events, trade_events, venues) = if extra.is_empty() {
+        (book_events, trade_events, Vec::new())
+    } else {
+        merge_venues(book_events, trade_events, extra, ticker, &mut load_stats)
+    };
 
-    let book_events = load_book_csv(&ob_path, ticker);
-    let trade_events = load_trades_csv(&tr_path, ticker);
+    let (book_events, collapsed) = dedup_book_events(book_events, DEFAULT_PRICE_SCALE);
+    load_stats.collapsed = collapsed;
+    if collapsed > 0 {
+        info!(%ticker, collapsed, "collapsed redundant book rows");
+    }
+    if load_stats.skipped > 0 {
+        warn!(
+            %ticker,
+            skipped = load_stats.skipped,
+            total = load_stats.total_lines,
+            samples = ?load_stats.samples,
+            "skipped malformed lines"
+        );
+    }
 
-    if book_events.is_empty() && trade_events.is_empty() {
+    if book_events.is_empty() && stream_range.is_none() && trade_events.is_empty() {
         return None;
     }
 
-    let mut min_ts = u64::MAX;
-    let mut max_ts = 0u64;
+    let (mut min_ts, mut max_ts) = stream_range.unwrap_or((u64::MAX, 0));
 
     for e in &book_events {
         min_ts = min(min_ts, e.ts);
@@ -556,95 +1079,117 @@ fn load_ticker_data(base_dir: &str, ticker: &str) -> Option<TickerData> {
         return None;
     }
 
+    let book_index = if book_mmap.is_some() {
+        stream_index
+    } else {
+        build_book_index(&book_events)
+    };
+
+    let book_quiet = stream_quiet.unwrap_or_else(|| quiet_spans(book_events.iter().map(|e| e.ts)));
+    let gaps = data_gaps(&book_quiet, &quiet_spans(trade_events.iter().map(|e| e.ts)));
+    if !gaps.is_empty() {
+        info!(%ticker, gaps = gaps.len(), "found clock gaps in the data");
+    }
+
     Some(TickerData {
         ticker: ticker.to_string(),
         book_events,
+        book_mmap,
+        book_index,
         trade_events,
         min_ts,
         max_ts,
+        price_scale: DEFAULT_PRICE_SCALE,
+        load_stats,
+        gaps,
+        venues,
     })
 }
 
-// reconstruct snapshot at target_ts (for replay)
-fn compute_snapshot_for(data: &TickerData, target_ts: u64) -> Snapshot {
-    let mut bids: BTreeMap<PriceKey, f64> = BTreeMap::new();
-    let mut asks: BTreeMap<PriceKey, f64> = BTreeMap::new();
-
-    let mut agg_by_tf: HashMap<u64, CandleAgg> = HashMap::new();
-    for tf in TF_CHOICES {
-        agg_by_tf.insert(*tf, CandleAgg::new(*tf));
+// Load every ticker's CSVs, at most one thread per core. Files are
+// independent; results are inserted in `tickers` order, not completion order.
+fn load_all_tickers(
+    base_dir: &Path,
+    extra: &[DataSource],
+    tickers: &[&str],
+) -> HashMap<String, TickerData> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .clamp(1, tickers.len().max(1));
+
+    let mut loaded: Vec<Option<TickerData>> = Vec::with_capacity(tickers.len());
+    for chunk in tickers.chunks(workers) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|tk| scope.spawn(move || load_ticker_data(base_dir, extra, tk)))
+                .collect();
+            for (tk, h) in chunk.iter().zip(handles) {
+                // a panicking loader drops that ticker, but says so
+                loaded.push(h.join().unwrap_or_else(|payload| {
+                    error!(
+                        ticker = %tk,
+                        panic = panic_message(payload.as_ref()),
+                        "ticker loader panicked; no replay data for it"
+                    );
+                    None
+                }));
+            }
+        });
     }
 
-    for e in &data.book_events {
-        if e.ts > target_ts {
-            break;
-        }
-
-        let map = if e.side.to_lowercase() == "bid" {
-            &mut bids
-        } else {
-            &mut asks
-        };
-
-        let key = price_to_key(e.price);
-
-        if e.size == 0.0 {
-            map.remove(&key);
-        } else {
-            map.insert(key, e.size);
+    let mut out = HashMap::new();
+    for (tk, td) in tickers.iter().zip(loaded) {
+        if let Some(td) = td {
+            out.insert(tk.to_string(), td);
         }
-
-        if let (Some((bp, _)), Some((ap, _))) = (bids.iter().next_back(), asks.iter().next()) {
-            let mid = (key_to_price(*bp) + key_to_price(*ap)) * 0.5;
-            let vol = e.size.abs().max(0.0);
-
-            for agg in agg_by_tf.values_mut() {
-                agg.update(e.ts, mid, vol);
-            }
-        }
-    }
-
-    let mut trades: Vec<TradeCsvEvent> = data
-        .trade_events
-        .iter()
-        .filter(|t| t.ts <= target_ts)
-        .cloned()
-        .collect();
-    trades.sort_by_key(|t| t.ts);
-    if trades.len() > 200 {
-        let start = trades.len() - 200;
-        trades = trades[start..].to_vec();
-    }
-
-    let mut candles_by_tf: HashMap<u64, Vec<Candle>> = HashMap::new();
-    for (tf, agg) in agg_by_tf.into_iter() {
-        candles_by_tf.insert(tf, agg.series().to_vec());
     }
+    out
+}
 
-    // use 1m candles (60s) for last_mid/vol if available
-    let (last_mid, last_vol) = if let Some(series) = candles_by_tf.get(&60) {
-        if let Some(c) = series.last() {
-            (c.close, c.volume)
-        } else {
-            (0.0, 0.0)
-        }
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
     } else {
-        (0.0, 0.0)
-    };
+        "non-string panic payload"
+    }
+}
 
-    Snapshot {
-        bids,
-        asks,
-        candles_by_tf,
-        last_mid,
-        last_vol,
-        trades,
+// reconstruct snapshot at target_ts (for replay); 1m candles give last_mid/vol.
+// Replay views read the snapshot's ts, not replay_ts.
+fn replay_snapshot(data: &TickerData, target_ts: u64, mid_mode: MidMode) -> TfSnapshot {
+    // streamed files have no book_events, so the engine gets their rows below
+    let mut engine = ReplayEngine::over(
+        data.events_upto(target_ts),
+        &data.trade_events,
+        data.price_scale,
+    )
+    .with_mid(mid_mode.mid_fn());
+
+    if let Some(bytes) = data.mapped_upto(target_ts) {
+        // streamed file: rows are in append (= time) order, stop at target_ts
+        for_each_mapped_line(bytes, |_, _, line| match parse_book_fields(line) {
+            Ok((ts, ..)) if ts > target_ts => false,
+            Ok((ts, tk, kind, side, price, size, seq)) => {
+                if tk == data.ticker {
+                    engine.apply_row(ts, kind, side, price, size, seq);
+                }
+                true
+            }
+            Err(_) => true,
+        });
     }
+    compute_snapshot_for(engine, target_ts, TF_CHOICES, 60)
 }
 
 // build CandleAgg history for all TFs from CSV (for seeding LIVE view)
 fn build_candles_from_book_events(
     events: &[BookCsvEvent],
+    scale: f64,
+    mid_mode: MidMode,
 ) -> (HashMap<u64, CandleAgg>, u64) {
     let mut bids: BTreeMap<PriceKey, f64> = BTreeMap::new();
     let mut asks: BTreeMap<PriceKey, f64> = BTreeMap::new();
@@ -655,27 +1200,23 @@ fn build_candles_from_book_events(
     }
 
     let mut last_ts = 0u64;
+    let mut blocks = SnapshotBlocks::default();
 
     for e in events {
         last_ts = e.ts;
 
-        let map = if e.side.to_lowercase() == "bid" {
-            &mut bids
-        } else {
-            &mut asks
-        };
-
-        let key = price_to_key(e.price);
-
-        if e.size == 0.0 {
-            map.remove(&key);
-        } else {
-            map.insert(key, e.size);
+        if blocks.starts_block(&e.kind, e.seq) {
+            bids.clear();
+            asks.clear();
         }
+        apply_level(&mut bids, &mut asks, e.side, e.price, e.size, scale);
 
-        if let (Some((bp, _)), Some((ap, _))) = (bids.iter().next_back(), asks.iter().next()) {
-            let mid = (key_to_price(*bp) + key_to_price(*ap)) * 0.5;
-            let vol = e.size.abs().max(0.0);
+        if let Some(mid) = book_mid(&bids, &asks, scale, mid_mode) {
+            let vol = if e.kind == SNAPSHOT_KIND {
+                0.0
+            } else {
+                e.size.abs()
+            };
 
             for agg in agg_by_tf.values_mut() {
                 agg.update(e.ts, mid, vol);
@@ -695,492 +1236,3356 @@ fn empty_live_candles() -> HashMap<u64, CandleAgg> {
     m
 }
 
-// ------------- crypto provider -------------
+// my own fills from trades_{ticker}.csv as chart markers:
+// (x = trade ts, y = close of the candle containing it, is_buy, size)
+fn trade_marker_points(
+    trades: &[TradeCsvEvent],
+    visible: &[Candle],
+    tf: u64,
+    cutoff_ts: u64,
+) -> Vec<(f64, f64, bool, f64)> {
+    let mut out = Vec::new();
+    let (first, last) = match (visible.first(), visible.last()) {
+        (Some(f), Some(l)) => (f.t, l.t + tf),
+        _ => return out,
+    };
 
-fn init_crypto_provider() {
-    let _ = rustls::crypto::ring::default_provider().install_default();
+    for tr in trades {
+        if tr.ts > cutoff_ts || tr.ts < first || tr.ts >= last {
+            continue;
+        }
+        let idx = visible.partition_point(|c| c.t <= tr.ts);
+        if idx == 0 {
+            continue;
+        }
+        let c = &visible[idx - 1];
+        if tr.ts >= c.t + tf {
+            continue;
+        }
+        let is_buy = tr.side == TradeSide::Buy;
+        let size = tr.size_str.trim().parse::<f64>().unwrap_or(0.0).abs();
+        out.push((tr.ts as f64, c.close, is_buy, size));
+    }
+
+    out
 }
 
-// ------------- trade command (real orders) -------------
+// ------------- spread over time -------------
 
-#[derive(Debug)]
-enum TradeCmd {
-    MarketOrder {
-        ticker: String,
-        side: OrderSide,
-        size: BigDecimal,
-    },
+// the top bar shows how far the live data trails the wall clock from here on
+const LIVE_LAG_SHOW_SECS: u64 = 2;
+
+fn live_clock_label(mode: TimeDisplayMode, data_ts: u64, wall_ts: u64) -> String {
+    let label = format!(
+        "Data ts: {} | wall: {}",
+        format_ts(mode, data_ts),
+        format_ts(mode, wall_ts)
+    );
+    match wall_ts.saturating_sub(data_ts) {
+        lag if lag >= LIVE_LAG_SHOW_SECS => format!("{label} ({lag}s behind)"),
+        _ => label,
+    }
 }
 
-// ------------- main app -------------
+// live spread samples kept for the spread panel (one per book update)
+const SPREAD_RING_MAX: usize = 50_000;
+
+// (bucket start, min, avg, max)
+type SpreadBucket = (u64, f64, f64, f64);
+
+// `samples` must be sorted by ts
+fn bucket_spreads(samples: impl IntoIterator<Item = (u64, f64)>, tf: u64) -> Vec<SpreadBucket> {
+    let tf = tf.max(1);
+    let mut out: Vec<SpreadBucket> = Vec::new();
+    let mut count = 0usize;
+
+    for (ts, spread) in samples {
+        let bucket_start = (ts / tf) * tf;
+        if let Some(last) = out.last_mut() {
+            if last.0 == bucket_start {
+                count += 1;
+                last.1 = last.1.min(spread);
+                last.3 = last.3.max(spread);
+                // running mean
+                last.2 += (spread - last.2) / count as f64;
+                continue;
+            }
+        }
+        count = 1;
+        out.push((bucket_start, spread, spread, spread));
+    }
 
-struct ComboApp {
-    // shared
-    mode: Mode,
-    time_mode: TimeDisplayMode,
-    chart: ChartSettings,
-    layout: LayoutSettings,
-    appearance: AppearanceSettings,
-    tickers: Vec<String>,
-    current_ticker: String,
-    ticker_tx: watch::Sender<String>,
+    out
+}
 
-    // live
-    live_book_rx: watch::Receiver<LiveBook>,
-    live_book: LiveBook,
-    live_candles: HashMap<u64, CandleAgg>,
-    live_last_ts: u64,
+/// Spread at every book event where both sides exist, bucketed to `tf`.
+/// Events on a one-sided book are skipped. Streamed tickers keep no events
+/// in memory, so they give an empty series.
+fn spread_series(data: &TickerData, tf: u64) -> Vec<SpreadBucket> {
+    let mut bids: BTreeMap<PriceKey, f64> = BTreeMap::new();
+    let mut asks: BTreeMap<PriceKey, f64> = BTreeMap::new();
+    let scale = data.price_scale;
+    let mut blocks = SnapshotBlocks::default();
 
-    // real trading UI
-    trade_tx: mpsc::Sender<TradeCmd>,
-    trade_size_input: f64,
-    ui_order_type: UiOrderType,
-    ui_limit_price: f64,
-    ui_leverage: f64,
-    ui_reduce_only: bool,
-    last_order_msg: String,
+    let samples = data.book_events.iter().filter_map(|e| {
+        if blocks.starts_block(&e.kind, e.seq) {
+            bids.clear();
+            asks.clear();
+        }
+        apply_level(&mut bids, &mut asks, e.side, e.price, e.size, scale);
+        book_spread(&bids, &asks, scale).map(|s| (e.ts, s))
+    });
 
-    // replay
-    replay_data: HashMap<String, TickerData>,
-    replay_ts: u64,
-    replay_tab: ReplayTab,
+    bucket_spreads(samples, tf)
 }
 
-impl ComboApp {
-    fn new(
-        book_rx: watch::Receiver<LiveBook>,
-        replay_data: HashMap<String, TickerData>,
-        ticker_tx: watch::Sender<String>,
-        trade_tx: mpsc::Sender<TradeCmd>,
-    ) -> Self {
-        let tickers = vec![
-            "ETH-USD".to_string(),
-            "BTC-USD".to_string(),
-            "SOL-USD".to_string(),
-        ];
+// min-max band (per-segment, polygons must be convex) under the avg line;
+// neither is drawn across a gap in `gaps`
+fn plot_spread(
+    plot_ui: &mut PlotUi,
+    buckets: &[SpreadBucket],
+    tf: f64,
+    color: Color32,
+    gaps: &[(u64, u64)],
+) {
+    let shade = Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), 50);
+    let x = |t: u64| t as f64 + tf * 0.5;
+    let mut runs = vec![0];
+    for (i, w) in buckets.windows(2).enumerate() {
+        let (a, b) = (w[0], w[1]);
+        if crosses_gap(gaps, a.0, b.0) {
+            runs.push(i + 1);
+            continue;
+        }
+        let band: PlotPoints =
+            vec![[x(a.0), a.1], [x(a.0), a.3], [x(b.0), b.3], [x(b.0), b.1]].into();
+        plot_ui.polygon(Polygon::new(band).fill_color(shade).stroke(Stroke::NONE));
+    }
+    runs.push(buckets.len());
+    for r in runs.windows(2) {
+        let avg: PlotPoints = buckets[r[0]..r[1]]
+            .iter()
+            .map(|b| [x(b.0), b.2])
+            .collect::<Vec<_>>()
+            .into();
+        plot_ui.line(Line::new(avg).color(color).name("avg spread"));
+    }
+}
 
-        let current_ticker = "ETH-USD".to_string();
+// ------------- candle patterns -------------
+
+// doji: body at most this fraction of the high-low range
+const DOJI_MAX_BODY: f64 = 0.1;
+// hammer / shooting star: long wick at least this many bodies...
+const HAMMER_MIN_WICK_BODIES: f64 = 2.0;
+// ...and the opposite wick at most this fraction of the range
+const HAMMER_MAX_SHORT_WICK: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pattern {
+    Doji,
+    BullishEngulfing,
+    BearishEngulfing,
+    Hammer,
+    ShootingStar,
+}
 
-        let replay_ts = replay_data
-            .get(&current_ticker)
-            .map(|td| td.max_ts)
-            .unwrap_or(0);
+impl Pattern {
+    fn label(&self) -> &'static str {
+        match self {
+            Pattern::Doji => "Doji",
+            Pattern::BullishEngulfing => "Bullish engulfing",
+            Pattern::BearishEngulfing => "Bearish engulfing",
+            Pattern::Hammer => "Hammer",
+            Pattern::ShootingStar => "Shooting star",
+        }
+    }
 
-        // seed live CandleAggs from CSV history if present
-        let (live_candles, live_last_ts) = if let Some(td) = replay_data.get(&current_ticker) {
-            build_candles_from_book_events(&td.book_events)
-        } else {
-            (empty_live_candles(), now_unix())
-        };
+    fn tag(&self) -> &'static str {
+        match self {
+            Pattern::Doji => "D",
+            Pattern::BullishEngulfing => "E+",
+            Pattern::BearishEngulfing => "E-",
+            Pattern::Hammer => "H",
+            Pattern::ShootingStar => "SS",
+        }
+    }
 
-        Self {
-            mode: Mode::Live,
-            time_mode: TimeDisplayMode::Local,
-            chart: ChartSettings::default(),
-            layout: LayoutSettings::default(),
-            appearance: AppearanceSettings::default(),
-            tickers,
-            current_ticker,
-            ticker_tx,
+    // Some(true) bullish, Some(false) bearish, None for indecision
+    fn bias(&self) -> Option<bool> {
+        match self {
+            Pattern::Doji => None,
+            Pattern::BullishEngulfing | Pattern::Hammer => Some(true),
+            Pattern::BearishEngulfing | Pattern::ShootingStar => Some(false),
+        }
+    }
+}
 
-            live_book_rx: book_rx,
-            live_book: LiveBook::default(),
-            live_candles,
-            live_last_ts,
+/// Single- and two-candle patterns by index into `candles`; one candle can
+/// match several. Flat (zero-range) candles never match.
+fn detect_patterns(candles: &[Candle]) -> Vec<(usize, Pattern)> {
+    let mut out = Vec::new();
 
-            trade_tx,
-            trade_size_input: 0.01,
-            ui_order_type: UiOrderType::Market,
-            ui_limit_price: 0.0,
-            ui_leverage: 5.0,
-            ui_reduce_only: false,
-            last_order_msg: String::new(),
+    for (i, c) in candles.iter().enumerate() {
+        let range = c.high - c.low;
+        if range <= 0.0 {
+            continue;
+        }
+        let body = (c.close - c.open).abs();
+        let upper = c.high - c.open.max(c.close);
+        let lower = c.open.min(c.close) - c.low;
+
+        let long_wick = HAMMER_MIN_WICK_BODIES * body;
+        let short_wick = HAMMER_MAX_SHORT_WICK * range;
+        if body <= DOJI_MAX_BODY * range {
+            out.push((i, Pattern::Doji));
+        } else if lower >= long_wick && upper <= short_wick {
+            out.push((i, Pattern::Hammer));
+        } else if upper >= long_wick && lower <= short_wick {
+            out.push((i, Pattern::ShootingStar));
+        }
 
-            replay_data,
-            replay_ts,
-            replay_tab: ReplayTab::Candles,
+        if i == 0 {
+            continue;
+        }
+        let p = &candles[i - 1];
+        let prev_body = (p.close - p.open).abs();
+        if body <= prev_body {
+            continue;
+        }
+        if p.close < p.open && c.close > c.open && c.open <= p.close && c.close >= p.open {
+            out.push((i, Pattern::BullishEngulfing));
+        } else if p.close > p.open && c.close < c.open && c.open >= p.close && c.close <= p.open {
+            out.push((i, Pattern::BearishEngulfing));
         }
     }
 
-    fn current_replay_ticker(&self) -> Option<&TickerData> {
-        self.replay_data.get(&self.current_ticker)
-    }
+    out
+}
 
-    fn live_series(&self) -> Vec<Candle> {
-        if let Some(agg) = self.live_candles.get(&self.chart.selected_tf) {
-            agg.series().to_vec()
-        } else {
-            Vec::new()
-        }
+// start of the day containing `ts` (UTC or local midnight)
+fn session_start(ts: u64, local_day: bool) -> u64 {
+    if !local_day {
+        return ts - ts % 86_400;
     }
+    Local
+        .timestamp_opt(ts as i64, 0)
+        .single()
+        .and_then(|dt| dt.date_naive().and_hms_opt(0, 0, 0))
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .map(|dt| dt.timestamp().max(0) as u64)
+        .unwrap_or(ts - ts % 86_400)
+}
 
-    fn replay_series<'a>(&self, snap: &'a Snapshot) -> &'a Vec<Candle> {
-        if let Some(series) = snap.candles_by_tf.get(&self.chart.selected_tf) {
-            series
-        } else if let Some(series) = snap.candles_by_tf.get(&60) {
-            // fallback: 1m
-            series
-        } else {
-            // extremely degenerate case, but type needs something
-            static EMPTY: Vec<Candle> = Vec::new();
-            &EMPTY
-        }
+// [lo, hi] egui_plot can draw: non-finite ends give `fallback`, swapped ends
+// are put back in order and a range under `min_span` opens up around its middle
+fn sane_range(lo: f64, hi: f64, min_span: f64, fallback: (f64, f64)) -> (f64, f64) {
+    if !lo.is_finite() || !hi.is_finite() || !(hi - lo).is_finite() {
+        return fallback;
+    }
+    let (lo, hi) = (lo.min(hi), lo.max(hi));
+    if hi - lo >= min_span {
+        return (lo, hi);
     }
+    let mid = lo * 0.5 + hi * 0.5;
+    (mid - min_span * 0.5, mid + min_span * 0.5)
+}
 
-    fn tick_live(&mut self) {
-        if self.live_book_rx.has_changed().unwrap_or(false) {
-            self.live_book = self.live_book_rx.borrow().clone();
-        }
+fn plot_bounds(x: (f64, f64), y: (f64, f64)) -> PlotBounds {
+    let (x0, x1) = sane_range(x.0, x.1, 1e-9, (0.0, 1.0));
+    let (y0, y1) = sane_range(y.0, y.1, 1e-9, (0.0, 1.0));
+    PlotBounds::from_min_max([x0, y0], [x1, y1])
+}
 
-        let ts = now_unix();
-        self.live_last_ts = ts;
+// auto-Y: low..high of `candles` with 5% padding, at least 0.1% of the price
+// tall so a flat run still gets a view; 0..1 when no price is finite
+fn candle_y_range(candles: &[Candle]) -> (f64, f64) {
+    let (lo, hi) = candles
+        .iter()
+        .flat_map(|c| [c.low, c.high])
+        .filter(|p| p.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| {
+            (lo.min(p), hi.max(p))
+        });
+    if lo > hi {
+        return (0.0, 1.0);
+    }
+    let pad = (hi - lo) * 0.05;
+    let min_span = (hi.abs() * 1e-3).max(1e-3);
+    sane_range(lo - pad, hi + pad, min_span, (0.0, 1.0))
+}
 
-        if let Some(mid) = self.live_book.mid() {
-            let vol = 0.0; // placeholder volume for now
+// the candle in effect at `ts`: the last one opened at or before it
+fn candle_at(candles: &[Candle], ts: u64) -> Option<&Candle> {
+    let i = candles.partition_point(|c| c.t <= ts);
+    i.checked_sub(1).map(|i| &candles[i])
+}
 
-            for agg in self.live_candles.values_mut() {
-                agg.update(ts, mid, vol);
-            }
+// (open, high, low) of the session containing `ts`. If the data starts
+// mid-session, the earliest available candle is used as the open.
+fn session_ohl(candles: &[Candle], ts: u64, local_day: bool) -> Option<(f64, f64, f64)> {
+    let start = session_start(ts, local_day);
+    let mut in_session = candles.iter().filter(|c| c.t >= start && c.t <= ts);
+    let first = in_session.next()?;
+    let (mut high, mut low) = (first.high, first.low);
+    for c in in_session {
+        high = high.max(c.high);
+        low = low.min(c.low);
+    }
+    Some((first.open, high, low))
+}
+
+// volume-by-price for the given candles: bucket index (price / bucket) -> volume.
+// trades_{ticker}.csv has no fill price, so each candle's volume is put at its
+// typical price (h + l + c) / 3.
+fn volume_profile(candles: &[Candle], bucket: f64) -> BTreeMap<i64, f64> {
+    let mut out = BTreeMap::new();
+    if bucket <= 0.0 {
+        return out;
+    }
+    for c in candles {
+        if c.volume <= 0.0 {
+            continue;
         }
+        let typical = (c.high + c.low + c.close) / 3.0;
+        let key = (typical / bucket).floor() as i64;
+        *out.entry(key).or_insert(0.0) += c.volume;
     }
+    out
+}
 
-    fn ensure_replay_ts_in_range(&mut self) {
-        let (min_ts, max_ts) = match self.replay_data.get(&self.current_ticker) {
-            Some(td) => (td.min_ts, td.max_ts),
-            None => return,
-        };
+// ------------- footprint candles -------------
 
-        if self.replay_ts < min_ts {
-            self.replay_ts = min_ts;
-        }
-        if self.replay_ts > max_ts {
-            self.replay_ts = max_ts;
+// below this many screen px per candle / per price row the numbers overlap,
+// so the chart keeps plain candles
+const FOOTPRINT_MIN_CANDLE_PX: f64 = 90.0;
+const FOOTPRINT_MIN_ROW_PX: f64 = 14.0;
+
+// `dpos` is screen px per plot unit (x, y), `row_h` a row's height in plot units
+fn footprint_readable(dpos: [f64; 2], tf: f64, row_h: f64) -> bool {
+    dpos[0].abs() * tf >= FOOTPRINT_MIN_CANDLE_PX && dpos[1].abs() * row_h >= FOOTPRINT_MIN_ROW_PX
+}
+
+// ------------- chart drawings -------------
+
+const DRAWING_COLOR: Color32 = Color32::from_rgb(80, 200, 255);
+// click tolerance for picking a drawing, as a fraction of the visible x / y span
+const DRAW_HIT_FRAC: f64 = 0.015;
+// y snaps to the candle's O/H/L/C when within this fraction of the y span
+const DRAW_SNAP_FRAC: f64 = 0.02;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DrawTool {
+    Select,
+    Line,
+    HLine,
+    Delete,
+}
+
+impl DrawTool {
+    fn label(&self) -> &'static str {
+        match self {
+            DrawTool::Select => "Select",
+            DrawTool::Line => "Trendline",
+            DrawTool::HLine => "H-ray",
+            DrawTool::Delete => "Delete",
         }
     }
 
-    fn ui_top_bar(&mut self, ui: &mut egui::Ui) {
-        ui.horizontal(|ui| {
-            // mode
-            ui.label("Mode:");
-            if ui
-                .selectable_label(self.mode == Mode::Live, "Live")
-                .clicked()
-            {
-                self.mode = Mode::Live;
-            }
-            if ui
-                .selectable_label(self.mode == Mode::Replay, "Replay")
-                .clicked()
-            {
-                self.mode = Mode::Replay;
-            }
+    fn all() -> [DrawTool; 4] {
+        [
+            DrawTool::Select,
+            DrawTool::Line,
+            DrawTool::HLine,
+            DrawTool::Delete,
+        ]
+    }
+}
 
-            ui.separator();
+// plot coordinates: x = unix secs, y = price
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Drawing {
+    Trend { a: [f64; 2], b: [f64; 2] },
+    // horizontal ray from x to the right edge of the chart
+    HRay { x: f64, y: f64 },
+}
 
-            // ticker menu
-            let tickers = self.tickers.clone();
-            ui.menu_button(format!("Ticker: {}", self.current_ticker), |ui| {
-                for t in &tickers {
-                    let selected = *t == self.current_ticker;
-                    if ui.selectable_label(selected, t).clicked() {
-                        self.current_ticker = t.clone();
+fn drawings_path(ticker: &str) -> PathBuf {
+    data_dir().join(format!("drawings_{ticker}.json"))
+}
 
-                        // notify live feed task
-                        let _ = self.ticker_tx.send(t.clone());
+fn load_drawings(path: &Path) -> Vec<Drawing> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
 
-                        // adjust replay ts to end of range for that ticker (if exists)
-                        if let Some(td) = self.replay_data.get(t) {
-                            self.replay_ts = td.max_ts;
-                        }
+// ------------- per-ticker workspace -------------
 
-                        ui.close_menu();
-                    }
-                }
-            });
+// edits are written once they have been quiet this long
+const WORKSPACE_SAVE_DELAY: Duration = Duration::from_secs(2);
+const BOOKMARK_COLOR: Color32 = Color32::from_rgb(200, 160, 255);
+const ALERT_COLOR: Color32 = Color32::from_rgb(255, 180, 0);
 
-            ui.separator();
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Bookmark {
+    ts: u64,
+    label: String,
+}
 
-            // time display
-            ui.label("Time:");
-            for mode in [TimeDisplayMode::Local, TimeDisplayMode::Unix] {
-                if ui
-                    .selectable_label(self.time_mode == mode, mode.label())
-                    .clicked()
-                {
-                    self.time_mode = mode;
-                }
-            }
+// on the ticker's live mid; edge-triggered like full_gui_x15's alerts: fires
+// when the mid reaches the price, re-arms once it is back on the other side
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct PriceAlert {
+    above: bool,
+    price: f64,
+    #[serde(skip)]
+    triggered: bool,
+}
 
-            if let Some(td) = self.current_replay_ticker() {
-                ui.separator();
-                ui.label(format!(
-                    "Replay range: {} → {}",
-                    format_ts(self.time_mode, td.min_ts),
-                    format_ts(self.time_mode, td.max_ts)
-                ));
-                ui.separator();
-                ui.label(format!(
-                    "Replay ts: {}",
-                    format_ts(self.time_mode, self.replay_ts)
-                ));
-            }
+impl PriceAlert {
+    fn op(&self) -> &'static str {
+        if self.above {
+            "≥"
+        } else {
+            "≤"
+        }
+    }
 
-            if matches!(self.mode, Mode::Live) {
-                ui.separator();
-                ui.label(format!(
-                    "Live ts: {}",
-                    format_ts(self.time_mode, self.live_last_ts)
-                ));
-            }
-        });
+    fn holds(&self, mid: f64) -> bool {
+        if self.above {
+            mid >= self.price
+        } else {
+            mid <= self.price
+        }
+    }
+}
 
-        ui.separator();
+// one market's annotations, bookmarks sorted by ts
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Workspace {
+    drawings: Vec<Drawing>,
+    bookmarks: Vec<Bookmark>,
+    alerts: Vec<PriceAlert>,
+}
 
-        // replay-only time slider
-        if matches!(self.mode, Mode::Replay) {
-            if let Some(td) = self.current_replay_ticker() {
-                let mut ts = self.replay_ts;
-                ui.horizontal(|ui| {
-                    ui.label("Replay time:");
-                    ui.add(
-                        egui::Slider::new(&mut ts, td.min_ts..=td.max_ts)
-                            .show_value(false)
-                            .text("ts"),
-                    );
-                    if ui.button("◀").clicked() && ts > td.min_ts {
-                        ts -= 1;
-                    }
-                    if ui.button("▶").clicked() && ts < td.max_ts {
-                        ts += 1;
-                    }
-                    if ui.button("Now").clicked() {
-                        ts = td.max_ts;
-                    }
-                    ui.label(format_ts(self.time_mode, ts));
-                });
-                self.replay_ts = ts;
-            } else {
-                ui.label("No replay CSV for this ticker.");
+impl Workspace {
+    // one bookmark per ts: re-adding at the same time just relabels it
+    fn add_bookmark(&mut self, ts: u64, label: String) {
+        match self.bookmarks.binary_search_by_key(&ts, |b| b.ts) {
+            Ok(i) => self.bookmarks[i].label = label,
+            Err(i) => self.bookmarks.insert(i, Bookmark { ts, label }),
+        }
+    }
+
+    fn prev_bookmark(&self, ts: u64) -> Option<u64> {
+        self.bookmarks
+            .iter()
+            .rev()
+            .find(|b| b.ts < ts)
+            .map(|b| b.ts)
+    }
+
+    fn next_bookmark(&self, ts: u64) -> Option<u64> {
+        self.bookmarks.iter().find(|b| b.ts > ts).map(|b| b.ts)
+    }
+
+    // the alerts that fire at `mid`
+    fn check_alerts(&mut self, mid: f64) -> Vec<PriceAlert> {
+        let mut fired = Vec::new();
+        for a in &mut self.alerts {
+            let holds = a.holds(mid);
+            if holds && !a.triggered {
+                fired.push(a.clone());
             }
+            a.triggered = holds;
+        }
+        fired
+    }
+}
 
-            ui.separator();
+fn workspace_path(ticker: &str) -> PathBuf {
+    data_dir().join(format!("workspace_{ticker}.json"))
+}
+
+// a ticker without a workspace file yet starts from its old drawings file
+fn load_workspace(path: &Path, legacy_drawings: &Path) -> Workspace {
+    let loaded = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Workspace>(&s).ok());
+    match loaded {
+        Some(mut ws) => {
+            ws.bookmarks.sort_by_key(|b| b.ts);
+            ws
         }
+        None => Workspace {
+            drawings: load_drawings(legacy_drawings),
+            ..Workspace::default()
+        },
+    }
+}
 
-        // shared chart controls
-        ui.horizontal(|ui| {
-            ui.label("History candles:");
-            ui.add(
-                egui::Slider::new(&mut self.chart.show_candles, 20..=1000)
-                    .logarithmic(true),
-            );
+fn save_workspace(path: &Path, ws: &Workspace) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(ws).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
 
-            ui.separator();
-            ui.label("X zoom:");
-            ui.add(
-                egui::Slider::new(&mut self.chart.x_zoom, 0.25..=4.0)
-                    .logarithmic(true)
-                    .text("zoom"),
-            );
+// ------------- chart export -------------
 
-            ui.horizontal(|ui| {
-                if ui.button("← Pan").clicked() {
-                    self.chart.x_pan_secs -= self.chart.selected_tf as f64 * 10.0;
-                }
-                if ui.button("Pan →").clicked() {
-                    self.chart.x_pan_secs += self.chart.selected_tf as f64 * 10.0;
-                }
-                if ui.button("Center").clicked() {
-                    self.chart.x_pan_secs = 0.0;
-                }
-            });
+fn chart_png_path(ticker: &str, tf: u64, ts: u64) -> PathBuf {
+    data_dir().join(format!("chart_{ticker}_{}_{ts}.png", tf_label(tf)))
+}
 
-            ui.separator();
-            ui.label("TF:");
-            egui::ComboBox::from_id_source("tf_combo")
-                .selected_text(tf_label(self.chart.selected_tf))
-                .show_ui(ui, |ui| {
-                    for tf in TF_CHOICES {
-                        ui.selectable_value(
-                            &mut self.chart.selected_tf,
-                            *tf,
-                            tf_label(*tf),
-                        );
-                    }
-                });
+fn chart_popout_id() -> egui::ViewportId {
+    egui::ViewportId::from_hash_of("chart_popout")
+}
 
-            ui.separator();
-            ui.checkbox(&mut self.chart.auto_y, "Auto Y");
+// `rect` (points) out of a viewport screenshot, clipped to the image; None
+// when none of it is on screen
+fn crop_screenshot(
+    shot: &egui::ColorImage,
+    rect: egui::Rect,
+    pixels_per_point: f32,
+) -> Option<egui::ColorImage> {
+    let [w, h] = shot.size;
+    let screen = egui::Rect::from_min_size(
+        egui::Pos2::ZERO,
+        egui::vec2(w as f32, h as f32) / pixels_per_point,
+    );
+    let rect = rect.intersect(screen);
+    rect.is_positive()
+        .then(|| shot.region(&rect, Some(pixels_per_point)))
+}
 
-            if !self.chart.auto_y {
-                ui.label("Y range:");
-                ui.add(
-                    egui::DragValue::new(&mut self.chart.y_min)
-                        .speed(1.0)
-                        .prefix("min "),
-                );
-                ui.add(
-                    egui::DragValue::new(&mut self.chart.y_max)
-                        .speed(1.0)
-                        .prefix("max "),
-                );
-                if ui.button("Reset Y").clicked() {
-                    self.chart.auto_y = true;
-                }
-            }
-        });
+fn save_png(path: &Path, shot: &egui::ColorImage) -> Result<(), String> {
+    let rgba: Vec<u8> = shot
+        .pixels
+        .iter()
+        .flat_map(|c| c.to_srgba_unmultiplied())
+        .collect();
+    let [w, h] = shot.size;
+    image::save_buffer(path, &rgba, w as u32, h as u32, image::ColorType::Rgba8)
+        .map_err(|e| e.to_string())
+}
 
-        ui.separator();
+// x to the middle of its candle bucket (where bodies are drawn); y to that
+// candle's nearest O/H/L/C when within `y_tol`, otherwise left as clicked
+fn snap_point(p: [f64; 2], tf: f64, candles: &[Candle], y_tol: f64) -> [f64; 2] {
+    let bucket = (p[0] / tf).floor() * tf;
+    let y = candle_at(candles, bucket.max(0.0) as u64)
+        .filter(|c| c.t as f64 == bucket)
+        .and_then(|c| {
+            [c.open, c.high, c.low, c.close]
+                .into_iter()
+                .min_by(|a, b| (a - p[1]).abs().total_cmp(&(b - p[1]).abs()))
+        })
+        .filter(|y| (y - p[1]).abs() <= y_tol)
+        .unwrap_or(p[1]);
+    [bucket + tf * 0.5, y]
+}
 
-        // Layout & appearance tweaks
-        egui::CollapsingHeader::new("Layout & appearance")
-            .default_open(false)
-            .show(ui, |ui| {
-                ui.label("Layout");
-                ui.add(
-                    egui::Slider::new(
-                        &mut self.layout.ladders_height_ratio,
-                        0.2..=0.6,
-                    )
-                    .text("Ladders/trading height"),
-                );
-                ui.add(
-                    egui::Slider::new(&mut self.layout.depth_width_ratio, 0.25..=0.7)
-                        .text("Depth width"),
-                );
-                ui.add(
-                    egui::Slider::new(
-                        &mut self.layout.volume_height_ratio,
-                        0.15..=0.6,
-                    )
-                    .text("Volume height (vs candles)"),
-                );
-                ui.add(
-                    egui::Slider::new(
-                        &mut self.layout.candle_body_width_factor,
-                        0.3..=1.0,
-                    )
-                    .text("Candle body width"),
-                );
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    ((p[0] - a[0] - t * dx).powi(2) + (p[1] - a[1] - t * dy).powi(2)).sqrt()
+}
 
-                ui.separator();
-                ui.label("Colors");
-                ui.horizontal(|ui| {
-                    ui.label("Bull:");
-                    ui.color_edit_button_srgba(&mut self.appearance.bull_color);
-                    ui.label("Bear:");
-                    ui.color_edit_button_srgba(&mut self.appearance.bear_color);
-                    ui.label("Volume:");
-                    ui.color_edit_button_srgba(&mut self.appearance.volume_color);
-                });
-            });
+// distance in view fractions, so x (secs) and y (price) weigh the same
+fn drawing_distance(d: &Drawing, p: [f64; 2], x_span: f64, y_span: f64) -> f64 {
+    let norm = |q: [f64; 2]| [q[0] / x_span.max(1e-9), q[1] / y_span.max(1e-9)];
+    let p = norm(p);
+    match d {
+        Drawing::Trend { a, b } => segment_distance(p, norm(*a), norm(*b)),
+        Drawing::HRay { x, y } => {
+            let start = norm([*x, *y]);
+            if p[0] >= start[0] {
+                (p[1] - start[1]).abs()
+            } else {
+                segment_distance(p, start, start)
+            }
+        }
+    }
+}
 
-        ui.separator();
+fn nearest_drawing(drawings: &[Drawing], p: [f64; 2], x_span: f64, y_span: f64) -> Option<usize> {
+    drawings
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (i, drawing_distance(d, p, x_span, y_span)))
+        .filter(|(_, dist)| *dist <= DRAW_HIT_FRAC)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
 
-        // replay sub-tab selector (only in replay mode)
-        if matches!(self.mode, Mode::Replay) {
-            ui.horizontal(|ui| {
-                ui.selectable_value(
-                    &mut self.replay_tab,
-                    ReplayTab::Orderbook,
-                    "Orderbook + Trades",
-                );
-                ui.selectable_value(
-                    &mut self.replay_tab,
-                    ReplayTab::Candles,
-                    "Candles + Volume",
-                );
-            });
-            ui.separator();
+// ------------- undo / redo -------------
+
+const UNDO_DEPTH: usize = 100;
+
+// chart overlays that Ctrl+Z covers; zoom, pan and TF are navigation, not edits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PanelToggles {
+    trade_markers: bool,
+    scale_trade_markers: bool,
+    patterns: bool,
+    spread: bool,
+    break_at_gaps: bool,
+    volume_profile: bool,
+    footprint: bool,
+    footprint_row_ticks: i64,
+    session_lines: bool,
+    pnl_hud: bool,
+    pnl_hud_corner: HudCorner,
+}
+
+impl PanelToggles {
+    fn of(c: &ChartSettings) -> Self {
+        Self {
+            trade_markers: c.show_trade_markers,
+            scale_trade_markers: c.scale_trade_markers,
+            patterns: c.show_patterns,
+            spread: c.show_spread,
+            break_at_gaps: c.break_at_gaps,
+            volume_profile: c.show_volume_profile,
+            footprint: c.show_footprint,
+            footprint_row_ticks: c.footprint_row_ticks,
+            session_lines: c.show_session_lines,
+            pnl_hud: c.show_pnl_hud,
+            pnl_hud_corner: c.pnl_hud_corner,
         }
     }
 
-    // ---- LIVE UI ----
+    fn apply(&self, c: &mut ChartSettings) {
+        c.show_trade_markers = self.trade_markers;
+        c.scale_trade_markers = self.scale_trade_markers;
+        c.show_patterns = self.patterns;
+        c.show_spread = self.spread;
+        c.break_at_gaps = self.break_at_gaps;
+        c.show_volume_profile = self.volume_profile;
+        c.show_footprint = self.footprint;
+        c.footprint_row_ticks = self.footprint_row_ticks;
+        c.show_session_lines = self.session_lines;
+        c.show_pnl_hud = self.pnl_hud;
+        c.pnl_hud_corner = self.pnl_hud_corner;
+    }
+}
 
-    fn ui_live(&mut self, ui: &mut egui::Ui) {
-        let series_vec = self.live_series();
-        let avail_w = ui.available_width();
-        let avail_h = ui.available_height();
+// Layout and annotation state, one undo step's worth. Market data is never
+// part of it.
+#[derive(Clone, PartialEq)]
+struct UndoState {
+    layout: LayoutSettings,
+    appearance: AppearanceSettings,
+    panels: PanelToggles,
+    // drawings are per ticker; this is whose they are
+    ticker: String,
+    drawings: Vec<Drawing>,
+}
 
-        ui.heading(format!("LIVE {}", self.current_ticker));
-        ui.separator();
+// Bounded undo/redo over whole-state snapshots. The app hands over the
+// state once per settled frame; any difference from the last one becomes an
+// undo step, so a slider drag or a two-click trendline is a single step.
+#[derive(Default)]
+struct UndoHistory {
+    current: Option<UndoState>,
+    undo: VecDeque<UndoState>,
+    redo: Vec<UndoState>,
+}
 
-        let ladders_h = avail_h * self.layout.ladders_height_ratio;
+impl UndoHistory {
+    fn observe(&mut self, state: UndoState) {
+        let Some(current) = self.current.take() else {
+            self.current = Some(state);
+            return;
+        };
+        // switching tickers shows other drawings; it is not an edit
+        if current != state && current.ticker == state.ticker {
+            if self.undo.len() == UNDO_DEPTH {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(current);
+            self.redo.clear();
+        }
+        self.current = Some(state);
+    }
 
-        ui.allocate_ui(egui::vec2(avail_w, ladders_h), |ui| {
-            let left_w = avail_w * self.layout.depth_width_ratio;
-            let right_w = avail_w - left_w;
+    /// State to restore, if there is an earlier one.
+    fn undo(&mut self) -> Option<UndoState> {
+        let prev = self.undo.pop_back()?;
+        if let Some(current) = self.current.replace(prev.clone()) {
+            self.redo.push(current);
+        }
+        Some(prev)
+    }
 
-            ui.horizontal(|ui| {
-                // depth
-                ui.allocate_ui(egui::vec2(left_w, ladders_h), |ui| {
-                    let mut bid_points = Vec::new();
-                    let mut ask_points = Vec::new();
-
-                    let mut cum = 0.0;
-                    for (k, s) in self.live_book.bids.iter().rev() {
-                        let p = key_to_price(*k);
-                        cum += s;
-                        bid_points.push((p, cum));
-                    }
+    fn redo(&mut self) -> Option<UndoState> {
+        let next = self.redo.pop()?;
+        if let Some(current) = self.current.replace(next.clone()) {
+            self.undo.push_back(current);
+        }
+        Some(next)
+    }
+}
 
-                    cum = 0.0;
-                    for (k, s) in self.live_book.asks.iter() {
-                        let p = key_to_price(*k);
-                        cum += s;
-                        ask_points.push((p, cum));
-                    }
+// ------------- depth plot -------------
 
-                    Plot::new("live_depth")
-                        .height(ladders_h * 0.9)
-                        .show(ui, |plot_ui| {
-                            if !bid_points.is_empty() {
-                                let pts: PlotPoints = bid_points
-                                    .iter()
-                                    .map(|(x, y)| [*x, *y])
-                                    .collect::<Vec<_>>()
-                                    .into();
-                                plot_ui.line(Line::new(pts).name("Bids"));
-                            }
-                            if !ask_points.is_empty() {
-                                let pts: PlotPoints = ask_points
-                                    .iter()
-                                    .map(|(x, y)| [*x, *y])
-                                    .collect::<Vec<_>>()
-                                    .into();
-                                plot_ui.line(Line::new(pts).name("Asks"));
-                            }
-                        });
-                });
+// Cumulative depth curve; with `fill` also shades down to zero size. Polygons
+// must be convex, so the area goes in as one trapezoid per level step.
+fn plot_depth_side(plot_ui: &mut PlotUi, points: &[(f64, f64)], name: &str, fill: Option<Color32>) {
+    if points.is_empty() {
+        return;
+    }
+    let pts: PlotPoints = points
+        .iter()
+        .map(|(x, y)| [*x, *y])
+        .collect::<Vec<_>>()
+        .into();
+
+    match fill {
+        Some(color) => {
+            let shade = Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), 50);
+            for w in points.windows(2) {
+                let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+                let area: PlotPoints = vec![[x0, 0.0], [x0, y0], [x1, y1], [x1, 0.0]].into();
+                plot_ui.polygon(Polygon::new(area).fill_color(shade).stroke(Stroke::NONE));
+            }
+            plot_ui.line(Line::new(pts).color(color).name(name));
+        }
+        None => plot_ui.line(Line::new(pts).name(name)),
+    }
+}
 
-                ui.separator();
+// ------------- ladder walls -------------
+
+// levels on each side a ladder level is compared against
+const WALL_WINDOW: usize = 10;
+const WALL_BG: Color32 = Color32::from_rgba_premultiplied(90, 70, 0, 140);
+// my resting orders in the ladder's Mine column
+const MY_ORDER_COLOR: Color32 = Color32::from_rgb(240, 200, 60);
+// just-updated level, blended toward the normal background as it ages
+const FLASH_BG: Color32 = Color32::from_rgba_premultiplied(70, 150, 220, 200);
+
+fn blend(from: Color32, to: Color32, t: f32) -> Color32 {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgba_premultiplied(
+        mix(from.r(), to.r()),
+        mix(from.g(), to.g()),
+        mix(from.b(), to.b()),
+        mix(from.a(), to.a()),
+    )
+}
 
-                // RIGHT: trading panel (top) + ladders (scroll below)
-                ui.allocate_ui(egui::vec2(right_w, ladders_h), |ui| {
-                    ui.vertical(|ui| {
-                        // --- TRADING PANEL (always visible) ---
-                        ui.group(|ui| {
-                            ui.heading("TRADING PANEL (LIVE)");
+/// Keys whose size exceeds mean + `threshold`·σ of the `window` levels on
+/// either side of them (the level itself excluded). Sorted ascending.
+fn detect_walls(map: &BTreeMap<PriceKey, f64>, window: usize, threshold: f64) -> Vec<PriceKey> {
+    let levels: Vec<(PriceKey, f64)> = map.iter().map(|(k, s)| (*k, *s)).collect();
+    let mut walls = Vec::new();
+
+    for (i, (key, size)) in levels.iter().enumerate() {
+        let lo = i.saturating_sub(window);
+        let hi = (i + window + 1).min(levels.len());
+        let nearby: Vec<f64> = (lo..hi).filter(|j| *j != i).map(|j| levels[j].1).collect();
+        if nearby.len() < 2 {
+            continue;
+        }
+        let n = nearby.len() as f64;
+        let mean = nearby.iter().sum::<f64>() / n;
+        let var = nearby.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+        if *size > mean + threshold * var.sqrt() && *size > mean {
+            walls.push(*key);
+        }
+    }
 
-                            ui.label("Requires DYDX_TESTNET_MNEMONIC in your shell.");
+    walls
+}
 
-                            // order type + leverage row
-                            ui.horizontal(|ui| {
-                                ui.label("Order type:");
-                                for ot in [UiOrderType::Market, UiOrderType::Limit] {
-                                    if ui
-                                        .selectable_label(
-                                            self.ui_order_type == ot,
-                                            ot.label(),
-                                        )
-                                        .clicked()
-                                    {
-                                        self.ui_order_type = ot;
-                                    }
-                                }
+// ------------- market precision -------------
 
-                                ui.separator();
+// tick and step size from the indexer market metadata; zero means unknown
+// and the formatters fall back to fixed decimals
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct MarketPrecision {
+    tick_size: f64,
+    step_size: f64,
+}
 
-                                ui.label("Leverage (UI only):");
+impl MarketPrecision {
+    fn price(&self, price: f64) -> String {
+        fmt_price(price, self.tick_size)
+    }
+
+    fn size(&self, size: f64) -> String {
+        fmt_size(size, self.step_size)
+    }
+}
+
+// per-ticker cache, filled by the feeds whenever they look up a market
+static MARKET_PRECISION: OnceLock<Mutex<HashMap<String, MarketPrecision>>> = OnceLock::new();
+
+fn precision_cache() -> &'static Mutex<HashMap<String, MarketPrecision>> {
+    MARKET_PRECISION.get_or_init(Default::default)
+}
+
+fn market_precision(ticker: &str) -> MarketPrecision {
+    precision_cache()
+        .lock()
+        .ok()
+        .and_then(|c| c.get(ticker).copied())
+        .unwrap_or_default()
+}
+
+fn cache_market_precision(ticker: &str, market: &PerpetualMarket) {
+    let prec = MarketPrecision {
+        tick_size: market.tick_size.to_f64().unwrap_or(0.0),
+        step_size: market.step_size.to_f64().unwrap_or(0.0),
+    };
+    if let Ok(mut c) = precision_cache().lock() {
+        c.insert(ticker.to_string(), prec);
+    }
+}
+
+// one ladder level with the running totals from the best price out to it
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DepthLevel {
+    key: PriceKey,
+    price: f64,
+    size: f64,
+    cum_size: f64,
+    cum_notional: f64,
+}
+
+/// Levels in the order given (best first) with running size and notional;
+/// the depth plot and the ladder columns read the same totals.
+fn accumulate<'a>(
+    levels: impl Iterator<Item = (&'a PriceKey, &'a f64)> + 'a,
+    scale: f64,
+) -> impl Iterator<Item = DepthLevel> + 'a {
+    levels.scan((0.0, 0.0), move |cum, (k, s)| {
+        let price = key_to_price(*k, scale);
+        cum.0 += s;
+        cum.1 += price * s;
+        Some(DepthLevel {
+            key: *k,
+            price,
+            size: *s,
+            cum_size: cum.0,
+            cum_notional: cum.1,
+        })
+    })
+}
+
+fn depth_points(levels: &[DepthLevel]) -> Vec<(f64, f64)> {
+    levels.iter().map(|l| (l.price, l.cum_size)).collect()
+}
+
+// which columns a ladder grid shows after price and size
+#[derive(Clone, Copy, Debug, Default)]
+struct LadderCols {
+    prec: MarketPrecision,
+    cum_size: bool,
+    cum_notional: bool,
+}
+
+impl LadderCols {
+    fn header(&self, ui: &mut egui::Ui) {
+        ui.label("Price");
+        ui.label("Size");
+        if self.cum_size {
+            ui.label("Cum");
+        }
+        if self.cum_notional {
+            ui.label("Cum $");
+        }
+        ui.end_row();
+    }
+}
+
+// `flash` is the level's flash_strength, 0 for a plain row; true when any
+// cell of the row was clicked
+fn ladder_row(
+    ui: &mut egui::Ui,
+    level: &DepthLevel,
+    is_wall: bool,
+    flash: f32,
+    cols: LadderCols,
+) -> bool {
+    let prec = cols.prec;
+    let mut cells = vec![
+        egui::RichText::new(format!("{:>9}", prec.price(level.price))),
+        egui::RichText::new(format!("{:>8}", prec.size(level.size))),
+    ];
+    if cols.cum_size {
+        cells.push(egui::RichText::new(format!("{:>9}", prec.size(level.cum_size))).weak());
+    }
+    if cols.cum_notional {
+        cells.push(egui::RichText::new(format!("{:>10.0}", level.cum_notional)).weak());
+    }
+    let base = if is_wall {
+        WALL_BG
+    } else {
+        Color32::TRANSPARENT
+    };
+    let bg = blend(base, FLASH_BG, flash);
+    let mut clicked = false;
+    for mut cell in cells {
+        if is_wall {
+            cell = cell.strong();
+        }
+        if bg != Color32::TRANSPARENT {
+            cell = cell.background_color(bg);
+        }
+        clicked |= ui
+            .add(egui::Label::new(cell).sense(egui::Sense::click()))
+            .clicked();
+    }
+    ui.end_row();
+    clicked
+}
+
+/// Side a click on a ladder level suggests: join it (bid -> buy, ask ->
+/// sell) or, with `takes`, trade against it.
+fn ladder_click_side(is_bid: bool, takes: bool) -> OrderSide {
+    if is_bid != takes {
+        OrderSide::Buy
+    } else {
+        OrderSide::Sell
+    }
+}
+
+// asks above bids, best prices meeting in the middle; each side comes with
+// its sorted wall keys
+fn stacked_ladder(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    bids: (&BTreeMap<PriceKey, f64>, &[PriceKey]),
+    asks: (&BTreeMap<PriceKey, f64>, &[PriceKey]),
+    scale: f64,
+    rows: usize,
+    prec: MarketPrecision,
+) {
+    let cols = LadderCols {
+        prec,
+        ..LadderCols::default()
+    };
+    egui::Grid::new(id).striped(true).show(ui, |ui| {
+        let top_asks: Vec<_> = accumulate(asks.0.iter(), scale).take(rows).collect();
+        for l in top_asks.iter().rev() {
+            ladder_row(ui, l, asks.1.binary_search(&l.key).is_ok(), 0.0, cols);
+        }
+        ui.label("---");
+        ui.label("---");
+        ui.end_row();
+        for l in accumulate(bids.0.iter().rev(), scale).take(rows) {
+            ladder_row(ui, &l, bids.1.binary_search(&l.key).is_ok(), 0.0, cols);
+        }
+    });
+}
+
+// ------------- ladder binning -------------
+
+// rows per column in the multi-resolution ladder
+const MULTI_RES_ROWS: usize = 10;
+
+/// Sums levels into buckets `width` keys wide. Bids round down and asks up,
+/// so a bucket never crosses to the other side of the spread.
+fn bin_levels(
+    levels: &BTreeMap<PriceKey, f64>,
+    width: PriceKey,
+    is_bid: bool,
+) -> BTreeMap<PriceKey, f64> {
+    let width = width.max(1);
+    let mut out = BTreeMap::new();
+    for (k, s) in levels {
+        let floor = k.div_euclid(width) * width;
+        let bucket = if is_bid || floor == *k {
+            floor
+        } else {
+            floor + width
+        };
+        *out.entry(bucket).or_insert(0.0) += s;
+    }
+    out
+}
+
+// "1, 10,100" -> [1, 10, 100]; zeros and junk are dropped
+fn parse_multiples(input: &str) -> Vec<u32> {
+    input
+        .split(',')
+        .filter_map(|m| m.trim().parse::<u32>().ok())
+        .filter(|m| *m > 0)
+        .collect()
+}
+
+// ------------- crypto provider -------------
+
+fn init_crypto_provider() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
+
+// ------------- logging -------------
+
+// fmt subscriber on stderr. RUST_LOG filters as usual (default `info`, e.g.
+// `RUST_LOG=full_gui11=debug,dydx=debug`); `json` emits one object per line.
+fn init_tracing(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        fmt.json().init();
+    } else {
+        fmt.init();
+    }
+}
+
+// `--log-json` or LOG_FORMAT=json
+fn log_json_from_args(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--log-json")
+        || env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
+// ------------- trade command (real orders) -------------
+
+#[derive(Debug)]
+enum TradeCmd {
+    MarketOrder {
+        ticker: String,
+        side: OrderSide,
+        size: BigDecimal,
+        tif: TimeInForce,
+        reduce_only: bool,
+    },
+    // limit order from a one-click ladder row, resting as long as `expiry` says
+    LimitOrder {
+        ticker: String,
+        side: OrderSide,
+        price: BigDecimal,
+        size: BigDecimal,
+        expiry: LimitExpiry,
+        tif: TimeInForce,
+        reduce_only: bool,
+    },
+    // reduce-only market order for the whole open position, sized at send time
+    Flatten {
+        ticker: String,
+    },
+    // cancel one of the resting orders the trader published
+    Cancel {
+        client_id: u32,
+    },
+    // emergency stop: cancel resting orders, flatten every open position.
+    // Only ever arrives on the kill channel, ahead of anything queued
+    Kill,
+    // trade from another subaccount of the same wallet from now on
+    SetSubaccount {
+        number: u32,
+    },
+}
+
+// ------------- trading identity -------------
+
+const MNEMONIC_ENV: &str = "DYDX_TESTNET_MNEMONIC";
+// subaccount at startup; the trading panel can switch to any parent subaccount
+const TRADING_SUBACCOUNT: u32 = 0;
+const MAX_PARENT_SUBACCOUNT: u32 = 127;
+
+// which account orders go out as, shown in the trading panel
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TraderIdentity {
+    NotSet,
+    Invalid(String),
+    Ready { address: String, subaccount: u32 },
+}
+
+impl TraderIdentity {
+    fn label(&self) -> String {
+        match self {
+            TraderIdentity::NotSet => format!("{MNEMONIC_ENV} not set; trading disabled"),
+            TraderIdentity::Invalid(e) => format!("mnemonic invalid: {e}"),
+            TraderIdentity::Ready {
+                address,
+                subaccount,
+            } => {
+                // bech32 is ascii, byte slicing is fine
+                let short = if address.len() > 16 {
+                    format!("{}…{}", &address[..10], &address[address.len() - 4..])
+                } else {
+                    address.clone()
+                };
+                format!("Trading as {short} (subaccount {subaccount})")
+            }
+        }
+    }
+}
+
+// Derives the wallet and its address once, offline, so a bad mnemonic shows up
+// in the UI before any order. The wallet goes on to the trader task.
+fn derive_wallet(raw: Option<String>) -> (Option<Wallet>, TraderIdentity) {
+    let Some(raw) = raw else {
+        return (None, TraderIdentity::NotSet);
+    };
+    let mnemonic = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let derived = Wallet::from_mnemonic(&mnemonic).and_then(|w| {
+        let address = w.account_offline(0)?.address().to_string();
+        Ok((w, address))
+    });
+    match derived {
+        Ok((wallet, address)) => (
+            Some(wallet),
+            TraderIdentity::Ready {
+                address,
+                subaccount: TRADING_SUBACCOUNT,
+            },
+        ),
+        Err(e) => (None, TraderIdentity::Invalid(e.to_string())),
+    }
+}
+
+// ------------- funding -------------
+
+// dYdX v4 perps settle funding every hour
+const FUNDING_PERIOD_SECS: u64 = 3_600;
+const FUNDING_REFRESH_SECS: u64 = 60;
+
+#[derive(Clone, Debug)]
+struct FundingInfo {
+    ticker: String,
+    // next funding rate per period, as a fraction (0.0001 = 0.01%)
+    rate: f64,
+}
+
+// ------------- order events (from trader task) -------------
+
+// extra place_order attempts after the first, for retryable errors only
+const ORDER_RETRIES: u32 = 2;
+// short-term limit orders lapse after this many blocks (the chain's maximum),
+// so a resting one-click order never outlives a KILL for long
+const LIMIT_ORDER_BLOCKS: u32 = 20;
+// long-term (good-til-time) limit orders: default and maximum lifetime; the
+// chain refuses expiries more than 95 days out
+const DEFAULT_LIMIT_GTT_MINS: u32 = 60;
+const MAX_LIMIT_GTT_MINS: u32 = 90 * 24 * 60;
+// market orders may fill this far (%) past the market's oracle price and no
+// further; `--max-slippage-pct P`
+const DEFAULT_MAX_SLIPPAGE_PCT: u32 = 1;
+
+fn max_slippage_pct_from_args(args: &[String]) -> BigDecimal {
+    let default = BigDecimal::from(DEFAULT_MAX_SLIPPAGE_PCT);
+    let Some(v) = arg_value(args.iter().cloned(), "--max-slippage-pct") else {
+        return default;
+    };
+    match BigDecimal::from_str(&v) {
+        Ok(pct) if pct > 0 && pct < 100 => pct,
+        _ => {
+            warn!("--max-slippage-pct: not a percentage in (0, 100): {v:?}; using {default}");
+            default
+        }
+    }
+}
+
+// reduce-only, and IOC so whatever the slippage bound can't fill is dropped
+// rather than left resting at the worst price
+fn close_order((ticker, side, size): (String, OrderSide, BigDecimal)) -> OrderReq {
+    (ticker, side, size, true, None, TimeInForce::Ioc)
+}
+
+// "45s", "30m", "2h 5m", "3d 4h": the two largest units of a duration
+fn fmt_duration_secs(secs: u64) -> String {
+    let (d, h, m) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    match (d, h, m) {
+        (0, 0, 0) => format!("{secs}s"),
+        (0, 0, m) => format!("{m}m"),
+        (0, h, 0) => format!("{h}h"),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, 0, _) => format!("{d}d"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+// how long a limit order rests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LimitExpiry {
+    // short-term: lapses LIMIT_ORDER_BLOCKS blocks after placing
+    Blocks,
+    // long-term (stateful) order good until `secs` after placing; cancels
+    // and KILL have to reach the chain to get rid of it
+    Time { secs: u64 },
+}
+
+impl LimitExpiry {
+    fn label(self) -> String {
+        match self {
+            LimitExpiry::Blocks => format!("IOC ({LIMIT_ORDER_BLOCKS} blocks)"),
+            LimitExpiry::Time { secs } => format!("GTT {}", fmt_duration_secs(secs)),
+        }
+    }
+
+    // expiry for an order placed at height `h` / wall time `now`
+    fn good_until(self, h: &Height, now: DateTime<Utc>) -> OrderGoodUntil {
+        match self {
+            LimitExpiry::Blocks => h.ahead(LIMIT_ORDER_BLOCKS).into(),
+            LimitExpiry::Time { secs } => {
+                (now + TimeDelta::seconds(secs.min(i64::MAX as u64) as i64)).into()
+            }
+        }
+    }
+}
+
+// true once an order good until `until` can no longer be resting
+fn good_until_lapsed(until: &OrderGoodUntil, h: &Height, now: DateTime<Utc>) -> bool {
+    match until {
+        OrderGoodUntil::Block(b) => b.0 < h.0,
+        OrderGoodUntil::Time(t) => *t < now,
+    }
+}
+// cosmos-sdk ErrWrongSequence
+const SEQUENCE_MISMATCH_CODE: u32 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlaceErrorKind {
+    // stale account sequence: re-sync it, then retry
+    Sequence,
+    // timeouts / node unreachable: retry as is
+    Transient,
+    // rejected by the chain (post-only would cross, undercollateralized, ...)
+    Fatal,
+}
+
+fn classify_place_error(e: &NodeError) -> PlaceErrorKind {
+    let (code, message) = match e {
+        NodeError::Broadcast(b) => (b.code, b.message.to_lowercase()),
+        NodeError::General(g) => (None, g.to_string().to_lowercase()),
+    };
+    if code == Some(SEQUENCE_MISMATCH_CODE) || message.contains("account sequence mismatch") {
+        return PlaceErrorKind::Sequence;
+    }
+    // a chain code means the tx got there and was judged; only bare transport
+    // failures are worth sending again
+    let transient = [
+        "timed out",
+        "timeout",
+        "deadline",
+        "unavailable",
+        "connection",
+    ];
+    if code.is_none() && transient.iter().any(|t| message.contains(t)) {
+        PlaceErrorKind::Transient
+    } else {
+        PlaceErrorKind::Fatal
+    }
+}
+
+// stage a failed place is reported under: post-only orders the chain
+// refused for crossing the book get their own, everything else is "place"
+fn place_fail_stage(e: &NodeError) -> &'static str {
+    let message = e.to_string().to_lowercase().replace(['-', '_', ' '], "");
+    if message.contains("postonly") && message.contains("cross") {
+        "post_only_would_cross"
+    } else {
+        "place"
+    }
+}
+
+// what happened to each submitted order, shown in the trading panel
+#[derive(Clone, Debug)]
+enum OrderEvent {
+    Placed {
+        ticker: String,
+        tx_hash: String,
+    },
+    Retrying {
+        ticker: String,
+        attempt: u32,
+        error: String,
+    },
+    Failed {
+        ticker: String,
+        stage: &'static str,
+        error: String,
+    },
+    Canceled {
+        ticker: String,
+        client_id: u32,
+    },
+}
+
+impl OrderEvent {
+    fn message(&self) -> String {
+        match self {
+            OrderEvent::Placed { ticker, tx_hash } => format!("{ticker}: placed ({tx_hash})"),
+            OrderEvent::Retrying {
+                ticker,
+                attempt,
+                error,
+            } => format!("{ticker}: retry {attempt}/{ORDER_RETRIES} after {error}"),
+            OrderEvent::Failed {
+                ticker,
+                stage,
+                error,
+            } => format!("{ticker}: FAILED at {stage}: {error}"),
+            OrderEvent::Canceled { ticker, client_id } => {
+                format!("{ticker}: cancel sent for order {client_id}")
+            }
+        }
+    }
+}
+
+// UI ends of the trader task: commands out, account, open orders and order
+// events back. KILL has its own channel so a full command queue can't hold it
+// up; the value is how many kills were asked for
+struct TraderLink {
+    trade_tx: mpsc::Sender<TradeCmd>,
+    kill_tx: watch::Sender<u64>,
+    account_rx: watch::Receiver<Option<AccountInfo>>,
+    open_orders_rx: watch::Receiver<Vec<OpenOrder>>,
+    order_event_rx: mpsc::Receiver<OrderEvent>,
+    identity: TraderIdentity,
+}
+
+// ------------- account (from trader task) -------------
+
+const ACCOUNT_REFRESH_SECS: u64 = 15;
+// after the window closes, how long an in-flight order / exit KILL gets
+const TRADER_SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+struct AccountInfo {
+    equity: f64,
+    free_collateral: f64,
+    updated_ts: u64,
+    // last fetch failed; values are from `updated_ts`
+    stale: bool,
+    // open perp positions by ticker
+    positions: HashMap<String, LivePosition>,
+}
+
+#[derive(Clone, Debug)]
+struct LivePosition {
+    long: bool,
+    size: f64,
+    entry_price: f64,
+    unrealized_pnl: f64,
+}
+
+impl AccountInfo {
+    fn from_subaccount(info: &SubaccountResponseObject) -> Self {
+        let positions = info
+            .open_perpetual_positions
+            .iter()
+            .map(|(tk, pos)| {
+                (
+                    tk.0.clone(),
+                    LivePosition {
+                        long: matches!(pos.side, PositionSide::Long),
+                        size: pos.size.0.abs().to_f64().unwrap_or(0.0),
+                        entry_price: pos.entry_price.0.to_f64().unwrap_or(0.0),
+                        unrealized_pnl: pos.unrealized_pnl.to_f64().unwrap_or(0.0),
+                    },
+                )
+            })
+            .collect();
+        Self {
+            equity: info.equity.to_f64().unwrap_or(0.0),
+            free_collateral: info.free_collateral.to_f64().unwrap_or(0.0),
+            updated_ts: now_unix(),
+            stale: false,
+            positions,
+        }
+    }
+
+    // estimated liquidation price of the `ticker` position, by the sim's
+    // maintenance equation: equity less the position's unrealized PnL is what
+    // it is worth at entry; other positions are held where they are
+    fn liquidation_price(&self, ticker: &str) -> Option<f64> {
+        let p = self.positions.get(ticker)?;
+        let at_entry = self.equity - p.unrealized_pnl;
+        liquidation_price(p.long, p.size, at_entry, p.entry_price, DEFAULT_MAINT_RATE)
+    }
+}
+
+// why a reduce-only `side` order can't go out against `account`'s position
+// in `ticker`; None when it only shrinks it, or when the account isn't known
+// yet (the chain still refuses one that would grow the position)
+fn reduce_only_rejection(
+    account: Option<&AccountInfo>,
+    ticker: &str,
+    side: OrderSide,
+) -> Option<String> {
+    let account = account?;
+    let Some(pos) = account.positions.get(ticker) else {
+        return Some(format!("Reduce-only: {ticker} is flat, nothing to reduce"));
+    };
+    let adds = if pos.long {
+        side == OrderSide::Buy
+    } else {
+        side == OrderSide::Sell
+    };
+    let (side_label, pos_label) = match side {
+        OrderSide::Buy => ("BUY", "LONG"),
+        _ => ("SELL", "SHORT"),
+    };
+    adds.then(|| format!("Reduce-only {side_label} would add to the {pos_label} {ticker} position"))
+}
+
+// ------------- open orders (from trader task) -------------
+
+// a resting limit order placed from this window, as the ladders show it
+#[derive(Clone, Debug, PartialEq)]
+struct OpenOrder {
+    client_id: u32,
+    ticker: String,
+    side: OrderSide,
+    price: f64,
+    // unfilled remainder as of the last refresh
+    size: f64,
+}
+
+// the trader's record of one: the chain id to cancel by, the block or time
+// it lapses at and the size it was placed with
+struct TrackedOrder {
+    id: OrderId,
+    until: OrderGoodUntil,
+    placed_size: f64,
+    order: OpenOrder,
+}
+
+fn publish_open_orders(tx: &watch::Sender<Vec<OpenOrder>>, open: &BTreeMap<u32, TrackedOrder>) {
+    let _ = tx.send(open.values().map(|t| t.order.clone()).collect());
+}
+
+// my orders gathered onto one ladder row
+#[derive(Clone, Debug, Default, PartialEq)]
+struct RowOrders {
+    size: f64,
+    client_ids: Vec<u32>,
+    // prices of orders shown here that aren't this row's level
+    off_level: Vec<f64>,
+}
+
+/// Orders by the index of the row they show on: the row at their price, or
+/// the nearest row when that level isn't among `rows`.
+fn orders_by_row<'a>(
+    rows: &[DepthLevel],
+    orders: impl Iterator<Item = &'a OpenOrder>,
+    scale: f64,
+) -> HashMap<usize, RowOrders> {
+    let mut out: HashMap<usize, RowOrders> = HashMap::new();
+    for o in orders {
+        let key = price_to_key(o.price, scale);
+        let Some((i, row)) = rows
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, l)| (l.key - key).abs())
+        else {
+            break;
+        };
+        let entry = out.entry(i).or_default();
+        entry.size += o.size;
+        entry.client_ids.push(o.client_id);
+        if row.key != key {
+            entry.off_level.push(o.price);
+        }
+    }
+    out
+}
+
+// leading "Mine" cell of a live ladder row; true when its cancel was clicked
+fn my_orders_cell(ui: &mut egui::Ui, row: Option<&RowOrders>, prec: MarketPrecision) -> bool {
+    let Some(r) = row else {
+        ui.label("");
+        return false;
+    };
+    ui.horizontal(|ui| {
+        let mark = if r.off_level.is_empty() { "●" } else { "≈" };
+        let text = egui::RichText::new(format!("{mark} {}", prec.size(r.size)))
+            .strong()
+            .color(MY_ORDER_COLOR);
+        let label = ui.label(text);
+        if !r.off_level.is_empty() {
+            let prices: Vec<String> = r.off_level.iter().map(|p| prec.price(*p)).collect();
+            label.on_hover_text(format!(
+                "order at {} isn't a visible level; shown at the nearest row",
+                prices.join(", ")
+            ));
+        }
+        ui.small_button("✕")
+            .on_hover_text("Cancel the order(s) on this row")
+            .clicked()
+    })
+    .inner
+}
+
+// ------------- live trade tape -------------
+
+const TAPE_MAX_ROWS: usize = 500;
+
+// `source` of trades seen on the trades channel; everything else
+// (gui_manual / gui_live / bot_auto / trader) is one of my own orders
+const MARKET_SOURCE: &str = "market";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TradeSourceFilter {
+    All,
+    Mine,
+    Market,
+}
+
+impl TradeSourceFilter {
+    fn label(self) -> &'static str {
+        match self {
+            TradeSourceFilter::All => "All trades",
+            TradeSourceFilter::Mine => "My fills",
+            TradeSourceFilter::Market => "Market trades",
+        }
+    }
+
+    fn all() -> [TradeSourceFilter; 3] {
+        [
+            TradeSourceFilter::All,
+            TradeSourceFilter::Mine,
+            TradeSourceFilter::Market,
+        ]
+    }
+
+    fn matches(self, source: &str) -> bool {
+        match self {
+            TradeSourceFilter::All => true,
+            TradeSourceFilter::Mine => source != MARKET_SOURCE,
+            TradeSourceFilter::Market => source == MARKET_SOURCE,
+        }
+    }
+}
+
+fn source_color(source: &str) -> Color32 {
+    match source {
+        MARKET_SOURCE => Color32::GRAY,
+        "gui_manual" => Color32::LIGHT_BLUE,
+        "gui_live" => Color32::from_rgb(0, 200, 200),
+        "bot_auto" => Color32::from_rgb(255, 165, 0),
+        "trader" => Color32::from_rgb(200, 120, 255),
+        _ => Color32::WHITE,
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TapeTrade {
+    ts: u64,
+    ticker: String,
+    source: String,
+    side: TradeSide, // taker side
+    // unknown for my own market orders until fills are tracked
+    price: Option<f64>,
+    size: f64,
+}
+
+impl TapeTrade {
+    fn from_update(
+        ticker: &str,
+        ts: u64,
+        side: &IndexerSide,
+        price: &BigDecimal,
+        size: &BigDecimal,
+    ) -> Self {
+        Self {
+            ts,
+            ticker: ticker.to_string(),
+            source: MARKET_SOURCE.to_string(),
+            side: TradeSide::from_buy(matches!(side, IndexerSide::Buy)),
+            price: price.to_f64(),
+            size: size.to_f64().unwrap_or(0.0),
+        }
+    }
+}
+
+// ------------- snapshot worker -------------
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SnapKey {
+    ticker: String,
+    ts: u64,
+    mid_mode: MidMode,
+}
+
+// a TfSnapshot carries every TF, so the TF is not part of the key
+type SnapshotWorker = snapshot_worker::SnapshotWorker<SnapKey, TickerData, TfSnapshot>;
+
+// ------------- main app -------------
+
+struct ComboApp {
+    // shared
+    mode: Mode,
+    time_mode: TimeDisplayMode,
+    // price the live and replay candles are built from
+    mid_mode: MidMode,
+    // settings of the ticker on screen; the others wait in `parked_charts`
+    chart: ChartSettings,
+    parked_charts: HashMap<String, ChartSettings>,
+    // candle chart drawn in its own OS window instead of the main one
+    chart_popped: bool,
+    // the candle plot's screen rect this frame, and the viewport it is in
+    chart_rect: Option<(egui::ViewportId, egui::Rect)>,
+    // Save PNG: where the chart goes once its viewport's screenshot is back
+    chart_export: Option<PathBuf>,
+    chart_export_msg: String,
+    // Ctrl + scroll not yet worth a TF step
+    tf_scroll: f32,
+    layout: LayoutSettings,
+    appearance: AppearanceSettings,
+    ladder_multiples_input: String,
+    tickers: Vec<String>,
+    current_ticker: String,
+    ticker_tx: watch::Sender<String>,
+
+    // live: one feed per ticker; `live_book` is the current ticker's
+    live_book_rxs: HashMap<String, watch::Receiver<LiveBook>>,
+    live_books: HashMap<String, LiveBook>,
+    funding_rx: watch::Receiver<Option<FundingInfo>>,
+    live_book: LiveBook,
+    // MULTI_TF candles per ticker for Multi mode, seeded on first use
+    multi_candles: HashMap<String, CandleAgg>,
+    pair: PairSettings,
+    // (ts, spread) of the current ticker, newest at the back
+    live_spreads: VecDeque<(u64, f64)>,
+    // replay spread series per (ticker, tf), built on first use
+    spread_cache: HashMap<(String, u64), Arc<Vec<SpreadBucket>>>,
+    live_candles: HashMap<u64, CandleAgg>,
+    // data time: when the current ticker's book last changed (the CSV seed's
+    // last row until then), not the wall clock, so the chart's now-line stops
+    // where a stalled feed did
+    live_last_ts: u64,
+    tape_rx: mpsc::Receiver<TapeTrade>,
+    // newest at the back, capped at TAPE_MAX_ROWS
+    tape: VecDeque<TapeTrade>,
+    // shared by the live tape and the replay trades grid
+    trade_source_filter: TradeSourceFilter,
+    // replay trades from one merged venue only; None shows all
+    venue_filter: Option<String>,
+
+    // real trading UI
+    trade_tx: mpsc::Sender<TradeCmd>,
+    kill_tx: watch::Sender<u64>,
+    account_rx: watch::Receiver<Option<AccountInfo>>,
+    open_orders_rx: watch::Receiver<Vec<OpenOrder>>,
+    order_event_rx: mpsc::Receiver<OrderEvent>,
+    trader_identity: TraderIdentity,
+    trade_size_input: f64,
+    ui_order_type: UiOrderType,
+    ui_tif: UiTif,
+    ui_limit_price: f64,
+    // side the last ladder click suggested, highlighted on the order buttons
+    ui_limit_side: Option<OrderSide>,
+    // ladder clicks take the level (bid -> sell) instead of joining it
+    ladder_click_takes: bool,
+    // ladder clicks place the limit order at once; needs hotkeys armed
+    one_click_orders: bool,
+    // one-click limits rest until a wall-clock expiry instead of a few blocks
+    limit_good_til_time: bool,
+    limit_gtt_mins: u32,
+    ui_leverage: f64,
+    ui_reduce_only: bool,
+    last_order_msg: String,
+    // b / s / x / arrow keys place orders only while armed
+    hotkeys_armed: bool,
+    hotkey_size_step: f64,
+    kill_confirm_open: bool,
+    // panic mode: KILL fires without the confirmation dialog
+    kill_skip_confirm: bool,
+    // closing the window sends a KILL first
+    flatten_on_exit: bool,
+
+    // replay
+    replay_data: HashMap<String, Arc<TickerData>>,
+    replay_ts: u64,
+    replay_tab: ReplayTab,
+    snap_worker: SnapshotWorker,
+
+    // repaint cadence; slows down once feeds and input go quiet
+    repaint: RepaintThrottle,
+
+    draw_tool: DrawTool,
+    // drawings, bookmarks and alerts per ticker, loaded on first use
+    workspaces: HashMap<String, Workspace>,
+    // tickers with unsaved workspace edits, by when they were last edited
+    workspace_dirty: HashMap<String, Instant>,
+    bookmark_label: String,
+    alert_above: bool,
+    alert_price: f64,
+    // the last alert that fired, until dismissed
+    alert_banner: Option<String>,
+    // first click of a trendline in progress
+    draw_anchor: Option<[f64; 2]>,
+    selected_drawing: Option<usize>,
+
+    // Ctrl+Z / Ctrl+Shift+Z over layout, overlays and drawings
+    history: UndoHistory,
+}
+
+impl ComboApp {
+    fn new(
+        book_rxs: HashMap<String, watch::Receiver<LiveBook>>,
+        funding_rx: watch::Receiver<Option<FundingInfo>>,
+        replay_data: HashMap<String, TickerData>,
+        ticker_tx: watch::Sender<String>,
+        trader: TraderLink,
+        tape_rx: mpsc::Receiver<TapeTrade>,
+    ) -> Self {
+        let tickers = vec![
+            "ETH-USD".to_string(),
+            "BTC-USD".to_string(),
+            "SOL-USD".to_string(),
+        ];
+
+        let current_ticker = "ETH-USD".to_string();
+        let pair = PairSettings::new(&tickers);
+
+        let replay_ts = replay_data
+            .get(&current_ticker)
+            .map(|td| td.max_ts)
+            .unwrap_or(0);
+
+        // seed live CandleAggs from CSV history if present
+        let (live_candles, live_last_ts) = if let Some(td) = replay_data.get(&current_ticker) {
+            build_candles_from_book_events(&td.book_events, td.price_scale, MidMode::Simple)
+        } else {
+            (empty_live_candles(), now_unix())
+        };
+
+        let replay_data = replay_data
+            .into_iter()
+            .map(|(tk, td)| (tk, Arc::new(td)))
+            .collect();
+
+        Self {
+            mode: Mode::Live,
+            time_mode: TimeDisplayMode::Local,
+            mid_mode: MidMode::default(),
+            chart: ChartSettings::default(),
+            parked_charts: HashMap::new(),
+            chart_popped: false,
+            chart_rect: None,
+            chart_export: None,
+            chart_export_msg: String::new(),
+            tf_scroll: 0.0,
+            layout: LayoutSettings::default(),
+            appearance: AppearanceSettings::default(),
+            ladder_multiples_input: "1, 10, 100".to_string(),
+            tickers,
+            current_ticker,
+            ticker_tx,
+
+            live_book_rxs: book_rxs,
+            live_books: HashMap::new(),
+            funding_rx,
+            live_book: LiveBook::default(),
+            multi_candles: HashMap::new(),
+            pair,
+            live_spreads: VecDeque::new(),
+            spread_cache: HashMap::new(),
+            live_candles,
+            live_last_ts,
+            tape_rx,
+            tape: VecDeque::new(),
+            trade_source_filter: TradeSourceFilter::All,
+            venue_filter: None,
+
+            trade_tx: trader.trade_tx,
+            kill_tx: trader.kill_tx,
+            account_rx: trader.account_rx,
+            open_orders_rx: trader.open_orders_rx,
+            order_event_rx: trader.order_event_rx,
+            trader_identity: trader.identity,
+            trade_size_input: 0.01,
+            ui_order_type: UiOrderType::Market,
+            ui_tif: UiTif::Gtc,
+            ui_limit_price: 0.0,
+            ui_limit_side: None,
+            ladder_click_takes: false,
+            one_click_orders: false,
+            limit_good_til_time: false,
+            limit_gtt_mins: DEFAULT_LIMIT_GTT_MINS,
+            ui_leverage: 5.0,
+            ui_reduce_only: false,
+            last_order_msg: String::new(),
+            hotkeys_armed: false,
+            hotkey_size_step: 0.01,
+            kill_confirm_open: false,
+            kill_skip_confirm: false,
+            flatten_on_exit: false,
+
+            replay_data,
+            replay_ts,
+            replay_tab: ReplayTab::Candles,
+            snap_worker: SnapshotWorker::spawn(|data, key| {
+                replay_snapshot(data, key.ts, key.mid_mode)
+            }),
+
+            repaint: RepaintThrottle::new(Duration::from_millis(50)).with_args(env::args().skip(1)),
+
+            draw_tool: DrawTool::Select,
+            workspaces: HashMap::new(),
+            workspace_dirty: HashMap::new(),
+            bookmark_label: String::new(),
+            alert_above: true,
+            alert_price: 0.0,
+            alert_banner: None,
+            draw_anchor: None,
+            selected_drawing: None,
+
+            history: UndoHistory::default(),
+        }
+    }
+
+    fn workspace(&mut self, ticker: &str) -> &mut Workspace {
+        self.workspaces
+            .entry(ticker.to_string())
+            .or_insert_with_key(|tk| load_workspace(&workspace_path(tk), &drawings_path(tk)))
+    }
+
+    fn current_workspace(&mut self) -> &mut Workspace {
+        let ticker = self.current_ticker.clone();
+        self.workspace(&ticker)
+    }
+
+    fn current_drawings(&mut self) -> &mut Vec<Drawing> {
+        &mut self.current_workspace().drawings
+    }
+
+    // written by flush_workspaces once the edits settle
+    fn mark_workspace_dirty(&mut self) {
+        self.workspace_dirty
+            .insert(self.current_ticker.clone(), Instant::now());
+    }
+
+    // `force` writes every pending workspace, quiet or not
+    fn flush_workspaces(&mut self, now: Instant, force: bool) {
+        let due: Vec<String> = self
+            .workspace_dirty
+            .iter()
+            .filter(|(_, edited)| force || now.duration_since(**edited) >= WORKSPACE_SAVE_DELAY)
+            .map(|(tk, _)| tk.clone())
+            .collect();
+        for tk in due {
+            self.workspace_dirty.remove(&tk);
+            let Some(ws) = self.workspaces.get(&tk) else {
+                continue;
+            };
+            let path = workspace_path(&tk);
+            if let Err(e) = save_workspace(&path, ws) {
+                warn!(path = %path.display(), error = %e, "cannot save workspace");
+            }
+        }
+    }
+
+    fn undo_state(&mut self) -> UndoState {
+        UndoState {
+            layout: self.layout.clone(),
+            appearance: self.appearance.clone(),
+            panels: PanelToggles::of(&self.chart),
+            ticker: self.current_ticker.clone(),
+            drawings: self.current_drawings().clone(),
+        }
+    }
+
+    fn restore_undo_state(&mut self, state: UndoState) {
+        self.layout = state.layout;
+        self.appearance = state.appearance;
+        state.panels.apply(&mut self.chart);
+        self.workspace(&state.ticker).drawings = state.drawings;
+        self.workspace_dirty.insert(state.ticker, Instant::now());
+        self.selected_drawing = None;
+        self.draw_anchor = None;
+    }
+
+    // shortcuts first, then record whatever this frame changed; nothing is
+    // recorded mid-drag so a drag lands as one step
+    fn handle_undo(&mut self, ctx: &egui::Context) {
+        if !ctx.wants_keyboard_input() {
+            let (redo, undo) = ctx.input_mut(|i| {
+                let shift = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+                let redo = i.consume_key(shift, egui::Key::Z);
+                (redo, i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z))
+            });
+            let state = if redo {
+                self.history.redo()
+            } else if undo {
+                self.history.undo()
+            } else {
+                None
+            };
+            if let Some(state) = state {
+                self.restore_undo_state(state);
+            }
+        }
+        if !ctx.input(|i| i.pointer.any_down()) {
+            let state = self.undo_state();
+            self.history.observe(state);
+        }
+    }
+
+    fn handle_chart_click(&mut self, p: [f64; 2], candles: &[Candle], x_span: f64, y_span: f64) {
+        let tf = self.chart.selected_tf as f64;
+        let snapped = snap_point(p, tf, candles, y_span * DRAW_SNAP_FRAC);
+        match self.draw_tool {
+            DrawTool::Select => {
+                self.selected_drawing = nearest_drawing(self.current_drawings(), p, x_span, y_span);
+                // percent mode: a click off any drawing rebases on that candle
+                if self.selected_drawing.is_none() && self.chart.y_mode == YAxisMode::Percent {
+                    if let Some(c) = candle_at(candles, p[0].max(0.0) as u64) {
+                        self.chart.pct_ref_t = Some(c.t);
+                    }
+                }
+            }
+            DrawTool::Line => match self.draw_anchor.take() {
+                Some(a) => {
+                    self.current_drawings()
+                        .push(Drawing::Trend { a, b: snapped });
+                    self.mark_workspace_dirty();
+                }
+                None => self.draw_anchor = Some(snapped),
+            },
+            DrawTool::HLine => {
+                self.current_drawings().push(Drawing::HRay {
+                    x: snapped[0],
+                    y: snapped[1],
+                });
+                self.mark_workspace_dirty();
+            }
+            DrawTool::Delete => {
+                if let Some(i) = nearest_drawing(self.current_drawings(), p, x_span, y_span) {
+                    self.remove_drawing(i);
+                }
+            }
+        }
+    }
+
+    fn remove_drawing(&mut self, idx: usize) {
+        let drawings = self.current_drawings();
+        if idx < drawings.len() {
+            drawings.remove(idx);
+            self.selected_drawing = None;
+            self.mark_workspace_dirty();
+        }
+    }
+
+    // bid / ask colors for the shaded depth plot, None while it is off
+    fn depth_fill_colors(&self) -> (Option<Color32>, Option<Color32>) {
+        if self.appearance.depth_filled {
+            (
+                Some(self.appearance.bull_color),
+                Some(self.appearance.bear_color),
+            )
+        } else {
+            (None, None)
+        }
+    }
+
+    fn ladder_cols(&self, prec: MarketPrecision) -> LadderCols {
+        LadderCols {
+            prec,
+            cum_size: self.appearance.ladder_cum_size,
+            cum_notional: self.appearance.ladder_cum_notional,
+        }
+    }
+
+    // a ladder click: Limit at the level's price, side per ladder_click_side;
+    // sent straight away while one-click orders are live
+    fn fill_from_ladder(&mut self, price: f64, is_bid: bool) {
+        let side = ladder_click_side(is_bid, self.ladder_click_takes);
+        self.ui_order_type = UiOrderType::Limit;
+        self.ui_limit_price = price;
+        self.ui_limit_side = Some(side);
+        if self.one_click_live() {
+            self.send_limit_order(side, price);
+        }
+    }
+
+    // one-click only fires while armed and with a wallet to trade from; the
+    // kill switch disarms, which turns it off with everything else
+    fn one_click_live(&self) -> bool {
+        self.one_click_orders
+            && self.hotkeys_armed
+            && matches!(self.trader_identity, TraderIdentity::Ready { .. })
+    }
+
+    fn send_cancel(&mut self, client_id: u32) {
+        self.last_order_msg = match self.trade_tx.try_send(TradeCmd::Cancel { client_id }) {
+            Ok(()) => format!("Cancelling order {client_id}"),
+            Err(e) => format!("Cancel could not be queued: {e}"),
+        };
+    }
+
+    fn limit_expiry(&self) -> LimitExpiry {
+        if self.limit_good_til_time {
+            LimitExpiry::Time {
+                secs: u64::from(self.limit_gtt_mins.clamp(1, MAX_LIMIT_GTT_MINS)) * 60,
+            }
+        } else {
+            LimitExpiry::Blocks
+        }
+    }
+
+    fn send_limit_order(&mut self, side: OrderSide, price: f64) {
+        let side_label = match side {
+            OrderSide::Buy => "BUY",
+            _ => "SELL",
+        };
+        let prec = market_precision(&self.current_ticker);
+        let s_str = format!("{:.8}", self.trade_size_input.max(0.0));
+        let p_str = prec.price(price);
+        let (Ok(size), Ok(price)) = (BigDecimal::from_str(&s_str), BigDecimal::from_str(&p_str))
+        else {
+            self.last_order_msg = format!("Invalid size or price for {side_label}");
+            return;
+        };
+        if let Some(msg) = self.reduce_only_rejection(side) {
+            self.last_order_msg = msg;
+            return;
+        }
+        let expiry = self.limit_expiry();
+        let tif = self.ui_tif.for_order(UiOrderType::Limit);
+        let reduce_label = if self.ui_reduce_only {
+            " reduce-only"
+        } else {
+            ""
+        };
+        if matches!(expiry, LimitExpiry::Time { .. }) && !tif.rests() {
+            self.last_order_msg = format!(
+                "{} can't be a long-term order; pick GTC or Post-Only, or Short-term expiry",
+                tif.label()
+            );
+            return;
+        }
+        self.last_order_msg = match self.trade_tx.try_send(TradeCmd::LimitOrder {
+            ticker: self.current_ticker.clone(),
+            side,
+            price,
+            size,
+            expiry,
+            tif: tif.time_in_force(),
+            reduce_only: self.ui_reduce_only,
+        }) {
+            Ok(()) => format!(
+                "[LMT] {side_label} {} size {s_str} @ {p_str} {} {}{} (one-click, testnet)",
+                self.current_ticker,
+                tif.label(),
+                expiry.label(),
+                reduce_label
+            ),
+            Err(e) => format!("Limit {side_label} could not be queued: {e}"),
+        };
+    }
+
+    // the current ticker's live position at the live mid, for the chart HUD;
+    // entry and liquidation come from the last account fetch
+    fn live_pnl_hud(&self) -> Option<PnlHud> {
+        let account = self.account_rx.borrow();
+        let a = account.as_ref()?;
+        let p = a.positions.get(&self.current_ticker)?;
+        Some(PnlHud {
+            long: p.long,
+            size: p.size,
+            entry: p.entry_price,
+            mark: self.live_book.mid_with(self.mid_mode.mid_fn())?,
+            liquidation: a.liquidation_price(&self.current_ticker),
+        })
+    }
+
+    // checked on send against the cached position; None while reduce-only is off
+    fn reduce_only_rejection(&self, side: OrderSide) -> Option<String> {
+        if !self.ui_reduce_only {
+            return None;
+        }
+        let account = self.account_rx.borrow();
+        reduce_only_rejection(account.as_ref(), &self.current_ticker, side)
+    }
+
+    fn send_market_order(&mut self, side: OrderSide) {
+        let order_type_label = match self.ui_order_type {
+            UiOrderType::Market => "MKT",
+            UiOrderType::Limit => "LMT(UI)",
+        };
+        let side_label = match side {
+            OrderSide::Buy => "BUY",
+            _ => "SELL",
+        };
+
+        if let Some(msg) = self.reduce_only_rejection(side) {
+            self.last_order_msg = msg;
+            return;
+        }
+
+        let size_val = self.trade_size_input.max(0.0);
+        let s_str = format!("{:.8}", size_val);
+        // these always go out as market orders, whatever the form's type
+        let tif = self.ui_tif.for_order(UiOrderType::Market);
+        if let Ok(size_bd) = BigDecimal::from_str(&s_str) {
+            let _ = self.trade_tx.try_send(TradeCmd::MarketOrder {
+                ticker: self.current_ticker.clone(),
+                side,
+                size: size_bd,
+                tif: tif.time_in_force(),
+                reduce_only: self.ui_reduce_only,
+            });
+            self.last_order_msg = format!(
+                "[{}] {} {} size {} (exec: MARKET {}; reduce_only={}, limit_price={} [UI only])",
+                order_type_label,
+                side_label,
+                self.current_ticker,
+                s_str,
+                tif.label(),
+                self.ui_reduce_only,
+                if self.ui_limit_price > 0.0 {
+                    self.ui_limit_price.to_string()
+                } else {
+                    "n/a".into()
+                },
+            );
+        } else {
+            self.last_order_msg = format!("Invalid size for {side_label}");
+        }
+    }
+
+    // the trader re-reads the position before sending; the cached one only
+    // short-circuits the obviously-flat case
+    fn send_flatten(&mut self) {
+        let known_flat = self
+            .account_rx
+            .borrow()
+            .as_ref()
+            .is_some_and(|a| !a.positions.contains_key(&self.current_ticker));
+        if known_flat {
+            self.last_order_msg = format!("{} is flat; nothing to close", self.current_ticker);
+            return;
+        }
+        let _ = self.trade_tx.try_send(TradeCmd::Flatten {
+            ticker: self.current_ticker.clone(),
+        });
+        self.last_order_msg = format!(
+            "FLATTEN {} (reduce-only IOC MARKET for the open position)",
+            self.current_ticker
+        );
+    }
+
+    fn set_subaccount(&mut self, number: u32) {
+        let TraderIdentity::Ready { subaccount, .. } = &mut self.trader_identity else {
+            return;
+        };
+        if *subaccount == number {
+            return;
+        }
+        *subaccount = number;
+        let _ = self.trade_tx.try_send(TradeCmd::SetSubaccount { number });
+        self.last_order_msg = format!("switched to subaccount {number}");
+    }
+
+    // window close: the trader task gets its last commands from here, main
+    // then waits for it to drain (see TRADER_SHUTDOWN_GRACE)
+    fn on_close_requested(&mut self) {
+        self.flush_workspaces(Instant::now(), true);
+        if self.flatten_on_exit {
+            info!("flatten on exit: sending KILL");
+            self.kill_tx.send_modify(|n| *n += 1);
+        }
+    }
+
+    fn request_kill(&mut self) {
+        if self.kill_skip_confirm {
+            self.send_kill();
+        } else {
+            self.kill_confirm_open = true;
+        }
+    }
+
+    fn send_kill(&mut self) {
+        self.kill_confirm_open = false;
+        // keep a stray keypress from re-entering right after the kill
+        self.hotkeys_armed = false;
+        self.kill_tx.send_modify(|n| *n += 1);
+        self.last_order_msg = if self.kill_tx.is_closed() {
+            "KILL not delivered: the trader has stopped".to_string()
+        } else {
+            "KILL sent: cancelling resting orders, flattening all positions".to_string()
+        };
+    }
+
+    fn ui_kill_confirm(&mut self, ctx: &egui::Context) {
+        let mut open = self.kill_confirm_open;
+        let mut confirmed = false;
+        egui::Window::new("Confirm KILL")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Cancel resting orders and flatten every open position?");
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(egui::RichText::new("KILL").strong().color(Color32::WHITE))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.kill_confirm_open = false;
+                    }
+                });
+            });
+        if confirmed {
+            self.send_kill();
+        } else if !open {
+            self.kill_confirm_open = false;
+        }
+    }
+
+    // live-mode order hotkeys; off unless armed and while a text field has focus.
+    // K (kill) works unarmed, it only ever reduces exposure.
+    fn handle_trade_hotkeys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        // bare keys only: Ctrl+S, Cmd+X and friends are someone else's shortcut
+        let bare = egui::Modifiers::NONE;
+        if ctx.input_mut(|i| i.consume_key(bare, egui::Key::K)) {
+            self.request_kill();
+        }
+        if !self.hotkeys_armed {
+            return;
+        }
+        let (buy, sell, flatten, up, down) = ctx.input_mut(|i| {
+            (
+                i.consume_key(bare, egui::Key::B),
+                i.consume_key(bare, egui::Key::S),
+                i.consume_key(bare, egui::Key::X),
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+            )
+        });
+
+        if up {
+            self.trade_size_input += self.hotkey_size_step;
+        }
+        if down {
+            self.trade_size_input = (self.trade_size_input - self.hotkey_size_step).max(0.0);
+        }
+        if buy {
+            self.send_market_order(OrderSide::Buy);
+        } else if sell {
+            self.send_market_order(OrderSide::Sell);
+        } else if flatten {
+            self.send_flatten();
+        }
+    }
+
+    // live candles are one price series: re-seed them from the CSV history
+    // when the mid they are built from changes
+    fn rebuild_live_candles(&mut self) {
+        let (candles, last_ts) = match self.replay_data.get(&self.current_ticker) {
+            Some(td) => {
+                build_candles_from_book_events(&td.book_events, td.price_scale, self.mid_mode)
+            }
+            None => (empty_live_candles(), now_unix()),
+        };
+        self.live_candles = candles;
+        self.live_last_ts = last_ts;
+        // re-seeded with the new mid next time Multi is shown
+        self.multi_candles.clear();
+    }
+
+    fn ensure_multi_candles(&mut self) {
+        if !self.multi_candles.is_empty() {
+            return;
+        }
+        for tk in &self.tickers {
+            let mut agg = self
+                .replay_data
+                .get(tk)
+                .and_then(|td| {
+                    build_candles_from_book_events(&td.book_events, td.price_scale, self.mid_mode)
+                        .0
+                        .remove(&MULTI_TF)
+                })
+                .unwrap_or_else(|| CandleAgg::new(MULTI_TF));
+            agg.set_max_candles(Some(MULTI_HISTORY_CANDLES));
+            self.multi_candles.insert(tk.clone(), agg);
+        }
+    }
+
+    fn switch_ticker(&mut self, t: &str) {
+        self.flush_workspaces(Instant::now(), true);
+        // each market keeps its own zoom / pan / TF / y-range
+        let next = self.parked_charts.remove(t).unwrap_or_default();
+        let prev = std::mem::replace(&mut self.chart, next);
+        self.parked_charts.insert(self.current_ticker.clone(), prev);
+
+        self.current_ticker = t.to_string();
+
+        // every ticker has its own feed; this one just moves the funding poller
+        let _ = self.ticker_tx.send(t.to_string());
+        self.live_book = self.live_books.get(t).cloned().unwrap_or_default();
+        self.tape.clear();
+        self.live_spreads.clear();
+        self.draw_anchor = None;
+        self.selected_drawing = None;
+        self.alert_price = 0.0;
+
+        // adjust replay ts to end of range for that ticker (if exists)
+        if let Some(td) = self.replay_data.get(t) {
+            self.replay_ts = td.max_ts;
+        }
+    }
+
+    fn current_replay_ticker(&self) -> Option<&TickerData> {
+        self.replay_data
+            .get(&self.current_ticker)
+            .map(|td| td.as_ref())
+    }
+
+    fn live_series(&self) -> Vec<Candle> {
+        if let Some(agg) = self.live_candles.get(&self.chart.selected_tf) {
+            agg.series().to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn replay_series<'a>(&self, snap: &'a TfSnapshot) -> &'a Vec<Candle> {
+        if let Some(series) = snap.candles_by_tf.get(&self.chart.selected_tf) {
+            series
+        } else if let Some(series) = snap.candles_by_tf.get(&60) {
+            // fallback: 1m
+            series
+        } else {
+            // extremely degenerate case, but type needs something
+            static EMPTY: Vec<Candle> = Vec::new();
+            &EMPTY
+        }
+    }
+
+    fn tick_live(&mut self) {
+        let mut book_changed = false;
+        let mut activity = false;
+        for (tk, rx) in self.live_book_rxs.iter_mut() {
+            if rx.has_changed().unwrap_or(false) {
+                activity = true;
+                let book = rx.borrow_and_update().clone();
+                if *tk == self.current_ticker {
+                    book_changed = true;
+                    if let Some(spread) = book.spread() {
+                        self.live_spreads.push_back((now_unix(), spread));
+                        if self.live_spreads.len() > SPREAD_RING_MAX {
+                            self.live_spreads.pop_front();
+                        }
+                    }
+                    self.live_book = book.clone();
+                }
+                self.live_books.insert(tk.clone(), book);
+            }
+        }
+
+        while let Ok(ev) = self.order_event_rx.try_recv() {
+            activity = true;
+            self.last_order_msg = ev.message();
+        }
+
+        while let Ok(t) = self.tape_rx.try_recv() {
+            activity = true;
+            // the tape is shared by every ticker's feed
+            if t.ticker != self.current_ticker {
+                continue;
+            }
+            self.tape.push_back(t);
+            if self.tape.len() > TAPE_MAX_ROWS {
+                self.tape.pop_front();
+            }
+        }
+
+        if activity {
+            self.repaint.touch(Instant::now());
+        }
+
+        let ts = now_unix();
+        if book_changed {
+            self.live_last_ts = ts;
+
+            if let Some(mid) = self.live_book.mid_with(self.mid_mode.mid_fn()) {
+                let vol = 0.0; // placeholder volume for now
+
+                for agg in self.live_candles.values_mut() {
+                    agg.update(ts, mid, vol);
+                }
+            }
+        }
+
+        for (tk, agg) in self.multi_candles.iter_mut() {
+            if let Some(mid) = self
+                .live_books
+                .get(tk)
+                .and_then(|b| b.mid_with(self.mid_mode.mid_fn()))
+            {
+                agg.update(ts, mid, 0.0);
+            }
+        }
+
+        self.check_price_alerts();
+    }
+
+    // every ticker's alerts, not just the one on screen
+    fn check_price_alerts(&mut self) {
+        for tk in self.tickers.clone() {
+            let Some(mid) = self
+                .live_books
+                .get(&tk)
+                .and_then(|b| b.mid_with(self.mid_mode.mid_fn()))
+            else {
+                continue;
+            };
+            for a in self.workspace(&tk).check_alerts(mid) {
+                let prec = market_precision(&tk);
+                let msg = format!("{tk} mid {} {}", a.op(), prec.price(a.price));
+                info!(ticker = %tk, mid, "alert: {msg}");
+                self.alert_banner = Some(msg);
+            }
+        }
+    }
+
+    fn ensure_replay_ts_in_range(&mut self) {
+        let (min_ts, max_ts) = match self.replay_data.get(&self.current_ticker) {
+            Some(td) => (td.min_ts, td.max_ts),
+            None => return,
+        };
+
+        if self.replay_ts < min_ts {
+            self.replay_ts = min_ts;
+        }
+        if self.replay_ts > max_ts {
+            self.replay_ts = max_ts;
+        }
+    }
+
+    fn funding_label(&self) -> String {
+        match self.funding_rx.borrow().as_ref() {
+            Some(f) if f.ticker == self.current_ticker => {
+                let left = FUNDING_PERIOD_SECS - now_unix() % FUNDING_PERIOD_SECS;
+                format!(
+                    "funding: {:+.4}% / 1h, next in {:02}:{:02}",
+                    f.rate * 100.0,
+                    left / 60,
+                    left % 60
+                )
+            }
+            _ => "funding: n/a".to_string(),
+        }
+    }
+
+    fn ui_top_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            // mode
+            ui.label("Mode:");
+            if ui
+                .selectable_label(self.mode == Mode::Live, "Live")
+                .clicked()
+            {
+                self.mode = Mode::Live;
+            }
+            if ui
+                .selectable_label(self.mode == Mode::Replay, "Replay")
+                .clicked()
+            {
+                self.mode = Mode::Replay;
+            }
+            if ui
+                .selectable_label(self.mode == Mode::Multi, "Multi")
+                .clicked()
+            {
+                self.ensure_multi_candles();
+                self.mode = Mode::Multi;
+            }
+
+            ui.separator();
+
+            // ticker menu
+            let tickers = self.tickers.clone();
+            ui.menu_button(format!("Ticker: {}", self.current_ticker), |ui| {
+                for t in &tickers {
+                    let selected = *t == self.current_ticker;
+                    if ui.selectable_label(selected, t).clicked() {
+                        if !selected {
+                            self.switch_ticker(t);
+                        }
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            ui.separator();
+
+            // time display
+            ui.label("Time:");
+            for mode in [TimeDisplayMode::Local, TimeDisplayMode::Unix] {
+                if ui
+                    .selectable_label(self.time_mode == mode, mode.label())
+                    .clicked()
+                {
+                    self.time_mode = mode;
+                }
+            }
+
+            ui.separator();
+
+            ui.label("Candles from:");
+            let prev_mid_mode = self.mid_mode;
+            for mode in [MidMode::Simple, MidMode::Weighted] {
+                ui.selectable_value(&mut self.mid_mode, mode, mode.label())
+                    .on_hover_text(
+                        "Weighted: (bid·ask size + ask·bid size) / (bid size + ask size)",
+                    );
+            }
+            if self.mid_mode != prev_mid_mode {
+                self.rebuild_live_candles();
+            }
+
+            if let Some(td) = self.current_replay_ticker() {
+                ui.separator();
+                let st = &td.load_stats;
+                let text = format!(
+                    "loaded {} rows, skipped {} malformed, collapsed {} redundant",
+                    fmt_count(st.parsed),
+                    fmt_count(st.skipped),
+                    fmt_count(st.collapsed)
+                );
+                let resp = if st.skipped > 0 {
+                    ui.colored_label(Color32::YELLOW, text)
+                } else {
+                    ui.label(text)
+                };
+                if !st.samples.is_empty() {
+                    resp.on_hover_text(st.samples.join("\n"));
+                }
+
+                ui.separator();
+                ui.label(format!(
+                    "Replay range: {} → {}",
+                    format_ts(self.time_mode, td.min_ts),
+                    format_ts(self.time_mode, td.max_ts)
+                ));
+                ui.separator();
+                ui.label(format!(
+                    "Replay ts: {}",
+                    format_ts(self.time_mode, self.replay_ts)
+                ));
+            }
+
+            if matches!(self.mode, Mode::Live | Mode::Multi) {
+                ui.separator();
+                ui.label(live_clock_label(
+                    self.time_mode,
+                    self.live_last_ts,
+                    now_unix(),
+                ))
+                .on_hover_text("Data: last book update applied. Wall: this machine's clock");
+            }
+
+            ui.separator();
+            ui.label(self.funding_label());
+
+            if let Some(msg) = self.alert_banner.clone() {
+                ui.separator();
+                ui.colored_label(ALERT_COLOR, format!("⚠ {msg}"));
+                if ui.small_button("✕").clicked() {
+                    self.alert_banner = None;
+                }
+            }
+        });
+
+        ui.separator();
+
+        // replay-only time slider
+        if matches!(self.mode, Mode::Replay) {
+            if let Some(td) = self.current_replay_ticker() {
+                let mut ts = self.replay_ts;
+                let gap_secs = self.chart.gap_secs;
+                let gaps: Vec<(u64, u64)> = td.gaps_over(gap_secs).collect();
+                let next_gap_end = td.next_gap_end(ts, gap_secs);
+                ui.horizontal(|ui| {
+                    ui.label("Replay time:");
+                    ui.add(
+                        egui::Slider::new(&mut ts, td.min_ts..=td.max_ts)
+                            .show_value(false)
+                            .text("ts"),
+                    );
+                    if ui.button("◀").clicked() && ts > td.min_ts {
+                        ts -= 1;
+                    }
+                    if ui.button("▶").clicked() && ts < td.max_ts {
+                        ts += 1;
+                    }
+                    if ui.button("Now").clicked() {
+                        ts = td.max_ts;
+                    }
+                    if ui
+                        .add_enabled(next_gap_end.is_some(), egui::Button::new("Skip gap ▶"))
+                        .on_hover_text(format!("{} gaps of {gap_secs}s or more", gaps.len()))
+                        .clicked()
+                    {
+                        if let Some(end) = next_gap_end {
+                            ts = end;
+                        }
+                    }
+                    ui.label(format_ts(self.time_mode, ts));
+                    if self.snap_worker.is_pending() {
+                        ui.spinner();
+                        ui.weak("recomputing…");
+                    }
+                });
+                if let Some(t) = replay_timeline(ui, td.min_ts, td.max_ts, ts, &gaps) {
+                    ts = t;
+                }
+                self.replay_ts = ts;
+                self.ui_replay_bookmarks(ui);
+            } else {
+                ui.label("No replay CSV for this ticker.");
+            }
+
+            ui.separator();
+        }
+
+        // shared chart controls
+        ui.horizontal(|ui| {
+            ui.label("History candles:");
+            ui.add(
+                egui::Slider::new(
+                    &mut self.chart.show_candles,
+                    SHOW_CANDLES_MIN..=SHOW_CANDLES_MAX,
+                )
+                .logarithmic(true),
+            );
+
+            ui.separator();
+            ui.label("X zoom:");
+            ui.add(
+                egui::Slider::new(&mut self.chart.x_zoom, 0.25..=4.0)
+                    .logarithmic(true)
+                    .text("zoom"),
+            );
+
+            ui.horizontal(|ui| {
+                if ui.button("← Pan").clicked() {
+                    self.chart.x_pan_secs -= self.chart.selected_tf as f64 * 10.0;
+                }
+                if ui.button("Pan →").clicked() {
+                    self.chart.x_pan_secs += self.chart.selected_tf as f64 * 10.0;
+                }
+                if ui.button("Center").clicked() {
+                    self.chart.x_pan_secs = 0.0;
+                }
+                if ui
+                    .button("Fit")
+                    .on_hover_text("Reset zoom, pan and Y range (F over the chart)")
+                    .clicked()
+                {
+                    self.chart.fit();
+                }
+                let pop_label = if self.chart_popped {
+                    "Dock chart"
+                } else {
+                    "Pop out chart"
+                };
+                if ui.button(pop_label).clicked() {
+                    self.chart_popped = !self.chart_popped;
+                }
+                if ui
+                    .button("Save PNG")
+                    .on_hover_text("Candles and overlays as on screen, into the data dir")
+                    .clicked()
+                {
+                    self.request_chart_png(ui.ctx());
+                }
+                if !self.chart_export_msg.is_empty() {
+                    ui.weak(&self.chart_export_msg);
+                }
+            });
+
+            ui.separator();
+            ui.label("TF:");
+            let mut tf = self.chart.selected_tf;
+            egui::ComboBox::from_id_source("tf_combo")
+                .selected_text(tf_label(tf))
+                .show_ui(ui, |ui| {
+                    for choice in TF_CHOICES {
+                        ui.selectable_value(&mut tf, *choice, tf_label(*choice));
+                    }
+                })
+                .response
+                .on_hover_text(", / . step down / up (Ctrl + scroll over the candles)");
+            self.chart.set_tf(tf);
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_trade_markers, "Trade markers");
+            if self.chart.show_trade_markers {
+                ui.checkbox(&mut self.chart.scale_trade_markers, "Scale by size");
+            }
+
+            ui.checkbox(&mut self.chart.show_spread, "Spread panel");
+            ui.checkbox(&mut self.chart.break_at_gaps, "Break at gaps")
+                .on_hover_text("Replay: shade clock gaps, don't join lines across them");
+            ui.checkbox(&mut self.chart.show_patterns, "Patterns")
+                .on_hover_text("Doji (D), engulfing (E+/E-), hammer (H), shooting star (SS)");
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_volume_profile, "Volume profile");
+            if self.chart.show_volume_profile {
+                ui.add(
+                    egui::DragValue::new(&mut self.chart.profile_bucket)
+                        .speed(0.1)
+                        .clamp_range(0.0001..=10_000.0)
+                        .prefix("bucket "),
+                );
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_footprint, "Footprint")
+                .on_hover_text(
+                    "Sell x buy volume per price row from recorded trades; \
+                     plain candles until zoomed in far enough to read",
+                );
+            if self.chart.show_footprint {
+                ui.add(
+                    egui::DragValue::new(&mut self.chart.footprint_row_ticks)
+                        .speed(1)
+                        .clamp_range(1..=10_000)
+                        .prefix("row ticks "),
+                );
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_session_lines, "Session O/H/L");
+            if self.chart.show_session_lines {
+                ui.checkbox(&mut self.chart.session_local_day, "Local day");
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_pnl_hud, "PnL HUD")
+                .on_hover_text("Live position, uPnL and liquidation distance over the candles");
+            if self.chart.show_pnl_hud {
+                egui::ComboBox::from_id_source("pnl_hud_corner")
+                    .selected_text(self.chart.pnl_hud_corner.label())
+                    .show_ui(ui, |ui| {
+                        for c in HudCorner::all() {
+                            ui.selectable_value(&mut self.chart.pnl_hud_corner, *c, c.label());
+                        }
+                    });
+            }
+
+            ui.separator();
+            ui.label("Y axis:");
+            for m in [YAxisMode::Price, YAxisMode::Percent] {
+                ui.selectable_value(&mut self.chart.y_mode, m, m.label());
+            }
+            if self.chart.y_mode == YAxisMode::Percent {
+                match self.chart.pct_ref_t {
+                    Some(t) => {
+                        ui.label(format!("ref {}", format_ts(self.time_mode, t)));
+                        if ui.button("Reset ref").clicked() {
+                            self.chart.pct_ref_t = None;
+                        }
+                    }
+                    None => {
+                        ui.weak("ref: first visible (click a candle to rebase)");
+                    }
+                }
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.auto_y, "Auto Y");
+
+            if !self.chart.auto_y {
+                ui.label("Y range:");
+                ui.add(
+                    egui::DragValue::new(&mut self.chart.y_min)
+                        .speed(1.0)
+                        .prefix("min "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.chart.y_max)
+                        .speed(1.0)
+                        .prefix("max "),
+                );
+                if ui.button("Reset Y").clicked() {
+                    self.chart.auto_y = true;
+                }
+            }
+        });
+
+        // drawing toolbar (candle chart overlay)
+        ui.horizontal(|ui| {
+            ui.label("Draw:");
+            for tool in DrawTool::all() {
+                if ui
+                    .selectable_label(self.draw_tool == tool, tool.label())
+                    .clicked()
+                {
+                    self.draw_tool = tool;
+                    self.draw_anchor = None;
+                }
+            }
+            if self.draw_anchor.is_some() {
+                ui.weak("click the second point…");
+            }
+            if let Some(i) = self.selected_drawing {
+                ui.separator();
+                ui.label(format!("selected #{}", i + 1));
+                if ui.button("Remove").clicked() {
+                    self.remove_drawing(i);
+                }
+            }
+
+            ui.separator();
+            self.ui_price_alerts(ui);
+        });
+
+        ui.separator();
+
+        // Layout & appearance tweaks
+        egui::CollapsingHeader::new("Layout & appearance")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.label("Layout");
+                ui.add(
+                    egui::Slider::new(&mut self.layout.ladders_height_ratio, 0.2..=0.6)
+                        .text("Ladders/trading height"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.layout.depth_width_ratio, 0.25..=0.7)
+                        .text("Depth width"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.layout.volume_height_ratio, 0.15..=0.6)
+                        .text("Volume height (vs candles)"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.layout.candle_body_width_factor, 0.3..=1.0)
+                        .text("Candle body width"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.layout.candle_wick_width, 0.5..=4.0)
+                        .text("Wick width"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Candle bodies:");
+                    for body in CandleBody::all() {
+                        ui.selectable_value(&mut self.layout.candle_body, *body, body.label());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Candle color by:");
+                    for basis in CandleColorBasis::all() {
+                        ui.selectable_value(
+                            &mut self.layout.candle_color_basis,
+                            *basis,
+                            basis.label(),
+                        );
+                    }
+                });
+
+                ui.separator();
+                ui.label("Colors");
+                ui.horizontal(|ui| {
+                    ui.label("Bull:");
+                    ui.color_edit_button_srgba(&mut self.appearance.bull_color);
+                    ui.label("Bear:");
+                    ui.color_edit_button_srgba(&mut self.appearance.bear_color);
+                    ui.label("Volume:");
+                    ui.color_edit_button_srgba(&mut self.appearance.volume_color);
+                });
+                ui.checkbox(&mut self.appearance.depth_filled, "Filled depth areas");
+                ui.add(
+                    egui::Slider::new(&mut self.chart.gap_secs, GAP_FLOOR_SECS..=86_400)
+                        .logarithmic(true)
+                        .suffix("s")
+                        .text("Clock gap threshold"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.appearance.wall_sigma, 1.0..=5.0)
+                        .text("Wall threshold (σ)"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Ladder columns:");
+                    ui.checkbox(&mut self.appearance.ladder_cum_size, "Cum size");
+                    ui.checkbox(&mut self.appearance.ladder_cum_notional, "Cum notional");
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.appearance.flash_updates, "Flash updated levels");
+                    ui.add_enabled(
+                        self.appearance.flash_updates,
+                        egui::Slider::new(&mut self.appearance.flash_secs, 0.2..=5.0)
+                            .suffix("s")
+                            .text("fade"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    let mut ms = self.repaint.interval.as_millis() as u64;
+                    let slider = egui::Slider::new(&mut ms, 16..=500)
+                        .logarithmic(true)
+                        .suffix("ms")
+                        .text("Repaint every");
+                    if ui.add(slider).changed() {
+                        self.repaint.interval = Duration::from_millis(ms);
+                    }
+                    let mut throttle = self.repaint.idle_interval.is_some();
+                    if ui
+                        .checkbox(&mut throttle, "Slow down when idle")
+                        .on_hover_text(
+                            "Repaint every 500ms after a few seconds without data or input; \
+                             a replay stops repainting until input",
+                        )
+                        .changed()
+                    {
+                        self.repaint.idle_interval = throttle.then_some(DEFAULT_IDLE_INTERVAL);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(
+                        &mut self.appearance.multi_res_ladder,
+                        "Multi-resolution ladder",
+                    );
+                    ui.label("tick multiples:");
+                    let resp = ui.add(
+                        egui::TextEdit::singleline(&mut self.ladder_multiples_input)
+                            .desired_width(90.0),
+                    );
+                    if resp.changed() {
+                        let multiples = parse_multiples(&self.ladder_multiples_input);
+                        if !multiples.is_empty() {
+                            self.appearance.ladder_multiples = multiples;
+                        }
+                    }
+                });
+            });
+
+        ui.separator();
+
+        // replay sub-tab selector (only in replay mode)
+        if matches!(self.mode, Mode::Replay) {
+            ui.horizontal(|ui| {
+                ui.selectable_value(
+                    &mut self.replay_tab,
+                    ReplayTab::Orderbook,
+                    "Orderbook + Trades",
+                );
+                ui.selectable_value(&mut self.replay_tab, ReplayTab::Candles, "Candles + Volume");
+            });
+            ui.separator();
+        }
+    }
+
+    // spread buckets at the chart TF up to `at_ts`
+    fn spread_buckets(&mut self, is_live: bool, at_ts: u64) -> Vec<SpreadBucket> {
+        let tf = self.chart.selected_tf;
+        if is_live {
+            return bucket_spreads(self.live_spreads.iter().copied(), tf);
+        }
+        let Some(td) = self.replay_data.get(&self.current_ticker) else {
+            return Vec::new();
+        };
+        let all = self
+            .spread_cache
+            .entry((self.current_ticker.clone(), tf))
+            .or_insert_with(|| Arc::new(spread_series(td, tf)));
+        let end = all.partition_point(|b| b.0 <= at_ts);
+        all[..end].to_vec()
+    }
+
+    fn candle_style(&self) -> CandleRenderStyle {
+        CandleRenderStyle {
+            body_width_factor: self.layout.candle_body_width_factor,
+            wick_width: self.layout.candle_wick_width,
+            up_color: self.appearance.bull_color,
+            down_color: self.appearance.bear_color,
+            body: self.layout.candle_body,
+            color_basis: self.layout.candle_color_basis,
+        }
+    }
+
+    // ---- MULTI UI ----
+
+    fn ui_multi(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading(format!("MULTI · {} candles", tf_label(MULTI_TF)));
+            ui.checkbox(&mut self.pair.show, "Pair panel");
+        });
+        ui.separator();
+        if self.pair.show {
+            self.ui_pair(ui);
+            ui.separator();
+        }
+
+        let tickers = self.tickers.clone();
+        let mut open = None;
+        ui.columns(tickers.len().max(1), |cols| {
+            for (ui, tk) in cols.iter_mut().zip(&tickers) {
+                if self.ui_multi_cell(ui, tk) {
+                    open = Some(tk.clone());
+                }
+            }
+        });
+
+        // a cell's Open button goes back to the full single-ticker view
+        if let Some(tk) = open {
+            if tk != self.current_ticker {
+                self.switch_ticker(&tk);
+            }
+            self.mode = Mode::Live;
+        }
+    }
+
+    // ratio or hedged spread of two tickers, and its rolling z-score with
+    // ±z_band bands
+    fn ui_pair(&mut self, ui: &mut egui::Ui) {
+        let tickers = self.tickers.clone();
+        ui.horizontal(|ui| {
+            for (id, tk) in [("pair_a", &mut self.pair.a), ("pair_b", &mut self.pair.b)] {
+                egui::ComboBox::from_id_source(id)
+                    .selected_text(tk.as_str())
+                    .show_ui(ui, |ui| {
+                        for t in &tickers {
+                            ui.selectable_value(tk, t.clone(), t);
+                        }
+                    });
+            }
+            ui.separator();
+            ui.selectable_value(&mut self.pair.spread, false, "A / B");
+            ui.selectable_value(&mut self.pair.spread, true, "A − h·B");
+            if self.pair.spread {
+                ui.add(
+                    egui::DragValue::new(&mut self.pair.hedge)
+                        .speed(0.001)
+                        .prefix("h "),
+                );
+            }
+            ui.separator();
+            ui.add(
+                egui::DragValue::new(&mut self.pair.z_window)
+                    .clamp_range(2..=MULTI_HISTORY_CANDLES)
+                    .prefix("z window "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.pair.z_band)
+                    .speed(0.05)
+                    .clamp_range(0.5..=5.0)
+                    .prefix("bands ±"),
+            );
+        });
+
+        let series = |tk: &str| {
+            self.multi_candles
+                .get(tk)
+                .map(|a| a.series())
+                .unwrap_or(&[])
+        };
+        let metric = self.pair.metric();
+        let values = aligned(series(&self.pair.a), series(&self.pair.b), metric);
+        let z = rolling_zscore(&values, self.pair.z_window);
+
+        ui.horizontal(|ui| {
+            match values.last() {
+                Some((_, v)) => ui.label(format!("{} {v:.5}", metric.label())),
+                None => ui.label("no overlapping candles yet"),
+            };
+            match z.last() {
+                Some((_, z)) if z.abs() >= self.pair.z_band => {
+                    ui.colored_label(Color32::from_rgb(255, 180, 0), format!("z {z:+.2}"))
+                }
+                Some((_, z)) => ui.label(format!("z {z:+.2}")),
+                None => ui.label(format!("z needs {} candles", self.pair.z_window)),
+            };
+        });
+
+        let mode = self.time_mode;
+        let line = |pts: &[(u64, f64)]| -> PlotPoints {
+            pts.iter()
+                .map(|(t, v)| [*t as f64, *v])
+                .collect::<Vec<_>>()
+                .into()
+        };
+        let link = ui.id().with("pair_x");
+        Plot::new("pair_value")
+            .height(140.0)
+            .link_axis(link, true, false)
+            .x_axis_formatter(move |mark, _bounds, _transform| format_ts(mode, mark.value as u64))
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(line(&values)).name(metric.label()));
+            });
+        let band = self.pair.z_band;
+        let band_color = Color32::from_rgb(255, 180, 0);
+        Plot::new("pair_zscore")
+            .height(100.0)
+            .link_axis(link, true, false)
+            .x_axis_formatter(move |mark, _bounds, _transform| format_ts(mode, mark.value as u64))
+            .show(ui, |plot_ui| {
+                plot_ui.hline(HLine::new(0.0).color(Color32::GRAY));
+                for y in [band, -band] {
+                    plot_ui.hline(
+                        HLine::new(y)
+                            .color(band_color)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+                plot_ui.line(Line::new(line(&z)).name("z-score"));
+            });
+    }
+
+    // returns true when the cell's Open button was clicked
+    fn ui_multi_cell(&self, ui: &mut egui::Ui, tk: &str) -> bool {
+        let book = self.live_books.get(tk);
+        let mut open = false;
+
+        ui.horizontal(|ui| {
+            ui.strong(tk);
+            match book.and_then(|b| b.mid_with(self.mid_mode.mid_fn())) {
+                Some(mid) => ui.label(format!("mid {}", market_precision(tk).price(mid))),
+                None => ui.label("waiting for book..."),
+            };
+            open = ui.small_button("Open").clicked();
+        });
+
+        let series = self
+            .multi_candles
+            .get(tk)
+            .map(|a| a.series())
+            .unwrap_or(&[]);
+        let visible = &series[series.len().saturating_sub(MULTI_PANE_CANDLES)..];
+        let style = self.candle_style();
+        let mode = self.time_mode;
+        Plot::new(format!("multi_candles_{tk}"))
+            .height(ui.available_height() * 0.5)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .x_axis_formatter(move |mark, _bounds, _transform| format_ts(mode, mark.value as u64))
+            .show(ui, |plot_ui| {
+                draw_candles(plot_ui, visible, MULTI_TF as f64, &style);
+            });
+
+        let Some(book) = book else {
+            return open;
+        };
+        let sigma = self.appearance.wall_sigma;
+        let bid_walls = detect_walls(&book.bids, WALL_WINDOW, sigma);
+        let ask_walls = detect_walls(&book.asks, WALL_WINDOW, sigma);
+
+        stacked_ladder(
+            ui,
+            format!("multi_ladder_{tk}"),
+            (&book.bids, &bid_walls),
+            (&book.asks, &ask_walls),
+            book.scale,
+            MULTI_LADDER_ROWS,
+            market_precision(tk),
+        );
+
+        open
+    }
+
+    // one stacked ladder per tick multiple, fine to coarse
+    fn ui_multi_res_ladder(&self, ui: &mut egui::Ui) {
+        let book = &self.live_book;
+        let multiples = &self.appearance.ladder_multiples;
+        let prec = market_precision(&self.current_ticker);
+        ui.columns(multiples.len().max(1), |cols| {
+            for (ui, m) in cols.iter_mut().zip(multiples) {
+                let width = book.tick_keys * *m as PriceKey;
+                ui.label(format!("{}× tick ({})", m, key_to_price(width, book.scale)));
+                stacked_ladder(
+                    ui,
+                    ("multi_res_ladder", *m),
+                    (&bin_levels(&book.bids, width, true), &[]),
+                    (&bin_levels(&book.asks, width, false), &[]),
+                    book.scale,
+                    MULTI_RES_ROWS,
+                    prec,
+                );
+            }
+        });
+    }
+
+    // ---- LIVE UI ----
+
+    fn ui_live(&mut self, ui: &mut egui::Ui) {
+        let series_vec = self.live_series();
+        let avail_w = ui.available_width();
+        let avail_h = ui.available_height();
+
+        ui.horizontal(|ui| {
+            ui.heading(format!("LIVE {}", self.current_ticker));
+            let gaps = self.live_book.seq_gaps;
+            if gaps > 0 {
+                ui.colored_label(
+                    Color32::from_rgb(230, 160, 0),
+                    format!("{gaps} sequence gaps detected"),
+                )
+                .on_hover_text("Messages missing from the feed; the book was resynced after each");
+            }
+        });
+        ui.separator();
+
+        let ladders_h = avail_h * self.layout.ladders_height_ratio;
+
+        ui.allocate_ui(egui::vec2(avail_w, ladders_h), |ui| {
+            let left_w = avail_w * self.layout.depth_width_ratio;
+            let right_w = avail_w - left_w;
+
+            // running totals once per frame, for the depth plot and ladders
+            let scale = self.live_book.scale;
+            let bid_levels: Vec<_> = accumulate(self.live_book.bids.iter().rev(), scale).collect();
+            let ask_levels: Vec<_> = accumulate(self.live_book.asks.iter(), scale).collect();
+
+            ui.horizontal(|ui| {
+                // depth
+                ui.allocate_ui(egui::vec2(left_w, ladders_h), |ui| {
+                    let bid_points = depth_points(&bid_levels);
+                    let ask_points = depth_points(&ask_levels);
+
+                    let (bid_fill, ask_fill) = self.depth_fill_colors();
+                    Plot::new("live_depth")
+                        .height(ladders_h * 0.9)
+                        .show(ui, |plot_ui| {
+                            plot_depth_side(plot_ui, &bid_points, "Bids", bid_fill);
+                            plot_depth_side(plot_ui, &ask_points, "Asks", ask_fill);
+                        });
+                });
+
+                ui.separator();
+
+                // RIGHT: trading panel (top) + ladders (scroll below)
+                ui.allocate_ui(egui::vec2(right_w, ladders_h), |ui| {
+                    ui.vertical(|ui| {
+                        // --- TRADING PANEL (always visible) ---
+                        ui.group(|ui| {
+                            ui.heading("TRADING PANEL (LIVE)");
+
+                            match &self.trader_identity {
+                                TraderIdentity::Ready {
+                                    address,
+                                    subaccount,
+                                } => {
+                                    let mut number = *subaccount;
+                                    ui.horizontal(|ui| {
+                                        ui.label(self.trader_identity.label())
+                                            .on_hover_text(address);
+                                        ui.add(
+                                            egui::DragValue::new(&mut number)
+                                                .clamp_range(0..=MAX_PARENT_SUBACCOUNT)
+                                                .prefix("sub "),
+                                        );
+                                    });
+                                    self.set_subaccount(number);
+                                }
+                                other => {
+                                    ui.colored_label(Color32::RED, other.label());
+                                }
+                            }
+
+                            let account = self.account_rx.borrow().clone();
+                            match &account {
+                                Some(a) => {
+                                    let text = format!(
+                                        "Equity: {:.2} USDC | Free collateral: {:.2} USDC",
+                                        a.equity, a.free_collateral
+                                    );
+                                    if a.stale {
+                                        ui.colored_label(
+                                            Color32::YELLOW,
+                                            format!(
+                                                "{text} (stale since {})",
+                                                format_ts(self.time_mode, a.updated_ts)
+                                            ),
+                                        );
+                                    } else {
+                                        ui.label(text);
+                                    }
+                                }
+                                None => {
+                                    ui.label("Equity / free collateral: n/a");
+                                }
+                            }
+                            if let Some(a) = &account {
+                                match a.positions.get(&self.current_ticker) {
+                                    Some(p) => ui.label(format!(
+                                        "Position: {} {}",
+                                        if p.long { "LONG" } else { "SHORT" },
+                                        market_precision(&self.current_ticker).size(p.size)
+                                    )),
+                                    None => ui.label("Position: flat"),
+                                };
+                                let liq = a.liquidation_price(&self.current_ticker);
+                                let mid = self.live_book.mid_with(self.mid_mode.mid_fn());
+                                if let (Some(liq), Some(mid)) = (liq, mid) {
+                                    let away = (liq - mid).abs() / mid * 100.0;
+                                    ui.colored_label(
+                                        Color32::from_rgb(255, 90, 90),
+                                        format!(
+                                            "Est. liquidation: {} ({away:.1}% from mid)",
+                                            market_precision(&self.current_ticker).price(liq)
+                                        ),
+                                    )
+                                    .on_hover_text(format!(
+                                        "Where equity meets {:.0}% maintenance margin, from the \
+                                         last account fetch; other positions held fixed",
+                                        DEFAULT_MAINT_RATE * 100.0
+                                    ));
+                                }
+                            }
+
+                            // order type + leverage row
+                            ui.horizontal(|ui| {
+                                ui.label("Order type:");
+                                for ot in [UiOrderType::Market, UiOrderType::Limit] {
+                                    if ui
+                                        .selectable_label(
+                                            self.ui_order_type == ot,
+                                            ot.label(),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.ui_order_type = ot;
+                                        if ot == UiOrderType::Market {
+                                            self.ui_limit_side = None;
+                                        }
+                                    }
+                                }
+
+                                ui.separator();
+
+                                ui.label("TIF:");
+                                let ot = self.ui_order_type;
+                                egui::ComboBox::from_id_source("order_tif")
+                                    .selected_text(self.ui_tif.for_order(ot).label())
+                                    .show_ui(ui, |ui| {
+                                        for tif in UiTif::ALL {
+                                            ui.add_enabled_ui(tif.applies_to(ot), |ui| {
+                                                ui.selectable_value(
+                                                    &mut self.ui_tif,
+                                                    tif,
+                                                    tif.label(),
+                                                )
+                                            })
+                                            .response
+                                            .on_disabled_hover_text(
+                                                "Market orders fill at once or not at all",
+                                            );
+                                        }
+                                    });
+
+                                ui.separator();
+
+                                ui.label("Leverage (UI only):");
                                 ui.add(
                                     egui::DragValue::new(&mut self.ui_leverage)
                                         .speed(0.5)
@@ -1188,809 +4593,4046 @@ impl ComboApp {
                                 );
                             });
 
-                            // size + limit price
-                            ui.horizontal(|ui| {
-                                ui.label("Size (units):");
-                                ui.add(
-                                    egui::DragValue::new(
-                                        &mut self.trade_size_input,
-                                    )
-                                    .speed(0.001)
-                                    .clamp_range(0.0..=1000.0),
+                            // size + limit price
+                            ui.horizontal(|ui| {
+                                ui.label("Size (units):");
+                                ui.add(
+                                    egui::DragValue::new(
+                                        &mut self.trade_size_input,
+                                    )
+                                    .speed(0.001)
+                                    .clamp_range(0.0..=1000.0),
+                                );
+
+                                if matches!(self.ui_order_type, UiOrderType::Limit)
+                                {
+                                    ui.separator();
+                                    ui.label("Limit price:");
+                                    ui.add(
+                                        egui::DragValue::new(
+                                            &mut self.ui_limit_price,
+                                        )
+                                        .speed(0.1)
+                                        .clamp_range(0.0..=1_000_000.0),
+                                    );
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.ui_reduce_only, "Reduce-only")
+                                    .on_hover_text(
+                                        "Orders may only shrink the open position; refused \
+                                         when flat or on the position's side",
+                                    );
+                            });
+
+                            // execution preview
+                            if let Some(mid) = self.live_book.mid_with(self.mid_mode.mid_fn()) {
+                                let size_val = self.trade_size_input.max(0.0);
+                                let notional = size_val * mid;
+                                let lev = self.ui_leverage.max(1.0);
+                                let margin = if lev > 0.0 {
+                                    notional / lev
+                                } else {
+                                    0.0
+                                };
+
+                                ui.label(format!(
+                                    "Mid: {} | Notional ≈ {:.4} | Implied margin @ x{:.1} ≈ {:.4}",
+                                    market_precision(&self.current_ticker).price(mid),
+                                    notional,
+                                    lev,
+                                    margin
+                                ));
+
+                                // real numbers from the subaccount when we have them
+                                if let Some(a) = &account {
+                                    let eff_lev = if a.equity > 0.0 {
+                                        notional / a.equity
+                                    } else {
+                                        0.0
+                                    };
+                                    ui.label(format!(
+                                        "Free collateral after ≈ {:.2} | Effective leverage vs equity ≈ x{:.2}",
+                                        a.free_collateral - margin,
+                                        eff_lev
+                                    ));
+                                }
+                            }
+
+                            ui.separator();
+
+                            ui.horizontal(|ui| {
+                                // the side a ladder click suggested stays highlighted
+                                let suggested = self.ui_limit_side;
+                                let buy = egui::Button::new("Market BUY")
+                                    .selected(suggested == Some(OrderSide::Buy));
+                                if ui.add(buy).clicked() {
+                                    self.send_market_order(OrderSide::Buy);
+                                }
+                                let sell = egui::Button::new("Market SELL")
+                                    .selected(suggested == Some(OrderSide::Sell));
+                                if ui.add(sell).clicked() {
+                                    self.send_market_order(OrderSide::Sell);
+                                }
+                                if ui
+                                    .button("Flatten")
+                                    .on_hover_text("Reduce-only market close of the open position")
+                                    .clicked()
+                                {
+                                    self.send_flatten();
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                let kill = egui::Button::new(
+                                    egui::RichText::new("KILL").strong().color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(180, 20, 20))
+                                .min_size(egui::vec2(80.0, 28.0));
+                                if ui
+                                    .add(kill)
+                                    .on_hover_text("Cancel resting orders and flatten all positions (K)")
+                                    .clicked()
+                                {
+                                    self.request_kill();
+                                }
+                                ui.checkbox(&mut self.kill_skip_confirm, "Panic mode (no confirm)");
+                                ui.checkbox(&mut self.flatten_on_exit, "Flatten on exit");
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.hotkeys_armed, "Arm hotkeys");
+                                ui.label("size step:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.hotkey_size_step)
+                                        .speed(0.001)
+                                        .clamp_range(0.0001..=100.0),
+                                );
+                                if self.hotkeys_armed {
+                                    ui.colored_label(
+                                        Color32::YELLOW,
+                                        "hotkeys active: B buy · S sell · X flatten · ↑/↓ size · K kill",
+                                    );
+                                }
+                            });
+
+                            ui.label(
+                                "Note: Market BUY/SELL always send market orders; limits go out from ladder clicks.",
+                            );
+
+                            if !self.last_order_msg.is_empty() {
+                                ui.separator();
+                                ui.label(&self.last_order_msg);
+                            }
+                        });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.label("Live ladders (top 20)");
+                            ui.checkbox(&mut self.ladder_click_takes, "Click takes")
+                                .on_hover_text(
+                                    "Clicking a price sets a Limit order there; \
+                                     off: bid -> buy, ask -> sell; on: bid -> sell, ask -> buy",
+                                );
+                            ui.checkbox(&mut self.one_click_orders, "One-click orders")
+                                .on_hover_text(
+                                    "While hotkeys are armed, a ladder click places the limit \
+                                     order at once at the current size",
+                                );
+                            // a block expiry, not a time in force: it rests until then
+                            let short_term = format!("Short-term ({LIMIT_ORDER_BLOCKS} blocks)");
+                            egui::ComboBox::from_id_source("limit_expiry")
+                                .selected_text(if self.limit_good_til_time {
+                                    "GTC-ish (time)"
+                                } else {
+                                    short_term.as_str()
+                                })
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.limit_good_til_time,
+                                        false,
+                                        short_term.as_str(),
+                                    )
+                                    .on_hover_text(format!(
+                                        "Short-term order, lapses after {LIMIT_ORDER_BLOCKS} blocks"
+                                    ));
+                                    ui.selectable_value(
+                                        &mut self.limit_good_til_time,
+                                        true,
+                                        "GTC-ish (time)",
+                                    )
+                                    .on_hover_text(
+                                        "Long-term order, rests until the expiry below or a cancel",
+                                    );
+                                });
+                            if self.limit_good_til_time {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.limit_gtt_mins)
+                                        .clamp_range(1..=MAX_LIMIT_GTT_MINS)
+                                        .suffix(" min"),
+                                )
+                                .on_hover_text(fmt_duration_secs(
+                                    u64::from(self.limit_gtt_mins) * 60,
+                                ));
+                            }
+                            if self.one_click_live() {
+                                ui.label(
+                                    egui::RichText::new("ONE-CLICK LIVE: real testnet orders")
+                                        .strong()
+                                        .color(Color32::WHITE)
+                                        .background_color(Color32::from_rgb(180, 20, 20)),
+                                );
+                            } else if self.one_click_orders {
+                                ui.weak("(arm hotkeys to enable)");
+                            }
+                        });
+
+                        let sigma = self.appearance.wall_sigma;
+                        let bid_walls = detect_walls(&self.live_book.bids, WALL_WINDOW, sigma);
+                        let ask_walls = detect_walls(&self.live_book.asks, WALL_WINDOW, sigma);
+                        let cols = self.ladder_cols(market_precision(&self.current_ticker));
+                        let fade = if self.appearance.flash_updates {
+                            Duration::from_secs_f32(self.appearance.flash_secs)
+                        } else {
+                            Duration::ZERO
+                        };
+
+                        // price + is_bid of a clicked level, applied after drawing
+                        let mut clicked: Option<(f64, bool)> = None;
+                        let mut cancels: Vec<u32> = Vec::new();
+
+                        // my resting orders, as a leading Mine column when any
+                        let mine: Vec<OpenOrder> = self
+                            .open_orders_rx
+                            .borrow()
+                            .iter()
+                            .filter(|o| o.ticker == self.current_ticker)
+                            .cloned()
+                            .collect();
+                        let bid_rows = &bid_levels[..bid_levels.len().min(20)];
+                        let ask_rows = &ask_levels[..ask_levels.len().min(20)];
+                        let my_bids = orders_by_row(
+                            bid_rows,
+                            mine.iter().filter(|o| o.side == OrderSide::Buy),
+                            scale,
+                        );
+                        let my_asks = orders_by_row(
+                            ask_rows,
+                            mine.iter().filter(|o| o.side != OrderSide::Buy),
+                            scale,
+                        );
+                        let prec = cols.prec;
+
+                        // --- LADDERS BELOW, SCROLLABLE ---
+                        egui::ScrollArea::vertical()
+                            .auto_shrink([false, false])
+                            .max_height(ladders_h * 0.7)
+                            .show(ui, |ui| {
+                                ui.columns(3, |columns| {
+                                    columns[0].label("Bids");
+                                    egui::Grid::new("live_bids_grid")
+                                        .striped(true)
+                                        .show(&mut columns[0], |ui| {
+                                            if !mine.is_empty() {
+                                                ui.label("Mine");
+                                            }
+                                            cols.header(ui);
+                                            for (i, l) in bid_rows.iter().enumerate() {
+                                                if !mine.is_empty()
+                                                    && my_orders_cell(ui, my_bids.get(&i), prec)
+                                                {
+                                                    cancels.extend(&my_bids[&i].client_ids);
+                                                }
+                                                let wall = bid_walls.binary_search(&l.key).is_ok();
+                                                let flash = self.live_book.flash(&l.key, fade);
+                                                if ladder_row(ui, l, wall, flash, cols) {
+                                                    clicked = Some((l.price, true));
+                                                }
+                                            }
+                                        });
+
+                                    columns[1].label("Asks");
+                                    egui::Grid::new("live_asks_grid")
+                                        .striped(true)
+                                        .show(&mut columns[1], |ui| {
+                                            if !mine.is_empty() {
+                                                ui.label("Mine");
+                                            }
+                                            cols.header(ui);
+                                            for (i, l) in ask_rows.iter().enumerate() {
+                                                if !mine.is_empty()
+                                                    && my_orders_cell(ui, my_asks.get(&i), prec)
+                                                {
+                                                    cancels.extend(&my_asks[&i].client_ids);
+                                                }
+                                                let wall = ask_walls.binary_search(&l.key).is_ok();
+                                                let flash = self.live_book.flash(&l.key, fade);
+                                                if ladder_row(ui, l, wall, flash, cols) {
+                                                    clicked = Some((l.price, false));
+                                                }
+                                            }
+                                        });
+
+                                    columns[2].label("Time & sales");
+                                    self.ui_tape(&mut columns[2]);
+                                });
+
+                                if self.appearance.multi_res_ladder {
+                                    ui.separator();
+                                    self.ui_multi_res_ladder(ui);
+                                }
+                            });
+
+                        if let Some((price, is_bid)) = clicked {
+                            self.fill_from_ladder(price, is_bid);
+                        }
+                        for client_id in cancels {
+                            self.send_cancel(client_id);
+                        }
+                    });
+                });
+            });
+        });
+
+        ui.separator();
+
+        if self.chart_popped {
+            self.ui_chart_docked_note(ui);
+        } else {
+            self.ui_candles_generic(ui, &series_vec, None, true);
+        }
+    }
+
+    fn ui_source_filter(&mut self, ui: &mut egui::Ui, id: &str) {
+        egui::ComboBox::from_id_source(id)
+            .selected_text(self.trade_source_filter.label())
+            .show_ui(ui, |ui| {
+                for f in TradeSourceFilter::all() {
+                    ui.selectable_value(&mut self.trade_source_filter, f, f.label());
+                }
+            });
+    }
+
+    fn ui_venue_filter(&mut self, ui: &mut egui::Ui, venues: &[String]) {
+        egui::ComboBox::from_id_source("replay_trades_venue")
+            .selected_text(self.venue_filter.as_deref().unwrap_or("All venues"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.venue_filter, None, "All venues");
+                for v in venues {
+                    ui.selectable_value(&mut self.venue_filter, Some(v.clone()), v);
+                }
+            });
+    }
+
+    fn ui_tape(&mut self, ui: &mut egui::Ui) {
+        self.ui_source_filter(ui, "live_tape_source");
+
+        let (bull, bear) = (self.appearance.bull_color, self.appearance.bear_color);
+        let filter = self.trade_source_filter;
+        let prec = market_precision(&self.current_ticker);
+        egui::Grid::new("live_tape_grid")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Time");
+                ui.label("Source");
+                ui.label("Side");
+                ui.label("Price");
+                ui.label("Size");
+                ui.end_row();
+
+                for t in self.tape.iter().rev().filter(|t| filter.matches(&t.source)) {
+                    let color = if t.side == TradeSide::Buy { bull } else { bear };
+                    ui.label(format_ts(self.time_mode, t.ts));
+                    ui.colored_label(source_color(&t.source), &t.source);
+                    ui.colored_label(color, t.side.as_str());
+                    match t.price {
+                        Some(p) => ui.colored_label(color, format!("{:>9}", prec.price(p))),
+                        None => ui.weak("mkt"),
+                    };
+                    ui.label(format!("{:>8}", prec.size(t.size)));
+                    ui.end_row();
+                }
+            });
+    }
+
+    // ---- REPLAY UI ----
+
+    fn ui_replay(&mut self, ui: &mut egui::Ui) {
+        let Some((key, snap)) = self.take_replay_snapshot(ui) else {
+            return;
+        };
+
+        match self.replay_tab {
+            ReplayTab::Orderbook => self.ui_replay_orderbook(ui, &snap),
+            ReplayTab::Candles if self.chart_popped => self.ui_chart_docked_note(ui),
+            ReplayTab::Candles => {
+                let series_vec = self.replay_series(&snap).clone();
+                self.ui_candles_generic(ui, &series_vec, Some(&snap), false);
+            }
+        }
+
+        self.snap_worker.latest = Some((key, snap));
+    }
+
+    // Snapshot for the current ticker at replay_ts; the caller hands it back
+    // through `snap_worker.latest` once drawn. None (with a note) while missing.
+    fn take_replay_snapshot(&mut self, ui: &mut egui::Ui) -> Option<(SnapKey, TfSnapshot)> {
+        self.ensure_replay_ts_in_range();
+
+        let td = match self.replay_data.get(&self.current_ticker) {
+            Some(td) => Arc::clone(td),
+            None => {
+                ui.heading("No replay data for this ticker.");
+                ui.label(format!("Make sure CSVs exist in {}.", data_dir().display()));
+                return None;
+            }
+        };
+
+        self.snap_worker.poll();
+        let key = SnapKey {
+            ticker: self.current_ticker.clone(),
+            ts: self.replay_ts,
+            mid_mode: self.mid_mode,
+        };
+        self.snap_worker.request(key, &td);
+
+        // keep drawing the last finished snapshot while a newer one computes;
+        // a snapshot of another ticker is never shown
+        match self.snap_worker.latest.take() {
+            Some((k, s)) if k.ticker == self.current_ticker => Some((k, s)),
+            other => {
+                self.snap_worker.latest = other;
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Reconstructing snapshot…");
+                });
+                None
+            }
+        }
+    }
+
+    // add / step through / jump to the current ticker's bookmarks
+    fn ui_replay_bookmarks(&mut self, ui: &mut egui::Ui) {
+        let ts = self.replay_ts;
+        let mode = self.time_mode;
+        let ws = self.current_workspace();
+        let (prev, next) = (ws.prev_bookmark(ts), ws.next_bookmark(ts));
+        let marks = ws.bookmarks.clone();
+
+        let mut seek = None;
+        let mut remove = None;
+        ui.horizontal(|ui| {
+            ui.label("Bookmarks:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.bookmark_label)
+                    .hint_text("label")
+                    .desired_width(110.0),
+            );
+            if ui.button("Add @ ts").clicked() {
+                let label = match self.bookmark_label.trim() {
+                    "" => format_ts(mode, ts),
+                    l => l.to_string(),
+                };
+                self.current_workspace().add_bookmark(ts, label);
+                self.bookmark_label.clear();
+                self.mark_workspace_dirty();
+            }
+            if ui
+                .add_enabled(prev.is_some(), egui::Button::new("◀ Prev"))
+                .clicked()
+            {
+                seek = prev;
+            }
+            if ui
+                .add_enabled(next.is_some(), egui::Button::new("Next ▶"))
+                .clicked()
+            {
+                seek = next;
+            }
+            ui.menu_button(format!("All ({})", marks.len()), |ui| {
+                if marks.is_empty() {
+                    ui.weak("No bookmarks yet.");
+                }
+                for (i, b) in marks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(b.ts == ts, &b.label)
+                            .on_hover_text(format_ts(mode, b.ts))
+                            .clicked()
+                        {
+                            seek = Some(b.ts);
+                            ui.close_menu();
+                        }
+                        if ui.small_button("✕").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+            });
+        });
+
+        if let Some(t) = seek {
+            self.replay_ts = t;
+        }
+        if let Some(i) = remove {
+            self.current_workspace().bookmarks.remove(i);
+            self.mark_workspace_dirty();
+        }
+    }
+
+    fn ui_price_alerts(&mut self, ui: &mut egui::Ui) {
+        let prec = market_precision(&self.current_ticker);
+        if self.alert_price <= 0.0 {
+            if let Some(mid) = self.live_book.mid_with(self.mid_mode.mid_fn()) {
+                self.alert_price = mid;
+            }
+        }
+
+        ui.label("Alert: mid");
+        ui.selectable_value(&mut self.alert_above, true, "≥");
+        ui.selectable_value(&mut self.alert_above, false, "≤");
+        ui.add(
+            egui::DragValue::new(&mut self.alert_price)
+                .speed(prec.tick_size.max(0.01))
+                .clamp_range(0.0..=1_000_000.0),
+        );
+        if ui.button("Add alert").clicked() && self.alert_price > 0.0 {
+            let alert = PriceAlert {
+                above: self.alert_above,
+                price: self.alert_price,
+                triggered: false,
+            };
+            self.current_workspace().alerts.push(alert);
+            self.mark_workspace_dirty();
+        }
+
+        let alerts = self.current_workspace().alerts.clone();
+        let mut remove = None;
+        ui.menu_button(format!("Alerts ({})", alerts.len()), |ui| {
+            if alerts.is_empty() {
+                ui.weak("No alerts for this ticker.");
+            }
+            for (i, a) in alerts.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let text = format!("mid {} {}", a.op(), prec.price(a.price));
+                    if a.triggered {
+                        ui.colored_label(ALERT_COLOR, text);
+                    } else {
+                        ui.label(text);
+                    }
+                    if ui.small_button("✕").clicked() {
+                        remove = Some(i);
+                    }
+                });
+            }
+        });
+        if let Some(i) = remove {
+            self.current_workspace().alerts.remove(i);
+            self.mark_workspace_dirty();
+        }
+    }
+
+    // ---- POP-OUT CHART ----
+
+    // the screenshot lands as an event in the chart's viewport next frame,
+    // where finish_chart_png picks it up
+    fn request_chart_png(&mut self, ctx: &egui::Context) {
+        let viewport = if self.chart_popped {
+            chart_popout_id()
+        } else {
+            egui::ViewportId::ROOT
+        };
+        let path = chart_png_path(&self.current_ticker, self.chart.selected_tf, now_unix());
+        self.chart_export = Some(path);
+        ctx.send_viewport_cmd_to(viewport, egui::ViewportCommand::Screenshot);
+        ctx.request_repaint_of(viewport);
+    }
+
+    // runs after the chart is drawn in each viewport it can be in
+    fn finish_chart_png(&mut self, ctx: &egui::Context) {
+        if self.chart_export.is_none() {
+            return;
+        }
+        let here = ctx.viewport_id();
+        let shot = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Screenshot { viewport_id, image } if *viewport_id == here => {
+                    Some(image.clone())
+                }
+                _ => None,
+            })
+        });
+        let (Some(shot), Some(path)) = (shot, self.chart_export.take()) else {
+            return;
+        };
+        let cropped = self
+            .chart_rect
+            .filter(|(viewport, _)| *viewport == here)
+            .and_then(|(_, rect)| crop_screenshot(&shot, rect, ctx.pixels_per_point()));
+        let Some(cropped) = cropped else {
+            self.chart_export_msg = "PNG not saved: chart not on screen".to_string();
+            return;
+        };
+        self.chart_export_msg = match save_png(&path, &cropped) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "cannot save chart PNG");
+                format!("PNG not saved: {e}")
+            }
+        };
+    }
+
+    fn ui_chart_docked_note(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.weak("Chart is open in its own window.");
+            if ui.button("Dock chart").clicked() {
+                self.chart_popped = false;
+            }
+        });
+    }
+
+    // same chart as the grid, for the same ticker / TF / settings; runs every
+    // frame from `update` so it follows the live feed and the replay slider
+    fn show_chart_viewport(&mut self, ctx: &egui::Context) {
+        let title = format!(
+            "{} chart · {}",
+            self.current_ticker,
+            tf_label(self.chart.selected_tf)
+        );
+        ctx.show_viewport_immediate(
+            chart_popout_id(),
+            egui::ViewportBuilder::default()
+                .with_title(title)
+                .with_inner_size([1200.0, 800.0]),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| match self.mode {
+                    Mode::Live | Mode::Multi => {
+                        let series_vec = self.live_series();
+                        self.ui_candles_generic(ui, &series_vec, None, true);
+                    }
+                    Mode::Replay => {
+                        if let Some((key, snap)) = self.take_replay_snapshot(ui) {
+                            let series_vec = self.replay_series(&snap).clone();
+                            self.ui_candles_generic(ui, &series_vec, Some(&snap), false);
+                            self.snap_worker.latest = Some((key, snap));
+                        }
+                    }
+                });
+                self.finish_chart_png(ctx);
+
+                // closing the window puts the chart back in the grid
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.chart_popped = false;
+                }
+            },
+        );
+    }
+
+    fn ui_replay_orderbook(&mut self, ui: &mut egui::Ui, snap: &TfSnapshot) {
+        let prec = market_precision(&self.current_ticker);
+        let cols = self.ladder_cols(prec);
+        // same candle the candles tab outlines, so the two tabs read as one state
+        let ohlc = candle_at(self.replay_series(snap), snap.ts)
+            .map(|c| {
+                format!(
+                    "  ·  {} O {} H {} L {} C {}",
+                    tf_label(self.chart.selected_tf),
+                    prec.price(c.open),
+                    prec.price(c.high),
+                    prec.price(c.low),
+                    prec.price(c.close)
+                )
+            })
+            .unwrap_or_default();
+        ui.heading(format!(
+            "REPLAY {} @ {}{}",
+            self.current_ticker,
+            format_ts(self.time_mode, snap.ts),
+            ohlc
+        ));
+
+        let avail_w = ui.available_width();
+        let avail_h = ui.available_height();
+        let depth_w = avail_w * self.layout.depth_width_ratio;
+        let ladders_w = avail_w - depth_w;
+        let bid_levels: Vec<_> = accumulate(snap.bids.iter().rev(), snap.price_scale).collect();
+        let ask_levels: Vec<_> = accumulate(snap.asks.iter(), snap.price_scale).collect();
+
+        ui.horizontal(|ui| {
+            // depth
+            ui.allocate_ui(egui::vec2(depth_w, avail_h), |ui| {
+                let bid_points = depth_points(&bid_levels);
+                let ask_points = depth_points(&ask_levels);
+
+                let (bid_fill, ask_fill) = self.depth_fill_colors();
+                Plot::new("replay_depth")
+                    .height(avail_h * 0.9)
+                    .show(ui, |plot_ui| {
+                        plot_depth_side(plot_ui, &bid_points, "Bids", bid_fill);
+                        plot_depth_side(plot_ui, &ask_points, "Asks", ask_fill);
+                    });
+            });
+
+            ui.separator();
+
+            // ladders + trades
+            ui.allocate_ui(egui::vec2(ladders_w, avail_h), |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        let venues = self
+                            .replay_data
+                            .get(&self.current_ticker)
+                            .map(|td| td.venues.clone())
+                            .unwrap_or_default();
+                        ui.horizontal(|ui| {
+                            ui.label("Snapshot ladders");
+                            if venues.len() > 1 {
+                                ui.weak(format!("combined: {}", venues.join(" + ")));
+                            }
+                        });
+
+                        let sigma = self.appearance.wall_sigma;
+                        let bid_walls = detect_walls(&snap.bids, WALL_WINDOW, sigma);
+                        let ask_walls = detect_walls(&snap.asks, WALL_WINDOW, sigma);
+
+                        ui.columns(2, |columns| {
+                            columns[0].label("Bids");
+                            egui::Grid::new("replay_bids_grid").striped(true).show(
+                                &mut columns[0],
+                                |ui| {
+                                    cols.header(ui);
+                                    for l in bid_levels.iter().take(20) {
+                                        let wall = bid_walls.binary_search(&l.key).is_ok();
+                                        ladder_row(ui, l, wall, 0.0, cols);
+                                    }
+                                },
+                            );
+
+                            columns[1].label("Asks");
+                            egui::Grid::new("replay_asks_grid").striped(true).show(
+                                &mut columns[1],
+                                |ui| {
+                                    cols.header(ui);
+                                    for l in ask_levels.iter().take(20) {
+                                        let wall = ask_walls.binary_search(&l.key).is_ok();
+                                        ladder_row(ui, l, wall, 0.0, cols);
+                                    }
+                                },
+                            );
+                        });
+
+                        ui.separator();
+                        ui.label(format!(
+                            "Last mid: {}   Last vol: {}",
+                            prec.price(snap.last_mid),
+                            prec.size(snap.last_vol)
+                        ));
+
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Recent trades:");
+                            self.ui_source_filter(ui, "replay_trades_source");
+                            if venues.len() > 1 {
+                                self.ui_venue_filter(ui, &venues);
+                            }
+                        });
+                        let filter = self.trade_source_filter;
+                        let venue = self.venue_filter.as_deref();
+                        egui::ScrollArea::vertical()
+                            .max_height(avail_h * 0.4)
+                            .show(ui, |ui| {
+                                egui::Grid::new("replay_trades_grid").striped(true).show(
+                                    ui,
+                                    |ui| {
+                                        ui.label("Time");
+                                        if venues.len() > 1 {
+                                            ui.label("Venue");
+                                        }
+                                        ui.label("Source");
+                                        ui.label("Side");
+                                        ui.label("Size");
+                                        ui.end_row();
+
+                                        for tr in snap.trades.iter().rev().filter(|tr| {
+                                            filter.matches(&tr.source)
+                                                && venue.is_none_or(|v| v == tr.venue)
+                                        }) {
+                                            ui.label(format_ts(self.time_mode, tr.ts));
+                                            if venues.len() > 1 {
+                                                ui.label(&tr.venue);
+                                            }
+                                            ui.colored_label(source_color(&tr.source), &tr.source);
+                                            ui.label(tr.side.as_str());
+                                            ui.label(&tr.size_str);
+                                            ui.end_row();
+                                        }
+                                    },
+                                );
+                            });
+                    });
+            });
+        });
+    }
+
+    // ---- generic candles+volume for live & replay ----
+
+    fn ui_candles_generic(
+        &mut self,
+        ui: &mut egui::Ui,
+        series_vec: &Vec<Candle>,
+        snap: Option<&TfSnapshot>,
+        is_live: bool,
+    ) {
+        if series_vec.is_empty() {
+            ui.label(if is_live {
+                "No live candles yet (waiting for book mid)..."
+            } else {
+                "No candles yet at this replay time."
+            });
+            return;
+        }
+
+        let len = series_vec.len();
+        let window_len = self.chart.show_candles.min(len).max(1);
+        let visible = &series_vec[len - window_len..];
+
+        let (y_min, y_max) = if self.chart.auto_y {
+            let (min_v, max_v) = candle_y_range(visible);
+            self.chart.y_min = min_v;
+            self.chart.y_max = max_v;
+            (min_v, max_v)
+        } else {
+            sane_range(
+                self.chart.y_min,
+                self.chart.y_max,
+                1e-9,
+                candle_y_range(visible),
+            )
+        };
+
+        let avail_h = ui.available_height();
+        let avail_w = ui.available_width();
+
+        // the spread panel takes its share off the top of the volume split
+        let spread_h = if self.chart.show_spread {
+            avail_h * 0.18
+        } else {
+            0.0
+        };
+        let chart_h = avail_h - spread_h;
+        let volume_ratio = self.layout.volume_height_ratio.clamp(0.05, 0.8);
+        let candles_h = chart_h * (1.0 - volume_ratio);
+        let volume_h = chart_h * volume_ratio;
+
+        let tf = self.chart.selected_tf as f64;
+        let last = visible.last().unwrap();
+        let x_center = last.t as f64 + tf * 0.5;
+        let base_span = tf * self.chart.show_candles as f64;
+        let span = base_span / self.chart.x_zoom.max(1e-6);
+        // a zero zoom or a runaway pan would leave no window at all
+        let (x_min, x_max) = sane_range(
+            x_center - span * 0.5 + self.chart.x_pan_secs,
+            x_center + span * 0.5 + self.chart.x_pan_secs,
+            tf,
+            (x_center - base_span * 0.5, x_center + base_span * 0.5),
+        );
+
+        // percent mode only changes what is plotted: everything below stays in
+        // price space and goes through `py` when drawn, clicks come back via `price_of`
+        let pct_ref = match self.chart.y_mode {
+            YAxisMode::Price => None,
+            YAxisMode::Percent => self
+                .chart
+                .pct_ref_t
+                .and_then(|t| candle_at(series_vec, t).filter(|c| c.t == t))
+                .or_else(|| series_vec.iter().find(|c| c.t as f64 + tf >= x_min))
+                .map(|c| c.open)
+                .filter(|open| *open > 0.0),
+        };
+        let py = move |p: f64| match pct_ref {
+            Some(r) => (p / r - 1.0) * 100.0,
+            None => p,
+        };
+        let price_of = move |y: f64| match pct_ref {
+            Some(r) => r * (1.0 + y / 100.0),
+            None => y,
+        };
+
+        // replay draws at the snapshot's ts, which can trail replay_ts while
+        // a newer snapshot is still being computed
+        let at_ts = if is_live {
+            self.live_last_ts
+        } else {
+            snap.map(|s| s.ts).unwrap_or(self.replay_ts)
+        };
+        let current_t = if is_live {
+            None
+        } else {
+            candle_at(series_vec, at_ts).map(|c| c.t)
+        };
+        let spread = self
+            .chart
+            .show_spread
+            .then(|| self.spread_buckets(is_live, at_ts));
+        let gaps: Vec<(u64, u64)> = match self.current_replay_ticker() {
+            Some(td) if !is_live && self.chart.break_at_gaps => {
+                td.gaps_over(self.chart.gap_secs).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        // in replay only show fills that already happened at the snapshot ts
+        let markers = match (self.chart.show_trade_markers, self.current_replay_ticker()) {
+            (true, Some(td)) => {
+                let cutoff = if is_live { u64::MAX } else { at_ts };
+                trade_marker_points(&td.trade_events, visible, self.chart.selected_tf, cutoff)
+            }
+            _ => Vec::new(),
+        };
+        let scale_markers = self.chart.scale_trade_markers;
+        let max_marker_size = markers
+            .iter()
+            .map(|m| m.3)
+            .fold(0.0_f64, f64::max)
+            .max(1e-12);
+
+        let patterns = if self.chart.show_patterns {
+            detect_patterns(visible)
+        } else {
+            Vec::new()
+        };
+
+        // volume profile over the candles actually inside the x window
+        let profile_bucket = self.chart.profile_bucket;
+        let profile = if self.chart.show_volume_profile {
+            let in_window: Vec<Candle> = series_vec
+                .iter()
+                .filter(|c| c.t as f64 + tf >= x_min && (c.t as f64) <= x_max)
+                .copied()
+                .collect();
+            volume_profile(&in_window, profile_bucket)
+        } else {
+            BTreeMap::new()
+        };
+        let poc_key = profile
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(k, _)| *k);
+        let max_profile_vol = profile.values().copied().fold(0.0_f64, f64::max);
+
+        // footprints of the candles in the x window, only worked out when the
+        // whole allocated area would be big enough to read them; replay only
+        // counts trades printed by the snapshot ts
+        let prec = market_precision(&self.current_ticker);
+        let fp_row_h = prec.tick_size * self.chart.footprint_row_ticks as f64;
+        let fp_row_plot_h = (py(y_min + fp_row_h) - py(y_min)).abs();
+        let fp_room = [
+            avail_w as f64 / (x_max - x_min),
+            candles_h as f64 / (py(y_max) - py(y_min)),
+        ];
+        let footprints: Vec<(Candle, Vec<FootprintRow>)> =
+            match (self.chart.show_footprint, self.current_replay_ticker()) {
+                (true, Some(td)) if footprint_readable(fp_room, tf, fp_row_plot_h) => {
+                    let cutoff = if is_live { u64::MAX } else { at_ts };
+                    let n = td.trade_events.partition_point(|t| t.ts <= cutoff);
+                    let trades = &td.trade_events[..n];
+                    visible
+                        .iter()
+                        .filter(|c| c.t as f64 + tf >= x_min && (c.t as f64) <= x_max)
+                        .map(|c| {
+                            let rows = footprint(
+                                trades,
+                                c,
+                                self.chart.selected_tf,
+                                prec.tick_size,
+                                self.chart.footprint_row_ticks,
+                            );
+                            (*c, rows)
+                        })
+                        .filter(|(_, rows)| rows.iter().any(|r| r.total() > 0.0))
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+
+        let session = if self.chart.show_session_lines {
+            session_ohl(series_vec, at_ts, self.chart.session_local_day)
+        } else {
+            None
+        };
+        let liq = if is_live {
+            let account = self.account_rx.borrow();
+            account
+                .as_ref()
+                .and_then(|a| a.liquidation_price(&self.current_ticker))
+        } else {
+            None
+        };
+        let hud = if is_live && self.chart.show_pnl_hud {
+            self.live_pnl_hud()
+        } else {
+            None
+        };
+
+        let ws = self.current_workspace();
+        let drawings = ws.drawings.clone();
+        let bookmarks = ws.bookmarks.clone();
+        let alerts = ws.alerts.clone();
+        // candles in plot space (percent mode keeps up/down since the ref is > 0)
+        let plotted: Vec<Candle> = visible
+            .iter()
+            .map(|c| Candle {
+                open: py(c.open),
+                high: py(c.high),
+                low: py(c.low),
+                close: py(c.close),
+                ..*c
+            })
+            .collect();
+        let selected_drawing = self.selected_drawing;
+        let draw_anchor = self.draw_anchor;
+
+        // candles
+        ui.allocate_ui(egui::vec2(avail_w, candles_h), |ui| {
+            let mode = self.time_mode;
+            let bull = self.appearance.bull_color;
+            let bear = self.appearance.bear_color;
+            let candle_style = self.candle_style();
+
+            let mut plot = Plot::new(if is_live {
+                "candles_live"
+            } else {
+                "candles_replay"
+            })
+            .height(candles_h)
+            .include_y(py(y_min))
+            .include_y(py(y_max))
+            .allow_drag(true)
+            .allow_zoom(true)
+            .x_axis_formatter(move |mark, _bounds, _transform| {
+                let ts = mark.value as u64;
+                format_ts(mode, ts)
+            });
+            if pct_ref.is_some() {
+                plot = plot.y_axis_formatter(|mark, _chars, _range| {
+                    let decimals = if mark.step_size < 0.1 { 2 } else { 1 };
+                    format!("{:+.*}%", decimals, mark.value)
+                });
+            }
+
+            let plot_resp = plot.show(ui, |plot_ui| {
+                plot_ui.set_plot_bounds(plot_bounds((x_min, x_max), (py(y_min), py(y_max))));
+                if pct_ref.is_some() {
+                    plot_ui.hline(HLine::new(0.0).name("ref").color(Color32::GRAY));
+                }
+
+                // profile bars grow leftwards from the right edge, max 25% of width
+                if max_profile_vol > 0.0 {
+                    let max_w = (x_max - x_min) * 0.25;
+                    for (key, vol) in &profile {
+                        let y0 = py(*key as f64 * profile_bucket);
+                        let y1 = py((*key + 1) as f64 * profile_bucket);
+                        let w = max_w * (*vol / max_profile_vol);
+                        let color = if Some(*key) == poc_key {
+                            Color32::from_rgba_unmultiplied(255, 200, 0, 140)
+                        } else {
+                            Color32::from_rgba_unmultiplied(150, 150, 150, 60)
+                        };
+                        let pts: PlotPoints =
+                            vec![[x_max - w, y0], [x_max - w, y1], [x_max, y1], [x_max, y0]].into();
+                        plot_ui.polygon(Polygon::new(pts).fill_color(color).stroke(Stroke::NONE));
+                    }
+                }
+
+                for (a, b) in &gaps {
+                    let (a, b) = (*a as f64, *b as f64);
+                    if b < x_min || a > x_max {
+                        continue;
+                    }
+                    let pts: PlotPoints = vec![
+                        [a, py(y_min)],
+                        [a, py(y_max)],
+                        [b, py(y_max)],
+                        [b, py(y_min)],
+                    ]
+                    .into();
+                    plot_ui.polygon(
+                        Polygon::new(pts)
+                            .fill_color(Color32::from_rgba_unmultiplied(120, 120, 120, 40))
+                            .stroke(Stroke::NONE)
+                            .name("gap"),
+                    );
+                }
+
+                // footprints stand in for the candles they cover; candles with
+                // no priced trades, or all of them when too dense, stay plain
+                let show_fp = !footprints.is_empty()
+                    && footprint_readable(plot_ui.transform().dpos_dvalue(), tf, fp_row_plot_h);
+                if show_fp {
+                    let plain: Vec<Candle> = plotted
+                        .iter()
+                        .filter(|c| footprints.binary_search_by_key(&c.t, |(f, _)| f.t).is_err())
+                        .copied()
+                        .collect();
+                    draw_candles(plot_ui, &plain, tf, &candle_style);
+                } else {
+                    draw_candles(plot_ui, &plotted, tf, &candle_style);
+                }
+
+                // sells grow left and buys right from the middle, scaled to the
+                // candle's busiest side, with "sell x buy" over them
+                for (c, rows) in footprints.iter().filter(|_| show_fp) {
+                    let mid = c.t as f64 + tf * 0.5;
+                    let half = tf * 0.45;
+                    let max_side = rows
+                        .iter()
+                        .map(|r| r.buy.max(r.sell))
+                        .fold(0.0_f64, f64::max)
+                        .max(1e-12);
+                    for r in rows {
+                        let (y0, y1) = (py(r.price), py(r.price + fp_row_h));
+                        for (vol, dir, color) in [(r.sell, -1.0, bear), (r.buy, 1.0, bull)] {
+                            if vol <= 0.0 {
+                                continue;
+                            }
+                            let x1 = mid + dir * half * (vol / max_side);
+                            let pts: PlotPoints =
+                                vec![[mid, y0], [mid, y1], [x1, y1], [x1, y0]].into();
+                            plot_ui.polygon(
+                                Polygon::new(pts)
+                                    .fill_color(color.gamma_multiply(0.35))
+                                    .stroke(Stroke::NONE),
+                            );
+                        }
+                        if r.total() > 0.0 {
+                            let label = format!("{} x {}", prec.size(r.sell), prec.size(r.buy));
+                            plot_ui.text(
+                                Text::new(PlotPoint::new(mid, (y0 + y1) * 0.5), label)
+                                    .color(Color32::WHITE),
+                            );
+                        }
+                    }
+                    // open / close outline so the candle's direction still shows
+                    let color = if c.close >= c.open { bull } else { bear };
+                    let (left, right) = (c.t as f64 + tf * 0.05, c.t as f64 + tf * 0.95);
+                    let (o, cl) = (py(c.open), py(c.close));
+                    let outline: PlotPoints =
+                        vec![[left, o], [left, cl], [right, cl], [right, o], [left, o]].into();
+                    plot_ui.line(Line::new(outline).color(color).width(1.5).name("footprint"));
+                }
+
+                for c in &plotted {
+                    let left = c.t as f64;
+                    let right = left + tf;
+                    let (low, high) = (c.low, c.high);
+
+                    // outline the candle the depth tab is showing
+                    if Some(c.t) == current_t {
+                        let outline: PlotPoints = vec![
+                            [left, low],
+                            [left, high],
+                            [right, high],
+                            [right, low],
+                            [left, low],
+                        ]
+                        .into();
+                        plot_ui.line(
+                            Line::new(outline)
+                                .color(Color32::from_rgb(255, 200, 0))
+                                .width(1.5)
+                                .name("replay candle"),
+                        );
+                    }
+                }
+
+                for (x, y, is_buy, size) in &markers {
+                    let radius = if scale_markers {
+                        4.0 + 6.0 * (*size / max_marker_size) as f32
+                    } else {
+                        6.0
+                    };
+                    let (shape, color, name) = if *is_buy {
+                        (MarkerShape::Up, bull, "buy")
+                    } else {
+                        (MarkerShape::Down, bear, "sell")
+                    };
+                    plot_ui.points(
+                        Points::new(vec![[*x, py(*y)]])
+                            .shape(shape)
+                            .color(color)
+                            .filled(true)
+                            .radius(radius)
+                            .name(name),
+                    );
+                }
+
+                // bullish tags under the low, the rest over the high
+                let pad = (py(y_max) - py(y_min)) * 0.03;
+                for (i, pat) in &patterns {
+                    let c = &visible[*i];
+                    let x = c.t as f64 + tf * 0.5;
+                    let (y, color, anchor) = match pat.bias() {
+                        Some(true) => (py(c.low) - pad, bull, egui::Align2::CENTER_TOP),
+                        Some(false) => (py(c.high) + pad, bear, egui::Align2::CENTER_BOTTOM),
+                        None => (py(c.high) + pad, Color32::GRAY, egui::Align2::CENTER_BOTTOM),
+                    };
+                    plot_ui.text(
+                        Text::new(PlotPoint::new(x, y), pat.tag())
+                            .color(color)
+                            .anchor(anchor)
+                            .name(pat.label()),
+                    );
+                }
+
+                plot_ui.vline(VLine::new(at_ts as f64).name("now_ts"));
+
+                if let Some((open, high, low)) = session {
+                    for (label, y, color) in [
+                        ("O", open, Color32::from_rgb(100, 150, 255)),
+                        ("H", high, Color32::from_rgb(0, 200, 200)),
+                        ("L", low, Color32::from_rgb(220, 120, 0)),
+                    ] {
+                        plot_ui.hline(HLine::new(py(y)).name(label).color(color));
+                        plot_ui.text(
+                            Text::new(PlotPoint::new(x_min, py(y)), label)
+                                .color(color)
+                                .anchor(egui::Align2::LEFT_BOTTOM),
+                        );
+                    }
+                }
+
+                if let Some(liq) = liq {
+                    let color = Color32::from_rgb(255, 60, 60);
+                    plot_ui.hline(
+                        HLine::new(py(liq))
+                            .name("liquidation")
+                            .color(color)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                    plot_ui.text(
+                        Text::new(PlotPoint::new(x_min, py(liq)), "Liq")
+                            .color(color)
+                            .anchor(egui::Align2::LEFT_BOTTOM),
+                    );
+                }
+
+                for a in &alerts {
+                    plot_ui.hline(
+                        HLine::new(py(a.price))
+                            .name(format!("alert {}", a.op()))
+                            .color(ALERT_COLOR)
+                            .style(LineStyle::dotted_loose()),
+                    );
+                }
+                for b in &bookmarks {
+                    plot_ui.vline(
+                        VLine::new(b.ts as f64)
+                            .name(&b.label)
+                            .color(BOOKMARK_COLOR)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+
+                for (i, d) in drawings.iter().enumerate() {
+                    let pts: PlotPoints = match d {
+                        Drawing::Trend { a, b } => vec![[a[0], py(a[1])], [b[0], py(b[1])]].into(),
+                        Drawing::HRay { x, y } => {
+                            vec![[*x, py(*y)], [x_max.max(*x), py(*y)]].into()
+                        }
+                    };
+                    let width = if Some(i) == selected_drawing {
+                        3.0
+                    } else {
+                        1.5
+                    };
+                    plot_ui.line(Line::new(pts).color(DRAWING_COLOR).width(width));
+                }
+                if let Some(a) = draw_anchor {
+                    plot_ui.points(
+                        Points::new(vec![[a[0], py(a[1])]])
+                            .color(DRAWING_COLOR)
+                            .filled(true)
+                            .radius(4.0),
+                    );
+                }
+
+                // clicks go to the drawing tools
+                if plot_ui.response().clicked() {
+                    plot_ui.pointer_coordinate().map(|p| [p.x, price_of(p.y)])
+                } else {
+                    None
+                }
+            });
+
+            if let Some(hud) = hud {
+                let rows = hud.rows(|p| prec.price(p), |s| prec.size(s));
+                let frame = *plot_resp.transform.frame();
+                pnl_hud::paint(ui, frame, self.chart.pnl_hud_corner, &rows);
+            }
+
+            self.chart_rect = Some((ui.ctx().viewport_id(), plot_resp.response.rect));
+
+            if let Some(p) = plot_resp.inner {
+                self.handle_chart_click(p, series_vec, x_max - x_min, y_max - y_min);
+            }
+            let delete_pressed =
+                !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(egui::Key::Delete));
+            if let (Some(i), true) = (self.selected_drawing, delete_pressed) {
+                self.remove_drawing(i);
+            }
+
+            let hovered = plot_resp.response.hovered();
+
+            // F over the candles plot: same as the Fit button
+            if hovered
+                && !ui.ctx().wants_keyboard_input()
+                && ui.input(|i| i.key_pressed(egui::Key::F))
+            {
+                self.chart.fit();
+            }
+
+            // , / . step the TF; so does Ctrl + scroll (or a pinch) over the plot
+            let mut tf_steps = 0;
+            if !ui.ctx().wants_keyboard_input() {
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Comma) {
+                        tf_steps -= 1;
+                    }
+                    if i.key_pressed(egui::Key::Period) {
+                        tf_steps += 1;
+                    }
+                });
+            }
+            let zoom = ui.input(|i| i.zoom_delta());
+            if hovered && zoom != 1.0 {
+                // zooming in (scroll up) goes to a finer TF
+                self.tf_scroll += zoom.ln();
+                if self.tf_scroll.abs() >= TF_SCROLL_STEP {
+                    tf_steps -= self.tf_scroll.signum() as i32;
+                    self.tf_scroll = 0.0;
+                }
+            }
+            if tf_steps != 0 {
+                self.chart.set_tf(step_tf(self.chart.selected_tf, tf_steps));
+            }
+
+            // vertical zoom: Shift + scroll over candles plot
+            let mut scroll_y = 0.0f32;
+            let mut shift = false;
+            ui.ctx().input(|i| {
+                scroll_y = i.raw_scroll_delta.y;
+                shift = i.modifiers.shift;
+            });
+            if hovered && shift && scroll_y != 0.0 {
+                self.chart.auto_y = false;
+                let factor = 1.0 + (scroll_y as f64 * 0.002); // smooth
+                let factor = factor.clamp(0.2, 5.0);
+                let center = (self.chart.y_min + self.chart.y_max) * 0.5;
+                let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
+                self.chart.y_min = center - half_span;
+                self.chart.y_max = center + half_span;
+            }
+        });
+
+        ui.separator();
+
+        // volume
+        ui.allocate_ui(egui::vec2(avail_w, volume_h), |ui| {
+            let mode = self.time_mode;
+            let vol_color = self.appearance.volume_color;
+
+            let plot_resp = Plot::new(if is_live {
+                "volume_live"
+            } else {
+                "volume_replay"
+            })
+            .height(volume_h)
+            .include_y(0.0)
+            .allow_drag(true)
+            .allow_zoom(true)
+            .x_axis_formatter(move |mark, _bounds, _transform| {
+                let ts = mark.value as u64;
+                format_ts(mode, ts)
+            })
+            .show(ui, |plot_ui| {
+                let max_vol = visible
+                    .iter()
+                    .map(|c| c.volume)
+                    .fold(0.0_f64, f64::max)
+                    .max(1e-6);
+                let y_max_v = max_vol * 1.1;
+
+                plot_ui.set_plot_bounds(plot_bounds((x_min, x_max), (0.0, y_max_v)));
+
+                for c in visible {
+                    let left = c.t as f64;
+                    let mid = left + tf * 0.5;
+
+                    let line_pts: PlotPoints = vec![[mid, 0.0], [mid, c.volume]].into();
+                    plot_ui.line(Line::new(line_pts).color(vol_color).width(2.0));
+                }
+            });
+
+            // vertical zoom also works on volume (Shift + scroll)
+            let hovered = plot_resp.response.hovered();
+            let mut scroll_y = 0.0f32;
+            let mut shift = false;
+            ui.ctx().input(|i| {
+                scroll_y = i.raw_scroll_delta.y;
+                shift = i.modifiers.shift;
+            });
+            if hovered && shift && scroll_y != 0.0 {
+                self.chart.auto_y = false;
+                let factor = 1.0 + (scroll_y as f64 * 0.002);
+                let factor = factor.clamp(0.2, 5.0);
+                let center = (self.chart.y_min + self.chart.y_max) * 0.5;
+                let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
+                self.chart.y_min = center - half_span;
+                self.chart.y_max = center + half_span;
+            }
+        });
+
+        if let Some(buckets) = spread {
+            ui.separator();
+            let mode = self.time_mode;
+            let color = Color32::from_rgb(200, 160, 255);
+            Plot::new(if is_live {
+                "spread_live"
+            } else {
+                "spread_replay"
+            })
+            .height(spread_h)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .x_axis_formatter(move |mark, _bounds, _transform| format_ts(mode, mark.value as u64))
+            .show(ui, |plot_ui| {
+                let shown = buckets
+                    .iter()
+                    .filter(|b| b.0 as f64 + tf >= x_min && b.0 as f64 <= x_max);
+                let lo = shown.clone().map(|b| b.1).fold(f64::MAX, f64::min);
+                let hi = shown.map(|b| b.3).fold(f64::MIN, f64::max);
+                if lo <= hi {
+                    let pad = (hi - lo).max(1e-9) * 0.1;
+                    plot_ui.set_plot_bounds(plot_bounds((x_min, x_max), (lo - pad, hi + pad)));
+                }
+                plot_spread(plot_ui, &buckets, tf, color, &gaps);
+            });
+        }
+    }
+}
+
+impl eframe::App for ComboApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.viewport().close_requested()) {
+            self.on_close_requested();
+        }
+        if matches!(self.mode, Mode::Multi) {
+            self.tick_live();
+        }
+        // set again by whichever viewport draws the candles this frame
+        self.chart_rect = None;
+        if matches!(self.mode, Mode::Live) {
+            self.tick_live();
+            self.handle_trade_hotkeys(ctx);
+            if self.kill_confirm_open {
+                self.ui_kill_confirm(ctx);
+            }
+        }
+
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            self.ui_top_bar(ui);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| match self.mode {
+            Mode::Live => self.ui_live(ui),
+            Mode::Replay => self.ui_replay(ui),
+            Mode::Multi => self.ui_multi(ui),
+        });
+        self.finish_chart_png(ctx);
+
+        if self.chart_popped {
+            self.show_chart_viewport(ctx);
+        }
+
+        self.handle_undo(ctx);
+
+        // replay only changes on input, or when a snapshot being computed lands
+        let now = Instant::now();
+        self.flush_workspaces(now, false);
+        if !self.workspace_dirty.is_empty() {
+            ctx.request_repaint_after(WORKSPACE_SAVE_DELAY);
+        }
+        if ctx.input(|i| !i.events.is_empty()) || self.snap_worker.is_pending() {
+            self.repaint.touch(now);
+        }
+        let paused = matches!(self.mode, Mode::Replay);
+        if let Some(after) = self.repaint.next_repaint(now, paused) {
+            ctx.request_repaint_after(after);
+        }
+    }
+}
+
+// ------------- prometheus metrics -------------
+
+struct Metrics {
+    registry: Registry,
+    book_msgs: IntCounterVec,
+    trades: IntCounterVec,
+    subscribes: IntCounterVec,
+    seq_gaps: IntCounterVec,
+    // 1 while the orders feed for the ticker is streaming
+    feed_up: IntGaugeVec,
+    // unix ts of the last book message, for staleness alerts
+    feed_last_msg_ts: IntGaugeVec,
+    orders_placed: IntCounterVec,
+    orders_failed: IntCounterVec,
+}
+
+// only set with --metrics-port; every hook below is a no-op otherwise
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+fn metrics() -> Option<&'static Metrics> {
+    METRICS.get()
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let counter =
+            |name: &str, help: &str, labels: &[&str]| -> prometheus::Result<IntCounterVec> {
+                let c = IntCounterVec::new(Opts::new(name, help), labels)?;
+                registry.register(Box::new(c.clone()))?;
+                Ok(c)
+            };
+        let gauge = |name: &str, help: &str| -> prometheus::Result<IntGaugeVec> {
+            let g = IntGaugeVec::new(Opts::new(name, help), &["ticker"])?;
+            registry.register(Box::new(g.clone()))?;
+            Ok(g)
+        };
+
+        Ok(Self {
+            book_msgs: counter(
+                "ladder_book_messages_total",
+                "Orderbook feed messages received",
+                &["ticker"],
+            )?,
+            trades: counter(
+                "ladder_trades_received_total",
+                "Market trades received on the trades feed",
+                &["ticker"],
+            )?,
+            subscribes: counter(
+                "ladder_feed_subscribes_total",
+                "Feed (re)subscriptions; more than one per ticker means reconnects",
+                &["ticker"],
+            )?,
+            seq_gaps: counter(
+                "ladder_feed_sequence_gaps_total",
+                "Holes in the feed's message ids, each followed by a resync",
+                &["ticker"],
+            )?,
+            feed_up: gauge("ladder_feed_up", "1 while the orders feed is streaming")?,
+            feed_last_msg_ts: gauge(
+                "ladder_feed_last_message_timestamp_seconds",
+                "Unix time of the last orderbook message",
+            )?,
+            orders_placed: counter(
+                "ladder_orders_placed_total",
+                "Orders accepted by the node",
+                &["ticker"],
+            )?,
+            orders_failed: counter(
+                "ladder_orders_failed_total",
+                "Orders that failed, by stage",
+                &["ticker", "stage"],
+            )?,
+            registry,
+        })
+    }
+
+    fn render(&self) -> String {
+        let mut buf = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buf) {
+            error!(error = %e, "metrics encode failed");
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+// Registers the metrics; the caller spawns serve_metrics on its runtime.
+fn init_metrics() -> bool {
+    match Metrics::new() {
+        Ok(m) => METRICS.set(m).is_ok(),
+        Err(e) => {
+            error!(error = %e, "metrics init failed");
+            false
+        }
+    }
+}
+
+fn metrics_order_failed(ticker: &str, stage: &str) {
+    if let Some(m) = metrics() {
+        m.orders_failed.with_label_values(&[ticker, stage]).inc();
+    }
+}
+
+// Bare-bones HTTP/1.1: GET /metrics gets the text exposition, anything else 404.
+// a scraper sends its request right away; anything slower is dropped
+const METRICS_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// `--metrics-port N [--metrics-addr IP]`; loopback only unless an address is given
+fn metrics_addr_from_args(args: &[String]) -> Option<SocketAddr> {
+    let port = arg_value(args.iter().cloned(), "--metrics-port")?;
+    let Ok(port) = port.parse::<u16>() else {
+        warn!("--metrics-port: not a port number: {port:?}");
+        return None;
+    };
+    let ip = match arg_value(args.iter().cloned(), "--metrics-addr") {
+        None => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Some(a) => match a.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => {
+                warn!("--metrics-addr: not an IP address: {a:?}");
+                return None;
+            }
+        },
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+async fn serve_metrics(addr: SocketAddr) {
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!(%addr, error = %e, "metrics: cannot bind");
+            return;
+        }
+    };
+    info!("metrics: serving http://{addr}/metrics");
+
+    loop {
+        let (mut sock, _) = match listener.accept().await {
+            Ok(x) => x,
+            Err(e) => {
+                warn!(error = %e, "metrics accept failed");
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let mut req = [0u8; 1024];
+            let n = match tokio::time::timeout(METRICS_READ_TIMEOUT, sock.read(&mut req)).await {
+                Ok(Ok(n)) => n,
+                // idle or broken client: drop it, the next scrape reconnects
+                _ => return,
+            };
+            let head = String::from_utf8_lossy(&req[..n]);
+            let (status, body) = match (head.starts_with("GET /metrics"), metrics()) {
+                (true, Some(m)) => ("200 OK", m.render()),
+                _ => ("404 Not Found", String::new()),
+            };
+            let resp = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        });
+    }
+}
+
+// ------------- async live feed -------------
+
+// full-book snapshot rows every this many seconds while streaming;
+// `--snapshot-secs N`, 0 turns them off
+const DEFAULT_SNAPSHOT_SECS: u64 = 60;
+
+fn snapshot_every() -> Option<Duration> {
+    static EVERY: OnceLock<Option<Duration>> = OnceLock::new();
+    *EVERY.get_or_init(|| {
+        let secs = arg_value(env::args().skip(1), "--snapshot-secs")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SNAPSHOT_SECS);
+        (secs > 0).then(|| Duration::from_secs(secs))
+    })
+}
+
+// per-ticker counters bumped by run_live_feed; read by the headless stats log
+#[derive(Default, Debug)]
+struct FeedStats {
+    book_msgs: AtomicU64,
+    trades: AtomicU64,
+    resubscribes: AtomicU64,
+    seq_gaps: AtomicU64,
+}
+
+// how far the orders and trades feeds can overtake each other, in messages
+const SEQ_REORDER_WINDOW: usize = 64;
+
+// The indexer numbers every message on a connection (message_id), whatever
+// its channel, and our orders + trades feeds are that connection's only
+// subscriptions. So the ids they deliver should leave no holes; one still
+// open after SEQ_REORDER_WINDOW later ids is a dropped message.
+#[derive(Debug, Default)]
+struct SeqTracker {
+    // lowest id not seen yet
+    next: Option<u64>,
+    // seen ids above `next`
+    ahead: BTreeSet<u64>,
+}
+
+impl SeqTracker {
+    /// Record `id`; returns the missing ids when a hole is given up on.
+    fn observe(&mut self, id: u64) -> Option<Range<u64>> {
+        let Some(mut next) = self.next else {
+            self.next = Some(id + 1);
+            return None;
+        };
+        // duplicate, or a straggler from before a resubscribe
+        if id < next {
+            return None;
+        }
+        self.ahead.insert(id);
+        let mut gap = None;
+        loop {
+            while self.ahead.remove(&next) {
+                next += 1;
+            }
+            match self.ahead.first() {
+                Some(&first) if self.ahead.len() > SEQ_REORDER_WINDOW => {
+                    gap = Some(next..first);
+                    next = first;
+                }
+                _ => break,
+            }
+        }
+        self.next = Some(next);
+        gap
+    }
+}
+
+// count and log a gap; returns the feed's total so far
+fn note_seq_gap(current: &str, missing: &Range<u64>, stats: &FeedStats) -> u64 {
+    let n = stats.seq_gaps.fetch_add(1, Ordering::Relaxed) + 1;
+    warn!(
+        from = missing.start,
+        to = missing.end,
+        gaps = n,
+        "feed message ids skipped; resyncing book"
+    );
+    if let Some(m) = metrics() {
+        m.seq_gaps.with_label_values(&[current]).inc();
+    }
+    n
+}
+
+async fn run_live_feed(
+    book_tx: watch::Sender<LiveBook>,
+    tape_tx: mpsc::Sender<TapeTrade>,
+    ticker_rx: watch::Receiver<String>,
+    stats: Arc<FeedStats>,
+) {
+    let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(error = %e, "failed to load testnet.toml");
+            return;
+        }
+    };
+
+    let mut indexer = IndexerClient::new(config.indexer);
+    let mut ticker_rx = ticker_rx;
+
+    loop {
+        let current = ticker_rx.borrow().clone();
+        let keep_going = stream_ticker(
+            &mut indexer,
+            &current,
+            &book_tx,
+            &tape_tx,
+            &mut ticker_rx,
+            &stats,
+        )
+        .instrument(info_span!("feed", ticker = %current))
+        .await;
+        if !keep_going {
+            return;
+        }
+    }
+}
+
+// One subscription for `current`; returns when the ticker changes (true) or
+// the orders feed cannot be opened (false).
+async fn stream_ticker(
+    indexer: &mut IndexerClient,
+    current: &str,
+    book_tx: &watch::Sender<LiveBook>,
+    tape_tx: &mpsc::Sender<TapeTrade>,
+    ticker_rx: &mut watch::Receiver<String>,
+    stats: &FeedStats,
+) -> bool {
+    info!("subscribing live feed");
+    stats.resubscribes.fetch_add(1, Ordering::Relaxed);
+    if let Some(m) = metrics() {
+        m.subscribes.with_label_values(&[current]).inc();
+    }
+
+    // key scale from the market tick size; default if the lookup fails
+    let (scale, tick) = match indexer
+        .markets()
+        .get_perpetual_market(&Ticker(current.to_string()))
+        .await
+    {
+        Ok(m) => {
+            cache_market_precision(current, &m);
+            let tick = m.tick_size.to_f64().unwrap_or(0.0);
+            (scale_from_tick(tick), tick)
+        }
+        Err(e) => {
+            warn!(error = %e, "market meta lookup failed; using default price scale");
+            (DEFAULT_PRICE_SCALE, 0.0)
+        }
+    };
+
+    let mut feeds: Feeds<'_> = indexer.feed();
+    let ticker = Ticker(current.to_string());
+
+    let mut feed: DxFeed<OrdersMessage> = match feeds.orders(&ticker, false).await {
+        Ok(f) => f,
+        Err(e) => {
+            error!(error = %e, "orders feed subscribe failed");
+            return false;
+        }
+    };
+
+    // the tape is optional: without it the book keeps streaming
+    let mut trades_feed: Option<DxFeed<TradesMessage>> = match feeds.trades(&ticker, false).await {
+        Ok(f) => Some(f),
+        Err(e) => {
+            warn!(error = %e, "trades feed subscribe failed; tape disabled");
+            None
+        }
+    };
+
+    let mut book = LiveBook::with_scale(scale);
+    book.tick_keys = price_to_key(tick, scale).max(1);
+    book.seq_gaps = stats.seq_gaps.load(Ordering::Relaxed);
+    let mut seq = SeqTracker::default();
+    let mut snapshot_timer = snapshot_every()
+        .map(|every| tokio::time::interval_at(tokio::time::Instant::now() + every, every));
+    if let Some(m) = metrics() {
+        m.feed_up.with_label_values(&[current]).set(1);
+    }
+
+    loop {
+        tokio::select! {
+            msg = feed.recv() => {
+                let Some(msg) = msg else {
+                    warn!("orders feed ended; resubscribing");
+                    break;
+                };
+                stats.book_msgs.fetch_add(1, Ordering::Relaxed);
+                if let Some(m) = metrics() {
+                    m.book_msgs.with_label_values(&[current]).inc();
+                    m.feed_last_msg_ts
+                        .with_label_values(&[current])
+                        .set(now_unix() as i64);
+                }
+                let gap = match msg {
+                    OrdersMessage::Initial(init) => {
+                        debug!(
+                            bids = init.contents.bids.len(),
+                            asks = init.contents.asks.len(),
+                            "book snapshot"
+                        );
+                        // a fresh snapshot; ids before it may be another connection's
+                        seq = SeqTracker::default();
+                        seq.observe(init.message_id);
+                        let id = init.message_id;
+                        book.apply_initial(
+                            init.contents.bids,
+                            init.contents.asks,
+                            Some(id),
+                            |kind, side, p, s| append_book_csv(current, kind, side, p, s, id),
+                        );
+                        None
+                    }
+                    OrdersMessage::Update(upd) => {
+                        let gap = seq.observe(upd.message_id);
+                        let id = upd.message_id;
+                        book.apply_update(
+                            upd.contents.bids,
+                            upd.contents.asks,
+                            Some(id),
+                            |kind, side, p, s| append_book_csv(current, kind, side, p, s, id),
+                        );
+                        gap
+                    }
+                };
+                if let Some(missing) = &gap {
+                    book.seq_gaps = note_seq_gap(current, missing, stats);
+                }
+                let _ = book_tx.send(book.clone());
+                if gap.is_some() {
+                    break;
+                }
+            }
+            _ = async { snapshot_timer.as_mut()?.tick().await; Some(()) }, if snapshot_timer.is_some() => {
+                book.write_snapshot(|kind, side, p, s, id| {
+                    append_book_csv(current, kind, side, p, s, id)
+                });
+            }
+            msg = async { trades_feed.as_mut()?.recv().await }, if trades_feed.is_some() => {
+                let mut tape = Vec::new();
+                let mut gap = None;
+                match msg {
+                    // recent history: show it, but it is not new, so don't log it
+                    Some(TradesMessage::Initial(init)) => {
+                        gap = seq.observe(init.message_id);
+                        for t in init.contents.trades {
+                            let ts = t.created_at.timestamp().max(0) as u64;
+                            tape.push(TapeTrade::from_update(
+                                current, ts, &t.side, &t.price.0, &t.size.0,
+                            ));
+                        }
+                    }
+                    Some(TradesMessage::Update(upd)) => {
+                        gap = seq.observe(upd.message_id);
+                        for c in upd.contents {
+                            for t in c.trades {
+                                let ts = t.created_at.timestamp().max(0) as u64;
+                                let tr = TapeTrade::from_update(
+                                    current, ts, &t.side, &t.price.0, &t.size.0,
                                 );
+                                append_market_trade_csv(&tr);
+                                tape.push(tr);
+                            }
+                        }
+                    }
+                    None => {
+                        warn!("trades feed ended; tape disabled until resubscribe");
+                        trades_feed = None;
+                    }
+                }
+                tape.sort_by_key(|t| t.ts);
+                stats.trades.fetch_add(tape.len() as u64, Ordering::Relaxed);
+                if let Some(m) = metrics() {
+                    m.trades
+                        .with_label_values(&[current])
+                        .inc_by(tape.len() as u64);
+                }
+                for t in tape {
+                    // UI not draining (e.g. minimized): drop rather than stall the book
+                    let _ = tape_tx.try_send(t);
+                }
+                // can't tell which feed lost it; resync the book either way
+                if let Some(missing) = &gap {
+                    book.seq_gaps = note_seq_gap(current, missing, stats);
+                    let _ = book_tx.send(book.clone());
+                    break;
+                }
+            }
+        }
 
-                                if matches!(self.ui_order_type, UiOrderType::Limit)
-                                {
-                                    ui.separator();
-                                    ui.label("Limit price:");
-                                    ui.add(
-                                        egui::DragValue::new(
-                                            &mut self.ui_limit_price,
-                                        )
-                                        .speed(0.1)
-                                        .clamp_range(0.0..=1_000_000.0),
-                                    );
-                                }
-                            });
+        if ticker_rx.has_changed().unwrap_or(false) {
+            break;
+        }
+    }
 
-                            ui.horizontal(|ui| {
-                                ui.checkbox(&mut self.ui_reduce_only, "Reduce-only");
-                            });
+    if let Some(m) = metrics() {
+        m.feed_up.with_label_values(&[current]).set(0);
+    }
+    true
+}
 
-                            // execution preview
-                            if let Some(mid) = self.live_book.mid() {
-                                let size_val = self.trade_size_input.max(0.0);
-                                let notional = size_val * mid;
-                                let lev = self.ui_leverage.max(1.0);
-                                let margin = if lev > 0.0 {
-                                    notional / lev
-                                } else {
-                                    0.0
-                                };
+// ------------- funding rate poller -------------
 
-                                ui.label(format!(
-                                    "Mid: {:.2} | Notional ≈ {:.4} | Implied margin @ x{:.1} ≈ {:.4}",
-                                    mid, notional, lev, margin
-                                ));
+async fn run_funding_feed(
+    funding_tx: watch::Sender<Option<FundingInfo>>,
+    mut ticker_rx: watch::Receiver<String>,
+) {
+    let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(error = %e, "failed to load testnet.toml");
+            return;
+        }
+    };
+
+    let indexer = IndexerClient::new(config.indexer);
+
+    loop {
+        let current = ticker_rx.borrow_and_update().clone();
+
+        let info = match indexer
+            .markets()
+            .get_perpetual_market(&Ticker(current.clone()))
+            .await
+        {
+            Ok(m) => {
+                cache_market_precision(&current, &m);
+                m.next_funding_rate.to_f64().map(|rate| FundingInfo {
+                    ticker: current.clone(),
+                    rate,
+                })
+            }
+            Err(e) => {
+                warn!(ticker = %current, error = %e, "funding rate fetch failed");
+                None
+            }
+        };
+        let _ = funding_tx.send(info);
+
+        // refresh on a slow timer, or right away when the ticker changes
+        let _ = tokio::time::timeout(
+            Duration::from_secs(FUNDING_REFRESH_SECS),
+            ticker_rx.changed(),
+        )
+        .await;
+    }
+}
+
+// ------------- async trade executor (real orders) -------------
+
+// (ticker, side, size, reduce_only, limit price + expiry; None sends a market
+// order, time in force)
+type OrderReq = (
+    String,
+    OrderSide,
+    BigDecimal,
+    bool,
+    Option<(BigDecimal, LimitExpiry)>,
+    TimeInForce,
+);
+
+// trader ends of TraderLink's command and kill channels
+struct TraderInbox {
+    cmds: mpsc::Receiver<TradeCmd>,
+    kill: watch::Receiver<u64>,
+}
+
+impl TraderInbox {
+    // KILL first, then queued commands in order; None once the UI is gone
+    async fn next(&mut self) -> Option<TradeCmd> {
+        tokio::select! {
+            biased;
+            // a closed kill channel just stops matching; the queue still drains
+            Ok(()) = self.kill.changed() => Some(TradeCmd::Kill),
+            cmd = self.cmds.recv() => cmd,
+        }
+    }
+}
+
+async fn run_trader(
+    wallet: Option<Wallet>,
+    mut inbox: TraderInbox,
+    account_tx: watch::Sender<Option<AccountInfo>>,
+    orders_tx: watch::Sender<Vec<OpenOrder>>,
+    events_tx: mpsc::Sender<OrderEvent>,
+    tape_tx: mpsc::Sender<TapeTrade>,
+    max_slippage_pct: BigDecimal,
+) {
+    // one client id per logical order, reused by its retries so the chain
+    // sees a replacement rather than a second order where it can
+    let mut next_client_id = (now_unix() as u32) & 0x00ff_ffff;
+
+    let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
+        Ok(c) => c,
+        Err(e) => {
+            error!(error = %e, "failed to load testnet.toml");
+            return;
+        }
+    };
+
+    // derive_wallet already logged why
+    let Some(wallet) = wallet else {
+        return;
+    };
+
+    let mut node = match NodeClient::connect(config.node).await {
+        Ok(n) => n,
+        Err(e) => {
+            error!(error = %e, "node connect failed");
+            return;
+        }
+    };
+
+    let mut account = match wallet.account(0, &mut node).await {
+        Ok(a) => a,
+        Err(e) => {
+            error!(error = %e, "account sync failed");
+            return;
+        }
+    };
+
+    let mut sub = match account.subaccount(TRADING_SUBACCOUNT) {
+        Ok(s) => s,
+        Err(e) => {
+            error!(error = %e, "subaccount derive failed");
+            return;
+        }
+    };
+
+    let indexer = IndexerClient::new(config.indexer);
+    let mut account_refresh = tokio::time::interval(Duration::from_secs(ACCOUNT_REFRESH_SECS));
+    // limit orders placed from here that may still rest, by client id
+    let mut open: BTreeMap<u32, TrackedOrder> = BTreeMap::new();
+
+    loop {
+        let cmd = tokio::select! {
+            cmd = inbox.next() => match cmd {
+                Some(cmd) => cmd,
+                None => break,
+            },
+            _ = account_refresh.tick() => {
+                match indexer.accounts().get_subaccount(&sub).await {
+                    Ok(info) => {
+                        let _ = account_tx.send(Some(AccountInfo::from_subaccount(&info)));
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "subaccount fetch failed; marking account stale");
+                        // keep the last known numbers, just flag them
+                        account_tx.send_modify(|a| {
+                            if let Some(a) = a {
+                                a.stale = true;
                             }
+                        });
+                    }
+                }
+                if !open.is_empty() {
+                    refresh_open_orders(&indexer, &mut node, &sub, &mut open).await;
+                    publish_open_orders(&orders_tx, &open);
+                }
+                continue;
+            }
+        };
 
-                            ui.separator();
+        let orders: Vec<OrderReq> = match cmd {
+            TradeCmd::MarketOrder {
+                ticker,
+                side,
+                size,
+                tif,
+                reduce_only,
+            } => vec![(ticker, side, size, reduce_only, None, tif)],
+            TradeCmd::LimitOrder {
+                ticker,
+                side,
+                price,
+                size,
+                expiry,
+                tif,
+                reduce_only,
+            } => vec![(ticker, side, size, reduce_only, Some((price, expiry)), tif)],
+            TradeCmd::Flatten { ticker } => closing_orders(&indexer, &sub, Some(&ticker))
+                .await
+                .into_iter()
+                .map(close_order)
+                .collect(),
+            TradeCmd::Kill => {
+                // `open` only follows orders placed from here; the indexer
+                // also lists earlier sessions' and other clients' orders
+                let others: Vec<TrackedOrder> = listed_open_orders(&indexer, &sub)
+                    .await
+                    .into_iter()
+                    .filter(|l| open.values().all(|t| t.id != l.id))
+                    .collect();
+                warn!(
+                    resting = open.len() + others.len(),
+                    "KILL: cancelling resting orders, flattening all"
+                );
+                // a failed cancel stays in `open`, on the ladders, until it
+                // lapses or a later cancel lands
+                let ours: Vec<u32> = open.keys().copied().collect();
+                for client_id in ours {
+                    let t = &open[&client_id];
+                    if cancel_tracked(&mut node, &mut account, t, &events_tx).await {
+                        open.remove(&client_id);
+                    }
+                }
+                for t in &others {
+                    cancel_tracked(&mut node, &mut account, t, &events_tx).await;
+                }
+                publish_open_orders(&orders_tx, &open);
+                closing_orders(&indexer, &sub, None)
+                    .await
+                    .into_iter()
+                    .map(close_order)
+                    .collect()
+            }
+            TradeCmd::Cancel { client_id } => {
+                if let Some(t) = open.remove(&client_id) {
+                    if !cancel_tracked(&mut node, &mut account, &t, &events_tx).await {
+                        open.insert(client_id, t);
+                    }
+                    publish_open_orders(&orders_tx, &open);
+                }
+                Vec::new()
+            }
+            TradeCmd::SetSubaccount { number } => {
+                match account.subaccount(number) {
+                    Ok(s) => {
+                        info!(subaccount = number, "switched subaccount");
+                        sub = s;
+                        // the cached numbers belong to the old subaccount
+                        let _ = account_tx.send(None);
+                        account_refresh.reset_immediately();
+                    }
+                    Err(e) => {
+                        error!(subaccount = number, error = %e, "subaccount derive failed");
+                    }
+                }
+                Vec::new()
+            }
+        };
 
-                            ui.horizontal(|ui| {
-                                let order_type_label = match self.ui_order_type {
-                                    UiOrderType::Market => "MKT",
-                                    UiOrderType::Limit => "LMT(UI)",
-                                };
+        for (ticker, side, size, reduce_only, limit, tif) in orders {
+            let client_id = next_client_id;
+            next_client_id = next_client_id.wrapping_add(1);
+            let limit_price = limit.as_ref().map(|(p, _)| p.to_string());
+            let expiry = limit.as_ref().map(|(_, e)| *e);
+            let span = info_span!(
+                "order",
+                %ticker,
+                ?side,
+                %size,
+                reduce_only,
+                ?limit_price,
+                ?expiry,
+                ?tif,
+                client_id
+            );
+            async {
+                info!(
+                    "submitting {} order",
+                    if limit.is_some() { "limit" } else { "market" }
+                );
 
-                                if ui.button("Market BUY").clicked() {
-                                    let size_val =
-                                        self.trade_size_input.max(0.0);
-                                    let s_str =
-                                        format!("{:.8}", size_val);
-                                    if let Ok(size_bd) =
-                                        BigDecimal::from_str(&s_str)
-                                    {
-                                        let _ = self
-                                            .trade_tx
-                                            .try_send(TradeCmd::MarketOrder {
-                                                ticker: self
-                                                    .current_ticker
-                                                    .clone(),
-                                                side: OrderSide::Buy,
-                                                size: size_bd,
-                                            });
-                                        self.last_order_msg = format!(
-                                            "[{}] BUY {} size {} (exec: MARKET; reduce_only={}, limit_price={} [UI only])",
-                                            order_type_label,
-                                            self.current_ticker,
-                                            s_str,
-                                            self.ui_reduce_only,
-                                            if self.ui_limit_price > 0.0 {
-                                                self.ui_limit_price.to_string()
-                                            } else {
-                                                "n/a".into()
-                                            },
-                                        );
-                                    } else {
-                                        self.last_order_msg =
-                                            "Invalid size for BUY"
-                                                .to_string();
-                                    }
-                                }
-                                if ui.button("Market SELL").clicked() {
-                                    let size_val =
-                                        self.trade_size_input.max(0.0);
-                                    let s_str =
-                                        format!("{:.8}", size_val);
-                                    if let Ok(size_bd) =
-                                        BigDecimal::from_str(&s_str)
-                                    {
-                                        let _ = self
-                                            .trade_tx
-                                            .try_send(TradeCmd::MarketOrder {
-                                                ticker: self
-                                                    .current_ticker
-                                                    .clone(),
-                                                side: OrderSide::Sell,
-                                                size: size_bd,
-                                            });
-                                        self.last_order_msg = format!(
-                                            "[{}] SELL {} size {} (exec: MARKET; reduce_only={}, limit_price={} [UI only])",
-                                            order_type_label,
-                                            self.current_ticker,
-                                            s_str,
-                                            self.ui_reduce_only,
-                                            if self.ui_limit_price > 0.0 {
-                                                self.ui_limit_price.to_string()
-                                            } else {
-                                                "n/a".into()
-                                            },
-                                        );
-                                    } else {
-                                        self.last_order_msg =
-                                            "Invalid size for SELL"
-                                                .to_string();
-                                    }
-                                }
+                let market = match indexer
+                    .markets()
+                    .get_perpetual_market(&ticker.clone().into())
+                    .await
+                {
+                    Ok(m) => m,
+                    Err(e) => {
+                        error!(stage = "market_meta", error = %e, "order failed");
+                        metrics_order_failed(&ticker, "market_meta");
+                        let _ = events_tx.try_send(OrderEvent::Failed {
+                            ticker: ticker.clone(),
+                            stage: "market_meta",
+                            error: e.to_string(),
+                        });
+                        return;
+                    }
+                };
+
+                let mut attempt = 0;
+                loop {
+                    // fresh GoodTilBlock on every attempt
+                    let h = match node.latest_block_height().await {
+                        Ok(h) => h,
+                        Err(e) => {
+                            error!(stage = "height", error = %e, "order failed");
+                            metrics_order_failed(&ticker, "height");
+                            let _ = events_tx.try_send(OrderEvent::Failed {
+                                ticker: ticker.clone(),
+                                stage: "height",
+                                error: e.to_string(),
+                            });
+                            return;
+                        }
+                    };
+
+                    let builder = OrderBuilder::new(market.clone(), sub.clone());
+                    let until = expiry.map(|e| e.good_until(&h, Utc::now()));
+                    let builder = match (&limit, &until) {
+                        (Some((price, LimitExpiry::Time { .. })), Some(until)) => builder
+                            .limit(side, price.clone(), size.clone())
+                            .until(until.clone())
+                            .long_term(),
+                        (Some((price, _)), Some(until)) => builder
+                            .limit(side, price.clone(), size.clone())
+                            .until(until.clone()),
+                        // worst price from the oracle price; no oracle price
+                        // fails the build rather than going out unbounded
+                        _ => builder
+                            .market(side, size.clone())
+                            .allowed_slippage(max_slippage_pct.clone())
+                            .until(h.ahead(10)),
+                    };
+                    let (id, order) = match builder
+                        .reduce_only(reduce_only)
+                        .time_in_force(tif)
+                        .build(client_id)
+                    {
+                        Ok(x) => x,
+                        Err(e) => {
+                            error!(stage = "build", error = %e, "order failed");
+                            metrics_order_failed(&ticker, "build");
+                            let _ = events_tx.try_send(OrderEvent::Failed {
+                                ticker: ticker.clone(),
+                                stage: "build",
+                                error: e.to_string(),
+                            });
+                            return;
+                        }
+                    };
+
+                    let e = match node.place_order(&mut account, order).await {
+                        Ok(tx_hash) => {
+                            info!(%tx_hash, attempt, "order placed");
+                            if let Some(m) = metrics() {
+                                m.orders_placed.with_label_values(&[&ticker]).inc();
+                            }
+                            // a resting limit order isn't a fill: it is tracked
+                            // for the ladders, only market orders go to the
+                            // trades CSV and tape
+                            if let (Some((price, _)), Some(until)) = (&limit, until) {
+                                let placed_size = size.to_f64().unwrap_or(0.0);
+                                let order = OpenOrder {
+                                    client_id,
+                                    ticker: ticker.clone(),
+                                    side,
+                                    price: price.to_f64().unwrap_or(0.0),
+                                    size: placed_size,
+                                };
+                                open.insert(
+                                    client_id,
+                                    TrackedOrder {
+                                        id,
+                                        until,
+                                        placed_size,
+                                        order,
+                                    },
+                                );
+                                publish_open_orders(&orders_tx, &open);
+                            } else {
+                                append_trade_csv(
+                                    &ticker,
+                                    "gui_live",
+                                    TradeSide::from_buy(side == OrderSide::Buy),
+                                    size.to_f64().unwrap_or(0.0),
+                                );
+                                let _ = tape_tx.try_send(TapeTrade {
+                                    ts: now_unix(),
+                                    ticker: ticker.clone(),
+                                    source: "gui_live".to_string(),
+                                    side: TradeSide::from_buy(side == OrderSide::Buy),
+                                    price: None,
+                                    size: size.to_f64().unwrap_or(0.0),
+                                });
+                            }
+                            let _ = events_tx.try_send(OrderEvent::Placed {
+                                ticker: ticker.clone(),
+                                tx_hash: tx_hash.to_string(),
                             });
+                            return;
+                        }
+                        Err(e) => e,
+                    };
+
+                    let kind = classify_place_error(&e);
+                    if kind == PlaceErrorKind::Fatal || attempt >= ORDER_RETRIES {
+                        let stage = place_fail_stage(&e);
+                        error!(stage, attempt, ?kind, error = %e, "order failed");
+                        metrics_order_failed(&ticker, stage);
+                        let _ = events_tx.try_send(OrderEvent::Failed {
+                            ticker: ticker.clone(),
+                            stage,
+                            error: e.to_string(),
+                        });
+                        return;
+                    }
+
+                    attempt += 1;
+                    warn!(attempt, ?kind, error = %e, "place failed; retrying");
+                    let _ = events_tx.try_send(OrderEvent::Retrying {
+                        ticker: ticker.clone(),
+                        attempt,
+                        error: e.to_string(),
+                    });
+                    if kind == PlaceErrorKind::Sequence {
+                        match node.query_address(account.address()).await {
+                            Ok((_, sequence)) => account.set_sequence_number(sequence),
+                            Err(e) => warn!(error = %e, "sequence re-sync failed"),
+                        }
+                    }
+                }
+            }
+            .instrument(span)
+            .await;
+        }
+    }
+}
+
+// drops tracked orders that lapsed, filled or were cancelled, and updates
+// the unfilled size of the rest; orders the indexer hasn't seen yet stay
+async fn refresh_open_orders(
+    indexer: &IndexerClient,
+    node: &mut NodeClient,
+    sub: &Subaccount,
+    open: &mut BTreeMap<u32, TrackedOrder>,
+) {
+    match node.latest_block_height().await {
+        Ok(h) => {
+            let now = Utc::now();
+            open.retain(|_, t| !good_until_lapsed(&t.until, &h, now));
+        }
+        Err(e) => warn!(error = %e, "height fetch failed; keeping open orders"),
+    }
+    let opts = ListOrdersOpts {
+        limit: Some(100),
+        return_latest_orders: Some(true),
+        ..Default::default()
+    };
+    let listed = match indexer
+        .accounts()
+        .get_subaccount_orders(sub, Some(opts))
+        .await
+    {
+        Ok(l) => l,
+        Err(e) => {
+            warn!(error = %e, "open orders fetch failed");
+            return;
+        }
+    };
+    for o in listed {
+        let Some(t) = open.get_mut(&o.client_id.0) else {
+            continue;
+        };
+        if o.ticker.0 != t.order.ticker {
+            continue;
+        }
+        let done = matches!(
+            o.status,
+            ApiOrderStatus::OrderStatus(
+                OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::BestEffortCanceled
+            )
+        );
+        if done {
+            open.remove(&o.client_id.0);
+        } else {
+            t.order.size = (t.placed_size - o.total_filled.to_f64().unwrap_or(0.0)).max(0.0);
+        }
+    }
+}
 
-                            ui.label(
-                                "Note: Limit + reduce-only currently configure UI only; backend still sends market orders.",
-                            );
+// most orders one indexer listing returns
+const OPEN_ORDERS_LIST_LIMIT: u32 = 1000;
+
+// every OPEN order the indexer lists for the subaccount, wherever it was
+// placed from; empty (logged) when the listing fails
+async fn listed_open_orders(indexer: &IndexerClient, sub: &Subaccount) -> Vec<TrackedOrder> {
+    let opts = ListOrdersOpts {
+        limit: Some(OPEN_ORDERS_LIST_LIMIT),
+        status: Some(OrderStatus::Open),
+        return_latest_orders: Some(true),
+        ..Default::default()
+    };
+    match indexer
+        .accounts()
+        .get_subaccount_orders(sub, Some(opts))
+        .await
+    {
+        Ok(listed) => listed
+            .iter()
+            .filter_map(|o| tracked_from_listed(o, sub))
+            .collect(),
+        Err(e) => {
+            error!(stage = "open_orders", error = %e, "KILL could not list resting orders");
+            metrics_order_failed("*", "open_orders");
+            Vec::new()
+        }
+    }
+}
 
-                            if !self.last_order_msg.is_empty() {
-                                ui.separator();
-                                ui.label(&self.last_order_msg);
-                            }
-                        });
+// an indexer order in the shape cancel_tracked takes; None unless it is OPEN
+// with a block or time to cancel until
+fn tracked_from_listed(o: &OrderResponseObject, sub: &Subaccount) -> Option<TrackedOrder> {
+    if !matches!(o.status, ApiOrderStatus::OrderStatus(OrderStatus::Open)) {
+        return None;
+    }
+    let until = match (&o.good_til_block_time, &o.good_til_block) {
+        (Some(at), _) => OrderGoodUntil::Time(*at),
+        (None, Some(h)) => OrderGoodUntil::Block(h.clone()),
+        (None, None) => return None,
+    };
+    let placed_size = o.size.0.to_f64().unwrap_or(0.0);
+    let side = match o.side {
+        IndexerSide::Buy => OrderSide::Buy,
+        IndexerSide::Sell => OrderSide::Sell,
+    };
+    Some(TrackedOrder {
+        id: OrderId {
+            subaccount_id: Some(sub.clone().into()),
+            client_id: o.client_id.0,
+            order_flags: o.order_flags.clone() as u32,
+            clob_pair_id: o.clob_pair_id.0,
+        },
+        until,
+        placed_size,
+        order: OpenOrder {
+            client_id: o.client_id.0,
+            ticker: o.ticker.0.clone(),
+            side,
+            price: o.price.0.to_f64().unwrap_or(0.0),
+            size: (placed_size - o.total_filled.to_f64().unwrap_or(0.0)).max(0.0),
+        },
+    })
+}
 
-                        ui.separator();
+// short-term cancels are best effort (gossiped only) and the order lapses
+// at `until` anyway; long-term ones are good as long as the order they
+// cancel. True when the cancel went out
+async fn cancel_tracked(
+    node: &mut NodeClient,
+    account: &mut Account,
+    t: &TrackedOrder,
+    events_tx: &mpsc::Sender<OrderEvent>,
+) -> bool {
+    let ticker = t.order.ticker.clone();
+    let client_id = t.order.client_id;
+    let fail = |stage: &'static str, error: String| {
+        error!(%ticker, client_id, stage, %error, "cancel failed");
+        metrics_order_failed(&ticker, stage);
+        let _ = events_tx.try_send(OrderEvent::Failed {
+            ticker: ticker.clone(),
+            stage,
+            error,
+        });
+        false
+    };
+    let until = match &t.until {
+        OrderGoodUntil::Time(at) => OrderGoodUntil::Time(*at),
+        OrderGoodUntil::Block(_) => match node.latest_block_height().await {
+            Ok(h) => h.ahead(LIMIT_ORDER_BLOCKS).into(),
+            Err(e) => return fail("height", e.to_string()),
+        },
+    };
+    match node.cancel_order(account, t.id.clone(), until).await {
+        Ok(tx_hash) => {
+            info!(%ticker, client_id, %tx_hash, "order cancel sent");
+            let _ = events_tx.try_send(OrderEvent::Canceled {
+                ticker: ticker.clone(),
+                client_id,
+            });
+            true
+        }
+        Err(e) => fail("cancel", e.to_string()),
+    }
+}
 
-                        ui.label("Live ladders (top 20)");
+// Reduce-only market orders (ticker, side, size) that close the open positions,
+// only `ticker`'s when given; empty (logged) when flat or the subaccount fetch fails.
+async fn closing_orders(
+    indexer: &IndexerClient,
+    sub: &Subaccount,
+    ticker: Option<&str>,
+) -> Vec<(String, OrderSide, BigDecimal)> {
+    let info = match indexer.accounts().get_subaccount(sub).await {
+        Ok(info) => info,
+        Err(e) => {
+            let tk = ticker.unwrap_or("*");
+            error!(ticker = %tk, stage = "position", error = %e, "flatten failed");
+            metrics_order_failed(tk, "position");
+            return Vec::new();
+        }
+    };
+    let orders: Vec<_> = info
+        .open_perpetual_positions
+        .iter()
+        .filter(|(tk, _)| ticker.is_none_or(|t| tk.0 == t))
+        .map(|(tk, pos)| {
+            let side = match pos.side {
+                PositionSide::Long => OrderSide::Sell,
+                PositionSide::Short => OrderSide::Buy,
+            };
+            (tk.0.clone(), side, pos.size.0.abs())
+        })
+        .collect();
+    if orders.is_empty() {
+        info!(ticker = %ticker.unwrap_or("*"), "flatten: no open position");
+    }
+    orders
+}
 
-                        // --- LADDERS BELOW, SCROLLABLE ---
-                        egui::ScrollArea::vertical()
-                            .auto_shrink([false, false])
-                            .max_height(ladders_h * 0.7)
-                            .show(ui, |ui| {
-                                ui.columns(2, |cols| {
-                                    cols[0].label("Bids");
-                                    egui::Grid::new("live_bids_grid")
-                                        .striped(true)
-                                        .show(&mut cols[0], |ui| {
-                                            ui.label("Price");
-                                            ui.label("Size");
-                                            ui.end_row();
-                                            for (k, s) in self
-                                                .live_book
-                                                .bids
-                                                .iter()
-                                                .rev()
-                                                .take(20)
-                                            {
-                                                let p = key_to_price(*k);
-                                                ui.label(format!(
-                                                    "{:>9.2}",
-                                                    p
-                                                ));
-                                                ui.label(format!(
-                                                    "{:>8.4}",
-                                                    s
-                                                ));
-                                                ui.end_row();
-                                            }
-                                        });
+// ------------- headless collector -------------
 
-                                    cols[1].label("Asks");
-                                    egui::Grid::new("live_asks_grid")
-                                        .striped(true)
-                                        .show(&mut cols[1], |ui| {
-                                            ui.label("Price");
-                                            ui.label("Size");
-                                            ui.end_row();
-                                            for (k, s) in self
-                                                .live_book
-                                                .asks
-                                                .iter()
-                                                .take(20)
-                                            {
-                                                let p = key_to_price(*k);
-                                                ui.label(format!(
-                                                    "{:>9.2}",
-                                                    p
-                                                ));
-                                                ui.label(format!(
-                                                    "{:>8.4}",
-                                                    s
-                                                ));
-                                                ui.end_row();
-                                            }
-                                        });
-                                });
-                            });
-                    });
-                });
-            });
-        });
+const HEADLESS_STATS_SECS: u64 = 30;
 
-        ui.separator();
+fn file_len(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
 
-        self.ui_candles_generic(ui, &series_vec, None, true);
-    }
+// `--headless [--tickers ETH-USD,BTC-USD]`: one live feed per ticker writing
+// CSVs, a stats line every HEADLESS_STATS_SECS, no window.
+fn run_headless(tickers: Vec<String>, metrics_addr: Option<SocketAddr>) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
 
-    // ---- REPLAY UI ----
+    rt.block_on(async move {
+        if let Some(addr) = metrics_addr {
+            if init_metrics() {
+                tokio::spawn(serve_metrics(addr));
+            }
+        }
+        tokio::spawn(csv_sink::flush_periodically());
+
+        let mut feeds = Vec::new();
+        for tk in &tickers {
+            // nothing reads the book / tape here; the feed only needs somewhere to send
+            let (book_tx, _) = watch::channel(LiveBook::default());
+            let (tape_tx, _) = mpsc::channel::<TapeTrade>(1);
+            let (ticker_tx, ticker_rx) = watch::channel(tk.clone());
+            let stats = Arc::new(FeedStats::default());
+            tokio::spawn(run_live_feed(
+                book_tx,
+                tape_tx,
+                ticker_rx,
+                Arc::clone(&stats),
+            ));
+            // keep the sender alive so the feed never sees a ticker change
+            feeds.push((tk.clone(), ticker_tx, stats, (0u64, 0u64)));
+        }
 
-    fn ui_replay(&mut self, ui: &mut egui::Ui) {
-        self.ensure_replay_ts_in_range();
+        info!(
+            tickers = %tickers.join(","),
+            data_dir = %data_dir().display(),
+            "headless collector started"
+        );
 
-        let snapshot = self
-            .current_replay_ticker()
-            .map(|td| compute_snapshot_for(td, self.replay_ts));
+        let mut every = tokio::time::interval(Duration::from_secs(HEADLESS_STATS_SECS));
+        every.tick().await;
+        let mut last = std::time::Instant::now();
 
-        if snapshot.is_none() {
-            ui.heading("No replay data for this ticker.");
-            ui.label("Make sure CSVs exist in ./data.");
-            return;
-        }
+        loop {
+            let stop = tokio::select! {
+                _ = every.tick() => false,
+                _ = tokio::signal::ctrl_c() => true,
+            };
 
-        let snap = snapshot.as_ref().unwrap();
+            let secs = last.elapsed().as_secs_f64().max(1e-3);
+            last = std::time::Instant::now();
+            for (tk, _, stats, prev) in feeds.iter_mut() {
+                let book = stats.book_msgs.load(Ordering::Relaxed);
+                let trades = stats.trades.load(Ordering::Relaxed);
+                info!(
+                    ticker = %tk,
+                    book_msgs_per_sec = (book - prev.0) as f64 / secs,
+                    trades_per_sec = (trades - prev.1) as f64 / secs,
+                    subscribes = stats.resubscribes.load(Ordering::Relaxed),
+                    seq_gaps = stats.seq_gaps.load(Ordering::Relaxed),
+                    orderbook_kib = file_len(&data_write_path(&format!("orderbook_{tk}"))) / 1024,
+                    trades_kib = file_len(&data_write_path(&format!("trades_{tk}"))) / 1024,
+                    "feed stats"
+                );
+                *prev = (book, trades);
+            }
 
-        match self.replay_tab {
-            ReplayTab::Orderbook => self.ui_replay_orderbook(ui, snap),
-            ReplayTab::Candles => {
-                let series_vec = self.replay_series(snap).clone();
-                self.ui_candles_generic(ui, &series_vec, Some(snap), false);
+            if stop {
+                break;
             }
         }
-    }
-
-    fn ui_replay_orderbook(&self, ui: &mut egui::Ui, snap: &Snapshot) {
-        ui.heading(format!(
-            "REPLAY {} @ {}",
-            self.current_ticker,
-            format_ts(self.time_mode, self.replay_ts)
-        ));
 
-        let avail_w = ui.available_width();
-        let avail_h = ui.available_height();
-        let depth_w = avail_w * self.layout.depth_width_ratio;
-        let ladders_w = avail_w - depth_w;
+        info!("SIGINT, stopping feeds");
+    });
 
-        ui.horizontal(|ui| {
-            // depth
-            ui.allocate_ui(egui::vec2(depth_w, avail_h), |ui| {
-                let mut bid_points = Vec::new();
-                let mut ask_points = Vec::new();
+    // rows are buffered whole, so stopping the feeds mid-stream never leaves a
+    // half-written line; the last of them go out once nothing can add more
+    rt.shutdown_timeout(Duration::from_secs(2));
+    csv_sink::flush_all();
+}
 
-                let mut cum = 0.0;
-                for (k, s) in snap.bids.iter().rev() {
-                    let p = key_to_price(*k);
-                    cum += s;
-                    bid_points.push((p, cum));
-                }
+// ------------- main -------------
 
-                cum = 0.0;
-                for (k, s) in snap.asks.iter() {
-                    let p = key_to_price(*k);
-                    cum += s;
-                    ask_points.push((p, cum));
-                }
+fn main() {
+    init_crypto_provider();
 
-                Plot::new("replay_depth")
-                    .height(avail_h * 0.9)
-                    .show(ui, |plot_ui| {
-                        if !bid_points.is_empty() {
-                            let pts: PlotPoints = bid_points
-                                .iter()
-                                .map(|(x, y)| [*x, *y])
-                                .collect::<Vec<_>>()
-                                .into();
-                            plot_ui.line(Line::new(pts).name("Bids"));
-                        }
-                        if !ask_points.is_empty() {
-                            let pts: PlotPoints = ask_points
-                                .iter()
-                                .map(|(x, y)| [*x, *y])
-                                .collect::<Vec<_>>()
-                                .into();
-                            plot_ui.line(Line::new(pts).name("Asks"));
-                        }
-                    });
+    let args: Vec<String> = env::args().skip(1).collect();
+    init_tracing(log_json_from_args(&args));
+    let metrics_addr = metrics_addr_from_args(&args);
+
+    if args.iter().any(|a| a == "--headless") {
+        let tickers = arg_value(args.clone(), "--tickers")
+            .map(|v| {
+                v.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(|| {
+                vec![
+                    "ETH-USD".to_string(),
+                    "BTC-USD".to_string(),
+                    "SOL-USD".to_string(),
+                ]
             });
+        run_headless(tickers, metrics_addr);
+        return;
+    }
 
-            ui.separator();
+    let (tape_tx, tape_rx) = mpsc::channel::<TapeTrade>(TAPE_MAX_ROWS * 2);
 
-            // ladders + trades
-            ui.allocate_ui(egui::vec2(ladders_w, avail_h), |ui| {
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false, false])
-                    .show(ui, |ui| {
-                        ui.label("Snapshot ladders");
-
-                        ui.columns(2, |cols| {
-                            cols[0].label("Bids");
-                            egui::Grid::new("replay_bids_grid")
-                                .striped(true)
-                                .show(&mut cols[0], |ui| {
-                                    ui.label("Price");
-                                    ui.label("Size");
-                                    ui.end_row();
-                                    for (k, s) in
-                                        snap.bids.iter().rev().take(20)
-                                    {
-                                        let p = key_to_price(*k);
-                                        ui.label(format!("{:>9.2}", p));
-                                        ui.label(format!("{:>8.4}", s));
-                                        ui.end_row();
-                                    }
-                                });
+    // preload replay data from --data-dir (default ./data), plus any
+    // --merge-source <venue>=<dir> combined into the same book
+    let base_dir = data_dir();
+    let extra_sources = merge_sources_from_args(env::args().skip(1));
+    let tickers = vec!["ETH-USD", "BTC-USD", "SOL-USD"];
+    let replay_data = load_all_tickers(base_dir, &extra_sources, &tickers);
 
-                            cols[1].label("Asks");
-                            egui::Grid::new("replay_asks_grid")
-                                .striped(true)
-                                .show(&mut cols[1], |ui| {
-                                    ui.label("Price");
-                                    ui.label("Size");
-                                    ui.end_row();
-                                    for (k, s) in
-                                        snap.asks.iter().take(20)
-                                    {
-                                        let p = key_to_price(*k);
-                                        ui.label(format!("{:>9.2}", p));
-                                        ui.label(format!("{:>8.4}", s));
-                                        ui.end_row();
-                                    }
-                                });
-                        });
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
 
-                        ui.separator();
-                        ui.label(format!(
-                            "Last mid: {:.2}   Last vol: {:.4}",
-                            snap.last_mid, snap.last_vol
-                        ));
+    // the UI's current ticker; only the funding poller follows it
+    let (ticker_tx, _) = watch::channel::<String>("ETH-USD".to_string());
 
-                        ui.separator();
-                        ui.label("Recent trades:");
-                        egui::ScrollArea::vertical()
-                            .max_height(avail_h * 0.4)
-                            .show(ui, |ui| {
-                                egui::Grid::new("replay_trades_grid")
-                                    .striped(true)
-                                    .show(ui, |ui| {
-                                        ui.label("Time");
-                                        ui.label("Side");
-                                        ui.label("Size");
-                                        ui.end_row();
+    let (trade_tx, trade_rx) = mpsc::channel::<TradeCmd>(32);
+    let (kill_tx, kill_rx) = watch::channel(0u64);
 
-                                        for tr in snap.trades.iter().rev() {
-                                            ui.label(format_ts(
-                                                self.time_mode, tr.ts,
-                                            ));
-                                            ui.label(&tr.side);
-                                            ui.label(&tr.size_str);
-                                            ui.end_row();
-                                        }
-                                    });
-                            });
-                    });
-            });
-        });
+    if let Some(addr) = metrics_addr {
+        if init_metrics() {
+            rt.spawn(serve_metrics(addr));
+        }
+    }
+    rt.spawn(csv_sink::flush_periodically());
+
+    let (funding_tx, funding_rx) = watch::channel::<Option<FundingInfo>>(None);
+
+    // spawn live feed + funding poller
+    rt.spawn(run_funding_feed(funding_tx, ticker_tx.subscribe()).instrument(info_span!("funding")));
+    // one orders feed per ticker (Multi shows them all); they share the tape
+    let mut book_rxs = HashMap::new();
+    let mut feed_ticker_txs = Vec::new();
+    for tk in &tickers {
+        let (book_tx, book_rx) = watch::channel(LiveBook::default());
+        let (feed_ticker_tx, feed_ticker_rx) = watch::channel(tk.to_string());
+        rt.spawn(run_live_feed(
+            book_tx,
+            tape_tx.clone(),
+            feed_ticker_rx,
+            Arc::new(FeedStats::default()),
+        ));
+        book_rxs.insert(tk.to_string(), book_rx);
+        // keep the sender alive so the feed never sees a ticker change
+        feed_ticker_txs.push(feed_ticker_tx);
     }
 
-    // ---- generic candles+volume for live & replay ----
+    let (account_tx, account_rx) = watch::channel::<Option<AccountInfo>>(None);
+    let (orders_tx, open_orders_rx) = watch::channel::<Vec<OpenOrder>>(Vec::new());
 
-    fn ui_candles_generic(
-        &mut self,
-        ui: &mut egui::Ui,
-        series_vec: &Vec<Candle>,
-        _snap: Option<&Snapshot>,
-        is_live: bool,
-    ) {
-        if series_vec.is_empty() {
-            ui.label(if is_live {
-                "No live candles yet (waiting for book mid)..."
-            } else {
-                "No candles yet at this replay time."
-            });
-            return;
-        }
+    let (wallet, trader_identity) = derive_wallet(env::var(MNEMONIC_ENV).ok());
+    match &trader_identity {
+        TraderIdentity::NotSet => warn!("{MNEMONIC_ENV} not set; trading disabled"),
+        TraderIdentity::Invalid(e) => error!(error = %e, "invalid mnemonic"),
+        TraderIdentity::Ready { address, .. } => info!(%address, "trading account"),
+    }
 
-        let len = series_vec.len();
-        let window_len = self.chart.show_candles.min(len).max(1);
-        let visible = &series_vec[len - window_len..];
+    let (order_event_tx, order_event_rx) = mpsc::channel::<OrderEvent>(32);
 
-        let (y_min, y_max) = if self.chart.auto_y {
-            let lo = visible.iter().map(|c| c.low).fold(f64::MAX, f64::min);
-            let hi = visible.iter().map(|c| c.high).fold(f64::MIN, f64::max);
-            let span = (hi - lo).max(1e-3);
-            let pad = span * 0.05;
-            let min_v = lo - pad;
-            let max_v = hi + pad;
-            self.chart.y_min = min_v;
-            self.chart.y_max = max_v;
-            (min_v, max_v)
-        } else {
-            (self.chart.y_min, self.chart.y_max)
-        };
+    // spawn trader
+    let trader = rt.spawn(
+        run_trader(
+            wallet,
+            TraderInbox {
+                cmds: trade_rx,
+                kill: kill_rx,
+            },
+            account_tx,
+            orders_tx,
+            order_event_tx,
+            tape_tx,
+            max_slippage_pct_from_args(&args),
+        )
+        .instrument(info_span!("trader")),
+    );
 
-        let avail_h = ui.available_height();
-        let avail_w = ui.available_width();
+    let options = eframe::NativeOptions::default();
+    let app = ComboApp::new(
+        book_rxs,
+        funding_rx,
+        replay_data,
+        ticker_tx.clone(),
+        TraderLink {
+            trade_tx,
+            kill_tx,
+            account_rx,
+            open_orders_rx,
+            order_event_rx,
+            identity: trader_identity,
+        },
+        tape_rx,
+    );
 
-        let volume_ratio = self.layout.volume_height_ratio.clamp(0.05, 0.8);
-        let candles_h = avail_h * (1.0 - volume_ratio);
-        let volume_h = avail_h * volume_ratio;
+    if let Err(e) = eframe::run_native(
+        "dYdX Live + Replay Combo",
+        options,
+        Box::new(|_cc| Box::new(app)),
+    ) {
+        error!(error = %e, "eframe exited with an error");
+    }
 
-        let tf = self.chart.selected_tf as f64;
-        let last = visible.last().unwrap();
-        let x_center = last.t as f64 + tf * 0.5;
-        let base_span = tf * self.chart.show_candles as f64;
-        let span = base_span / self.chart.x_zoom.max(1e-6);
-        let x_min = x_center - span * 0.5 + self.chart.x_pan_secs;
-        let x_max = x_center + span * 0.5 + self.chart.x_pan_secs;
+    // the app (and with it trade_tx) is gone: the trader finishes what is
+    // queued, sees the channel close and returns
+    if rt
+        .block_on(tokio::time::timeout(TRADER_SHUTDOWN_GRACE, trader))
+        .is_err()
+    {
+        warn!("trader still busy after {TRADER_SHUTDOWN_GRACE:?}; exiting anyway");
+    }
 
-        // candles
-        ui.allocate_ui(egui::vec2(avail_w, candles_h), |ui| {
-            let mode = self.time_mode;
-            let bull = self.appearance.bull_color;
-            let bear = self.appearance.bear_color;
-            let body_factor = self
-                .layout
-                .candle_body_width_factor
-                .clamp(0.1, 1.2);
+    drop(feed_ticker_txs);
+    drop(rt);
+    csv_sink::flush_all();
+}
 
-            let plot_resp = Plot::new(if is_live {
-                "candles_live"
-            } else {
-                "candles_replay"
-            })
-            .height(candles_h)
-            .include_y(y_min)
-            .include_y(y_max)
-            .allow_drag(true)
-            .allow_zoom(true)
-            .x_axis_formatter(move |mark, _bounds, _transform| {
-                let ts = mark.value as u64;
-                format_ts(mode, ts)
-            })
-            .show(ui, |plot_ui| {
-                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                    [x_min, y_min],
-                    [x_max, y_max],
-                ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_mid_leans_toward_the_thin_side() {
+        let bids: BTreeMap<PriceKey, f64> = [(99, 5.0), (100, 3.0)].into_iter().collect();
+        let asks: BTreeMap<PriceKey, f64> = [(101, 1.0), (102, 8.0)].into_iter().collect();
+
+        assert_eq!(book_mid(&bids, &asks, 1.0, MidMode::Simple), Some(100.5));
+        // 3 bid vs 1 ask at the top: pressure pushes the mid up toward the ask
+        assert_eq!(book_mid(&bids, &asks, 1.0, MidMode::Weighted), Some(100.75));
+        assert_eq!(
+            book_mid(&bids, &BTreeMap::new(), 1.0, MidMode::Weighted),
+            None
+        );
+    }
 
-                for c in visible {
-                    let left = c.t as f64;
-                    let right = left + tf;
-                    let mid = left + tf * 0.5;
+    #[test]
+    fn metrics_bind_loopback_unless_told_otherwise() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(metrics_addr_from_args(&args(&["--headless"])), None);
+        assert_eq!(
+            metrics_addr_from_args(&args(&["--metrics-port", "9898"])),
+            Some("127.0.0.1:9898".parse().unwrap())
+        );
+        let anywhere = args(&["--metrics-addr", "0.0.0.0", "--metrics-port", "9898"]);
+        assert_eq!(
+            metrics_addr_from_args(&anywhere),
+            Some("0.0.0.0:9898".parse().unwrap())
+        );
+        // a bad address doesn't fall back to listening everywhere
+        assert_eq!(
+            metrics_addr_from_args(&args(&["--metrics-port", "9898", "--metrics-addr", "any"])),
+            None
+        );
+    }
+
+    #[test]
+    fn closes_are_reduce_only_ioc_with_a_bounded_price() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(max_slippage_pct_from_args(&[]), BigDecimal::from(1));
+        assert_eq!(
+            max_slippage_pct_from_args(&args(&["--max-slippage-pct", "0.5"])),
+            BigDecimal::from_str("0.5").unwrap()
+        );
+        // nonsense keeps the default rather than dropping the bound
+        for bad in ["-1", "0", "100", "lots"] {
+            let parsed = max_slippage_pct_from_args(&args(&["--max-slippage-pct", bad]));
+            assert_eq!(parsed, BigDecimal::from(1), "{bad}");
+        }
 
-                    let top = c.open.max(c.close);
-                    let bot = c.open.min(c.close);
+        let (ticker, side, size, reduce_only, limit, tif) =
+            close_order(("ETH-USD".to_string(), OrderSide::Sell, BigDecimal::from(2)));
+        assert_eq!(
+            (ticker.as_str(), side, size),
+            ("ETH-USD", OrderSide::Sell, BigDecimal::from(2))
+        );
+        assert!(reduce_only);
+        assert!(limit.is_none());
+        assert_eq!(tif, TimeInForce::Ioc);
+    }
 
-                    let color = if c.close >= c.open { bull } else { bear };
+    #[test]
+    fn bin_levels_rounds_each_side_away_from_the_spread() {
+        let bids: BTreeMap<PriceKey, f64> =
+            [(95, 1.0), (99, 2.0), (100, 3.0)].into_iter().collect();
+        let asks: BTreeMap<PriceKey, f64> =
+            [(101, 1.0), (105, 2.0), (110, 4.0)].into_iter().collect();
+
+        let b = bin_levels(&bids, 10, true);
+        assert_eq!(
+            b.into_iter().collect::<Vec<_>>(),
+            vec![(90, 3.0), (100, 3.0)]
+        );
+        let a = bin_levels(&asks, 10, false);
+        assert_eq!(a.into_iter().collect::<Vec<_>>(), vec![(110, 7.0)]);
+        // width 1 is the identity
+        assert_eq!(bin_levels(&asks, 1, false), asks);
+
+        assert_eq!(parse_multiples("1, 10,x,0,100"), vec![1, 10, 100]);
+    }
 
-                    // wick
-                    let wick_pts: PlotPoints =
-                        vec![[mid, c.low], [mid, c.high]].into();
-                    plot_ui.line(Line::new(wick_pts).color(color));
-
-                    // body width relative to TF
-                    let half_body = (tf * 0.5 * body_factor as f64).min(tf * 0.5);
-                    let body_left = mid - half_body;
-                    let body_right = mid + half_body;
-
-                    // filled body polygon
-                    let body_pts: PlotPoints = vec![
-                        [body_left, bot],
-                        [body_left, top],
-                        [body_right, top],
-                        [body_right, bot],
-                        [body_left, bot],
-                    ]
-                    .into();
-                    plot_ui.line(Line::new(body_pts).color(color).width(2.0));
-                }
+    #[test]
+    fn chart_bounds_stay_drawable_for_flat_empty_and_broken_series() {
+        let valid = |x: (f64, f64), y: (f64, f64)| plot_bounds(x, y).is_valid();
+
+        // one flat candle: a view around its price, not a zero-height one
+        let flat = [ohlc(T0, 3000.0, 3000.0, 3000.0, 3000.0)];
+        let (lo, hi) = candle_y_range(&flat);
+        assert!(lo < 3000.0 && hi > 3000.0 && hi - lo >= 3.0, "{lo}..{hi}");
+        assert!(valid((T0 as f64, T0 as f64 + 60.0), (lo, hi)));
+
+        // nothing to fit, or nothing finite: the fallback view
+        assert_eq!(candle_y_range(&[]), (0.0, 1.0));
+        let broken = [ohlc(T0, f64::NAN, f64::INFINITY, f64::NAN, f64::NAN)];
+        assert_eq!(candle_y_range(&broken), (0.0, 1.0));
+        // a bad print doesn't drag the axis to infinity
+        let spiked = [flat[0], ohlc(T0 + 1, 3001.0, f64::INFINITY, 2999.0, 3001.0)];
+        let (lo, hi) = candle_y_range(&spiked);
+        assert!(lo > 2990.0 && hi < 3010.0, "{lo}..{hi}");
+
+        assert_eq!(sane_range(f64::NAN, 1.0, 1e-9, (0.0, 1.0)), (0.0, 1.0));
+        assert_eq!(
+            sane_range(-f64::MAX, f64::MAX, 1e-9, (0.0, 1.0)),
+            (0.0, 1.0)
+        );
+        assert_eq!(sane_range(5.0, 2.0, 1e-9, (0.0, 1.0)), (2.0, 5.0));
+        assert_eq!(sane_range(4.0, 4.0, 2.0, (0.0, 1.0)), (3.0, 5.0));
+        assert!(valid((f64::NAN, 0.0), (0.0, f64::INFINITY)));
+        assert!(valid((1.0, 1.0), (7.0, 7.0)));
+    }
 
-                let now_x = if is_live {
-                    self.live_last_ts as f64
-                } else {
-                    self.replay_ts as f64
-                };
-                plot_ui.vline(VLine::new(now_x).name("now_ts"));
-            });
+    #[test]
+    fn live_clock_shows_the_lag_behind_wall_time() {
+        let label = |data, wall| live_clock_label(TimeDisplayMode::Unix, data, wall);
+        assert_eq!(label(T0, T0), format!("Data ts: {T0} | wall: {T0}"));
+        assert_eq!(
+            label(T0, T0 + 1),
+            format!("Data ts: {T0} | wall: {}", T0 + 1)
+        );
+        assert_eq!(
+            label(T0, T0 + 30),
+            format!("Data ts: {T0} | wall: {} (30s behind)", T0 + 30)
+        );
+    }
 
-            // vertical zoom: Shift + scroll over candles plot
-            let hovered = plot_resp.response.hovered();
-            let mut scroll_y = 0.0f32;
-            let mut shift = false;
-            ui.ctx().input(|i| {
-                scroll_y = i.raw_scroll_delta.y;
-                shift = i.modifiers.shift;
-            });
-            if hovered && shift && scroll_y != 0.0 {
-                self.chart.auto_y = false;
-                let factor = 1.0 + (scroll_y as f64 * 0.002); // smooth
-                let factor = factor.clamp(0.2, 5.0);
-                let center = (self.chart.y_min + self.chart.y_max) * 0.5;
-                let half_span =
-                    (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
-                self.chart.y_min = center - half_span;
-                self.chart.y_max = center + half_span;
-            }
-        });
+    #[test]
+    fn limit_expiry_picks_block_or_time_and_lapses_accordingly() {
+        let h = Height(1_000);
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+
+        let short = LimitExpiry::Blocks.good_until(&h, now);
+        assert!(matches!(short, OrderGoodUntil::Block(Height(1_020))));
+        assert!(!good_until_lapsed(&short, &Height(1_020), now));
+        assert!(good_until_lapsed(&short, &Height(1_021), now));
+
+        let long = LimitExpiry::Time { secs: 3_600 }.good_until(&h, now);
+        assert!(matches!(long, OrderGoodUntil::Time(t) if t == now + TimeDelta::hours(1)));
+        // block height means nothing to a long-term order
+        assert!(!good_until_lapsed(&long, &Height(u32::MAX), now));
+        assert!(good_until_lapsed(&long, &h, now + TimeDelta::minutes(61)));
+
+        assert_eq!(LimitExpiry::Time { secs: 3_600 }.label(), "GTT 1h");
+        assert_eq!(fmt_duration_secs(45), "45s");
+        assert_eq!(fmt_duration_secs(125 * 60), "2h 5m");
+        assert_eq!(fmt_duration_secs(90 * 24 * 3_600), "90d");
+    }
+
+    fn ohlc(t: u64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            t,
+            open,
+            high,
+            low,
+            close,
+            volume: 0.0,
+        }
+    }
 
-        ui.separator();
+    #[test]
+    fn detect_patterns_matches_textbook_shapes() {
+        let candles = [
+            // engulfing needs a previous candle: index 0 is never one
+            ohlc(0, 10.0, 10.5, 7.5, 8.0),
+            // big green body wrapping the red one before it
+            ohlc(60, 7.8, 10.6, 7.7, 10.4),
+            // open == close
+            ohlc(120, 10.0, 11.0, 9.0, 10.0),
+            // long lower wick, no upper wick
+            ohlc(180, 10.0, 10.5, 8.0, 10.5),
+            // long upper wick, no lower wick
+            ohlc(240, 10.5, 12.5, 10.0, 10.0),
+            // flat candle: no range, no pattern
+            ohlc(300, 10.0, 10.0, 10.0, 10.0),
+        ];
+        assert_eq!(
+            detect_patterns(&candles),
+            vec![
+                (1, Pattern::BullishEngulfing),
+                (2, Pattern::Doji),
+                (3, Pattern::Hammer),
+                (4, Pattern::ShootingStar),
+            ]
+        );
+
+        let bearish = [
+            ohlc(0, 8.0, 10.1, 7.9, 10.0),
+            ohlc(60, 10.2, 10.3, 7.5, 7.6),
+        ];
+        assert_eq!(
+            detect_patterns(&bearish),
+            vec![(1, Pattern::BearishEngulfing)]
+        );
+        assert!(detect_patterns(&[]).is_empty());
+    }
 
-        // volume
-        ui.allocate_ui(egui::vec2(avail_w, volume_h), |ui| {
-            let mode = self.time_mode;
-            let vol_color = self.appearance.volume_color;
+    #[test]
+    fn bucket_spreads_gives_min_avg_max_per_bucket() {
+        let samples = [(0, 1.0), (10, 3.0), (59, 2.0), (60, 5.0), (200, 0.5)];
+        assert_eq!(
+            bucket_spreads(samples, 60),
+            vec![
+                (0, 1.0, 2.0, 3.0),
+                (60, 5.0, 5.0, 5.0),
+                (180, 0.5, 0.5, 0.5)
+            ]
+        );
+
+        let bids: BTreeMap<PriceKey, f64> = [(99, 1.0), (100, 1.0)].into_iter().collect();
+        let asks: BTreeMap<PriceKey, f64> = [(102, 1.0)].into_iter().collect();
+        assert_eq!(book_spread(&bids, &asks, 1.0), Some(2.0));
+        // one-sided books have no spread
+        assert_eq!(book_spread(&bids, &BTreeMap::new(), 1.0), None);
+    }
 
-            let plot_resp = Plot::new(if is_live {
-                "volume_live"
-            } else {
-                "volume_replay"
-            })
-            .height(volume_h)
-            .include_y(0.0)
-            .allow_drag(true)
-            .allow_zoom(true)
-            .x_axis_formatter(move |mark, _bounds, _transform| {
-                let ts = mark.value as u64;
-                format_ts(mode, ts)
+    #[test]
+    fn data_gaps_only_count_spans_quiet_on_both_streams() {
+        let t0 = 1_700_000_000;
+        // book stops for 400s, trades keep going for the first 200s of it
+        let book = quiet_spans([t0, t0 + 10, t0 + 410, t0 + 420]);
+        let trades = quiet_spans([t0 + 5, t0 + 55, t0 + 105, t0 + 155, t0 + 205, t0 + 415]);
+        assert_eq!(data_gaps(&book, &trades), vec![(t0 + 205, t0 + 410)]);
+
+        // nothing before the first / after the last event is a gap, and a
+        // missing trades file leaves the book gaps as they are
+        assert_eq!(
+            data_gaps(&book, &quiet_spans([])),
+            vec![(t0 + 10, t0 + 410)]
+        );
+
+        let gaps = [(t0 + 205, t0 + 410)];
+        assert!(crosses_gap(&gaps, t0 + 180, t0 + 420));
+        assert!(!crosses_gap(&gaps, t0 + 420, t0 + 480));
+        assert!(!crosses_gap(&gaps, t0, t0 + 60));
+    }
+
+    #[test]
+    fn derive_wallet_reports_missing_bad_and_good_mnemonics() {
+        assert_eq!(derive_wallet(None).1, TraderIdentity::NotSet);
+
+        let (wallet, id) = derive_wallet(Some("not a real mnemonic".to_string()));
+        assert!(wallet.is_none());
+        assert!(matches!(id, TraderIdentity::Invalid(_)));
+
+        // BIP-39 test vector; extra whitespace is normalized away
+        let phrase = format!("{}  art\n", "abandon ".repeat(23));
+        let (wallet, id) = derive_wallet(Some(phrase));
+        assert!(wallet.is_some());
+        match id {
+            TraderIdentity::Ready {
+                address,
+                subaccount,
+            } => {
+                assert!(address.starts_with("dydx1"), "{address}");
+                assert_eq!(subaccount, TRADING_SUBACCOUNT);
+            }
+            other => panic!("expected Ready, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn place_errors_retry_only_on_sequence_and_transport_failures() {
+        use dydx_client::node::BroadcastError;
+
+        let broadcast = |code: Option<u32>, message: &str| {
+            NodeError::Broadcast(BroadcastError {
+                code,
+                message: message.to_string(),
             })
-            .show(ui, |plot_ui| {
-                let max_vol = visible
-                    .iter()
-                    .map(|c| c.volume)
-                    .fold(0.0_f64, f64::max)
-                    .max(1e-6);
-                let y_max_v = max_vol * 1.1;
+        };
+        let kind = |code, message| classify_place_error(&broadcast(code, message));
+        assert_eq!(
+            kind(Some(32), "account sequence mismatch, expected 7"),
+            PlaceErrorKind::Sequence
+        );
+        assert_eq!(
+            kind(None, "Deadline expired before operation could complete"),
+            PlaceErrorKind::Transient
+        );
+        // post-only crossing comes back with a clob code: never resend
+        assert_eq!(
+            kind(Some(2004), "post-only order would cross; timeout"),
+            PlaceErrorKind::Fatal
+        );
+        let general = NodeError::General(anyhow::anyhow!("Order does not have an ID"));
+        assert_eq!(classify_place_error(&general), PlaceErrorKind::Fatal);
+    }
 
-                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                    [x_min, 0.0],
-                    [x_max, y_max_v],
-                ));
+    #[test]
+    fn reduce_only_must_oppose_the_open_position() {
+        let mut account = AccountInfo {
+            equity: 1_000.0,
+            free_collateral: 900.0,
+            updated_ts: 0,
+            stale: false,
+            positions: HashMap::new(),
+        };
+        // unknown account: leave it to the chain
+        assert_eq!(reduce_only_rejection(None, "ETH-USD", OrderSide::Buy), None);
+        let flat = reduce_only_rejection(Some(&account), "ETH-USD", OrderSide::Sell);
+        assert!(flat.is_some_and(|m| m.contains("flat")));
 
-                for c in visible {
-                    let left = c.t as f64;
-                    let mid = left + tf * 0.5;
+        account.positions.insert(
+            "ETH-USD".to_string(),
+            LivePosition {
+                long: true,
+                size: 0.5,
+                entry_price: 2_000.0,
+                unrealized_pnl: 0.0,
+            },
+        );
+        let closes = reduce_only_rejection(Some(&account), "ETH-USD", OrderSide::Sell);
+        assert_eq!(closes, None);
+        assert_eq!(
+            reduce_only_rejection(Some(&account), "ETH-USD", OrderSide::Buy).as_deref(),
+            Some("Reduce-only BUY would add to the LONG ETH-USD position")
+        );
+    }
 
-                    let line_pts: PlotPoints =
-                        vec![[mid, 0.0], [mid, c.volume]].into();
-                    plot_ui
-                        .line(Line::new(line_pts).color(vol_color).width(2.0));
-                }
-            });
+    #[test]
+    fn live_liquidation_price_ignores_where_equity_was_valued() {
+        let position = |unrealized_pnl| LivePosition {
+            long: false,
+            size: 2.0,
+            entry_price: 100.0,
+            unrealized_pnl,
+        };
+        let account = |equity, pos| AccountInfo {
+            equity,
+            free_collateral: 0.0,
+            updated_ts: 0,
+            stale: false,
+            positions: HashMap::from([("SOL-USD".to_string(), pos)]),
+        };
+        // 50 at entry; the same account fetched after a 10-point rally
+        let at_entry = account(50.0, position(0.0));
+        let later = account(30.0, position(-20.0));
+        let liq = at_entry.liquidation_price("SOL-USD").unwrap();
+        assert!((later.liquidation_price("SOL-USD").unwrap() - liq).abs() < 1e-9);
+        // 2 * (liq - 100) of losses leaves 3% of the notional
+        assert!((50.0 - 2.0 * (liq - 100.0) - 2.0 * liq * DEFAULT_MAINT_RATE).abs() < 1e-9);
+        assert_eq!(at_entry.liquidation_price("ETH-USD"), None);
+    }
 
-            // vertical zoom also works on volume (Shift + scroll)
-            let hovered = plot_resp.response.hovered();
-            let mut scroll_y = 0.0f32;
-            let mut shift = false;
-            ui.ctx().input(|i| {
-                scroll_y = i.raw_scroll_delta.y;
-                shift = i.modifiers.shift;
-            });
-            if hovered && shift && scroll_y != 0.0 {
-                self.chart.auto_y = false;
-                let factor = 1.0 + (scroll_y as f64 * 0.002);
-                let factor = factor.clamp(0.2, 5.0);
-                let center = (self.chart.y_min + self.chart.y_max) * 0.5;
-                let half_span =
-                    (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
-                self.chart.y_min = center - half_span;
-                self.chart.y_max = center + half_span;
-            }
-        });
+    #[test]
+    fn pnl_hud_reads_side_pnl_and_distance_to_liquidation() {
+        let short = PnlHud {
+            long: false,
+            size: 2.0,
+            entry: 100.0,
+            mark: 104.0,
+            liquidation: Some(110.0),
+        };
+        assert_eq!(short.unrealized(), -8.0);
+        assert_eq!(short.unrealized_pct(), -4.0);
+        assert!((short.liq_distance_pct().unwrap() - 600.0 / 104.0).abs() < 1e-9);
+
+        let rows = short.rows(|p| format!("{p:.1}"), |s| format!("{s:.2}"));
+        let text: Vec<&str> = rows.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            text,
+            [
+                "SHORT 2.00 @ 100.0",
+                "uPnL -8.00 (-4.00%)",
+                "Liq 110.0 (5.8% away)"
+            ]
+        );
+
+        let long = PnlHud {
+            long: true,
+            liquidation: None,
+            ..short
+        };
+        assert_eq!(long.unrealized(), 8.0);
+        assert_eq!(long.liq_distance_pct(), None);
     }
-}
 
-impl eframe::App for ComboApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if matches!(self.mode, Mode::Live) {
-            self.tick_live();
-        }
+    #[test]
+    fn tf_steps_clamp_at_the_ends_and_keep_the_time_span() {
+        assert_eq!(step_tf(60, 1), 120);
+        assert_eq!(step_tf(60, -2), 15);
+        assert_eq!(step_tf(1, -1), 1);
+        assert_eq!(step_tf(86400, 3), 86400);
+        // off the list: counts from the next choice up
+        assert_eq!(step_tf(90, 0), 120);
+
+        let mut chart = ChartSettings::default();
+        assert_eq!((chart.selected_tf, chart.show_candles), (60, 200));
+        // 200 minutes on screen either way
+        chart.set_tf(300);
+        assert_eq!((chart.selected_tf, chart.show_candles), (300, 40));
+        chart.set_tf(60);
+        assert_eq!(chart.show_candles, 200);
+        // held inside the slider's range
+        chart.set_tf(86400);
+        assert_eq!(chart.show_candles, SHOW_CANDLES_MIN);
+        chart.set_tf(1);
+        assert_eq!(chart.show_candles, SHOW_CANDLES_MAX);
+    }
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            self.ui_top_bar(ui);
-        });
+    #[test]
+    fn loader_panics_keep_their_message() {
+        let payload = std::thread::spawn(|| panic!("bad row {}", 7))
+            .join()
+            .unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "bad row 7");
+        let payload = std::thread::spawn(|| std::panic::panic_any(7))
+            .join()
+            .unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "non-string panic payload");
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| match self.mode {
-            Mode::Live => self.ui_live(ui),
-            Mode::Replay => self.ui_replay(ui),
-        });
+    #[test]
+    fn footprints_need_room_per_candle_and_per_row() {
+        // 1m candles on 1200 px: 10 of them fit 120 px each, 100 only 12 px
+        let (tf, row_h) = (60.0, 1.0);
+        let x_px = |candles: f64| 1200.0 / (candles * tf);
+        // 20 px per $1 row; y grows upwards, so screen px per unit is negative
+        assert!(footprint_readable([x_px(10.0), -20.0], tf, row_h));
+        assert!(!footprint_readable([x_px(100.0), -20.0], tf, row_h));
+        // wide enough, but $1 rows of 10 px would overlap
+        assert!(!footprint_readable([x_px(10.0), -10.0], tf, row_h));
+        assert!(footprint_readable([x_px(10.0), -10.0], tf, 2.0 * row_h));
+    }
 
-        ctx.request_repaint_after(Duration::from_millis(50));
+    #[test]
+    fn chart_png_crops_the_plot_rect_at_the_screen_scale() {
+        // 4x4 physical pixels at 2 px per point, each pixel tagged by index
+        let pixels = (0..16u8)
+            .map(|i| Color32::from_rgb(i, 0, 0))
+            .collect::<Vec<_>>();
+        let shot = egui::ColorImage {
+            size: [4, 4],
+            pixels,
+        };
+        let rect = egui::Rect::from_min_max(egui::pos2(0.5, 0.5), egui::pos2(1.5, 2.0));
+        let img = crop_screenshot(&shot, rect, 2.0).unwrap();
+        assert_eq!(img.size, [2, 3]);
+        let reds: Vec<u8> = img.pixels.iter().map(|c| c.r()).collect();
+        assert_eq!(reds, vec![5, 6, 9, 10, 13, 14]);
+
+        // a rect hanging off the window keeps the visible part only
+        let past = egui::Rect::from_min_max(egui::pos2(1.0, 1.0), egui::pos2(9.0, 9.0));
+        assert_eq!(crop_screenshot(&shot, past, 2.0).unwrap().size, [2, 2]);
+        let off = egui::Rect::from_min_max(egui::pos2(5.0, 5.0), egui::pos2(9.0, 9.0));
+        assert!(crop_screenshot(&shot, off, 2.0).is_none());
+
+        let path = chart_png_path("ETH-USD", 300, 1_700_000_000);
+        assert!(path.ends_with("chart_ETH-USD_5m_1700000000.png"));
     }
-}
 
-// ------------- async live feed -------------
+    #[test]
+    fn order_form_tif_maps_to_the_chain_and_fits_the_order_type() {
+        use dydx_client::node::BroadcastError;
+
+        assert_eq!(UiTif::Gtc.time_in_force(), TimeInForce::Unspecified);
+        assert_eq!(UiTif::Fok.time_in_force(), TimeInForce::FillOrKill);
+        assert_eq!(UiTif::PostOnly.time_in_force(), TimeInForce::PostOnly);
+        // market orders only take IOC / FOK; anything else goes out as IOC
+        let market: Vec<UiTif> = UiTif::ALL
+            .into_iter()
+            .filter(|t| t.applies_to(UiOrderType::Market))
+            .collect();
+        assert_eq!(market, [UiTif::Ioc, UiTif::Fok]);
+        assert_eq!(UiTif::PostOnly.for_order(UiOrderType::Market), UiTif::Ioc);
+        assert_eq!(
+            UiTif::PostOnly.for_order(UiOrderType::Limit),
+            UiTif::PostOnly
+        );
+
+        let crossed = NodeError::Broadcast(BroadcastError {
+            code: Some(2004),
+            message: "Post-only order would cross one or more maker orders".to_string(),
+        });
+        assert_eq!(place_fail_stage(&crossed), "post_only_would_cross");
+        let broke = NodeError::General(anyhow::anyhow!("insufficient collateral"));
+        assert_eq!(place_fail_stage(&broke), "place");
+    }
 
-async fn run_live_feed(book_tx: watch::Sender<LiveBook>, ticker_rx: watch::Receiver<String>) {
-    let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to load testnet.toml: {e}");
-            return;
-        }
-    };
+    #[test]
+    fn csv_sink_buffers_rows_until_flushed() {
+        let dir = std::env::temp_dir().join(format!("csv_sink_test_{}", std::process::id()));
+        let path = dir.join("orderbook_TEST-USD.csv");
+        let _ = std::fs::remove_file(&path);
+
+        csv_sink::append_row(&path, "1,TEST-USD,delta,bid,100,1").unwrap();
+        csv_sink::append_row(&path, "2,TEST-USD,delta,ask,101,2").unwrap();
+        // the dir exists right away, the rows only after a flush
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+        csv_sink::flush_all();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1,TEST-USD,delta,bid,100,1\n2,TEST-USD,delta,ask,101,2\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 
-    let mut indexer = IndexerClient::new(config.indexer);
-    let mut ticker_rx = ticker_rx;
+    #[test]
+    fn seq_tracker_tolerates_reorder_but_reports_holes() {
+        let mut seq = SeqTracker::default();
+        // baseline, in order, a duplicate, and 12 overtaking 11
+        for id in [10, 12, 11, 11, 13] {
+            assert_eq!(seq.observe(id), None, "id {id}");
+        }
+        // 14 and 15 never arrive; a window's worth of later ids gives up on them
+        for id in 16..16 + SEQ_REORDER_WINDOW as u64 {
+            assert_eq!(seq.observe(id), None, "id {id}");
+        }
+        let last = 16 + SEQ_REORDER_WINDOW as u64;
+        assert_eq!(seq.observe(last), Some(14..16));
+        assert_eq!(seq.observe(last + 1), None);
+        // a straggler from before the gap is ignored
+        assert_eq!(seq.observe(15), None);
+    }
 
-    loop {
-        let current = ticker_rx.borrow().clone();
-        eprintln!("Subscribing live feed for {current}");
+    #[test]
+    fn detect_walls_flags_outlier_levels() {
+        let mut book: BTreeMap<PriceKey, f64> =
+            (0..30).map(|k| (k, 1.0 + (k % 3) as f64 * 0.1)).collect();
+        book.insert(12, 25.0);
+        book.insert(27, 1.3);
+
+        assert_eq!(detect_walls(&book, WALL_WINDOW, 2.0), vec![12, 27]);
+        // a high enough threshold leaves only the big one
+        assert_eq!(detect_walls(&book, WALL_WINDOW, 4.0), vec![12]);
+        // a flat book has no walls
+        let flat: BTreeMap<PriceKey, f64> = (0..30).map(|k| (k, 1.0)).collect();
+        assert!(detect_walls(&flat, WALL_WINDOW, 2.0).is_empty());
+    }
 
-        let mut feeds: Feeds<'_> = indexer.feed();
-        let ticker = Ticker(current.clone());
+    #[test]
+    fn accumulate_runs_totals_from_the_best_price_out() {
+        let bids: BTreeMap<PriceKey, f64> = [(99, 2.0), (100, 1.0), (98, 0.5)].into();
+        let levels: Vec<_> = accumulate(bids.iter().rev(), 1.0).collect();
+        let totals: Vec<_> = levels
+            .iter()
+            .map(|l| (l.key, l.cum_size, l.cum_notional))
+            .collect();
+        assert_eq!(
+            totals,
+            vec![(100, 1.0, 100.0), (99, 3.0, 298.0), (98, 3.5, 347.0)]
+        );
+        assert_eq!(depth_points(&levels)[1], (99.0, 3.0));
+
+        let asks: BTreeMap<PriceKey, f64> = [(1005, 1.0), (1010, 2.0)].into();
+        let last = accumulate(asks.iter(), 10.0).last().unwrap();
+        assert_eq!(
+            (last.price, last.cum_size, last.cum_notional),
+            (101.0, 3.0, 302.5)
+        );
+    }
 
-        let mut feed: DxFeed<OrdersMessage> = match feeds.orders(&ticker, false).await {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("orders feed error for {current}: {e}");
-                return;
+    #[test]
+    fn resting_orders_land_on_their_row_or_the_nearest() {
+        let bids: BTreeMap<PriceKey, f64> = [(100, 1.0), (99, 1.0), (96, 1.0)].into();
+        let rows: Vec<_> = accumulate(bids.iter().rev(), 1.0).collect();
+        let order = |client_id, price, size| OpenOrder {
+            client_id,
+            ticker: "ETH-USD".into(),
+            side: OrderSide::Buy,
+            price,
+            size,
+        };
+        let orders = [
+            order(1, 99.0, 0.5),
+            order(2, 99.0, 0.25),
+            order(3, 97.0, 1.0),
+            order(4, 90.0, 2.0),
+        ];
+        let by_row = orders_by_row(&rows, orders.iter(), 1.0);
+
+        assert_eq!(by_row.len(), 2);
+        // both orders at 99 sit on their own level
+        assert_eq!(
+            by_row[&1],
+            RowOrders {
+                size: 0.75,
+                client_ids: vec![1, 2],
+                off_level: vec![],
             }
+        );
+        // 97 isn't a level and 90 is past the last row: both show on 96
+        assert_eq!(by_row[&2].client_ids, vec![3, 4]);
+        assert_eq!(by_row[&2].off_level, vec![97.0, 90.0]);
+        assert!(orders_by_row(&[], orders.iter(), 1.0).is_empty());
+    }
+
+    fn test_app() -> (ComboApp, mpsc::Receiver<TradeCmd>) {
+        let (trade_tx, trade_rx) = mpsc::channel(8);
+        let (kill_tx, _) = watch::channel(0);
+        let (_, funding_rx) = watch::channel(None);
+        let (ticker_tx, _) = watch::channel("ETH-USD".to_string());
+        let (_, account_rx) = watch::channel(None);
+        let (_, open_orders_rx) = watch::channel(Vec::new());
+        let (_, order_event_rx) = mpsc::channel(8);
+        let (_, tape_rx) = mpsc::channel(8);
+        let app = ComboApp::new(
+            HashMap::new(),
+            funding_rx,
+            HashMap::new(),
+            ticker_tx,
+            TraderLink {
+                trade_tx,
+                kill_tx,
+                account_rx,
+                open_orders_rx,
+                order_event_rx,
+                identity: TraderIdentity::NotSet,
+            },
+            tape_rx,
+        );
+        (app, trade_rx)
+    }
+
+    #[test]
+    fn trade_hotkeys_ignore_keys_with_modifiers() {
+        let (mut app, mut trade_rx) = test_app();
+        app.hotkeys_armed = true;
+        let ctx = egui::Context::default();
+        let press = |key, modifiers| {
+            let event = egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            };
+            ctx.begin_frame(egui::RawInput {
+                events: vec![event],
+                modifiers,
+                ..Default::default()
+            });
         };
 
-        let mut book = LiveBook::default();
+        // Ctrl+S is "save" everywhere else; it must not sell
+        press(egui::Key::S, egui::Modifiers::CTRL);
+        app.handle_trade_hotkeys(&ctx);
+        let _ = ctx.end_frame();
+        assert!(trade_rx.try_recv().is_err());
+
+        press(egui::Key::S, egui::Modifiers::NONE);
+        app.handle_trade_hotkeys(&ctx);
+        let _ = ctx.end_frame();
+        assert!(matches!(
+            trade_rx.try_recv(),
+            Ok(TradeCmd::MarketOrder {
+                side: OrderSide::Sell,
+                ..
+            })
+        ));
+    }
 
-        while let Some(msg) = feed.recv().await {
-            match msg {
-                OrdersMessage::Initial(init) => {
-                    book.apply_initial(init.contents.bids, init.contents.asks, &current);
-                }
-                OrdersMessage::Update(upd) => {
-                    book.apply_update(upd.contents.bids, upd.contents.asks, &current);
-                }
-            }
-            let _ = book_tx.send(book.clone());
+    #[test]
+    fn kill_jumps_the_command_queue() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (trade_tx, cmds) = mpsc::channel(8);
+        let (kill_tx, kill) = watch::channel(0u64);
+        let mut inbox = TraderInbox { cmds, kill };
+        for _ in 0..3 {
+            trade_tx
+                .try_send(TradeCmd::Flatten {
+                    ticker: "ETH-USD".to_string(),
+                })
+                .unwrap();
+        }
+        kill_tx.send_modify(|n| *n += 1);
+
+        let mut next = || rt.block_on(inbox.next());
+        assert!(matches!(next(), Some(TradeCmd::Kill)));
+        // one kill, handled once; then the queue in order
+        assert!(matches!(next(), Some(TradeCmd::Flatten { .. })));
+
+        // a gone UI still gets its last kill through, then the queue runs dry
+        kill_tx.send_modify(|n| *n += 1);
+        drop((kill_tx, trade_tx));
+        assert!(matches!(next(), Some(TradeCmd::Kill)));
+        assert!(matches!(next(), Some(TradeCmd::Flatten { .. })));
+        assert!(matches!(next(), Some(TradeCmd::Flatten { .. })));
+        assert!(next().is_none());
+    }
 
-            if ticker_rx.has_changed().unwrap_or(false) {
-                break;
+    #[test]
+    fn kill_cancels_indexer_orders_it_did_not_place() {
+        let address = "dydx14zzueazeh0hj67cghhf9jypslcf9sh2n5k6art";
+        let sub = Subaccount::new(address.parse().unwrap(), 0.try_into().unwrap());
+        let listed = |status: &str, until: &str| {
+            let json = format!(
+                r#"{{"clientId": "77", "clientMetadata": "0", "clobPairId": "1",
+                "createdAtHeight": "100", {until}, "id": "x", "orderFlags": "64",
+                "postOnly": false, "price": "3000.5", "reduceOnly": false, "side": "SELL",
+                "size": "2", "status": "{status}", "subaccountId": "y", "subaccountNumber": 0,
+                "ticker": "ETH-USD", "timeInForce": "GTT", "totalFilled": "0.5",
+                "type": "LIMIT", "updatedAt": null, "updatedAtHeight": null}}"#
+            );
+            serde_json::from_str::<OrderResponseObject>(&json).unwrap()
+        };
+
+        let gtt = r#""goodTilBlockTime": "2030-01-01T00:00:00Z""#;
+        let t = tracked_from_listed(&listed("OPEN", gtt), &sub).unwrap();
+        assert_eq!(
+            (t.id.client_id, t.id.order_flags, t.id.clob_pair_id),
+            (77, 64, 1)
+        );
+        assert_eq!(t.id.subaccount_id.as_ref().unwrap().owner, address);
+        assert!(matches!(t.until, OrderGoodUntil::Time(_)));
+        assert_eq!(
+            t.order,
+            OpenOrder {
+                client_id: 77,
+                ticker: "ETH-USD".to_string(),
+                side: OrderSide::Sell,
+                price: 3000.5,
+                size: 1.5,
             }
-        }
+        );
+
+        let short = r#""goodTilBlock": "120""#;
+        let t = tracked_from_listed(&listed("OPEN", short), &sub).unwrap();
+        assert!(matches!(t.until, OrderGoodUntil::Block(Height(120))));
+        // already gone: nothing to cancel
+        assert!(tracked_from_listed(&listed("FILLED", short), &sub).is_none());
     }
-}
 
-// ------------- async trade executor (real orders) -------------
+    #[test]
+    fn ladder_clicks_join_or_take_the_level() {
+        assert_eq!(ladder_click_side(true, false), OrderSide::Buy);
+        assert_eq!(ladder_click_side(false, false), OrderSide::Sell);
+        assert_eq!(ladder_click_side(true, true), OrderSide::Sell);
+        assert_eq!(ladder_click_side(false, true), OrderSide::Buy);
+    }
 
-async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
-    let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("[trader] Failed to load testnet.toml: {e}");
-            return;
-        }
-    };
+    #[test]
+    fn level_flash_blends_toward_the_flash_color() {
+        assert_eq!(blend(WALL_BG, FLASH_BG, 0.0), WALL_BG);
+        assert_eq!(blend(WALL_BG, FLASH_BG, 1.0), FLASH_BG);
+        assert_eq!(
+            blend(Color32::TRANSPARENT, FLASH_BG, 0.0),
+            Color32::TRANSPARENT
+        );
+    }
 
-    let raw = match env::var("DYDX_TESTNET_MNEMONIC") {
-        Ok(v) => v,
-        Err(_) => {
-            eprintln!("[trader] DYDX_TESTNET_MNEMONIC not set; trading disabled");
-            return;
-        }
-    };
-    let mnemonic = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    #[test]
+    fn scale_from_tick_matches_tick_decimals() {
+        assert_eq!(scale_from_tick(1.0), 1.0);
+        assert_eq!(scale_from_tick(0.1), 10.0);
+        assert_eq!(scale_from_tick(0.01), 100.0);
+        assert_eq!(scale_from_tick(0.0001), 10_000.0);
+        assert_eq!(scale_from_tick(0.0), DEFAULT_PRICE_SCALE);
+        assert_eq!(scale_from_tick(f64::NAN), DEFAULT_PRICE_SCALE);
+    }
 
-    let wallet = match Wallet::from_mnemonic(&mnemonic) {
-        Ok(w) => w,
-        Err(e) => {
-            eprintln!("[trader] invalid mnemonic: {e}");
-            return;
-        }
-    };
+    #[test]
+    fn price_key_round_trips_without_drift() {
+        // (tick size, representative prices) for BTC / ETH / SOL-like markets
+        let cases: &[(f64, &[f64])] = &[
+            (1.0, &[97_123.0, 100_000.0, 65_432.0]),
+            (0.1, &[3_101.7, 2_999.9, 4_000.0]),
+            (0.001, &[142.375, 0.125, 250.001]),
+        ];
 
-    let mut node = match NodeClient::connect(config.node).await {
-        Ok(n) => n,
-        Err(e) => {
-            eprintln!("[trader] node connect failed: {e}");
-            return;
+        for (tick, prices) in cases {
+            let scale = scale_from_tick(*tick);
+            for p in *prices {
+                let key = price_to_key(*p, scale);
+                let back = key_to_price(key, scale);
+                assert_eq!(price_to_key(back, scale), key);
+                assert!((back - p).abs() < tick * 1e-6, "{p} -> {back}");
+            }
         }
-    };
+    }
 
-    let mut account = match wallet.account(0, &mut node).await {
-        Ok(a) => a,
-        Err(e) => {
-            eprintln!("[trader] account sync failed: {e}");
-            return;
-        }
-    };
+    #[test]
+    fn adjacent_ticks_do_not_collide() {
+        let tick = 0.001;
+        let scale = scale_from_tick(tick);
+        let a = price_to_key(142.375, scale);
+        let b = price_to_key(142.375 + tick, scale);
+        assert_eq!(b - a, 1);
+    }
 
-    let sub = match account.subaccount(0) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("[trader] subaccount derive failed: {e}");
-            return;
+    fn candle(t: u64) -> Candle {
+        Candle {
+            t,
+            open: 1.0,
+            high: 1.0,
+            low: 1.0,
+            close: 1.0,
+            volume: 0.0,
         }
-    };
+    }
 
-    let indexer = IndexerClient::new(config.indexer);
+    #[test]
+    fn candle_at_picks_bucket_containing_ts() {
+        let series = vec![candle(60), candle(120), candle(240)];
+        assert!(candle_at(&series, 59).is_none());
+        assert_eq!(candle_at(&series, 60).map(|c| c.t), Some(60));
+        assert_eq!(candle_at(&series, 179).map(|c| c.t), Some(120));
+        // gap without book updates: the last candle is still the one in effect
+        assert_eq!(candle_at(&series, 200).map(|c| c.t), Some(120));
+        assert_eq!(candle_at(&series, 10_000).map(|c| c.t), Some(240));
+    }
 
-    while let Some(cmd) = rx.recv().await {
-        match cmd {
-            TradeCmd::MarketOrder { ticker, side, size } => {
-                eprintln!("[trader] market {:?} {} size {}", side, ticker, size);
+    #[test]
+    fn drawing_points_snap_to_candles() {
+        let mut c = candle(120);
+        c.high = 105.0;
+        c.low = 95.0;
+        let series = vec![candle(60), c];
+        // x lands mid-bucket; y within tolerance goes to the high
+        assert_eq!(
+            snap_point([130.0, 104.5], 60.0, &series, 1.0),
+            [150.0, 105.0]
+        );
+        // too far from any O/H/L/C: price kept as clicked
+        assert_eq!(
+            snap_point([130.0, 100.0], 60.0, &series, 1.0),
+            [150.0, 100.0]
+        );
+        // no candle in that bucket
+        assert_eq!(
+            snap_point([200.0, 104.5], 60.0, &series, 1.0),
+            [210.0, 104.5]
+        );
+    }
 
-                let market = match indexer
-                    .markets()
-                    .get_perpetual_market(&ticker.clone().into())
-                    .await
-                {
-                    Ok(m) => m,
-                    Err(e) => {
-                        eprintln!("[trader] market meta error for {ticker}: {e}");
-                        continue;
-                    }
-                };
+    #[test]
+    fn nearest_drawing_hits_lines_and_rays() {
+        let drawings = vec![
+            Drawing::Trend {
+                a: [0.0, 0.0],
+                b: [100.0, 100.0],
+            },
+            Drawing::HRay { x: 50.0, y: 20.0 },
+        ];
+        assert_eq!(
+            nearest_drawing(&drawings, [50.0, 50.5], 100.0, 100.0),
+            Some(0)
+        );
+        // rays extend right of their start, not left
+        assert_eq!(
+            nearest_drawing(&drawings, [90.0, 20.0], 100.0, 100.0),
+            Some(1)
+        );
+        assert_eq!(nearest_drawing(&drawings, [5.0, 20.0], 100.0, 100.0), None);
+
+        let json = serde_json::to_string(&drawings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<Drawing>>(&json).unwrap(),
+            drawings
+        );
+    }
 
-                let h = match node.latest_block_height().await {
-                    Ok(h) => h,
-                    Err(e) => {
-                        eprintln!("[trader] height error: {e}");
-                        continue;
-                    }
-                };
+    #[test]
+    fn workspace_round_trips_and_adopts_old_drawings() {
+        let dir = std::env::temp_dir().join(format!("workspace_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (ws_path, legacy) = (dir.join("workspace_T.json"), dir.join("drawings_T.json"));
+
+        // no workspace yet: the old drawings file seeds it
+        let old = vec![Drawing::HRay { x: 1.0, y: 2.0 }];
+        std::fs::write(&legacy, serde_json::to_string(&old).unwrap()).unwrap();
+        let mut ws = load_workspace(&ws_path, &legacy);
+        assert_eq!(ws.drawings, old);
+
+        ws.add_bookmark(300, "b".to_string());
+        ws.add_bookmark(100, "a".to_string());
+        ws.add_bookmark(300, "b2".to_string());
+        assert_eq!(ws.bookmarks.len(), 2);
+        assert_eq!(ws.prev_bookmark(300), Some(100));
+        assert_eq!(ws.next_bookmark(100), Some(300));
+        assert_eq!(ws.next_bookmark(300), None);
+        ws.alerts.push(PriceAlert {
+            above: true,
+            price: 110.0,
+            triggered: true,
+        });
+        save_workspace(&ws_path, &ws).unwrap();
 
-                let (_id, order) = match OrderBuilder::new(market, sub.clone())
-                    .market(side, size.clone())
-                    .reduce_only(false)
-                    .price(100) // placeholder slippage guard; adjust later
-                    .time_in_force(TimeInForce::Unspecified)
-                    .until(h.ahead(10))
-                    .build(123456)
-                {
-                    Ok(x) => x,
-                    Err(e) => {
-                        eprintln!("[trader] build order error: {e}");
-                        continue;
-                    }
-                };
+        // the workspace file wins over the old drawings; triggers aren't saved
+        std::fs::write(&legacy, "[]").unwrap();
+        let back = load_workspace(&ws_path, &legacy);
+        assert_eq!(back.drawings, old);
+        assert_eq!(back.bookmarks[1].label, "b2");
+        assert!(!back.alerts[0].triggered);
 
-                match node.place_order(&mut account, order).await {
-                    Ok(tx_hash) => {
-                        eprintln!(
-                            "[trader] placed {:?} {} size {} tx={tx_hash:?}",
-                            side, ticker, size
-                        );
-                        append_trade_csv(
-                            &ticker,
-                            "gui_live",
-                            &format!("{:?}", side),
-                            &size.to_string(),
-                        );
-                    }
-                    Err(e) => {
-                        eprintln!("[trader] place_order error: {e}");
-                    }
-                }
-            }
+        // a file from before alerts existed still loads
+        std::fs::write(&ws_path, r#"{"bookmarks":[{"ts":5,"label":"x"}]}"#).unwrap();
+        let partial = load_workspace(&ws_path, &legacy);
+        assert_eq!((partial.bookmarks.len(), partial.alerts.len()), (1, 0));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn price_alerts_fire_once_per_crossing() {
+        let mut ws = Workspace {
+            alerts: vec![
+                PriceAlert {
+                    above: true,
+                    price: 110.0,
+                    triggered: false,
+                },
+                PriceAlert {
+                    above: false,
+                    price: 90.0,
+                    triggered: false,
+                },
+            ],
+            ..Workspace::default()
+        };
+        let fired = |ws: &mut Workspace, mid: f64| -> Vec<f64> {
+            ws.check_alerts(mid).iter().map(|a| a.price).collect()
+        };
+        assert!(fired(&mut ws, 100.0).is_empty());
+        assert_eq!(fired(&mut ws, 110.0), vec![110.0]);
+        // still above: no repeat until it comes back down
+        assert!(fired(&mut ws, 115.0).is_empty());
+        assert!(fired(&mut ws, 105.0).is_empty());
+        assert_eq!(fired(&mut ws, 111.0), vec![110.0]);
+        assert_eq!(fired(&mut ws, 80.0), vec![90.0]);
+    }
+
+    fn undo_state(ticker: &str, drawings: usize) -> UndoState {
+        UndoState {
+            layout: LayoutSettings::default(),
+            appearance: AppearanceSettings::default(),
+            panels: PanelToggles::of(&ChartSettings::default()),
+            ticker: ticker.to_string(),
+            drawings: (0..drawings)
+                .map(|i| Drawing::HRay {
+                    x: 0.0,
+                    y: i as f64,
+                })
+                .collect(),
         }
     }
-}
 
-// ------------- main -------------
+    #[test]
+    fn undo_history_steps_back_and_forth_over_changes() {
+        let count = |s: Option<UndoState>| s.map(|s| s.drawings.len());
+        let mut h = UndoHistory::default();
+        for n in 0..3 {
+            h.observe(undo_state("ETH-USD", n));
+            // unchanged frames add nothing
+            h.observe(undo_state("ETH-USD", n));
+        }
+        assert_eq!(count(h.undo()), Some(1));
+        assert_eq!(count(h.undo()), Some(0));
+        assert_eq!(count(h.undo()), None);
+        assert_eq!(count(h.redo()), Some(1));
+
+        // a restored state observed back is not a new step; a real edit
+        // drops the redo branch
+        h.observe(undo_state("ETH-USD", 1));
+        assert_eq!(h.redo.len(), 1);
+        let mut edited = undo_state("ETH-USD", 1);
+        edited.panels.spread = !edited.panels.spread;
+        h.observe(edited);
+        assert!(h.redo().is_none());
+        assert_eq!(count(h.undo()), Some(1));
+
+        // switching tickers is not undoable
+        let mut h = UndoHistory::default();
+        h.observe(undo_state("ETH-USD", 2));
+        h.observe(undo_state("BTC-USD", 0));
+        assert!(h.undo().is_none());
+
+        let mut h = UndoHistory::default();
+        for n in 0..UNDO_DEPTH + 10 {
+            h.observe(undo_state("ETH-USD", n));
+        }
+        assert_eq!(h.undo.len(), UNDO_DEPTH);
+    }
 
-fn main() {
-    init_crypto_provider();
+    fn synthetic_ticker(n: usize) -> TickerData {
+        let book_events: Vec<BookCsvEvent> = (0..n)
+            .map(|i| BookCsvEvent {
+                // several rows per second, like a real feed
+                ts: 1_700_000_000 + (i / 7) as u64,
+                ticker: "ETH-USD".to_string(),
+                kind: "update".to_string(),
+                side: [BookSide::Bid, BookSide::Ask][i % 2],
+                price: if i % 2 == 0 { 3000.0 } else { 3000.5 } + (i % 50) as f64 * 0.1,
+                size: (i % 5) as f64,
+                seq: None,
+                venue: String::new(),
+            })
+            .collect();
+        let book_index = build_book_index(&book_events);
+        TickerData {
+            ticker: "ETH-USD".to_string(),
+            min_ts: book_events.first().map(|e| e.ts).unwrap_or(0),
+            max_ts: book_events.last().map(|e| e.ts).unwrap_or(0),
+            book_events,
+            book_mmap: None,
+            book_index,
+            trade_events: Vec::new(),
+            price_scale: DEFAULT_PRICE_SCALE,
+            load_stats: LoadStats::default(),
+            gaps: Vec::new(),
+            venues: Vec::new(),
+        }
+    }
 
-    let (book_tx, book_rx) = watch::channel(LiveBook::default());
+    #[test]
+    fn events_upto_matches_linear_scan() {
+        let td = synthetic_ticker(3 * BOOK_INDEX_STRIDE + 17);
+        let probes = [
+            0,
+            td.min_ts,
+            td.min_ts + 1,
+            td.book_events[BOOK_INDEX_STRIDE].ts,
+            td.book_events[2 * BOOK_INDEX_STRIDE - 1].ts,
+            td.max_ts - 1,
+            td.max_ts,
+            u64::MAX,
+        ];
+        for ts in probes {
+            let linear = td.book_events.iter().take_while(|e| e.ts <= ts).count();
+            assert_eq!(td.events_upto(ts).len(), linear, "ts {ts}");
+        }
+    }
 
-    // preload replay data from ./data
-    let base_dir = "data";
-    let tickers = vec!["ETH-USD", "BTC-USD", "SOL-USD"];
-    let mut replay_data = HashMap::new();
-    for tk in tickers {
-        if let Some(td) = load_ticker_data(base_dir, tk) {
-            replay_data.insert(tk.to_string(), td);
+    // 60s-aligned so the 1m candles start at T0
+    const T0: u64 = 1_700_000_040;
+
+    fn book_row(ts: u64, kind: &str, side: BookSide, price: f64, size: f64) -> BookCsvEvent {
+        BookCsvEvent {
+            ts,
+            ticker: "ETH-USD".to_string(),
+            kind: kind.to_string(),
+            side,
+            price,
+            size,
+            seq: None,
+            venue: String::new(),
         }
     }
 
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .expect("tokio runtime");
+    // inits, a resize, a removal + replacement in the same second, and a late
+    // removal a minute on
+    fn replay_ticker() -> TickerData {
+        let mut td = synthetic_ticker(0);
+        td.book_events = vec![
+            book_row(T0, "init", BookSide::Bid, 100.0, 1.0),
+            book_row(T0, "init", BookSide::Ask, 101.0, 2.0),
+            book_row(T0, "init", BookSide::Bid, 99.5, 3.0),
+            book_row(T0 + 1, "update", BookSide::Bid, 100.0, 4.0),
+            book_row(T0 + 2, "update", BookSide::Ask, 101.0, 0.0),
+            book_row(T0 + 2, "update", BookSide::Ask, 101.5, 1.0),
+            book_row(T0 + 70, "update", BookSide::Bid, 99.5, 0.0),
+        ];
+        td.book_index = build_book_index(&td.book_events);
+        td.min_ts = T0;
+        td.max_ts = T0 + 70;
+        td
+    }
 
-    let (ticker_tx, ticker_rx) =
-        watch::channel::<String>("ETH-USD".to_string());
+    fn levels(v: &[(f64, f64)]) -> BTreeMap<PriceKey, f64> {
+        v.iter()
+            .map(|&(p, s)| (price_to_key(p, DEFAULT_PRICE_SCALE), s))
+            .collect()
+    }
 
-    let (trade_tx, trade_rx) = mpsc::channel::<TradeCmd>(32);
+    fn ohlcv(c: &Candle) -> (u64, f64, f64, f64, f64, f64) {
+        (c.t, c.open, c.high, c.low, c.close, c.volume)
+    }
 
-    // spawn live feed
-    rt.spawn(run_live_feed(book_tx, ticker_rx));
+    #[test]
+    fn snapshot_book_applies_rows_up_to_target_ts_only() {
+        let td = replay_ticker();
+        let check = |ts, bids: &[(f64, f64)], asks: &[(f64, f64)]| {
+            let snap = replay_snapshot(&td, ts, MidMode::Simple);
+            assert_eq!(snap.ts, ts);
+            assert_eq!(snap.bids, levels(bids), "bids at {ts}");
+            assert_eq!(snap.asks, levels(asks), "asks at {ts}");
+        };
 
-    // spawn trader
-    rt.spawn(run_trader(trade_rx));
+        check(T0 - 1, &[], &[]);
+        check(T0, &[(99.5, 3.0), (100.0, 1.0)], &[(101.0, 2.0)]);
+        check(T0 + 1, &[(99.5, 3.0), (100.0, 4.0)], &[(101.0, 2.0)]);
+        // zero size removes 101.0; side matching ignores case
+        check(T0 + 2, &[(99.5, 3.0), (100.0, 4.0)], &[(101.5, 1.0)]);
+        check(T0 + 69, &[(99.5, 3.0), (100.0, 4.0)], &[(101.5, 1.0)]);
+        check(T0 + 70, &[(100.0, 4.0)], &[(101.5, 1.0)]);
+    }
 
-    let options = eframe::NativeOptions::default();
-    let app = ComboApp::new(book_rx, replay_data, ticker_tx.clone(), trade_tx);
+    #[test]
+    fn snapshot_rows_reset_the_reconstructed_book() {
+        let snap_row = |side, price, size| BookCsvEvent {
+            seq: Some(9),
+            ..book_row(T0 + 3, SNAPSHOT_KIND, side, price, size)
+        };
+        let mut td = replay_ticker();
+        // the bid 99.5 pull at T0 + 70 went missing; a snapshot at T0 + 3
+        // doesn't have the level either, the same size 100.0 is restated
+        td.book_events.pop();
+        td.book_events.extend([
+            snap_row(BookSide::Bid, 100.0, 4.0),
+            snap_row(BookSide::Ask, 101.5, 1.0),
+            snap_row(BookSide::Ask, 102.0, 0.5),
+        ]);
+        td.book_events.sort_by_key(|e| e.ts);
+        let (events, collapsed) = dedup_book_events(td.book_events, DEFAULT_PRICE_SCALE);
+        assert_eq!(collapsed, 0, "snapshot rows are never collapsed");
+        td.book_events = events;
+        td.book_index = build_book_index(&td.book_events);
+
+        let snap = replay_snapshot(&td, T0 + 3, MidMode::Simple);
+        assert_eq!(snap.bids, levels(&[(100.0, 4.0)]));
+        assert_eq!(snap.asks, levels(&[(101.5, 1.0), (102.0, 0.5)]));
+        // restated levels add no volume
+        let last = snap.candles_by_tf[&1].last().map(ohlcv);
+        assert_eq!(last, Some((T0 + 3, 100.75, 100.75, 100.75, 100.75, 0.0)));
+    }
 
-    if let Err(e) = eframe::run_native(
-        "dYdX Live + Replay Combo",
-        options,
-        Box::new(|_cc| Box::new(app)),
-    ) {
-        eprintln!("eframe error: {e}");
+    #[test]
+    fn snapshot_candles_follow_mid_of_applied_rows() {
+        let td = replay_ticker();
+
+        // no mid while the asks are empty; nothing after target_ts
+        let snap = replay_snapshot(&td, T0 + 2, MidMode::Simple);
+        let m1: Vec<_> = snap.candles_by_tf[&60].iter().map(ohlcv).collect();
+        assert_eq!(m1, vec![(T0, 100.5, 100.75, 100.5, 100.75, 10.0)]);
+        let s1: Vec<_> = snap.candles_by_tf[&1].iter().map(ohlcv).collect();
+        assert_eq!(
+            s1,
+            vec![
+                (T0, 100.5, 100.5, 100.5, 100.5, 5.0),
+                (T0 + 1, 100.5, 100.5, 100.5, 100.5, 4.0),
+                (T0 + 2, 100.75, 100.75, 100.75, 100.75, 1.0),
+            ]
+        );
+        assert_eq!((snap.last_mid, snap.last_vol), (100.75, 10.0));
+
+        let snap = replay_snapshot(&td, T0 + 70, MidMode::Simple);
+        let m1: Vec<_> = snap.candles_by_tf[&60].iter().map(ohlcv).collect();
+        assert_eq!(
+            m1,
+            vec![
+                (T0, 100.5, 100.75, 100.5, 100.75, 10.0),
+                (T0 + 60, 100.75, 100.75, 100.75, 100.75, 0.0),
+            ]
+        );
+
+        let empty = replay_snapshot(&td, T0 - 1, MidMode::Simple);
+        assert!(empty.candles_by_tf.values().all(|c| c.is_empty()));
+        assert_eq!((empty.last_mid, empty.last_vol), (0.0, 0.0));
     }
 
-    drop(rt);
+    #[test]
+    fn streamed_snapshot_matches_in_memory() {
+        use std::io::Write;
+
+        let mem = replay_ticker();
+        let path = std::env::temp_dir().join(format!("orderbook_snap_{}.csv", std::process::id()));
+        {
+            let mut f = File::create(&path).unwrap();
+            writeln!(f, "ts,ticker,kind,side,price,size").unwrap();
+            // another ticker's rows share the file and must be skipped
+            writeln!(f, "{T0},BTC-USD,init,bid,50000,1").unwrap();
+            for e in &mem.book_events {
+                let (ts, kind, side, price, size) = (e.ts, &e.kind, &e.side, e.price, e.size);
+                writeln!(f, "{ts},ETH-USD,{kind},{side},{price},{size}").unwrap();
+            }
+        }
+        let mmap = map_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let (_, _, _, index, _) = scan_book_mmap(&mmap, "ETH-USD");
+
+        let mut streamed = synthetic_ticker(0);
+        streamed.book_mmap = Some(Arc::new(mmap));
+        streamed.book_index = index;
+
+        for ts in [T0 - 1, T0, T0 + 1, T0 + 2, T0 + 69, T0 + 70] {
+            let a = replay_snapshot(&mem, ts, MidMode::Simple);
+            let b = replay_snapshot(&streamed, ts, MidMode::Simple);
+            assert_eq!((&a.bids, &a.asks), (&b.bids, &b.asks), "ts {ts}");
+            let candles =
+                |s: &TfSnapshot| s.candles_by_tf[&1].iter().map(ohlcv).collect::<Vec<_>>();
+            assert_eq!(candles(&a), candles(&b), "ts {ts}");
+        }
+    }
+
+    // cargo test --release --bin full_gui11 bench_events_upto -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_events_upto_1m() {
+        use std::io::Write;
+        use std::time::Instant;
+
+        let path = std::env::temp_dir().join("orderbook_BENCH-USD.csv");
+        {
+            let mut f = File::create(&path).unwrap();
+            writeln!(f, "ts,ticker,kind,side,price,size").unwrap();
+            for e in &synthetic_ticker(1_000_000).book_events {
+                writeln!(
+                    f,
+                    "{},BENCH-USD,{},{},{},{}",
+                    e.ts, e.kind, e.side, e.price, e.size
+                )
+                .unwrap();
+            }
+        }
+        let (book_events, _) = load_book_csv(&path, "BENCH-USD");
+        let _ = std::fs::remove_file(&path);
+
+        let mut td = synthetic_ticker(0);
+        td.book_index = build_book_index(&book_events);
+        td.min_ts = book_events[0].ts;
+        td.max_ts = book_events[book_events.len() - 1].ts;
+        td.book_events = book_events;
+
+        let span = td.max_ts - td.min_ts;
+        let seeks: Vec<u64> = (0..200).map(|i| td.min_ts + span * i / 200).collect();
+
+        let t0 = Instant::now();
+        let mut linear = 0usize;
+        for &ts in &seeks {
+            linear += td.book_events.iter().take_while(|e| e.ts <= ts).count();
+        }
+        let linear_dt = t0.elapsed();
+
+        let t1 = Instant::now();
+        let mut indexed = 0usize;
+        for &ts in &seeks {
+            indexed += td.events_upto(ts).len();
+        }
+        let indexed_dt = t1.elapsed();
+
+        assert_eq!(linear, indexed);
+        println!(
+            "{} events, {} seeks: linear {:?}, events_upto {:?}",
+            td.book_events.len(),
+            seeks.len(),
+            linear_dt,
+            indexed_dt
+        );
+    }
 }
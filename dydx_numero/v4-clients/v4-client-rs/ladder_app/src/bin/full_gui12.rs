@@ -12,7 +12,7 @@
 //   - Real testnet market BUY/SELL buttons with CSV logging
 //
 // Replay mode:
-//   - Reads CSVs from ./data:
+//   - Reads CSVs from ./data (or --data-dir <path>):
 //       data/orderbook_{TICKER}.csv
 //       data/trades_{TICKER}.csv
 //   - Reconstructs book + candles + volume + recent trades
@@ -35,86 +35,40 @@
 //   cargo run -p ladder_app --bin full_gui11
 //
 
-mod candle_agg;
-
-use candle_agg::{Candle, CandleAgg};
-
-use chrono::{Local, TimeZone};
-
 use eframe::egui;
 use egui::{Color32, RichText};
 use egui_plot::{Line, Plot, PlotBounds, PlotPoints, VLine};
 
 use std::cmp::{max, min};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::env;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::fs::OpenOptions;
+use std::io::Write;
 use std::path::Path;
 use std::str::FromStr;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 use tokio::sync::{mpsc, watch};
+use tracing::{error, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
 
 // dYdX client
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, ToPrimitive};
 
 use dydx_client::config::ClientConfig;
-use dydx_client::indexer::{
-    Feed as DxFeed, Feeds, IndexerClient, OrderbookResponsePriceLevel, OrdersMessage, Ticker,
-};
+use dydx_client::indexer::{Feed as DxFeed, Feeds, IndexerClient, OrdersMessage, Ticker};
 use dydx_client::node::{NodeClient, OrderBuilder, OrderSide, Wallet};
 use dydx_proto::dydxprotocol::clob::order::TimeInForce;
 
-// =============== basic helpers ===============
-
-fn now_unix() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0))
-        .as_secs()
-}
-
-// integer keys so BTreeMap ordering is nice
-type PriceKey = i64;
-
-fn price_to_key(price: f64) -> PriceKey {
-    (price * 10_000.0).round() as PriceKey
-}
-
-fn key_to_price(key: PriceKey) -> f64 {
-    key as f64 / 10_000.0
-}
-
-// =============== time formatting ===============
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TimeDisplayMode {
-    Unix,
-    Local,
-}
-
-impl TimeDisplayMode {
-    fn label(self) -> &'static str {
-        match self {
-            TimeDisplayMode::Unix => "Unix",
-            TimeDisplayMode::Local => "Local",
-        }
-    }
-}
-
-fn format_ts(mode: TimeDisplayMode, ts: u64) -> String {
-    match mode {
-        TimeDisplayMode::Unix => format!("{ts}"),
-        TimeDisplayMode::Local => {
-            let dt = Local
-                .timestamp_opt(ts as i64, 0)
-                .single()
-                .unwrap_or_else(|| Local.timestamp_opt(0, 0).single().unwrap());
-            dt.format("%Y-%m-%d %H:%M:%S").to_string()
-        }
-    }
-}
+use ladder_app::book::{key_to_price, scale_from_tick, LiveBook, DEFAULT_PRICE_SCALE};
+use ladder_app::candle_agg::{Candle, CandleAgg};
+use ladder_app::csv_io::{
+    data_dir, data_file, load_book_csv, load_trades_csv, BookCsvEvent, TradeCsvEvent, TradeSide,
+};
+use ladder_app::repaint::RepaintThrottle;
+use ladder_app::replay::ReplayEngine;
+use ladder_app::snapshot::{compute_snapshot_for, TfSnapshot};
+use ladder_app::time_fmt::{format_ts, now_unix, TimeDisplayMode};
 
 // =============== chart + layout settings ===============
 
@@ -249,121 +203,8 @@ fn theme_from_id(id: ThemeId) -> Theme {
     }
 }
 
-// =============== live book ===============
-
-#[derive(Clone, Debug, Default)]
-struct LiveBook {
-    bids: BTreeMap<PriceKey, f64>,
-    asks: BTreeMap<PriceKey, f64>,
-}
-
-impl LiveBook {
-    fn apply_levels(
-        map: &mut BTreeMap<PriceKey, f64>,
-        levels: Vec<OrderbookResponsePriceLevel>,
-        side: &str,
-        ticker: &str,
-    ) {
-        for lvl in levels {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-
-            if s == 0.0 {
-                map.remove(&key);
-            } else {
-                map.insert(key, s);
-            }
-
-            append_book_csv(ticker, "delta", side, p, s);
-        }
-    }
-
-    fn apply_initial(
-        &mut self,
-        bids: Vec<OrderbookResponsePriceLevel>,
-        asks: Vec<OrderbookResponsePriceLevel>,
-        ticker: &str,
-    ) {
-        self.bids.clear();
-        self.asks.clear();
-
-        for lvl in bids {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-            if s != 0.0 {
-                self.bids.insert(key, s);
-            }
-            append_book_csv(ticker, "book_init", "bid", p, s);
-        }
-
-        for lvl in asks {
-            let price_bd = lvl.price.0;
-            let size_bd = lvl.size.0;
-            let p = price_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let s = size_bd.to_string().parse::<f64>().unwrap_or(0.0);
-            let key = price_to_key(p);
-            if s != 0.0 {
-                self.asks.insert(key, s);
-            }
-            append_book_csv(ticker, "book_init", "ask", p, s);
-        }
-    }
-
-    fn apply_update(
-        &mut self,
-        bids: Option<Vec<OrderbookResponsePriceLevel>>,
-        asks: Option<Vec<OrderbookResponsePriceLevel>>,
-        ticker: &str,
-    ) {
-        if let Some(b) = bids {
-            Self::apply_levels(&mut self.bids, b, "bid", ticker);
-        }
-        if let Some(a) = asks {
-            Self::apply_levels(&mut self.asks, a, "ask", ticker);
-        }
-    }
-
-    fn mid(&self) -> Option<f64> {
-        let bp = self.bids.iter().next_back();
-        let ap = self.asks.iter().next();
-        match (bp, ap) {
-            (Some((b, _)), Some((a, _))) => {
-                let pb = key_to_price(*b);
-                let pa = key_to_price(*a);
-                Some((pb + pa) * 0.5)
-            }
-            _ => None,
-        }
-    }
-}
-
 // =============== CSV + replay structures ===============
 
-#[derive(Clone, Debug)]
-struct BookCsvEvent {
-    ts: u64,
-    ticker: String,
-    kind: String,
-    side: String,
-    price: f64,
-    size: f64,
-}
-
-#[derive(Clone, Debug)]
-struct TradeCsvEvent {
-    ts: u64,
-    ticker: String,
-    source: String,
-    side: String,
-    size_str: String,
-}
-
 #[derive(Clone, Debug)]
 struct TickerData {
     ticker: String,
@@ -371,160 +212,40 @@ struct TickerData {
     trade_events: Vec<TradeCsvEvent>,
     min_ts: u64,
     max_ts: u64,
-}
-
-#[derive(Clone, Debug, Default)]
-struct Snapshot {
-    bids: BTreeMap<PriceKey, f64>,
-    asks: BTreeMap<PriceKey, f64>,
-    candles: HashMap<u64, Vec<Candle>>, // tf -> series
-    last_mid: f64,
-    last_vol: f64,
-    trades: Vec<TradeCsvEvent>,
+    // CSVs carry no tick size, so replay uses DEFAULT_PRICE_SCALE
+    price_scale: f64,
 }
 
 // --- CSV IO ---
 
 fn append_book_csv(ticker: &str, kind: &str, side: &str, price: f64, size: f64) {
     let ts = now_unix();
-    let dir = Path::new("data");
+    let dir = data_dir();
     let _ = std::fs::create_dir_all(dir);
     let path = dir.join(format!("orderbook_{ticker}.csv"));
 
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
         let _ = writeln!(f, "{ts},{ticker},{kind},{side},{price},{size}");
     }
 }
 
 fn append_trade_csv(ticker: &str, source: &str, side: &str, size_str: &str) {
     let ts = now_unix();
-    let dir = Path::new("data");
+    let dir = data_dir();
     let _ = std::fs::create_dir_all(dir);
     let path = dir.join(format!("trades_{ticker}.csv"));
 
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
         let _ = writeln!(f, "{ts},{ticker},{source},{side},{size_str}");
     }
 }
 
-fn load_book_csv(path: &Path, ticker: &str) -> Vec<BookCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
-    }
-    let f = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
-    };
-    let reader = BufReader::new(f);
-    let mut out = Vec::new();
-
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 6 {
-                continue;
-            }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            let kind = parts[2].to_string();
-            let side = parts[3].to_string();
-            let price = match parts[4].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let size = match parts[5].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+fn load_ticker_data(base_dir: &Path, ticker: &str) -> Option<TickerData> {
+    let ob_path = data_file(base_dir, &format!("orderbook_{ticker}"));
+    let tr_path = data_file(base_dir, &format!("trades_{ticker}"));
 
-            if tk != ticker {
-                continue;
-            }
-
-            out.push(BookCsvEvent {
-                ts,
-                ticker: tk,
-                kind,
-                side,
-                price,
-                size,
-            });
-        }
-    }
-
-    out.sort_by_key(|e| e.ts);
-    out
-}
-
-fn load_trades_csv(path: &Path, ticker: &str) -> Vec<TradeCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
-    }
-    let f = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
-    };
-    let reader = BufReader::new(f);
-    let mut out = Vec::new();
-
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 5 {
-                continue;
-            }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            let source = parts[2].to_string();
-            let side = parts[3].to_string();
-            let size_str = parts[4].to_string();
-
-            if tk != ticker {
-                continue;
-            }
-
-            out.push(TradeCsvEvent {
-                ts,
-                ticker: tk,
-                source,
-                side,
-                size_str,
-            });
-        }
-    }
-
-    out.sort_by_key(|t| t.ts);
-    out
-}
-
-fn load_ticker_data(base_dir: &str, ticker: &str) -> Option<TickerData> {
-    let ob_path = Path::new(base_dir).join(format!("orderbook_{ticker}.csv"));
-    let tr_path = Path::new(base_dir).join(format!("trades_{ticker}.csv"));
-
-    let book_events = load_book_csv(&ob_path, ticker);
-    let trade_events = load_trades_csv(&tr_path, ticker);
+    let (book_events, _) = load_book_csv(&ob_path, ticker);
+    let (trade_events, _) = load_trades_csv(&tr_path, ticker);
 
     if book_events.is_empty() && trade_events.is_empty() {
         return None;
@@ -552,92 +273,43 @@ fn load_ticker_data(base_dir: &str, ticker: &str) -> Option<TickerData> {
         trade_events,
         min_ts,
         max_ts,
+        price_scale: DEFAULT_PRICE_SCALE,
     })
 }
 
-// reconstruct snapshot at target_ts
-fn compute_snapshot_for(data: &TickerData, target_ts: u64) -> Snapshot {
-    let mut bids: BTreeMap<PriceKey, f64> = BTreeMap::new();
-    let mut asks: BTreeMap<PriceKey, f64> = BTreeMap::new();
-
-    let mut aggs: HashMap<u64, CandleAgg> = HashMap::new();
-    for (tf, _) in TF_CHOICES {
-        aggs.insert(*tf, CandleAgg::new(*tf));
-    }
-
-    for e in &data.book_events {
-        if e.ts > target_ts {
-            break;
-        }
-
-        let map = if e.side.to_lowercase() == "bid" {
-            &mut bids
-        } else {
-            &mut asks
-        };
-
-        let key = price_to_key(e.price);
-
-        if e.size == 0.0 {
-            map.remove(&key);
-        } else {
-            map.insert(key, e.size);
-        }
-
-        if let (Some((bp, _)), Some((ap, _))) = (bids.iter().next_back(), asks.iter().next()) {
-            let mid = (key_to_price(*bp) + key_to_price(*ap)) * 0.5;
-            let vol = e.size.abs().max(0.0);
-
-            for agg in aggs.values_mut() {
-                agg.update(e.ts, mid, vol);
-            }
-        }
-    }
+// reconstruct snapshot at target_ts; 1m candles give last_mid/vol
+fn replay_snapshot(data: &TickerData, target_ts: u64) -> TfSnapshot {
+    let tfs: Vec<u64> = TF_CHOICES.iter().map(|(tf, _)| *tf).collect();
+    let engine = ReplayEngine::over(&data.book_events, &data.trade_events, data.price_scale);
+    compute_snapshot_for(engine, target_ts, &tfs, 60)
+}
 
-    let mut trades: Vec<TradeCsvEvent> = data
-        .trade_events
-        .iter()
-        .filter(|t| t.ts <= target_ts)
-        .cloned()
-        .collect();
-    trades.sort_by_key(|t| t.ts);
-    if trades.len() > 200 {
-        let start = trades.len() - 200;
-        trades = trades[start..].to_vec();
-    }
+// =============== crypto provider ===============
 
-    let series_1m = aggs
-        .get(&60)
-        .map(|a| a.series())
-        .unwrap_or(&[] as &[Candle]);
+fn init_crypto_provider() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
 
-    let (last_mid, last_vol) = if let Some(c) = series_1m.last() {
-        (c.close, c.volume)
+// =============== logging ===============
+
+// fmt subscriber on stderr. RUST_LOG filters as usual (default `info`, e.g.
+// `RUST_LOG=full_gui12=debug,dydx=debug`); `json` emits one object per line.
+fn init_tracing(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        fmt.json().init();
     } else {
-        (0.0, 0.0)
-    };
-
-    let mut candles: HashMap<u64, Vec<Candle>> = HashMap::new();
-    for (tf, _) in TF_CHOICES {
-        if let Some(a) = aggs.get(tf) {
-            candles.insert(*tf, a.series().to_vec());
-        }
-    }
-
-    Snapshot {
-        bids,
-        asks,
-        candles,
-        last_mid,
-        last_vol,
-        trades,
+        fmt.init();
     }
 }
 
-// =============== crypto provider ===============
-
-fn init_crypto_provider() {
-    let _ = rustls::crypto::ring::default_provider().install_default();
+// `--log-json` or LOG_FORMAT=json
+fn log_json_from_args(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--log-json")
+        || env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
 }
 
 // =============== trading command ===============
@@ -655,6 +327,7 @@ enum TradeCmd {
         side: OrderSide,
         size: BigDecimal,
         leverage: f64,
+        reduce_only: bool,
     },
     LimitOrder {
         ticker: String,
@@ -662,6 +335,7 @@ enum TradeCmd {
         size: BigDecimal,
         price: BigDecimal,
         leverage: f64,
+        reduce_only: bool,
     },
 }
 
@@ -702,6 +376,13 @@ struct ComboApp {
     replay_data: HashMap<String, TickerData>,
     replay_ts: u64,
     replay_tab: ReplayTab,
+    // reconstruction of replay_ts, rebuilt only when replay_dirty is set
+    // (ticker or replay time changed) instead of on every frame
+    replay_snapshot: Option<TfSnapshot>,
+    replay_dirty: bool,
+
+    // repaint cadence; replay waits for input once idle
+    repaint: RepaintThrottle,
 }
 
 impl ComboApp {
@@ -759,6 +440,10 @@ impl ComboApp {
             replay_data,
             replay_ts,
             replay_tab: ReplayTab::Candles,
+            replay_snapshot: None,
+            replay_dirty: true,
+
+            repaint: RepaintThrottle::new(Duration::from_millis(50)).with_args(env::args().skip(1)),
         }
     }
 
@@ -780,10 +465,10 @@ impl ComboApp {
         }
     }
 
-    fn replay_series(&self, snap: &Snapshot) -> Vec<Candle> {
-        if let Some(v) = snap.candles.get(&self.chart.selected_tf) {
+    fn replay_series(&self, snap: &TfSnapshot) -> Vec<Candle> {
+        if let Some(v) = snap.candles_by_tf.get(&self.chart.selected_tf) {
             v.clone()
-        } else if let Some(v) = snap.candles.get(&60) {
+        } else if let Some(v) = snap.candles_by_tf.get(&60) {
             v.clone()
         } else {
             Vec::new()
@@ -793,6 +478,7 @@ impl ComboApp {
     fn tick_live(&mut self) {
         if self.live_book_rx.has_changed().unwrap_or(false) {
             self.live_book = self.live_book_rx.borrow().clone();
+            self.repaint.touch(Instant::now());
         }
 
         let ts = now_unix();
@@ -813,12 +499,29 @@ impl ComboApp {
             None => return,
         };
 
-        if self.replay_ts < min_ts {
-            self.replay_ts = min_ts;
+        let ts = self.replay_ts.clamp(min_ts, max_ts);
+        self.set_replay_ts(ts);
+    }
+
+    fn set_replay_ts(&mut self, ts: u64) {
+        if ts != self.replay_ts {
+            self.replay_ts = ts;
+            self.mark_replay_dirty();
         }
-        if self.replay_ts > max_ts {
-            self.replay_ts = max_ts;
+    }
+
+    fn mark_replay_dirty(&mut self) {
+        self.replay_dirty = true;
+    }
+
+    fn recompute_replay_if_dirty(&mut self) {
+        if !self.replay_dirty {
+            return;
         }
+        self.replay_snapshot = self
+            .current_replay_ticker()
+            .map(|td| replay_snapshot(td, self.replay_ts));
+        self.replay_dirty = false;
     }
 
     // ---------- top bar ----------
@@ -853,6 +556,7 @@ impl ComboApp {
                         let selected = *t == self.current_ticker;
                         if ui.selectable_label(selected, t).clicked() {
                             self.current_ticker = t.clone();
+                            self.mark_replay_dirty();
 
                             let _ = self.ticker_tx.send(t.clone());
 
@@ -912,27 +616,33 @@ impl ComboApp {
 
             if let Some(td) = self.current_replay_ticker() {
                 ui.separator();
-                ui.label(RichText::new(format!(
-                    "Replay range: {} → {}",
-                    format_ts(self.time_mode, td.min_ts),
-                    format_ts(self.time_mode, td.max_ts)
-                ))
-                .small());
+                ui.label(
+                    RichText::new(format!(
+                        "Replay range: {} → {}",
+                        format_ts(self.time_mode, td.min_ts),
+                        format_ts(self.time_mode, td.max_ts)
+                    ))
+                    .small(),
+                );
                 ui.separator();
-                ui.label(RichText::new(format!(
-                    "Replay ts: {}",
-                    format_ts(self.time_mode, self.replay_ts)
-                ))
-                .small());
+                ui.label(
+                    RichText::new(format!(
+                        "Replay ts: {}",
+                        format_ts(self.time_mode, self.replay_ts)
+                    ))
+                    .small(),
+                );
             }
 
             if matches!(self.mode, Mode::Live) {
                 ui.separator();
-                ui.label(RichText::new(format!(
-                    "Live ts: {}",
-                    format_ts(self.time_mode, self.live_last_ts)
-                ))
-                .small());
+                ui.label(
+                    RichText::new(format!(
+                        "Live ts: {}",
+                        format_ts(self.time_mode, self.live_last_ts)
+                    ))
+                    .small(),
+                );
             }
         });
 
@@ -960,7 +670,7 @@ impl ComboApp {
                     }
                     ui.label(format_ts(self.time_mode, ts));
                 });
-                self.replay_ts = ts;
+                self.set_replay_ts(ts);
             } else {
                 ui.label("No replay CSV for this ticker.");
             }
@@ -971,10 +681,7 @@ impl ComboApp {
         // shared chart controls
         ui.horizontal(|ui| {
             ui.label("History candles:");
-            ui.add(
-                egui::Slider::new(&mut self.chart.show_candles, 20..=600)
-                    .logarithmic(true),
-            );
+            ui.add(egui::Slider::new(&mut self.chart.show_candles, 20..=600).logarithmic(true));
 
             ui.separator();
             ui.label("X zoom:");
@@ -1038,11 +745,7 @@ impl ComboApp {
                     ReplayTab::Orderbook,
                     "Orderbook + Trades",
                 );
-                ui.selectable_value(
-                    &mut self.replay_tab,
-                    ReplayTab::Candles,
-                    "Candles + Volume",
-                );
+                ui.selectable_value(&mut self.replay_tab, ReplayTab::Candles, "Candles + Volume");
             });
             ui.separator();
         }
@@ -1057,9 +760,7 @@ impl ComboApp {
         let avail_w = ui.available_width();
         let avail_h = ui.available_height();
 
-        ui.heading(
-            RichText::new(format!("LIVE {}", self.current_ticker)).color(theme.text),
-        );
+        ui.heading(RichText::new(format!("LIVE {}", self.current_ticker)).color(theme.text));
 
         ui.separator();
 
@@ -1108,14 +809,14 @@ impl ComboApp {
 
         let mut cum = 0.0;
         for (k, s) in self.live_book.bids.iter().rev() {
-            let p = key_to_price(*k);
+            let p = key_to_price(*k, self.live_book.scale);
             cum += s;
             bid_points.push((p, cum));
         }
 
         cum = 0.0;
         for (k, s) in self.live_book.asks.iter() {
-            let p = key_to_price(*k);
+            let p = key_to_price(*k, self.live_book.scale);
             cum += s;
             ask_points.push((p, cum));
         }
@@ -1156,7 +857,7 @@ impl ComboApp {
                     ui.label("Size");
                     ui.end_row();
                     for (k, s) in self.live_book.bids.iter().rev().take(20) {
-                        let p = key_to_price(*k);
+                        let p = key_to_price(*k, self.live_book.scale);
                         ui.label(format!("{:>9.2}", p));
                         ui.label(format!("{:>8.4}", s));
                         ui.end_row();
@@ -1171,7 +872,7 @@ impl ComboApp {
                     ui.label("Size");
                     ui.end_row();
                     for (k, s) in self.live_book.asks.iter().take(20) {
-                        let p = key_to_price(*k);
+                        let p = key_to_price(*k, self.live_book.scale);
                         ui.label(format!("{:>9.2}", p));
                         ui.label(format!("{:>8.4}", s));
                         ui.end_row();
@@ -1184,9 +885,7 @@ impl ComboApp {
         let theme = self.theme();
 
         ui.group(|ui| {
-            ui.heading(
-                RichText::new("Real testnet orders").color(theme.text),
-            );
+            ui.heading(RichText::new("Real testnet orders").color(theme.text));
             ui.label(
                 RichText::new("Requires DYDX_TESTNET_MNEMONIC in your shell.")
                     .small()
@@ -1230,7 +929,10 @@ impl ComboApp {
                 );
             });
 
-            ui.checkbox(&mut self.trade_reduce_only, "Reduce-only (not yet wired)");
+            // no position feed here to check against; the chain refuses a
+            // reduce-only order that would grow the position
+            ui.checkbox(&mut self.trade_reduce_only, "Reduce-only")
+                .on_hover_text("Market orders may only shrink the open position");
 
             ui.separator();
 
@@ -1295,10 +997,12 @@ impl ComboApp {
                     side,
                     size: size_bd.clone(),
                     leverage: self.trade_leverage,
+                    reduce_only: self.trade_reduce_only,
                 });
                 self.last_order_msg = format!(
-                    "Sent MARKET {:?} {} size {}x (lev {:.1}x). Check terminal + trades CSV.",
-                    side, ticker, s_str, self.trade_leverage
+                    "Sent MARKET {:?} {} size {}x (lev {:.1}x, reduce_only={}). \
+                     Check terminal + trades CSV.",
+                    side, ticker, s_str, self.trade_leverage, self.trade_reduce_only
                 );
             }
             OrderKind::Limit => {
@@ -1321,6 +1025,7 @@ impl ComboApp {
                     size: size_bd.clone(),
                     price: price_bd.clone(),
                     leverage: self.trade_leverage,
+                    reduce_only: self.trade_reduce_only,
                 });
 
                 self.last_order_msg = format!(
@@ -1336,29 +1041,27 @@ impl ComboApp {
 
     fn ui_replay(&mut self, ui: &mut egui::Ui) {
         self.ensure_replay_ts_in_range();
+        self.recompute_replay_if_dirty();
 
-        let snapshot = self
-            .current_replay_ticker()
-            .map(|td| compute_snapshot_for(td, self.replay_ts));
-
-        if snapshot.is_none() {
+        // taken out for the frame so the views can borrow self mutably
+        let Some(snap) = self.replay_snapshot.take() else {
             ui.heading("No replay data for this ticker.");
-            ui.label("Make sure CSVs exist in ./data.");
+            ui.label(format!("Make sure CSVs exist in {}.", data_dir().display()));
             return;
-        }
-
-        let snap = snapshot.as_ref().unwrap();
+        };
 
         match self.replay_tab {
-            ReplayTab::Orderbook => self.ui_replay_orderbook(ui, snap),
+            ReplayTab::Orderbook => self.ui_replay_orderbook(ui, &snap),
             ReplayTab::Candles => {
-                let series_vec = self.replay_series(snap);
-                self.ui_candles_generic(ui, &series_vec, Some(snap), false);
+                let series_vec = self.replay_series(&snap);
+                self.ui_candles_generic(ui, &series_vec, Some(&snap), false);
             }
         }
+
+        self.replay_snapshot = Some(snap);
     }
 
-    fn ui_replay_orderbook(&self, ui: &mut egui::Ui, snap: &Snapshot) {
+    fn ui_replay_orderbook(&self, ui: &mut egui::Ui, snap: &TfSnapshot) {
         let theme = self.theme();
 
         ui.heading(
@@ -1383,14 +1086,14 @@ impl ComboApp {
 
                 let mut cum = 0.0;
                 for (k, s) in snap.bids.iter().rev() {
-                    let p = key_to_price(*k);
+                    let p = key_to_price(*k, snap.price_scale);
                     cum += s;
                     bid_points.push((p, cum));
                 }
 
                 cum = 0.0;
                 for (k, s) in snap.asks.iter() {
-                    let p = key_to_price(*k);
+                    let p = key_to_price(*k, snap.price_scale);
                     cum += s;
                     ask_points.push((p, cum));
                 }
@@ -1406,9 +1109,7 @@ impl ComboApp {
                                 .map(|(x, y)| [*x, *y])
                                 .collect::<Vec<_>>()
                                 .into();
-                            plot_ui.line(
-                                Line::new(pts).color(theme.depth_bid).name("Bids"),
-                            );
+                            plot_ui.line(Line::new(pts).color(theme.depth_bid).name("Bids"));
                         }
                         if !ask_points.is_empty() {
                             let pts: PlotPoints = ask_points
@@ -1416,9 +1117,7 @@ impl ComboApp {
                                 .map(|(x, y)| [*x, *y])
                                 .collect::<Vec<_>>()
                                 .into();
-                            plot_ui.line(
-                                Line::new(pts).color(theme.depth_ask).name("Asks"),
-                            );
+                            plot_ui.line(Line::new(pts).color(theme.depth_ask).name("Asks"));
                         }
                     });
             });
@@ -1438,7 +1137,7 @@ impl ComboApp {
                             ui.label("Size");
                             ui.end_row();
                             for (k, s) in snap.bids.iter().rev().take(20) {
-                                let p = key_to_price(*k);
+                                let p = key_to_price(*k, snap.price_scale);
                                 ui.label(format!("{:>9.2}", p));
                                 ui.label(format!("{:>8.4}", s));
                                 ui.end_row();
@@ -1453,7 +1152,7 @@ impl ComboApp {
                             ui.label("Size");
                             ui.end_row();
                             for (k, s) in snap.asks.iter().take(20) {
-                                let p = key_to_price(*k);
+                                let p = key_to_price(*k, snap.price_scale);
                                 ui.label(format!("{:>9.2}", p));
                                 ui.label(format!("{:>8.4}", s));
                                 ui.end_row();
@@ -1482,7 +1181,7 @@ impl ComboApp {
 
                                 for tr in snap.trades.iter().rev() {
                                     ui.label(format_ts(self.time_mode, tr.ts));
-                                    ui.label(&tr.side);
+                                    ui.label(tr.side.as_str());
                                     ui.label(&tr.size_str);
                                     ui.end_row();
                                 }
@@ -1498,7 +1197,7 @@ impl ComboApp {
         &mut self,
         ui: &mut egui::Ui,
         series_vec: &Vec<Candle>,
-        _snap: Option<&Snapshot>,
+        _snap: Option<&TfSnapshot>,
         is_live: bool,
     ) {
         let theme = self.theme();
@@ -1561,10 +1260,7 @@ impl ComboApp {
                 format_ts(mode, ts)
             })
             .show(ui, |plot_ui| {
-                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                    [x_min, y_min],
-                    [x_max, y_max],
-                ));
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([x_min, y_min], [x_max, y_max]));
 
                 for c in visible {
                     let left = c.t as f64;
@@ -1617,8 +1313,7 @@ impl ComboApp {
                 let factor = 1.0 + (scroll_y as f64 * 0.002);
                 let factor = factor.clamp(0.2, 5.0);
                 let center = (self.chart.y_min + self.chart.y_max) * 0.5;
-                let half_span =
-                    (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
+                let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
                 self.chart.y_min = center - half_span;
                 self.chart.y_max = center + half_span;
             }
@@ -1655,22 +1350,14 @@ impl ComboApp {
                     .max(1e-6);
                 let y_max_v = max_vol * 1.1;
 
-                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                    [x_min, 0.0],
-                    [x_max, y_max_v],
-                ));
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([x_min, 0.0], [x_max, y_max_v]));
 
                 for c in visible {
                     let left = c.t as f64;
                     let mid = left + tf * 0.5;
 
-                    let line_pts: PlotPoints =
-                        vec![[mid, 0.0], [mid, c.volume]].into();
-                    plot_ui.line(
-                        Line::new(line_pts)
-                            .color(theme.volume)
-                            .width(2.0),
-                    );
+                    let line_pts: PlotPoints = vec![[mid, 0.0], [mid, c.volume]].into();
+                    plot_ui.line(Line::new(line_pts).color(theme.volume).width(2.0));
                 }
             });
 
@@ -1687,8 +1374,7 @@ impl ComboApp {
                 let factor = 1.0 + (scroll_y as f64 * 0.002);
                 let factor = factor.clamp(0.2, 5.0);
                 let center = (self.chart.y_min + self.chart.y_max) * 0.5;
-                let half_span =
-                    (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
+                let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
                 self.chart.y_min = center - half_span;
                 self.chart.y_max = center + half_span;
             }
@@ -1723,20 +1409,24 @@ impl eframe::App for ComboApp {
                 });
         });
 
-        ctx.request_repaint_after(Duration::from_millis(50));
+        let now = Instant::now();
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.repaint.touch(now);
+        }
+        let paused = matches!(self.mode, Mode::Replay);
+        if let Some(after) = self.repaint.next_repaint(now, paused) {
+            ctx.request_repaint_after(after);
+        }
     }
 }
 
 // =============== async live feed ===============
 
-async fn run_live_feed(
-    book_tx: watch::Sender<LiveBook>,
-    mut ticker_rx: watch::Receiver<String>,
-) {
+async fn run_live_feed(book_tx: watch::Sender<LiveBook>, mut ticker_rx: watch::Receiver<String>) {
     let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to load testnet.toml: {e}");
+            error!(error = %e, "failed to load testnet.toml");
             return;
         }
     };
@@ -1745,44 +1435,70 @@ async fn run_live_feed(
 
     loop {
         let current = ticker_rx.borrow().clone();
-        eprintln!("[live] Subscribing orders feed for {current}");
+        let span = info_span!("feed", ticker = %current);
+
+        // false when the orders feed can't be opened: give up like before
+        let keep_going = async {
+            info!("subscribing orders feed");
+
+            // key scale from the market tick size; default if the lookup fails
+            let scale = match indexer
+                .markets()
+                .get_perpetual_market(&Ticker(current.clone()))
+                .await
+            {
+                Ok(m) => scale_from_tick(m.tick_size.to_f64().unwrap_or(0.0)),
+                Err(e) => {
+                    warn!(error = %e, "market meta lookup failed; using default price scale");
+                    DEFAULT_PRICE_SCALE
+                }
+            };
 
-        let mut feeds: Feeds<'_> = indexer.feed();
-        let ticker = Ticker(current.clone());
+            let mut feeds: Feeds<'_> = indexer.feed();
+            let ticker = Ticker(current.clone());
 
-        let mut feed: DxFeed<OrdersMessage> =
-            match feeds.orders(&ticker, false).await {
+            let mut feed: DxFeed<OrdersMessage> = match feeds.orders(&ticker, false).await {
                 Ok(f) => f,
                 Err(e) => {
-                    eprintln!("[live] orders feed error for {current}: {e}");
-                    return;
+                    error!(error = %e, "orders feed subscribe failed");
+                    return false;
                 }
             };
 
-        let mut book = LiveBook::default();
+            let mut book = LiveBook::with_scale(scale);
 
-        while let Some(msg) = feed.recv().await {
-            match msg {
-                OrdersMessage::Initial(init) => {
-                    book.apply_initial(
-                        init.contents.bids,
-                        init.contents.asks,
-                        &current,
-                    );
+            while let Some(msg) = feed.recv().await {
+                match msg {
+                    OrdersMessage::Initial(init) => {
+                        book.apply_initial(
+                            init.contents.bids,
+                            init.contents.asks,
+                            None,
+                            |kind, side, p, s| append_book_csv(&current, kind, side.as_str(), p, s),
+                        );
+                    }
+                    OrdersMessage::Update(upd) => {
+                        book.apply_update(
+                            upd.contents.bids,
+                            upd.contents.asks,
+                            None,
+                            |kind, side, p, s| append_book_csv(&current, kind, side.as_str(), p, s),
+                        );
+                    }
                 }
-                OrdersMessage::Update(upd) => {
-                    book.apply_update(
-                        upd.contents.bids,
-                        upd.contents.asks,
-                        &current,
-                    );
+                let _ = book_tx.send(book.clone());
+
+                if ticker_rx.has_changed().unwrap_or(false) {
+                    break;
                 }
             }
-            let _ = book_tx.send(book.clone());
+            true
+        }
+        .instrument(span)
+        .await;
 
-            if ticker_rx.has_changed().unwrap_or(false) {
-                break;
-            }
+        if !keep_going {
+            return;
         }
     }
 }
@@ -1793,7 +1509,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[trader] Failed to load testnet.toml: {e}");
+            error!(error = %e, "failed to load testnet.toml");
             return;
         }
     };
@@ -1801,7 +1517,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let raw = match env::var("DYDX_TESTNET_MNEMONIC") {
         Ok(v) => v,
         Err(_) => {
-            eprintln!("[trader] DYDX_TESTNET_MNEMONIC not set; trading disabled");
+            warn!("DYDX_TESTNET_MNEMONIC not set; trading disabled");
             return;
         }
     };
@@ -1810,7 +1526,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let wallet = match Wallet::from_mnemonic(&mnemonic) {
         Ok(w) => w,
         Err(e) => {
-            eprintln!("[trader] invalid mnemonic: {e}");
+            error!(error = %e, "invalid mnemonic");
             return;
         }
     };
@@ -1818,7 +1534,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let mut node = match NodeClient::connect(config.node).await {
         Ok(n) => n,
         Err(e) => {
-            eprintln!("[trader] node connect failed: {e}");
+            error!(error = %e, "node connect failed");
             return;
         }
     };
@@ -1826,7 +1542,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let mut account = match wallet.account(0, &mut node).await {
         Ok(a) => a,
         Err(e) => {
-            eprintln!("[trader] account sync failed: {e}");
+            error!(error = %e, "account sync failed");
             return;
         }
     };
@@ -1834,7 +1550,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let sub = match account.subaccount(0) {
         Ok(s) => s,
         Err(e) => {
-            eprintln!("[trader] subaccount derive failed: {e}");
+            error!(error = %e, "subaccount derive failed");
             return;
         }
     };
@@ -1848,64 +1564,64 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
                 side,
                 size,
                 leverage,
+                reduce_only,
             } => {
-                eprintln!(
-                    "[trader] MARKET {:?} {} size {} lev {:.1}x",
-                    side, ticker, size, leverage
-                );
-
-                let market = match indexer
-                    .markets()
-                    .get_perpetual_market(&ticker.clone().into())
-                    .await
-                {
-                    Ok(m) => m,
-                    Err(e) => {
-                        eprintln!("[trader] market meta error for {ticker}: {e}");
-                        continue;
-                    }
-                };
+                let span = info_span!("order", %ticker, ?side, %size, leverage, reduce_only);
+                async {
+                    info!("submitting market order");
+
+                    let market = match indexer
+                        .markets()
+                        .get_perpetual_market(&ticker.clone().into())
+                        .await
+                    {
+                        Ok(m) => m,
+                        Err(e) => {
+                            error!(stage = "market_meta", error = %e, "order failed");
+                            return;
+                        }
+                    };
 
-                let h = match node.latest_block_height().await {
-                    Ok(h) => h,
-                    Err(e) => {
-                        eprintln!("[trader] height error: {e}");
-                        continue;
-                    }
-                };
+                    let h = match node.latest_block_height().await {
+                        Ok(h) => h,
+                        Err(e) => {
+                            error!(stage = "height", error = %e, "order failed");
+                            return;
+                        }
+                    };
 
-                let (_id, order) = match OrderBuilder::new(market, sub.clone())
-                    .market(side, size.clone())
-                    .reduce_only(false) // TODO: wire reduce_only + leverage semantics
-                    .price(100) // placeholder slippage guard
-                    .time_in_force(TimeInForce::Unspecified)
-                    .until(h.ahead(10))
-                    .build(123456)
-                {
-                    Ok(x) => x,
-                    Err(e) => {
-                        eprintln!("[trader] build order error: {e}");
-                        continue;
-                    }
-                };
+                    let (_id, order) = match OrderBuilder::new(market, sub.clone())
+                        .market(side, size.clone())
+                        .reduce_only(reduce_only) // TODO: wire leverage semantics
+                        .price(100) // placeholder slippage guard
+                        .time_in_force(TimeInForce::Unspecified)
+                        .until(h.ahead(10))
+                        .build(123456)
+                    {
+                        Ok(x) => x,
+                        Err(e) => {
+                            error!(stage = "build", error = %e, "order failed");
+                            return;
+                        }
+                    };
 
-                match node.place_order(&mut account, order).await {
-                    Ok(tx_hash) => {
-                        eprintln!(
-                            "[trader] placed MARKET {:?} {} size {} lev {:.1}x tx={tx_hash:?}",
-                            side, ticker, size, leverage
-                        );
-                        append_trade_csv(
-                            &ticker,
-                            "gui_live_market",
-                            &format!("{:?}", side),
-                            &size.to_string(),
-                        );
-                    }
-                    Err(e) => {
-                        eprintln!("[trader] place_order error: {e}");
+                    match node.place_order(&mut account, order).await {
+                        Ok(tx_hash) => {
+                            info!(%tx_hash, "order placed");
+                            append_trade_csv(
+                                &ticker,
+                                "gui_live_market",
+                                TradeSide::from_buy(side == OrderSide::Buy).as_str(),
+                                &size.to_string(),
+                            );
+                        }
+                        Err(e) => {
+                            error!(stage = "place", error = %e, "order failed");
+                        }
                     }
                 }
+                .instrument(span)
+                .await;
             }
             TradeCmd::LimitOrder {
                 ticker,
@@ -1913,16 +1629,22 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
                 size,
                 price,
                 leverage,
+                reduce_only,
             } => {
                 // Placeholder: just log; real limit wiring would use OrderBuilder::limit(...)
-                eprintln!(
-                    "[trader] LIMIT (placeholder) {:?} {} size {} price {} lev {:.1}x",
-                    side, ticker, size, price, leverage
+                info!(
+                    %ticker,
+                    ?side,
+                    %size,
+                    %price,
+                    leverage,
+                    reduce_only,
+                    "limit order (placeholder, not sent)"
                 );
                 append_trade_csv(
                     &ticker,
                     "gui_live_limit_placeholder",
-                    &format!("{:?}", side),
+                    TradeSide::from_buy(side == OrderSide::Buy).as_str(),
                     &format!("size={}@{}", size, price),
                 );
             }
@@ -1934,11 +1656,13 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
 
 fn main() {
     init_crypto_provider();
+    let args: Vec<String> = env::args().skip(1).collect();
+    init_tracing(log_json_from_args(&args));
 
     let (book_tx, book_rx) = watch::channel(LiveBook::default());
 
-    // preload replay data from ./data
-    let base_dir = "data";
+    // preload replay data from --data-dir (default ./data)
+    let base_dir = data_dir();
     let tickers = vec!["ETH-USD", "BTC-USD", "SOL-USD"];
     let mut replay_data = HashMap::new();
     for tk in tickers {
@@ -1952,8 +1676,7 @@ fn main() {
         .build()
         .expect("tokio runtime");
 
-    let (ticker_tx, ticker_rx) =
-        watch::channel::<String>("ETH-USD".to_string());
+    let (ticker_tx, ticker_rx) = watch::channel::<String>("ETH-USD".to_string());
 
     let (trade_tx, trade_rx) = mpsc::channel::<TradeCmd>(32);
 
@@ -1961,7 +1684,7 @@ fn main() {
     rt.spawn(run_live_feed(book_tx, ticker_rx));
 
     // spawn trader
-    rt.spawn(run_trader(trade_rx));
+    rt.spawn(run_trader(trade_rx).instrument(info_span!("trader")));
 
     let options = eframe::NativeOptions::default();
     let app = ComboApp::new(book_rx, replay_data, ticker_tx.clone(), trade_tx);
@@ -1971,7 +1694,7 @@ fn main() {
         options,
         Box::new(|_cc| Box::new(app)),
     ) {
-        eprintln!("eframe error: {e}");
+        error!(error = %e, "eframe exited with an error");
     }
 
     drop(rt);
@@ -2,7 +2,7 @@
 //
 // GUI for dYdX v4 using CSVs written by the background daemon:
 //
-//  - Daemon writes under ./data:
+//  - Daemon writes under ./data (or --data-dir <path>, same flag here):
 //      data/orderbook_{TICKER}.csv
 //      data/trades_{TICKER}.csv
 //
@@ -16,6 +16,8 @@
 //      * Rhai script engine for a simple “bot”, fed orderbook metrics.
 //      * Trading panel: market/“limit” (price guard), size, leverage,
 //        bot suggestions.
+//      * Repaints slow down once reloads and input go quiet
+//        (--repaint-ms / --idle-repaint-ms, 0 = never slow down).
 //
 //  Requirements:
 //    - Data daemon already running and writing CSVs regularly.
@@ -24,29 +26,28 @@
 //
 //  Build:
 //    cargo run --release -p ladder_app --bin full_gui_x14
+//    cargo run --release -p ladder_app --bin full_gui_x14 -- --data-dir /path/to/archive
 //
 
-mod candle_agg;
-
-use candle_agg::{Candle, CandleAgg};
-
-use chrono::{Local, TimeZone};
-
 use eframe::egui::{self, Color32};
-use egui_plot::{Line, Plot, PlotBounds, PlotPoints};
+use egui_plot::{Line, Plot, PlotBounds, PlotPoints, PlotUi, Polygon};
 
 use std::cmp::{max, min};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bigdecimal::BigDecimal;
+use notify_rust::Notification;
 use rhai::{Engine, Scope};
 
 use tokio::sync::mpsc;
+use tracing::{error, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
 
 // dYdX client pieces
 use dydx_client::config::ClientConfig;
@@ -54,55 +55,20 @@ use dydx_client::indexer::IndexerClient;
 use dydx_client::node::{NodeClient, OrderBuilder, OrderSide, Wallet};
 use dydx_proto::dydxprotocol::clob::order::TimeInForce;
 
-// ---------- basic helpers ----------
-
-fn now_unix() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or(Duration::from_secs(0))
-        .as_secs()
-}
-
-// We key prices in BTreeMap as scaled integers for nice ordering.
-type PriceKey = i64;
-
-fn price_to_key(price: f64) -> PriceKey {
-    (price * 10_000.0).round() as PriceKey
-}
-
-fn key_to_price(key: PriceKey) -> f64 {
-    key as f64 / 10_000.0
-}
-
-// ---------- time display mode ----------
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TimeDisplayMode {
-    Unix,
-    Local,
-}
-
-impl TimeDisplayMode {
-    fn label(self) -> &'static str {
-        match self {
-            TimeDisplayMode::Unix => "Unix",
-            TimeDisplayMode::Local => "Local",
-        }
-    }
-}
-
-fn format_ts(mode: TimeDisplayMode, ts: u64) -> String {
-    match mode {
-        TimeDisplayMode::Unix => format!("{ts}"),
-        TimeDisplayMode::Local => {
-            let dt = Local
-                .timestamp_opt(ts as i64, 0)
-                .single()
-                .unwrap_or_else(|| Local.timestamp_opt(0, 0).single().unwrap());
-            dt.format("%Y-%m-%d %H:%M:%S").to_string()
-        }
-    }
-}
+use ladder_app::book::{key_to_price, DEFAULT_PRICE_SCALE};
+use ladder_app::bot::{
+    detect_walls, feed_scope, liquidity_within_bps, read_bot_from_scope, BotInputs, BotOutput,
+    DEFAULT_LIQUIDITY_BPS, DEFAULT_WALL_SIGMA, WALL_WINDOW,
+};
+use ladder_app::candle_render::{draw_candles, CandleBody, CandleColorBasis, CandleRenderStyle};
+use ladder_app::csv_io::{
+    data_dir, data_file, load_book_csv, load_trades_csv, BookCsvEvent, TradeCsvEvent, TradeSide,
+};
+use ladder_app::repaint::RepaintThrottle;
+use ladder_app::replay::ReplayEngine;
+use ladder_app::snapshot::{compute_snapshot_for, TfSnapshot};
+use ladder_app::snapshot_worker;
+use ladder_app::time_fmt::{format_ts, now_unix, TimeDisplayMode};
 
 // ---------- chart settings ----------
 
@@ -116,6 +82,10 @@ struct ChartSettings {
     x_zoom: f64,
     x_pan_secs: f64,
     tf_secs: u64,
+    candle_body: CandleBody,
+    candle_color_basis: CandleColorBasis,
+    // shaded bid/ask areas under the depth curves
+    depth_filled: bool,
 }
 
 impl Default for ChartSettings {
@@ -128,6 +98,9 @@ impl Default for ChartSettings {
             x_zoom: 1.0,
             x_pan_secs: 0.0,
             tf_secs: 60, // default 1m
+            candle_body: CandleBody::Outline,
+            candle_color_basis: CandleColorBasis::BodyOpenClose,
+            depth_filled: false,
         }
     }
 }
@@ -145,13 +118,13 @@ const GRID_COLS: usize = 3;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum RowSpanMode {
-    Split3,       // 3 equal columns
-    Left2Right1,  // col0 spans 2/3, col1 is 1/3, col2 empty
-    Left1Right2,  // col0 1/3, col1 2/3, col2 empty
-    Full,         // one full-width cell
+    Split3,      // 3 equal columns
+    Left2Right1, // col0 spans 2/3, col1 is 1/3, col2 empty
+    Left1Right2, // col0 1/3, col1 2/3, col2 empty
+    Full,        // one full-width cell
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 struct RowConfig {
     height_factor: f32, // 0.5..3.0
     span_mode: RowSpanMode,
@@ -168,170 +141,440 @@ impl Default for RowConfig {
     }
 }
 
-// ---------- CSV + replay structures ----------
+impl RowSpanMode {
+    fn label(&self) -> &'static str {
+        match self {
+            RowSpanMode::Split3 => "3 cols",
+            RowSpanMode::Left2Right1 => "2+1",
+            RowSpanMode::Left1Right2 => "1+2",
+            RowSpanMode::Full => "full",
+        }
+    }
 
-#[derive(Clone, Debug)]
-struct BookCsvEvent {
-    ts: u64,
-    ticker: String,
-    kind: String,
-    side: String,
-    price: f64,
-    size: f64,
+    fn from_label(s: &str) -> Option<Self> {
+        match s {
+            "3 cols" => Some(RowSpanMode::Split3),
+            "2+1" => Some(RowSpanMode::Left2Right1),
+            "1+2" => Some(RowSpanMode::Left1Right2),
+            "full" => Some(RowSpanMode::Full),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
-struct TradeCsvEvent {
-    ts: u64,
-    ticker: String,
-    source: String,
-    side: String,
-    size_str: String,
+// ---------- layout presets ----------
+
+#[derive(Clone)]
+struct LayoutPreset {
+    name: String,
+    rows: [RowConfig; GRID_ROWS],
 }
 
-#[derive(Clone, Debug, Default)]
-struct TickerData {
-    ticker: String,
-    book_events: Vec<BookCsvEvent>,
-    trade_events: Vec<TradeCsvEvent>,
-    min_ts: u64,
-    max_ts: u64,
+// (span, height) for the first rows; the rest stay at RowConfig::default()
+fn rows_from(spec: &[(RowSpanMode, f32)]) -> [RowConfig; GRID_ROWS] {
+    let mut rows = [RowConfig::default(); GRID_ROWS];
+    for (cfg, (span_mode, height_factor)) in rows.iter_mut().zip(spec) {
+        cfg.span_mode = *span_mode;
+        cfg.height_factor = *height_factor;
+    }
+    rows
 }
 
-#[derive(Clone, Debug, Default)]
-struct Snapshot {
-    bids: BTreeMap<PriceKey, f64>,
-    asks: BTreeMap<PriceKey, f64>,
-    candles: Vec<Candle>,
-    trades: Vec<TradeCsvEvent>,
-    last_mid: f64,
-    last_vol: f64,
+// rows: 0 chart | 1 depth, ladders, trading | 2 script, bot, trades | 3 alerts
+fn builtin_layout_presets() -> Vec<LayoutPreset> {
+    use RowSpanMode::*;
+
+    let mut bot_dev = rows_from(&[
+        (Full, 1.4),
+        (Split3, 1.0),
+        (Left2Right1, 2.4),
+        (Split3, 1.2),
+    ]);
+    bot_dev[2].big_ratio = 0.7;
+
+    vec![
+        LayoutPreset {
+            name: "Default".to_string(),
+            rows: rows_from(&[(Full, 2.0), (Split3, 1.4), (Split3, 1.4)]),
+        },
+        LayoutPreset {
+            name: "Chart focus".to_string(),
+            rows: rows_from(&[
+                (Full, 3.0),
+                (Split3, 1.0),
+                (Split3, 0.8),
+                (Split3, 0.5),
+                (Split3, 0.5),
+                (Split3, 0.5),
+            ]),
+        },
+        LayoutPreset {
+            name: "Trading focus".to_string(),
+            rows: rows_from(&[
+                (Full, 1.4),
+                (Split3, 2.2),
+                (Split3, 1.2),
+                (Split3, 0.5),
+                (Split3, 0.5),
+                (Split3, 0.5),
+            ]),
+        },
+        LayoutPreset {
+            name: "Bot dev".to_string(),
+            rows: bot_dev,
+        },
+    ]
 }
 
-// ---------- CSV I/O ----------
+// layout_presets.csv in base_dir: name,row,span,height,big_ratio (one line per row)
+fn load_layout_presets(path: &Path) -> Vec<LayoutPreset> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+    let mut out: Vec<LayoutPreset> = Vec::new();
 
-fn append_trade_csv(ticker: &str, source: &str, side: &str, size_str: &str) {
-    let ts = now_unix();
-    let dir = Path::new("data");
-    let _ = std::fs::create_dir_all(dir);
-    let path = dir.join(format!("trades_{ticker}.csv"));
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => continue,
+        };
+        if i == 0 && line.starts_with("name,") {
+            continue;
+        }
+        let parts: Vec<&str> = line.split(',').collect();
+        if parts.len() < 5 {
+            continue;
+        }
+        let (row, span_mode, height_factor, big_ratio) = match (
+            parts[1].parse::<usize>(),
+            RowSpanMode::from_label(parts[2]),
+            parts[3].parse::<f32>(),
+            parts[4].parse::<f32>(),
+        ) {
+            (Ok(r), Some(m), Ok(h), Ok(b)) if r < GRID_ROWS => (r, m, h, b),
+            _ => continue,
+        };
 
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-    {
-        let _ = writeln!(f, "{ts},{ticker},{source},{side},{size_str}");
+        let idx = match out.iter().position(|p| p.name == parts[0]) {
+            Some(idx) => idx,
+            None => {
+                out.push(LayoutPreset {
+                    name: parts[0].to_string(),
+                    rows: [RowConfig::default(); GRID_ROWS],
+                });
+                out.len() - 1
+            }
+        };
+        out[idx].rows[row] = RowConfig {
+            height_factor: height_factor.clamp(0.5, 3.0),
+            span_mode,
+            big_ratio: big_ratio.clamp(0.3, 0.8),
+        };
     }
+
+    out
 }
 
-fn load_book_csv(path: &Path, ticker: &str) -> Vec<BookCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
-    }
-    let f = match File::open(path) {
+fn save_layout_presets(path: &Path, presets: &[LayoutPreset]) {
+    let mut f = match File::create(path) {
         Ok(f) => f,
-        Err(_) => return Vec::new(),
+        Err(e) => {
+            error!(path = %path.display(), error = %e, "layout presets: cannot write");
+            return;
+        }
     };
-    let reader = BufReader::new(f);
-    let mut out = Vec::new();
+    let _ = writeln!(f, "name,row,span,height,big_ratio");
+    for p in presets {
+        for (row, cfg) in p.rows.iter().enumerate() {
+            let _ = writeln!(
+                f,
+                "{},{},{},{},{}",
+                p.name,
+                row,
+                cfg.span_mode.label(),
+                cfg.height_factor,
+                cfg.big_ratio
+            );
+        }
+    }
+}
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 6 {
-                continue;
-            }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            if tk != ticker {
-                continue;
-            }
-            let kind = parts[2].to_string();
-            let side = parts[3].to_string();
-            let price = match parts[4].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let size = match parts[5].parse::<f64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            out.push(BookCsvEvent {
-                ts,
-                ticker: tk,
-                kind,
-                side,
-                price,
-                size,
-            });
+// ---------- alerts ----------
+
+// metrics an alert can watch; each one is a variable in the bot scope
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum AlertMetric {
+    Mid,
+    BestBid,
+    BestAsk,
+    Spread,
+    SpreadBps,
+    Rsi14,
+}
+
+impl AlertMetric {
+    fn all() -> &'static [AlertMetric] {
+        &[
+            AlertMetric::Mid,
+            AlertMetric::BestBid,
+            AlertMetric::BestAsk,
+            AlertMetric::Spread,
+            AlertMetric::SpreadBps,
+            AlertMetric::Rsi14,
+        ]
+    }
+
+    fn scope_key(self) -> &'static str {
+        match self {
+            AlertMetric::Mid => "mid",
+            AlertMetric::BestBid => "best_bid",
+            AlertMetric::BestAsk => "best_ask",
+            AlertMetric::Spread => "spread",
+            AlertMetric::SpreadBps => "spread_bps",
+            AlertMetric::Rsi14 => "rsi_14",
         }
     }
 
-    out.sort_by_key(|e| e.ts);
-    out
+    fn from_scope_key(s: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|m| m.scope_key() == s)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum AlertOp {
+    Above,
+    Below,
+}
+
+impl AlertOp {
+    fn label(self) -> &'static str {
+        match self {
+            AlertOp::Above => ">",
+            AlertOp::Below => "<",
+        }
+    }
+
+    fn from_label(s: &str) -> Option<Self> {
+        match s {
+            ">" => Some(AlertOp::Above),
+            "<" => Some(AlertOp::Below),
+            _ => None,
+        }
+    }
 }
 
-fn load_trades_csv(path: &Path, ticker: &str) -> Vec<TradeCsvEvent> {
-    if !path.exists() {
-        return Vec::new();
+#[derive(Clone, Copy, Debug)]
+struct AlertCondition {
+    metric: AlertMetric,
+    op: AlertOp,
+    threshold: f64,
+}
+
+impl AlertCondition {
+    fn holds(&self, value: f64) -> bool {
+        match self.op {
+            AlertOp::Above => value > self.threshold,
+            AlertOp::Below => value < self.threshold,
+        }
     }
-    let f = match File::open(path) {
+
+    fn describe(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.metric.scope_key(),
+            self.op.label(),
+            self.threshold
+        )
+    }
+}
+
+// min gap between two notifications/beeps of the same alert
+const ALERT_DEBOUNCE: Duration = Duration::from_secs(30);
+
+// edge-triggered: fires when the condition starts holding, re-arms when it clears
+#[derive(Clone, Debug)]
+struct Alert {
+    condition: AlertCondition,
+    triggered: bool,
+    bell: bool,
+    desktop: bool,
+    last_notified: Option<Instant>,
+}
+
+fn send_desktop_notification(summary: &str, body: &str) {
+    if let Err(e) = Notification::new().summary(summary).body(body).show() {
+        warn!(error = %e, "alerts: desktop notification failed");
+    }
+}
+
+// alerts.csv in base_dir: metric,op,threshold,bell[,desktop]
+fn load_alerts(path: &Path) -> Vec<Alert> {
+    let file = match File::open(path) {
         Ok(f) => f,
         Err(_) => return Vec::new(),
     };
-    let reader = BufReader::new(f);
+    let reader = BufReader::new(file);
     let mut out = Vec::new();
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() < 5 {
-                continue;
-            }
-            let ts = match parts[0].parse::<u64>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            let tk = parts[1].trim_matches('"').to_string();
-            if tk != ticker {
-                continue;
-            }
-            let source = parts[2].to_string();
-            let side = parts[3].to_string();
-            let size_str = parts[4].to_string();
-
-            out.push(TradeCsvEvent {
-                ts,
-                ticker: tk,
-                source,
-                side,
-                size_str,
-            });
+    for (i, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => continue,
+        };
+        if i == 0 && line.starts_with("metric,") {
+            continue;
         }
+        let parts: Vec<&str> = line.split(',').collect();
+        if parts.len() < 4 {
+            continue;
+        }
+        let (metric, op) = match (
+            AlertMetric::from_scope_key(parts[0]),
+            AlertOp::from_label(parts[1]),
+        ) {
+            (Some(m), Some(o)) => (m, o),
+            _ => continue,
+        };
+        let threshold = match parts[2].parse::<f64>() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        out.push(Alert {
+            condition: AlertCondition {
+                metric,
+                op,
+                threshold,
+            },
+            triggered: false,
+            bell: parts[3] == "1",
+            desktop: parts.get(4).map(|v| *v == "1").unwrap_or(false),
+            last_notified: None,
+        });
     }
 
-    out.sort_by_key(|t| t.ts);
     out
 }
 
+fn save_alerts(path: &Path, alerts: &[Alert]) {
+    let mut f = match File::create(path) {
+        Ok(f) => f,
+        Err(e) => {
+            error!(path = %path.display(), error = %e, "alerts: cannot write");
+            return;
+        }
+    };
+    let _ = writeln!(f, "metric,op,threshold,bell,desktop");
+    for a in alerts {
+        let _ = writeln!(
+            f,
+            "{},{},{},{},{}",
+            a.condition.metric.scope_key(),
+            a.condition.op.label(),
+            a.condition.threshold,
+            if a.bell { 1 } else { 0 },
+            if a.desktop { 1 } else { 0 }
+        );
+    }
+}
+
+// ---------- CSV + replay structures ----------
+
+// `source` written for market trades; everything else
+// (gui_manual / bot_auto / trader) is one of my own orders
+const MARKET_SOURCE: &str = "market";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TradeSourceFilter {
+    All,
+    Mine,
+    Market,
+}
+
+impl TradeSourceFilter {
+    fn label(self) -> &'static str {
+        match self {
+            TradeSourceFilter::All => "All trades",
+            TradeSourceFilter::Mine => "My fills",
+            TradeSourceFilter::Market => "Market trades",
+        }
+    }
+
+    fn all() -> [TradeSourceFilter; 3] {
+        [
+            TradeSourceFilter::All,
+            TradeSourceFilter::Mine,
+            TradeSourceFilter::Market,
+        ]
+    }
+
+    fn matches(self, source: &str) -> bool {
+        match self {
+            TradeSourceFilter::All => true,
+            TradeSourceFilter::Mine => source != MARKET_SOURCE,
+            TradeSourceFilter::Market => source == MARKET_SOURCE,
+        }
+    }
+}
+
+fn source_color(source: &str) -> Color32 {
+    match source {
+        MARKET_SOURCE => Color32::GRAY,
+        "gui_manual" => Color32::LIGHT_BLUE,
+        "gui_live" => Color32::from_rgb(0, 200, 200),
+        "bot_auto" => Color32::from_rgb(255, 165, 0),
+        "trader" => Color32::from_rgb(200, 120, 255),
+        _ => Color32::WHITE,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct TickerData {
+    ticker: String,
+    book_events: Vec<BookCsvEvent>,
+    trade_events: Vec<TradeCsvEvent>,
+    min_ts: u64,
+    max_ts: u64,
+    // CSVs carry no tick size, so this is DEFAULT_PRICE_SCALE for now
+    price_scale: f64,
+}
+
+// ---------- ladder walls ----------
+
+const WALL_BG: Color32 = Color32::from_rgba_premultiplied(90, 70, 0, 140);
+
+fn ladder_row(ui: &mut egui::Ui, price: f64, size: f64, is_wall: bool) {
+    let price = egui::RichText::new(format!("{:>9.2}", price));
+    let size = egui::RichText::new(format!("{:>8.4}", size));
+    if is_wall {
+        ui.label(price.strong().background_color(WALL_BG));
+        ui.label(size.strong().background_color(WALL_BG));
+    } else {
+        ui.label(price);
+        ui.label(size);
+    }
+    ui.end_row();
+}
+
+// ---------- CSV I/O ----------
+
+fn append_trade_csv(ticker: &str, source: &str, side: &str, size_str: &str) {
+    let ts = now_unix();
+    let dir = data_dir();
+    let _ = std::fs::create_dir_all(dir);
+    let path = dir.join(format!("trades_{ticker}.csv"));
+
+    if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(f, "{ts},{ticker},{source},{side},{size_str}");
+    }
+}
+
 fn load_ticker_data(base_dir: &Path, ticker: &str) -> Option<TickerData> {
-    let ob_path = base_dir.join(format!("orderbook_{ticker}.csv"));
-    let tr_path = base_dir.join(format!("trades_{ticker}.csv"));
+    let ob_path = data_file(base_dir, &format!("orderbook_{ticker}"));
+    let tr_path = data_file(base_dir, &format!("trades_{ticker}"));
 
-    let book_events = load_book_csv(&ob_path, ticker);
-    let trade_events = load_trades_csv(&tr_path, ticker);
+    let (book_events, _) = load_book_csv(&ob_path, ticker);
+    let (trade_events, _) = load_trades_csv(&tr_path, ticker);
 
     if book_events.is_empty() && trade_events.is_empty() {
         return None;
@@ -359,69 +602,61 @@ fn load_ticker_data(base_dir: &Path, ticker: &str) -> Option<TickerData> {
         trade_events,
         min_ts,
         max_ts,
+        price_scale: DEFAULT_PRICE_SCALE,
     })
 }
 
-// reconstruct snapshot at target_ts for given TF
-fn compute_snapshot_for(data: &TickerData, target_ts: u64, tf_secs: u64) -> Snapshot {
-    let mut bids: BTreeMap<PriceKey, f64> = BTreeMap::new();
-    let mut asks: BTreeMap<PriceKey, f64> = BTreeMap::new();
-
-    let mut agg = CandleAgg::new(tf_secs);
-
-    for e in &data.book_events {
-        if e.ts > target_ts {
-            break;
-        }
-
-        let map = if e.side.to_lowercase() == "bid" {
-            &mut bids
-        } else {
-            &mut asks
-        };
+// reconstruct snapshot at target_ts with candles for the given TF only
+fn replay_snapshot(data: &TickerData, target_ts: u64, tf_secs: u64) -> TfSnapshot {
+    let engine = ReplayEngine::over(&data.book_events, &data.trade_events, data.price_scale);
+    compute_snapshot_for(engine, target_ts, &[tf_secs], tf_secs)
+}
 
-        let key = price_to_key(e.price);
+// ---------- snapshot worker ----------
 
-        if e.size == 0.0 {
-            map.remove(&key);
-        } else {
-            map.insert(key, e.size);
-        }
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SnapKey {
+    ticker: String,
+    ts: u64,
+    tf: u64,
+    // bumped on every CSV reload so fresh data is recomputed
+    data_gen: u64,
+}
 
-        if let (Some((bp, _)), Some((ap, _))) = (bids.iter().next_back(), asks.iter().next()) {
-            let mid = (key_to_price(*bp) + key_to_price(*ap)) * 0.5;
-            let vol = e.size.abs().max(0.0);
-            agg.update(e.ts, mid, vol);
+type SnapshotWorker = snapshot_worker::SnapshotWorker<SnapKey, TickerData, TfSnapshot>;
+
+// ---------- depth plot ----------
+
+// Cumulative depth curve; `filled` also shades down to zero size, one
+// trapezoid per level step since plot polygons must be convex.
+fn plot_depth_side(
+    plot_ui: &mut PlotUi,
+    points: &[(f64, f64)],
+    name: &str,
+    color: Color32,
+    filled: bool,
+) {
+    if points.is_empty() {
+        return;
+    }
+    if filled {
+        let shade = Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), 50);
+        for w in points.windows(2) {
+            let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+            let area: PlotPoints = vec![[x0, 0.0], [x0, y0], [x1, y1], [x1, 0.0]].into();
+            plot_ui.polygon(
+                Polygon::new(area)
+                    .fill_color(shade)
+                    .stroke(egui::Stroke::NONE),
+            );
         }
     }
-
-    let mut trades: Vec<TradeCsvEvent> = data
-        .trade_events
+    let pts: PlotPoints = points
         .iter()
-        .filter(|t| t.ts <= target_ts)
-        .cloned()
-        .collect();
-    trades.sort_by_key(|t| t.ts);
-    if trades.len() > 200 {
-        let start = trades.len() - 200;
-        trades = trades[start..].to_vec();
-    }
-
-    let series = agg.series().to_vec();
-    let (last_mid, last_vol) = if let Some(c) = series.last() {
-        (c.close, c.volume)
-    } else {
-        (0.0, 0.0)
-    };
-
-    Snapshot {
-        bids,
-        asks,
-        candles: series,
-        trades,
-        last_mid,
-        last_vol,
-    }
+        .map(|(x, y)| [*x, *y])
+        .collect::<Vec<_>>()
+        .into();
+    plot_ui.line(Line::new(pts).color(color).name(name));
 }
 
 // ---------- crypto provider ----------
@@ -430,6 +665,28 @@ fn init_crypto_provider() {
     let _ = rustls::crypto::ring::default_provider().install_default();
 }
 
+// ---------- logging ----------
+
+// fmt subscriber on stderr. RUST_LOG filters as usual (default `info`, e.g.
+// `RUST_LOG=full_gui_x15=debug,dydx=debug`); `json` emits one object per line.
+fn init_tracing(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        fmt.json().init();
+    } else {
+        fmt.init();
+    }
+}
+
+// `--log-json` or LOG_FORMAT=json
+fn log_json_from_args(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--log-json")
+        || std::env::var("LOG_FORMAT").is_ok_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
 // ---------- trading ----------
 
 #[derive(Clone, Debug)]
@@ -453,7 +710,9 @@ struct TradeCmd {
 struct ComboApp {
     // data & mode
     base_dir: PathBuf,
-    ticker_data: HashMap<String, TickerData>,
+    ticker_data: HashMap<String, Arc<TickerData>>,
+    data_gen: u64,
+    snap_worker: SnapshotWorker,
     tickers: Vec<String>,
     current_ticker: String,
     mode: Mode,
@@ -462,6 +721,8 @@ struct ComboApp {
     // time & reload
     live_ts: u64,
     replay_ts: u64,
+    // replay pinned to max_ts, so each reload advances it: delayed live from disk
+    follow_tail: bool,
     last_reload_ts: u64,
     reload_secs: f64,
 
@@ -470,10 +731,15 @@ struct ComboApp {
     show_depth: bool,
     show_ladders: bool,
     show_trades: bool,
+    trade_source_filter: TradeSourceFilter,
     show_volume: bool,
 
     // layout: 3x6
     row_cfgs: [RowConfig; GRID_ROWS],
+    builtin_presets: Vec<LayoutPreset>,
+    // saved from the UI into layout_presets.csv
+    user_presets: Vec<LayoutPreset>,
+    preset_name_input: String,
 
     // script engine
     engine: Engine,
@@ -481,7 +747,15 @@ struct ComboApp {
     script_text: String,
     script_last_error: Option<String>,
     script_auto_run: bool,
+    // auto runs wait at least this long after the last run; 0 = run now
+    script_min_interval_secs: f64,
     script_last_run_ts: u64,
+    // repaint cadence; slows down once reloads and input go quiet
+    repaint: RepaintThrottle,
+    // band for bid/ask_liquidity_bps, in basis points of mid
+    liquidity_bps: f64,
+    // ladder levels this many stddevs above their neighbours count as walls
+    wall_sigma: f64,
 
     // bot results
     bot_signal: String,
@@ -498,6 +772,15 @@ struct ComboApp {
     trade_limit_price: f64,
     last_order_msg: String,
     trade_tx: mpsc::Sender<TradeCmd>,
+
+    // alerts
+    alerts: Vec<Alert>,
+    alert_new: AlertCondition,
+    alert_new_bell: bool,
+    alert_new_desktop: bool,
+    alerts_muted: bool,
+    alert_flash_until: Option<Instant>,
+    alert_last_msg: String,
 }
 
 impl ComboApp {
@@ -517,18 +800,10 @@ impl ComboApp {
             .map(|td| (td.max_ts, td.max_ts))
             .unwrap_or((now_unix(), now_unix()));
 
-        let mut row_cfgs: [RowConfig; GRID_ROWS] = [RowConfig::default(); GRID_ROWS];
-        // Example defaults:
-        // row 0: big full-width chart
-        row_cfgs[0].span_mode = RowSpanMode::Full;
-        row_cfgs[0].height_factor = 2.0;
-        // row 1: depth / ladders / trading
-        row_cfgs[1].span_mode = RowSpanMode::Split3;
-        row_cfgs[1].height_factor = 1.4;
-        // row 2: script + bot status + recent trades
-        row_cfgs[2].span_mode = RowSpanMode::Split3;
-        row_cfgs[2].height_factor = 1.4;
-        // others: leave default
+        // "Default" preset: big chart, then depth / ladders / trading, then
+        // script + bot status + recent trades; the rest at RowConfig::default()
+        let builtin_presets = builtin_layout_presets();
+        let row_cfgs = builtin_presets[0].rows;
 
         let mut engine = Engine::new();
         engine.set_max_expr_depths(64, 64);
@@ -540,8 +815,12 @@ impl ComboApp {
 // Inputs (set by Rust):
 //   ticker:            String
 //   mode:              "live" | "replay"
-//   best_bid, best_ask, mid, spread: f64
-//   bid_liquidity_near, ask_liquidity_near: f64
+//   best_bid, best_ask, mid, spread, spread_bps: f64
+//   rsi_14: f64 (RSI of the selected TF candles, 50 if not enough data)
+//   bid_liquidity_near, ask_liquidity_near: f64 (top 10 levels)
+//   bid_liquidity_bps, ask_liquidity_bps: f64 (size within liquidity_bps of mid)
+//   liquidity_bps: f64
+//   nearest_bid_wall, nearest_ask_wall: f64 (price of the closest outsized level, 0 if none)
 //   tf_secs, history_candles: i64
 //
 // Outputs (you MUST set these):
@@ -568,15 +847,26 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
     bot_size = 0.01;
     bot_comment = "Ask bubble detected";
 }
-"#.to_string();
+"#
+        .to_string();
 
         scope.set_value("bot_signal", "none".to_string());
         scope.set_value("bot_size", 0.0_f64);
         scope.set_value("bot_comment", "".to_string());
 
+        let alerts = load_alerts(&base_dir.join("alerts.csv"));
+        let user_presets = load_layout_presets(&base_dir.join("layout_presets.csv"));
+
+        let ticker_data = ticker_data
+            .into_iter()
+            .map(|(tk, td)| (tk, Arc::new(td)))
+            .collect();
+
         Self {
             base_dir,
             ticker_data,
+            data_gen: 0,
+            snap_worker: SnapshotWorker::spawn(|data, key| replay_snapshot(data, key.ts, key.tf)),
             tickers,
             current_ticker,
             mode: Mode::Live,
@@ -584,6 +874,7 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
 
             live_ts,
             replay_ts,
+            follow_tail: false,
             last_reload_ts: now_unix(),
             reload_secs: 5.0,
 
@@ -591,16 +882,25 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             show_depth: true,
             show_ladders: true,
             show_trades: true,
+            trade_source_filter: TradeSourceFilter::All,
             show_volume: true,
 
             row_cfgs,
+            builtin_presets,
+            user_presets,
+            preset_name_input: String::new(),
 
             engine,
             scope,
             script_text: default_script,
             script_last_error: None,
             script_auto_run: true,
+            liquidity_bps: DEFAULT_LIQUIDITY_BPS,
+            wall_sigma: DEFAULT_WALL_SIGMA,
+            script_min_interval_secs: 1.0,
             script_last_run_ts: 0,
+            repaint: RepaintThrottle::new(Duration::from_millis(50))
+                .with_args(std::env::args().skip(1)),
 
             bot_signal: "none".to_string(),
             bot_size: 0.0,
@@ -615,6 +915,18 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             trade_limit_price: 0.0,
             last_order_msg: String::new(),
             trade_tx,
+
+            alerts,
+            alert_new: AlertCondition {
+                metric: AlertMetric::Mid,
+                op: AlertOp::Above,
+                threshold: 0.0,
+            },
+            alert_new_bell: false,
+            alert_new_desktop: false,
+            alerts_muted: false,
+            alert_flash_until: None,
+            alert_last_msg: String::new(),
         }
     }
 
@@ -627,10 +939,12 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
     fn reload_current_ticker(&mut self) {
         if let Some(td) = load_ticker_data(&self.base_dir, &self.current_ticker) {
             self.live_ts = td.max_ts;
-            if self.replay_ts < td.min_ts || self.replay_ts > td.max_ts {
+            if self.follow_tail || self.replay_ts < td.min_ts || self.replay_ts > td.max_ts {
                 self.replay_ts = td.max_ts;
             }
-            self.ticker_data.insert(self.current_ticker.clone(), td);
+            self.ticker_data
+                .insert(self.current_ticker.clone(), Arc::new(td));
+            self.data_gen += 1;
         }
     }
 
@@ -648,129 +962,91 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         }
     }
 
-    fn current_snap_live(&self) -> Option<Snapshot> {
-        let tf = self.chart.tf_secs;
-        let td = self.ticker_data.get(&self.current_ticker)?;
-        let live_ts = td.max_ts;
-        Some(compute_snapshot_for(td, live_ts, tf))
-    }
-
-    fn current_snap_replay(&self) -> Option<Snapshot> {
-        let tf = self.chart.tf_secs;
+    fn snap_target(&self) -> Option<(SnapKey, Arc<TickerData>)> {
         let td = self.ticker_data.get(&self.current_ticker)?;
-        Some(compute_snapshot_for(td, self.replay_ts, tf))
+        let ts = match self.mode {
+            Mode::Live => td.max_ts,
+            Mode::Replay => self.replay_ts,
+        };
+        let key = SnapKey {
+            ticker: self.current_ticker.clone(),
+            ts,
+            tf: self.chart.tf_secs,
+            data_gen: self.data_gen,
+        };
+        Some((key, Arc::clone(td)))
     }
 
-    fn current_snap(&self) -> Option<Snapshot> {
-        match self.mode {
-            Mode::Live => self.current_snap_live(),
-            Mode::Replay => self.current_snap_replay(),
+    // Post the wanted snapshot to the worker and take the newest finished
+    // one out of it; give it back with `self.snap_worker.latest` after use.
+    // A snapshot of another ticker is never returned.
+    fn current_snap(&mut self) -> Option<(SnapKey, TfSnapshot)> {
+        self.snap_worker.poll();
+        if let Some((key, td)) = self.snap_target() {
+            self.snap_worker.request(key, &td);
+        }
+        match self.snap_worker.latest.take() {
+            Some((k, s)) if k.ticker == self.current_ticker => Some((k, s)),
+            other => {
+                self.snap_worker.latest = other;
+                None
+            }
         }
     }
 
     // ---------- bot + script ----------
 
-    fn feed_scope_from_snapshot(&mut self, snap: &Snapshot) {
-        let best_bid = snap
-            .bids
+    fn feed_scope_from_snapshot(&mut self, snap: &TfSnapshot) {
+        let closes: Vec<f64> = snap
+            .candles(self.chart.tf_secs)
             .iter()
-            .next_back()
-            .map(|(k, _)| key_to_price(*k))
-            .unwrap_or(0.0);
-        let best_ask = snap
-            .asks
-            .iter()
-            .next()
-            .map(|(k, _)| key_to_price(*k))
-            .unwrap_or(0.0);
-        let mid = if best_bid > 0.0 && best_ask > 0.0 {
-            (best_bid + best_ask) * 0.5
-        } else {
-            0.0
+            .map(|c| c.close)
+            .collect();
+        let inputs = BotInputs {
+            ticker: &self.current_ticker,
+            mode: match self.mode {
+                Mode::Live => "live",
+                Mode::Replay => "replay",
+            },
+            bids: &snap.bids,
+            asks: &snap.asks,
+            price_scale: snap.price_scale,
+            closes: &closes,
+            tf_secs: self.chart.tf_secs,
+            history_candles: self.chart.show_candles,
+            liquidity_bps: self.liquidity_bps,
+            wall_sigma: self.wall_sigma,
         };
-        let spread = if best_bid > 0.0 && best_ask > 0.0 {
-            best_ask - best_bid
-        } else {
-            0.0
+        let last = BotOutput {
+            signal: self.bot_signal.clone(),
+            size: self.bot_size,
+            comment: self.bot_comment.clone(),
         };
-
-        let mut bid_liq = 0.0;
-        for (_, s) in snap.bids.iter().rev().take(10) {
-            bid_liq += *s;
-        }
-        let mut ask_liq = 0.0;
-        for (_, s) in snap.asks.iter().take(10) {
-            ask_liq += *s;
-        }
-
-        self.scope.clear();
-
-        self.scope
-            .set_value("ticker", self.current_ticker.clone());
-        self.scope.set_value(
-            "mode",
-            match self.mode {
-                Mode::Live => "live".to_string(),
-                Mode::Replay => "replay".to_string(),
-            },
-        );
-        self.scope.set_value("best_bid", best_bid);
-        self.scope.set_value("best_ask", best_ask);
-        self.scope.set_value("mid", mid);
-        self.scope.set_value("spread", spread);
-        self.scope
-            .set_value("bid_liquidity_near", bid_liq);
-        self.scope
-            .set_value("ask_liquidity_near", ask_liq);
-        self.scope
-            .set_value("tf_secs", self.chart.tf_secs as i64);
-        self.scope
-            .set_value("history_candles", self.chart.show_candles as i64);
-
-        self.scope
-            .set_value("bot_signal", self.bot_signal.clone());
-        self.scope.set_value("bot_size", self.bot_size);
-        self.scope
-            .set_value("bot_comment", self.bot_comment.clone());
+        feed_scope(&mut self.scope, &inputs, &last);
     }
 
     fn read_bot_from_scope(&mut self) {
-        if let Some(sig) = self.scope.get_value::<String>("bot_signal") {
-            self.bot_signal = sig;
-        } else {
-            self.bot_signal = "none".to_string();
-        }
-        if let Some(size) = self.scope.get_value::<f64>("bot_size") {
-            self.bot_size = size.max(0.0);
-        } else {
-            self.bot_size = 0.0;
-        }
-        if let Some(comment) = self.scope.get_value::<String>("bot_comment") {
-            self.bot_comment = comment;
-        } else {
-            self.bot_comment.clear();
-        }
+        let out = read_bot_from_scope(&self.scope);
+        self.bot_signal = out.signal;
+        self.bot_size = out.size;
+        self.bot_comment = out.comment;
     }
 
-    fn run_script(&mut self, snap: &Snapshot) {
+    fn run_script(&mut self, snap: &TfSnapshot) {
         self.script_last_error = None;
 
         self.feed_scope_from_snapshot(snap);
 
         let res = self
             .engine
-            .eval_with_scope::<()>(
-                &mut self.scope,
-                &self.script_text,
-            );
+            .eval_with_scope::<()>(&mut self.scope, &self.script_text);
 
         match res {
             Ok(()) => {
                 self.read_bot_from_scope();
 
                 if self.bot_auto_trade
-                    && (self.bot_signal == "buy"
-                        || self.bot_signal == "sell")
+                    && (self.bot_signal == "buy" || self.bot_signal == "sell")
                     && self.bot_signal != self.bot_last_executed_signal
                     && self.bot_size > 0.0
                 {
@@ -780,8 +1056,7 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                         _ => None,
                     };
                     if let Some(side) = maybe_side {
-                        let size_str =
-                            format!("{:.8}", self.bot_size.max(0.0));
+                        let size_str = format!("{:.8}", self.bot_size.max(0.0));
                         if let Ok(size_bd) = BigDecimal::from_str(&size_str) {
                             let cmd = TradeCmd {
                                 ticker: self.current_ticker.clone(),
@@ -796,12 +1071,11 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                                 "[BOT] auto {:?} {} size {}",
                                 side, self.current_ticker, size_str
                             );
-                            self.bot_last_executed_signal =
-                                self.bot_signal.clone();
+                            self.bot_last_executed_signal = self.bot_signal.clone();
                             append_trade_csv(
                                 &self.current_ticker,
                                 "bot_auto",
-                                &format!("{:?}", side),
+                                TradeSide::from_buy(side == OrderSide::Buy).as_str(),
                                 &size_str,
                             );
                         }
@@ -816,9 +1090,180 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         self.script_last_run_ts = now_unix();
     }
 
+    // ---------- alerts ----------
+
+    fn alerts_path(&self) -> PathBuf {
+        self.base_dir.join("alerts.csv")
+    }
+
+    // ---------- layout presets ----------
+
+    fn save_current_as_preset(&mut self) {
+        // commas would break the csv row
+        let name = self.preset_name_input.trim().replace(',', " ");
+        if name.is_empty() {
+            return;
+        }
+        let preset = LayoutPreset {
+            name,
+            rows: self.row_cfgs,
+        };
+        match self.user_presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.user_presets.push(preset),
+        }
+        save_layout_presets(
+            &self.base_dir.join("layout_presets.csv"),
+            &self.user_presets,
+        );
+        self.preset_name_input.clear();
+    }
+
+    fn evaluate_alerts(&mut self, snap: &TfSnapshot) {
+        if self.alerts.is_empty() {
+            return;
+        }
+        self.feed_scope_from_snapshot(snap);
+
+        for alert in self.alerts.iter_mut() {
+            let key = alert.condition.metric.scope_key();
+            let value = match self.scope.get_value::<f64>(key) {
+                Some(v) => v,
+                None => continue,
+            };
+            // no book yet => nothing meaningful to compare
+            if value == 0.0 && key != "rsi_14" {
+                continue;
+            }
+
+            let holds = alert.condition.holds(value);
+            if holds && !alert.triggered {
+                alert.triggered = true;
+                self.alert_last_msg = format!(
+                    "[ALERT] {} {} (now {:.4})",
+                    self.current_ticker,
+                    alert.condition.describe(),
+                    value
+                );
+                self.alert_flash_until = Some(Instant::now() + Duration::from_millis(1500));
+
+                // OS side effects: per-alert opt-in, global mute, debounced
+                let debounced = alert
+                    .last_notified
+                    .map(|t| t.elapsed() < ALERT_DEBOUNCE)
+                    .unwrap_or(false);
+                if !self.alerts_muted && !debounced && (alert.bell || alert.desktop) {
+                    alert.last_notified = Some(Instant::now());
+                    if alert.bell {
+                        eprint!("\x07");
+                    }
+                    if alert.desktop {
+                        send_desktop_notification(
+                            &format!("{} alert", self.current_ticker),
+                            &format!("{} (now {:.4})", alert.condition.describe(), value),
+                        );
+                    }
+                }
+            } else if !holds && alert.triggered {
+                alert.triggered = false;
+            }
+        }
+    }
+
+    fn ui_alerts(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.heading("Alerts");
+            ui.checkbox(&mut self.alerts_muted, "Mute beeps/notifications");
+
+            let mut changed = false;
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("alert_new_metric")
+                    .selected_text(self.alert_new.metric.scope_key())
+                    .show_ui(ui, |ui| {
+                        for m in AlertMetric::all() {
+                            ui.selectable_value(&mut self.alert_new.metric, *m, m.scope_key());
+                        }
+                    });
+                egui::ComboBox::from_id_source("alert_new_op")
+                    .selected_text(self.alert_new.op.label())
+                    .show_ui(ui, |ui| {
+                        for op in [AlertOp::Above, AlertOp::Below] {
+                            ui.selectable_value(&mut self.alert_new.op, op, op.label());
+                        }
+                    });
+                ui.add(egui::DragValue::new(&mut self.alert_new.threshold).speed(0.1));
+                ui.checkbox(&mut self.alert_new_bell, "bell");
+                ui.checkbox(&mut self.alert_new_desktop, "desktop");
+                if ui.button("Add").clicked() {
+                    self.alerts.push(Alert {
+                        condition: self.alert_new,
+                        triggered: false,
+                        bell: self.alert_new_bell,
+                        desktop: self.alert_new_desktop,
+                        last_notified: None,
+                    });
+                    changed = true;
+                }
+            });
+
+            ui.separator();
+
+            let mut remove_idx = None;
+            egui::Grid::new("alerts_grid").striped(true).show(ui, |ui| {
+                for (i, alert) in self.alerts.iter_mut().enumerate() {
+                    let color = if alert.triggered {
+                        Color32::from_rgb(255, 80, 80)
+                    } else {
+                        Color32::GRAY
+                    };
+                    ui.colored_label(color, alert.condition.describe());
+                    if ui
+                        .add(egui::DragValue::new(&mut alert.condition.threshold).speed(0.1))
+                        .changed()
+                    {
+                        alert.triggered = false;
+                        changed = true;
+                    }
+                    if ui.checkbox(&mut alert.bell, "bell").changed() {
+                        changed = true;
+                    }
+                    if ui.checkbox(&mut alert.desktop, "desktop").changed() {
+                        changed = true;
+                    }
+                    if ui.button("x").clicked() {
+                        remove_idx = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if let Some(i) = remove_idx {
+                self.alerts.remove(i);
+                changed = true;
+            }
+            if changed {
+                save_alerts(&self.alerts_path(), &self.alerts);
+            }
+
+            if !self.alert_last_msg.is_empty() {
+                ui.separator();
+                ui.label(&self.alert_last_msg);
+            }
+        });
+    }
+
     // ---------- UI pieces ----------
 
-    fn ui_top_bar(&mut self, ui: &mut egui::Ui, snap_opt: Option<&Snapshot>) {
+    fn ui_top_bar(&mut self, ui: &mut egui::Ui, snap_opt: Option<&TfSnapshot>) {
+        let flashing = self
+            .alert_flash_until
+            .map(|t| Instant::now() < t)
+            .unwrap_or(false);
+        if flashing {
+            ui.colored_label(Color32::from_rgb(255, 80, 80), &self.alert_last_msg);
+        }
+
         ui.horizontal(|ui| {
             ui.label("Mode:");
             if ui
@@ -837,22 +1282,19 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             ui.separator();
 
             let tickers = self.tickers.clone();
-            ui.menu_button(
-                format!("Ticker: {}", self.current_ticker),
-                |ui| {
-                    for t in &tickers {
-                        let selected = *t == self.current_ticker;
-                        if ui.selectable_label(selected, t).clicked() {
-                            self.current_ticker = t.clone();
-                            if let Some((_, max_ts)) = self.ticker_range() {
-                                self.live_ts = max_ts;
-                                self.replay_ts = max_ts;
-                            }
-                            ui.close_menu();
+            ui.menu_button(format!("Ticker: {}", self.current_ticker), |ui| {
+                for t in &tickers {
+                    let selected = *t == self.current_ticker;
+                    if ui.selectable_label(selected, t).clicked() {
+                        self.current_ticker = t.clone();
+                        if let Some((_, max_ts)) = self.ticker_range() {
+                            self.live_ts = max_ts;
+                            self.replay_ts = max_ts;
                         }
+                        ui.close_menu();
                     }
-                },
-            );
+                }
+            });
 
             ui.separator();
 
@@ -881,23 +1323,24 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     ));
                 } else {
                     ui.separator();
-                    ui.label(format!(
-                        "Live @ {}",
-                        format_ts(self.time_mode, max_ts)
-                    ));
+                    ui.label(format!("Live @ {}", format_ts(self.time_mode, max_ts)));
                 }
             }
+
+            if self.snap_worker.is_pending() {
+                ui.separator();
+                ui.spinner();
+                ui.weak("recomputing…");
+            }
         });
 
         if matches!(self.mode, Mode::Replay) {
             if let Some((min_ts, max_ts)) = self.ticker_range() {
                 let mut ts = self.replay_ts;
+                let mut follow = self.follow_tail;
                 ui.horizontal(|ui| {
                     ui.label("Replay time:");
-                    ui.add(
-                        egui::Slider::new(&mut ts, min_ts..=max_ts)
-                            .show_value(false),
-                    );
+                    ui.add(egui::Slider::new(&mut ts, min_ts..=max_ts).show_value(false));
                     if ui.button("◀").clicked() && ts > min_ts {
                         ts -= 1;
                     }
@@ -907,9 +1350,16 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     if ui.button("Now").clicked() {
                         ts = max_ts;
                     }
+                    ui.checkbox(&mut follow, "Follow tail")
+                        .on_hover_text("Pin replay to the newest row; advances on every reload");
                     ui.label(format_ts(self.time_mode, ts));
                 });
-                self.replay_ts = ts;
+                // scrubbing back means the user wants history, not the tail
+                if ts != self.replay_ts && ts < max_ts {
+                    follow = false;
+                }
+                self.follow_tail = follow;
+                self.replay_ts = if follow { max_ts } else { ts };
             } else {
                 ui.label("No data yet for this ticker.");
             }
@@ -919,17 +1369,11 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
 
         ui.horizontal(|ui| {
             ui.label("History candles:");
-            ui.add(
-                egui::Slider::new(&mut self.chart.show_candles, 10..=2000)
-                    .logarithmic(true),
-            );
+            ui.add(egui::Slider::new(&mut self.chart.show_candles, 10..=2000).logarithmic(true));
 
             ui.separator();
             ui.label("X zoom:");
-            ui.add(
-                egui::Slider::new(&mut self.chart.x_zoom, 0.25..=4.0)
-                    .logarithmic(true),
-            );
+            ui.add(egui::Slider::new(&mut self.chart.x_zoom, 0.25..=4.0).logarithmic(true));
 
             if ui.button("Center X").clicked() {
                 self.chart.x_pan_secs = 0.0;
@@ -987,16 +1431,35 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                 .show_ui(ui, |ui| {
                     for (tf, label) in TF_OPTS {
                         if ui
-                            .selectable_label(
-                                self.chart.tf_secs == *tf,
-                                *label,
-                            )
+                            .selectable_label(self.chart.tf_secs == *tf, *label)
                             .clicked()
                         {
                             self.chart.tf_secs = *tf;
                         }
                     }
                 });
+
+            ui.separator();
+            ui.label("Candles:");
+            egui::ComboBox::from_id_source("candle_body_combo")
+                .selected_text(self.chart.candle_body.label())
+                .show_ui(ui, |ui| {
+                    for body in CandleBody::all() {
+                        ui.selectable_value(&mut self.chart.candle_body, *body, body.label());
+                    }
+                });
+            egui::ComboBox::from_id_source("candle_color_basis_combo")
+                .selected_text(self.chart.candle_color_basis.label())
+                .show_ui(ui, |ui| {
+                    for basis in CandleColorBasis::all() {
+                        ui.selectable_value(
+                            &mut self.chart.candle_color_basis,
+                            *basis,
+                            basis.label(),
+                        );
+                    }
+                });
+            ui.checkbox(&mut self.chart.depth_filled, "Filled depth");
         });
 
         ui.separator();
@@ -1015,73 +1478,90 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                 }
             });
 
+            ui.separator();
+            let mut apply = None;
+            ui.horizontal(|ui| {
+                ui.label("Preset:");
+                egui::ComboBox::from_id_source("layout_preset")
+                    .selected_text("apply…")
+                    .show_ui(ui, |ui| {
+                        for p in self.builtin_presets.iter().chain(&self.user_presets) {
+                            if ui
+                                .selectable_label(p.rows == self.row_cfgs, &p.name)
+                                .clicked()
+                            {
+                                apply = Some(p.rows);
+                            }
+                        }
+                    });
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.preset_name_input)
+                        .hint_text("preset name")
+                        .desired_width(120.0),
+                );
+                if ui.button("Save current as preset").clicked() {
+                    self.save_current_as_preset();
+                }
+            });
+            if let Some(rows) = apply {
+                self.row_cfgs = rows;
+                ui.ctx().request_repaint();
+            }
+
             ui.separator();
             ui.label("Row configs (height & span):");
             for row in 0..GRID_ROWS {
                 ui.horizontal(|ui| {
                     ui.label(format!("Row {row}:"));
                     ui.add(
-                        egui::Slider::new(
-                            &mut self.row_cfgs[row].height_factor,
-                            0.5..=3.0,
-                        )
-                        .text("H"),
+                        egui::Slider::new(&mut self.row_cfgs[row].height_factor, 0.5..=3.0)
+                            .text("H"),
                     );
-                    egui::ComboBox::from_id_source(format!(
-                        "span_row_{row}"
-                    ))
-                    .selected_text(match self.row_cfgs[row].span_mode {
-                        RowSpanMode::Split3 => "3 cols",
-                        RowSpanMode::Left2Right1 => "2+1",
-                        RowSpanMode::Left1Right2 => "1+2",
-                        RowSpanMode::Full => "full",
-                    })
-                    .show_ui(ui, |ui| {
-                        if ui
-                            .selectable_label(
-                                self.row_cfgs[row].span_mode
-                                    == RowSpanMode::Split3,
-                                "3 cols",
-                            )
-                            .clicked()
-                        {
-                            self.row_cfgs[row].span_mode =
-                                RowSpanMode::Split3;
-                        }
-                        if ui
-                            .selectable_label(
-                                self.row_cfgs[row].span_mode
-                                    == RowSpanMode::Left2Right1,
-                                "2+1",
-                            )
-                            .clicked()
-                        {
-                            self.row_cfgs[row].span_mode =
-                                RowSpanMode::Left2Right1;
-                        }
-                        if ui
-                            .selectable_label(
-                                self.row_cfgs[row].span_mode
-                                    == RowSpanMode::Left1Right2,
-                                "1+2",
-                            )
-                            .clicked()
-                        {
-                            self.row_cfgs[row].span_mode =
-                                RowSpanMode::Left1Right2;
-                        }
-                        if ui
-                            .selectable_label(
-                                self.row_cfgs[row].span_mode
-                                    == RowSpanMode::Full,
-                                "full",
-                            )
-                            .clicked()
-                        {
-                            self.row_cfgs[row].span_mode =
-                                RowSpanMode::Full;
-                        }
-                    });
+                    egui::ComboBox::from_id_source(format!("span_row_{row}"))
+                        .selected_text(match self.row_cfgs[row].span_mode {
+                            RowSpanMode::Split3 => "3 cols",
+                            RowSpanMode::Left2Right1 => "2+1",
+                            RowSpanMode::Left1Right2 => "1+2",
+                            RowSpanMode::Full => "full",
+                        })
+                        .show_ui(ui, |ui| {
+                            if ui
+                                .selectable_label(
+                                    self.row_cfgs[row].span_mode == RowSpanMode::Split3,
+                                    "3 cols",
+                                )
+                                .clicked()
+                            {
+                                self.row_cfgs[row].span_mode = RowSpanMode::Split3;
+                            }
+                            if ui
+                                .selectable_label(
+                                    self.row_cfgs[row].span_mode == RowSpanMode::Left2Right1,
+                                    "2+1",
+                                )
+                                .clicked()
+                            {
+                                self.row_cfgs[row].span_mode = RowSpanMode::Left2Right1;
+                            }
+                            if ui
+                                .selectable_label(
+                                    self.row_cfgs[row].span_mode == RowSpanMode::Left1Right2,
+                                    "1+2",
+                                )
+                                .clicked()
+                            {
+                                self.row_cfgs[row].span_mode = RowSpanMode::Left1Right2;
+                            }
+                            if ui
+                                .selectable_label(
+                                    self.row_cfgs[row].span_mode == RowSpanMode::Full,
+                                    "full",
+                                )
+                                .clicked()
+                            {
+                                self.row_cfgs[row].span_mode = RowSpanMode::Full;
+                            }
+                        });
 
                     if matches!(
                         self.row_cfgs[row].span_mode,
@@ -1089,11 +1569,8 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     ) {
                         ui.label("Big ratio:");
                         ui.add(
-                            egui::Slider::new(
-                                &mut self.row_cfgs[row].big_ratio,
-                                0.3..=0.8,
-                            )
-                            .show_value(false),
+                            egui::Slider::new(&mut self.row_cfgs[row].big_ratio, 0.3..=0.8)
+                                .show_value(false),
                         );
                     }
                 });
@@ -1129,19 +1606,13 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             ui.horizontal(|ui| {
                 ui.label("Side:");
                 if ui
-                    .selectable_label(
-                        matches!(self.trade_side, OrderSide::Buy),
-                        "BUY",
-                    )
+                    .selectable_label(matches!(self.trade_side, OrderSide::Buy), "BUY")
                     .clicked()
                 {
                     self.trade_side = OrderSide::Buy;
                 }
                 if ui
-                    .selectable_label(
-                        matches!(self.trade_side, OrderSide::Sell),
-                        "SELL",
-                    )
+                    .selectable_label(matches!(self.trade_side, OrderSide::Sell), "SELL")
                     .clicked()
                 {
                     self.trade_side = OrderSide::Sell;
@@ -1151,19 +1622,13 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             ui.horizontal(|ui| {
                 ui.label("Kind:");
                 if ui
-                    .selectable_label(
-                        matches!(self.trade_kind, TradeKind::Market),
-                        "Market",
-                    )
+                    .selectable_label(matches!(self.trade_kind, TradeKind::Market), "Market")
                     .clicked()
                 {
                     self.trade_kind = TradeKind::Market;
                 }
                 if ui
-                    .selectable_label(
-                        matches!(self.trade_kind, TradeKind::Limit),
-                        "Limit-ish",
-                    )
+                    .selectable_label(matches!(self.trade_kind, TradeKind::Limit), "Limit-ish")
                     .clicked()
                 {
                     self.trade_kind = TradeKind::Limit;
@@ -1191,16 +1656,11 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             if matches!(self.trade_kind, TradeKind::Limit) {
                 ui.horizontal(|ui| {
                     ui.label("Limit price (guard):");
-                    ui.add(
-                        egui::DragValue::new(&mut self.trade_limit_price)
-                            .speed(0.5),
-                    );
+                    ui.add(egui::DragValue::new(&mut self.trade_limit_price).speed(0.5));
                 });
             }
 
-            if !self.bot_signal.is_empty()
-                && self.bot_signal != "none"
-            {
+            if !self.bot_signal.is_empty() && self.bot_signal != "none" {
                 ui.separator();
                 ui.label(format!(
                     "Bot: {} size {:.4}  ({})",
@@ -1232,20 +1692,16 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     let _ = self.trade_tx.try_send(cmd);
                     self.last_order_msg = format!(
                         "Sent {:?} {:?} {} size {}",
-                        self.trade_kind,
-                        self.trade_side,
-                        ticker,
-                        size_str
+                        self.trade_kind, self.trade_side, ticker, size_str
                     );
                     append_trade_csv(
                         &ticker,
                         "gui_manual",
-                        &format!("{:?}", self.trade_side),
+                        TradeSide::from_buy(self.trade_side == OrderSide::Buy).as_str(),
                         &size_str,
                     );
                 } else {
-                    self.last_order_msg =
-                        "Invalid size for order".to_string();
+                    self.last_order_msg = "Invalid size for order".to_string();
                 }
             }
 
@@ -1269,10 +1725,31 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                 if ui.button("Run now").clicked() {
                     self.script_last_run_ts = 0;
                 }
-                ui.checkbox(
-                    &mut self.script_auto_run,
-                    "Auto run each refresh",
+                ui.checkbox(&mut self.script_auto_run, "Auto run every");
+                ui.add_enabled(
+                    self.script_auto_run,
+                    egui::Slider::new(&mut self.script_min_interval_secs, 1.0..=60.0)
+                        .logarithmic(true)
+                        .suffix(" s"),
+                );
+                ui.separator();
+                ui.label("Liquidity band:");
+                ui.add(
+                    egui::DragValue::new(&mut self.liquidity_bps)
+                        .speed(0.5)
+                        .clamp_range(0.5..=500.0)
+                        .suffix(" bps"),
+                )
+                .on_hover_text(
+                    "bid_liquidity_bps / ask_liquidity_bps sum size within this band of mid",
                 );
+                ui.label("Wall σ:");
+                ui.add(
+                    egui::DragValue::new(&mut self.wall_sigma)
+                        .speed(0.1)
+                        .clamp_range(1.0..=5.0),
+                )
+                .on_hover_text("Levels this many stddevs above their neighbours are walls");
             });
 
             ui.separator();
@@ -1303,21 +1780,38 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         });
     }
 
-    fn ui_recent_trades(&self, ui: &mut egui::Ui, snap: &Snapshot) {
+    fn ui_recent_trades(&mut self, ui: &mut egui::Ui, snap: &TfSnapshot) {
         ui.group(|ui| {
-            ui.heading("Recent trades");
+            ui.horizontal(|ui| {
+                ui.heading("Recent trades");
+                egui::ComboBox::from_id_source("recent_trades_source")
+                    .selected_text(self.trade_source_filter.label())
+                    .show_ui(ui, |ui| {
+                        for f in TradeSourceFilter::all() {
+                            ui.selectable_value(&mut self.trade_source_filter, f, f.label());
+                        }
+                    });
+            });
+            let filter = self.trade_source_filter;
             egui::ScrollArea::vertical().show(ui, |ui| {
                 egui::Grid::new("recent_trades_grid")
                     .striped(true)
                     .show(ui, |ui| {
                         ui.label("Time");
+                        ui.label("Source");
                         ui.label("Side");
                         ui.label("Size");
                         ui.end_row();
 
-                        for tr in snap.trades.iter().rev() {
+                        for tr in snap
+                            .trades
+                            .iter()
+                            .rev()
+                            .filter(|tr| filter.matches(&tr.source))
+                        {
                             ui.label(format_ts(self.time_mode, tr.ts));
-                            ui.label(&tr.side);
+                            ui.colored_label(source_color(&tr.source), &tr.source);
+                            ui.label(tr.side.as_str());
                             ui.label(&tr.size_str);
                             ui.end_row();
                         }
@@ -1326,9 +1820,30 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         });
     }
 
-    fn ui_ladders(&self, ui: &mut egui::Ui, snap: &Snapshot) {
+    fn ui_ladders(&self, ui: &mut egui::Ui, snap: &TfSnapshot) {
         ui.group(|ui| {
             ui.heading("Ladders (top 20)");
+            let best_bid = snap.bids.keys().next_back();
+            let best_ask = snap.asks.keys().next();
+            let mid = match (best_bid, best_ask) {
+                (Some(b), Some(a)) => {
+                    (key_to_price(*b, snap.price_scale) + key_to_price(*a, snap.price_scale)) * 0.5
+                }
+                _ => 0.0,
+            };
+            let (bid_liq, ask_liq) = liquidity_within_bps(
+                &snap.bids,
+                &snap.asks,
+                snap.price_scale,
+                mid,
+                self.liquidity_bps,
+            );
+            ui.label(format!(
+                "Within {} bps of mid: bids {:.4} / asks {:.4}",
+                self.liquidity_bps, bid_liq, ask_liq
+            ));
+            let bid_walls = detect_walls(&snap.bids, WALL_WINDOW, self.wall_sigma);
+            let ask_walls = detect_walls(&snap.asks, WALL_WINDOW, self.wall_sigma);
 
             ui.columns(2, |cols| {
                 cols[0].label("Bids");
@@ -1339,10 +1854,8 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                         ui.label("Size");
                         ui.end_row();
                         for (k, s) in snap.bids.iter().rev().take(20) {
-                            let p = key_to_price(*k);
-                            ui.label(format!("{:>9.2}", p));
-                            ui.label(format!("{:>8.4}", s));
-                            ui.end_row();
+                            let p = key_to_price(*k, snap.price_scale);
+                            ladder_row(ui, p, *s, bid_walls.binary_search(k).is_ok());
                         }
                     });
 
@@ -1354,76 +1867,48 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                         ui.label("Size");
                         ui.end_row();
                         for (k, s) in snap.asks.iter().take(20) {
-                            let p = key_to_price(*k);
-                            ui.label(format!("{:>9.2}", p));
-                            ui.label(format!("{:>8.4}", s));
-                            ui.end_row();
+                            let p = key_to_price(*k, snap.price_scale);
+                            ladder_row(ui, p, *s, ask_walls.binary_search(k).is_ok());
                         }
                     });
             });
         });
     }
 
-    fn ui_depth_plot(&self, ui: &mut egui::Ui, snap: &Snapshot, height: f32) {
+    fn ui_depth_plot(&self, ui: &mut egui::Ui, snap: &TfSnapshot, height: f32) {
         let mut bid_points = Vec::new();
         let mut ask_points = Vec::new();
 
         let mut cum = 0.0;
         for (k, s) in snap.bids.iter().rev() {
-            let p = key_to_price(*k);
+            let p = key_to_price(*k, snap.price_scale);
             cum += *s;
             bid_points.push((p, cum));
         }
 
         cum = 0.0;
         for (k, s) in snap.asks.iter() {
-            let p = key_to_price(*k);
+            let p = key_to_price(*k, snap.price_scale);
             cum += *s;
             ask_points.push((p, cum));
         }
 
-        Plot::new("depth_plot")
-            .height(height)
-            .show(ui, |plot_ui| {
-                if !bid_points.is_empty() {
-                    let pts: PlotPoints = bid_points
-                        .iter()
-                        .map(|(x, y)| [*x, *y])
-                        .collect::<Vec<_>>()
-                        .into();
-                    plot_ui.line(
-                        Line::new(pts)
-                            .color(Color32::from_rgb(80, 200, 120))
-                            .name("Bids"),
-                    );
-                }
-                if !ask_points.is_empty() {
-                    let pts: PlotPoints = ask_points
-                        .iter()
-                        .map(|(x, y)| [*x, *y])
-                        .collect::<Vec<_>>()
-                        .into();
-                    plot_ui.line(
-                        Line::new(pts)
-                            .color(Color32::from_rgb(220, 80, 80))
-                            .name("Asks"),
-                    );
-                }
-            });
+        let filled = self.chart.depth_filled;
+        Plot::new("depth_plot").height(height).show(ui, |plot_ui| {
+            let bid_color = Color32::from_rgb(80, 200, 120);
+            let ask_color = Color32::from_rgb(220, 80, 80);
+            plot_depth_side(plot_ui, &bid_points, "Bids", bid_color, filled);
+            plot_depth_side(plot_ui, &ask_points, "Asks", ask_color, filled);
+        });
     }
 
-    fn ui_candles_and_volume(
-        &mut self,
-        ui: &mut egui::Ui,
-        snap: &Snapshot,
-        height: f32,
-    ) {
-        if snap.candles.is_empty() {
+    fn ui_candles_and_volume(&mut self, ui: &mut egui::Ui, snap: &TfSnapshot, height: f32) {
+        let series = snap.candles(self.chart.tf_secs);
+        if series.is_empty() {
             ui.label("No candles yet at this TF.");
             return;
         }
 
-        let series = &snap.candles;
         let len = series.len();
         let window_len = self.chart.show_candles.min(len).max(1);
         let visible = &series[len - window_len..];
@@ -1446,6 +1931,8 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         let volume_h = if self.show_volume { height * 0.3 } else { 0.0 };
 
         let tf = self.chart.tf_secs as f64;
+        let candle_body = self.chart.candle_body;
+        let color_basis = self.chart.candle_color_basis;
         let last = visible.last().unwrap();
         let x_center = last.t as f64 + tf * 0.5;
         let base_span = tf * self.chart.show_candles as f64;
@@ -1453,15 +1940,60 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
         let x_min = x_center - span * 0.5 + self.chart.x_pan_secs;
         let x_max = x_center + span * 0.5 + self.chart.x_pan_secs;
 
-        ui.allocate_ui(
-            egui::vec2(ui.available_width(), candles_h),
-            |ui| {
+        ui.allocate_ui(egui::vec2(ui.available_width(), candles_h), |ui| {
+            let mode = self.time_mode;
+
+            let plot_resp = Plot::new("candles_plot")
+                .height(candles_h)
+                .include_y(y_min)
+                .include_y(y_max)
+                .allow_drag(true)
+                .allow_zoom(true)
+                .x_axis_formatter(move |mark, _range, _| {
+                    let ts = mark.value as u64;
+                    format_ts(mode, ts)
+                })
+                .show(ui, |plot_ui| {
+                    plot_ui
+                        .set_plot_bounds(PlotBounds::from_min_max([x_min, y_min], [x_max, y_max]));
+
+                    let style = CandleRenderStyle {
+                        body_width_factor: 1.0,
+                        wick_width: 1.0,
+                        up_color: Color32::from_rgb(40, 200, 120),
+                        down_color: Color32::from_rgb(220, 60, 60),
+                        body: candle_body,
+                        color_basis,
+                    };
+                    draw_candles(plot_ui, visible, tf, &style);
+                });
+
+            let hovered = plot_resp.response.hovered();
+            let mut scroll_y = 0.0f32;
+            let mut shift = false;
+            ui.ctx().input(|i| {
+                scroll_y = i.raw_scroll_delta.y;
+                shift = i.modifiers.shift;
+            });
+            if hovered && shift && scroll_y != 0.0 {
+                self.chart.auto_y = false;
+                let factor = 1.0 + (scroll_y as f64 * 0.002);
+                let factor = factor.clamp(0.2, 5.0);
+                let center = (self.chart.y_min + self.chart.y_max) * 0.5;
+                let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
+                self.chart.y_min = center - half_span;
+                self.chart.y_max = center + half_span;
+            }
+        });
+
+        if self.show_volume {
+            ui.separator();
+            ui.allocate_ui(egui::vec2(ui.available_width(), volume_h), |ui| {
                 let mode = self.time_mode;
 
-                let plot_resp = Plot::new("candles_plot")
-                    .height(candles_h)
-                    .include_y(y_min)
-                    .include_y(y_max)
+                let plot_resp = Plot::new("volume_plot")
+                    .height(volume_h)
+                    .include_y(0.0)
                     .allow_drag(true)
                     .allow_zoom(true)
                     .x_axis_formatter(move |mark, _range, _| {
@@ -1469,45 +2001,25 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                         format_ts(mode, ts)
                     })
                     .show(ui, |plot_ui| {
+                        let max_vol = visible
+                            .iter()
+                            .map(|c| c.volume)
+                            .fold(0.0_f64, f64::max)
+                            .max(1e-6);
+                        let y_max_v = max_vol * 1.1;
+
                         plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                            [x_min, y_min],
-                            [x_max, y_max],
+                            [x_min, 0.0],
+                            [x_max, y_max_v],
                         ));
 
                         for c in visible {
                             let left = c.t as f64;
-                            let right = left + tf;
                             let mid = left + tf * 0.5;
-                            let top = c.open.max(c.close);
-                            let bot = c.open.min(c.close);
-
-                            let color = if c.close >= c.open {
-                                Color32::from_rgb(40, 200, 120)
-                            } else {
-                                Color32::from_rgb(220, 60, 60)
-                            };
-
-                            let wick_pts: PlotPoints =
-                                vec![[mid, c.low], [mid, c.high]].into();
-                            plot_ui.line(
-                                Line::new(wick_pts)
-                                    .color(color)
-                                    .width(1.0),
-                            );
+                            let color = Color32::from_rgb(120, 170, 240);
 
-                            let body_pts: PlotPoints = vec![
-                                [left, bot],
-                                [left, top],
-                                [right, top],
-                                [right, bot],
-                                [left, bot],
-                            ]
-                            .into();
-                            plot_ui.line(
-                                Line::new(body_pts)
-                                    .color(color)
-                                    .width(2.0),
-                            );
+                            let pts: PlotPoints = vec![[mid, 0.0], [mid, c.volume]].into();
+                            plot_ui.line(Line::new(pts).color(color).width(2.0));
                         }
                     });
 
@@ -1522,94 +2034,16 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     self.chart.auto_y = false;
                     let factor = 1.0 + (scroll_y as f64 * 0.002);
                     let factor = factor.clamp(0.2, 5.0);
-                    let center =
-                        (self.chart.y_min + self.chart.y_max) * 0.5;
-                    let half_span = (self.chart.y_max - self.chart.y_min)
-                        .max(1e-6)
-                        * factor
-                        * 0.5;
+                    let center = (self.chart.y_min + self.chart.y_max) * 0.5;
+                    let half_span = (self.chart.y_max - self.chart.y_min).max(1e-6) * factor * 0.5;
                     self.chart.y_min = center - half_span;
                     self.chart.y_max = center + half_span;
                 }
-            },
-        );
-
-        if self.show_volume {
-            ui.separator();
-            ui.allocate_ui(
-                egui::vec2(ui.available_width(), volume_h),
-                |ui| {
-                    let mode = self.time_mode;
-
-                    let plot_resp = Plot::new("volume_plot")
-                        .height(volume_h)
-                        .include_y(0.0)
-                        .allow_drag(true)
-                        .allow_zoom(true)
-                        .x_axis_formatter(move |mark, _range, _| {
-                            let ts = mark.value as u64;
-                            format_ts(mode, ts)
-                        })
-                        .show(ui, |plot_ui| {
-                            let max_vol = visible
-                                .iter()
-                                .map(|c| c.volume)
-                                .fold(0.0_f64, f64::max)
-                                .max(1e-6);
-                            let y_max_v = max_vol * 1.1;
-
-                            plot_ui.set_plot_bounds(
-                                PlotBounds::from_min_max(
-                                    [x_min, 0.0],
-                                    [x_max, y_max_v],
-                                ),
-                            );
-
-                            for c in visible {
-                                let left = c.t as f64;
-                                let mid = left + tf * 0.5;
-                                let color =
-                                    Color32::from_rgb(120, 170, 240);
-
-                                let pts: PlotPoints =
-                                    vec![[mid, 0.0], [mid, c.volume]]
-                                        .into();
-                                plot_ui.line(
-                                    Line::new(pts)
-                                        .color(color)
-                                        .width(2.0),
-                                );
-                            }
-                        });
-
-                    let hovered = plot_resp.response.hovered();
-                    let mut scroll_y = 0.0f32;
-                    let mut shift = false;
-                    ui.ctx().input(|i| {
-                        scroll_y = i.raw_scroll_delta.y;
-                        shift = i.modifiers.shift;
-                    });
-                    if hovered && shift && scroll_y != 0.0 {
-                        self.chart.auto_y = false;
-                        let factor = 1.0 + (scroll_y as f64 * 0.002);
-                        let factor = factor.clamp(0.2, 5.0);
-                        let center = (self.chart.y_min
-                            + self.chart.y_max)
-                            * 0.5;
-                        let half_span = (self.chart.y_max
-                            - self.chart.y_min)
-                            .max(1e-6)
-                            * factor
-                            * 0.5;
-                        self.chart.y_min = center - half_span;
-                        self.chart.y_max = center + half_span;
-                    }
-                },
-            );
+            });
         }
     }
 
-    fn ui_grid(&mut self, ui: &mut egui::Ui, snap_opt: Option<&Snapshot>) {
+    fn ui_grid(&mut self, ui: &mut egui::Ui, snap_opt: Option<&TfSnapshot>) {
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
             .show(ui, |ui| {
@@ -1618,222 +2052,121 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                     let row_height = 140.0 * cfg.height_factor;
                     let width = ui.available_width();
 
-                    ui.allocate_ui(
-                        egui::vec2(width, row_height),
-                        |row_ui| {
-                            row_ui.horizontal(|row_ui| {
-                                let total_w = row_ui.available_width();
-                                let (w0, w1, w2) = match cfg.span_mode {
-                                    RowSpanMode::Split3 => {
-                                        let w = total_w / 3.0;
-                                        (w, w, w)
-                                    }
-                                    RowSpanMode::Left2Right1 => {
-                                        let big = total_w * cfg.big_ratio;
-                                        let small = total_w - big;
-                                        (big, small, 0.0)
-                                    }
-                                    RowSpanMode::Left1Right2 => {
-                                        let big = total_w * cfg.big_ratio;
-                                        let small = total_w - big;
-                                        (small, big, 0.0)
-                                    }
-                                    RowSpanMode::Full => {
-                                        (total_w, 0.0, 0.0)
-                                    }
-                                };
+                    ui.allocate_ui(egui::vec2(width, row_height), |row_ui| {
+                        row_ui.horizontal(|row_ui| {
+                            let total_w = row_ui.available_width();
+                            let (w0, w1, w2) = match cfg.span_mode {
+                                RowSpanMode::Split3 => {
+                                    let w = total_w / 3.0;
+                                    (w, w, w)
+                                }
+                                RowSpanMode::Left2Right1 => {
+                                    let big = total_w * cfg.big_ratio;
+                                    let small = total_w - big;
+                                    (big, small, 0.0)
+                                }
+                                RowSpanMode::Left1Right2 => {
+                                    let big = total_w * cfg.big_ratio;
+                                    let small = total_w - big;
+                                    (small, big, 0.0)
+                                }
+                                RowSpanMode::Full => (total_w, 0.0, 0.0),
+                            };
 
-                                let mut next_cell = |idx: usize,
-                                                     w: f32,
-                                                     f: &mut dyn FnMut(
-                                                         &mut egui::Ui,
-                                                     )| {
+                            let mut next_cell =
+                                |idx: usize, w: f32, f: &mut dyn FnMut(&mut egui::Ui)| {
                                     if w <= 0.0 {
                                         return;
                                     }
                                     row_ui
-                                        .allocate_ui(
-                                            egui::vec2(w, row_height),
-                                            |cell_ui| {
-                                                cell_ui.set_clip_rect(
-                                                    cell_ui
-                                                        .clip_rect()
-                                                        .intersect(
-                                                            cell_ui
-                                                                .max_rect(),
-                                                        ),
-                                                );
-                                                f(cell_ui);
-                                            },
-                                        )
+                                        .allocate_ui(egui::vec2(w, row_height), |cell_ui| {
+                                            cell_ui.set_clip_rect(
+                                                cell_ui.clip_rect().intersect(cell_ui.max_rect()),
+                                            );
+                                            f(cell_ui);
+                                        })
                                         .response
-                                        .on_hover_text(format!(
-                                            "row{}_col{}",
-                                            row, idx
-                                        ));
+                                        .on_hover_text(format!("row{}_col{}", row, idx));
                                 };
 
-                                match row {
-                                    0 => {
-                                        if let Some(snap) = snap_opt {
-                                            next_cell(
-                                                0,
-                                                w0,
-                                                &mut |cell| {
-                                                    self.ui_candles_and_volume(
-                                                        cell,
-                                                        snap,
-                                                        row_height,
-                                                    );
-                                                },
-                                            );
-                                        } else {
-                                            next_cell(
-                                                0,
-                                                w0,
-                                                &mut |cell| {
-                                                    cell.label(
-                                                        "No snapshot yet.",
-                                                    );
-                                                },
-                                            );
-                                        }
-                                    }
-                                    1 => {
-                                        if let Some(snap) = snap_opt {
-                                            next_cell(
-                                                0,
-                                                w0,
-                                                &mut |cell| {
-                                                    if self.show_depth {
-                                                        self.ui_depth_plot(
-                                                            cell,
-                                                            snap,
-                                                            row_height,
-                                                        );
-                                                    } else {
-                                                        cell.label(
-                                                            "Depth hidden",
-                                                        );
-                                                    }
-                                                },
-                                            );
-                                            next_cell(
-                                                1,
-                                                w1,
-                                                &mut |cell| {
-                                                    if self.show_ladders {
-                                                        self.ui_ladders(
-                                                            cell, snap,
-                                                        );
-                                                    } else {
-                                                        cell.label(
-                                                            "Ladders hidden",
-                                                        );
-                                                    }
-                                                },
-                                            );
-                                        } else {
-                                            next_cell(
-                                                0,
-                                                w0,
-                                                &mut |cell| {
-                                                    cell.label(
-                                                        "No depth/ladders yet.",
-                                                    );
-                                                },
-                                            );
-                                        }
-                                        next_cell(
-                                            2,
-                                            w2.max(w1),
-                                            &mut |cell| {
-                                                self.ui_trading_panel(
-                                                    cell,
-                                                );
-                                            },
-                                        );
+                            match row {
+                                0 => {
+                                    if let Some(snap) = snap_opt {
+                                        next_cell(0, w0, &mut |cell| {
+                                            self.ui_candles_and_volume(cell, snap, row_height);
+                                        });
+                                    } else {
+                                        next_cell(0, w0, &mut |cell| {
+                                            cell.label("No snapshot yet.");
+                                        });
                                     }
-                                    2 => {
-                                        next_cell(
-                                            0,
-                                            w0,
-                                            &mut |cell| {
-                                                self.ui_script_engine(
-                                                    cell,
-                                                );
-                                            },
-                                        );
-                                        next_cell(
-                                            1,
-                                            w1,
-                                            &mut |cell| {
-                                                cell.group(|ui| {
-                                                    ui.heading(
-                                                        "Bot status",
-                                                    );
-                                                    ui.label(
-                                                        format!(
-"Signal: {}  size {:.4}",
-self.bot_signal,
-self.bot_size
-                                                        ),
-                                                    );
-                                                    ui.label(
-                                                        format!(
-                                                            "Comment: {}",
-                                                            self
-                                                                .bot_comment
-                                                        ),
-                                                    );
-                                                    ui.label(
-                                                        format!(
-"Auto-trade: {}",
-if self.bot_auto_trade {
-"ON"
-} else {
-"OFF"
-}
-                                                        ),
-                                                    );
-                                                });
-                                            },
-                                        );
-                                        if let Some(snap) = snap_opt {
-                                            next_cell(
-                                                2,
-                                                w2,
-                                                &mut |cell| {
-                                                    if self.show_trades {
-                                                        self.ui_recent_trades(
-                                                            cell, snap,
-                                                        );
-                                                    } else {
-                                                        cell.label(
-                                                            "Trades hidden",
-                                                        );
-                                                    }
-                                                },
-                                            );
-                                        }
+                                }
+                                1 => {
+                                    if let Some(snap) = snap_opt {
+                                        next_cell(0, w0, &mut |cell| {
+                                            if self.show_depth {
+                                                self.ui_depth_plot(cell, snap, row_height);
+                                            } else {
+                                                cell.label("Depth hidden");
+                                            }
+                                        });
+                                        next_cell(1, w1, &mut |cell| {
+                                            if self.show_ladders {
+                                                self.ui_ladders(cell, snap);
+                                            } else {
+                                                cell.label("Ladders hidden");
+                                            }
+                                        });
+                                    } else {
+                                        next_cell(0, w0, &mut |cell| {
+                                            cell.label("No depth/ladders yet.");
+                                        });
                                     }
-                                    _ => {
-                                        next_cell(
-                                            0,
-                                            w0,
-                                            &mut |cell| {
-                                                cell.label(
-                                                    format!(
-                                                        "Row {row} (free)",
-                                                    ),
-                                                );
-                                            },
-                                        );
+                                    next_cell(2, w2.max(w1), &mut |cell| {
+                                        self.ui_trading_panel(cell);
+                                    });
+                                }
+                                2 => {
+                                    next_cell(0, w0, &mut |cell| {
+                                        self.ui_script_engine(cell);
+                                    });
+                                    next_cell(1, w1, &mut |cell| {
+                                        cell.group(|ui| {
+                                            ui.heading("Bot status");
+                                            ui.label(format!(
+                                                "Signal: {}  size {:.4}",
+                                                self.bot_signal, self.bot_size
+                                            ));
+                                            ui.label(format!("Comment: {}", self.bot_comment));
+                                            ui.label(format!(
+                                                "Auto-trade: {}",
+                                                if self.bot_auto_trade { "ON" } else { "OFF" }
+                                            ));
+                                        });
+                                    });
+                                    if let Some(snap) = snap_opt {
+                                        next_cell(2, w2, &mut |cell| {
+                                            if self.show_trades {
+                                                self.ui_recent_trades(cell, snap);
+                                            } else {
+                                                cell.label("Trades hidden");
+                                            }
+                                        });
                                     }
                                 }
-                            });
-                        },
-                    );
+                                3 => {
+                                    next_cell(0, w0, &mut |cell| {
+                                        self.ui_alerts(cell);
+                                    });
+                                }
+                                _ => {
+                                    next_cell(0, w0, &mut |cell| {
+                                        cell.label(format!("Row {row} (free)",));
+                                    });
+                                }
+                            }
+                        });
+                    });
 
                     ui.add_space(6.0);
                 }
@@ -1842,37 +2175,55 @@ if self.bot_auto_trade {
 }
 
 impl eframe::App for ComboApp {
-    fn update(
-        &mut self,
-        ctx: &egui::Context,
-        _frame: &mut eframe::Frame,
-    ) {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let now = now_unix();
         if now.saturating_sub(self.last_reload_ts) as f64 >= self.reload_secs {
+            let before = self.ticker_range();
             self.reload_current_ticker();
             self.clamp_ts_to_range();
             self.last_reload_ts = now;
+            if self.ticker_range() != before {
+                self.repaint.touch(Instant::now());
+            }
         }
 
-        let snap_opt = self.current_snap();
+        let current = self.current_snap();
+        let snap_opt = current.as_ref().map(|(_, s)| s);
 
-        if self.script_auto_run {
-            if let Some(ref snap) = snap_opt {
-                if now.saturating_sub(self.script_last_run_ts) >= 0 {
-                    self.run_script(snap);
-                }
+        // "Run now" zeroes the last run, which runs even with auto run off
+        let forced = self.script_last_run_ts == 0;
+        let due = self.script_auto_run
+            && now.saturating_sub(self.script_last_run_ts) as f64 >= self.script_min_interval_secs;
+        if forced || due {
+            if let Some(snap) = snap_opt {
+                self.run_script(snap);
             }
         }
 
+        if let Some(snap) = snap_opt {
+            self.evaluate_alerts(snap);
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            self.ui_top_bar(ui, snap_opt.as_ref());
+            self.ui_top_bar(ui, snap_opt);
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.ui_grid(ui, snap_opt.as_ref());
+            self.ui_grid(ui, snap_opt);
         });
 
-        ctx.request_repaint_after(Duration::from_millis(50));
+        if current.is_some() {
+            self.snap_worker.latest = current;
+        }
+
+        let wall = Instant::now();
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.repaint.touch(wall);
+        }
+        // never paused: the reload timer above needs update() to keep running
+        if let Some(after) = self.repaint.next_repaint(wall, false) {
+            ctx.request_repaint_after(after);
+        }
     }
 }
 
@@ -1882,7 +2233,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let config = match ClientConfig::from_file("client/tests/testnet.toml").await {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("[trader] failed to load testnet.toml: {e}");
+            error!(error = %e, "failed to load testnet.toml");
             return;
         }
     };
@@ -1890,9 +2241,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let raw = match std::env::var("DYDX_TESTNET_MNEMONIC") {
         Ok(v) => v,
         Err(_) => {
-            eprintln!(
-                "[trader] DYDX_TESTNET_MNEMONIC not set; trading disabled"
-            );
+            warn!("DYDX_TESTNET_MNEMONIC not set; trading disabled");
             return;
         }
     };
@@ -1901,7 +2250,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let wallet = match Wallet::from_mnemonic(&mnemonic) {
         Ok(w) => w,
         Err(e) => {
-            eprintln!("[trader] invalid mnemonic: {e}");
+            error!(error = %e, "invalid mnemonic");
             return;
         }
     };
@@ -1909,7 +2258,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let mut node = match NodeClient::connect(config.node).await {
         Ok(n) => n,
         Err(e) => {
-            eprintln!("[trader] node connect failed: {e}");
+            error!(error = %e, "node connect failed");
             return;
         }
     };
@@ -1917,7 +2266,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let mut account = match wallet.account(0, &mut node).await {
         Ok(a) => a,
         Err(e) => {
-            eprintln!("[trader] account sync failed: {e}");
+            error!(error = %e, "account sync failed");
             return;
         }
     };
@@ -1925,7 +2274,7 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
     let sub = match account.subaccount(0) {
         Ok(s) => s,
         Err(e) => {
-            eprintln!("[trader] subaccount derive failed: {e}");
+            error!(error = %e, "subaccount derive failed");
             return;
         }
     };
@@ -1942,68 +2291,67 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
             leverage: _,
         } = cmd;
 
-        eprintln!(
-            "[trader] {:?} {:?} {} size {} (limit guard: {})",
-            kind, side, ticker, size, limit_price
-        );
-
-        let market = match indexer
-            .markets()
-            .get_perpetual_market(&ticker.clone().into())
-            .await
-        {
-            Ok(m) => m,
-            Err(e) => {
-                eprintln!("[trader] market meta error for {ticker}: {e}");
-                continue;
-            }
-        };
+        let span = info_span!("order", %ticker, ?side, %size, ?kind);
+        async {
+            info!(limit_guard = limit_price, "submitting order");
 
-        let h = match node.latest_block_height().await {
-            Ok(h) => h,
-            Err(e) => {
-                eprintln!("[trader] height error: {e}");
-                continue;
-            }
-        };
+            let market = match indexer
+                .markets()
+                .get_perpetual_market(&ticker.clone().into())
+                .await
+            {
+                Ok(m) => m,
+                Err(e) => {
+                    error!(stage = "market_meta", error = %e, "order failed");
+                    return;
+                }
+            };
 
-        let mut builder = OrderBuilder::new(market, sub.clone())
-            .market(side.clone(), size.clone())
-            .reduce_only(false)
-            .time_in_force(TimeInForce::Unspecified)
-            .until(h.ahead(10));
+            let h = match node.latest_block_height().await {
+                Ok(h) => h,
+                Err(e) => {
+                    error!(stage = "height", error = %e, "order failed");
+                    return;
+                }
+            };
 
-        if limit_price > 0.0 {
-            // placeholder "price guard" wiring; you can refine the
-            // Price type for real limit orders later.
-            builder = builder.price(100);
-        }
+            let mut builder = OrderBuilder::new(market, sub.clone())
+                .market(side.clone(), size.clone())
+                .reduce_only(false)
+                .time_in_force(TimeInForce::Unspecified)
+                .until(h.ahead(10));
 
-        let (_id, order) = match builder.build(123456) {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("[trader] build order error: {e}");
-                continue;
+            if limit_price > 0.0 {
+                // placeholder "price guard" wiring; you can refine the
+                // Price type for real limit orders later.
+                builder = builder.price(100);
             }
-        };
 
-        match node.place_order(&mut account, order).await {
-            Ok(tx_hash) => {
-                eprintln!(
-                    "[trader] placed {:?} {} size {} tx={tx_hash:?}",
-                    side, ticker, size
-                );
-                append_trade_csv(
-                    &ticker,
-                    "trader",
-                    &format!("{:?}", side),
-                    &size.to_string(),
-                );
-            }
-            Err(e) => {
-                eprintln!("[trader] place_order error: {e}");
+            let (_id, order) = match builder.build(123456) {
+                Ok(x) => x,
+                Err(e) => {
+                    error!(stage = "build", error = %e, "order failed");
+                    return;
+                }
+            };
+
+            match node.place_order(&mut account, order).await {
+                Ok(tx_hash) => {
+                    info!(%tx_hash, "order placed");
+                    append_trade_csv(
+                        &ticker,
+                        "trader",
+                        TradeSide::from_buy(side == OrderSide::Buy).as_str(),
+                        &size.to_string(),
+                    );
+                }
+                Err(e) => {
+                    error!(stage = "place", error = %e, "order failed");
+                }
             }
         }
+        .instrument(span)
+        .await;
     }
 }
 
@@ -2011,8 +2359,10 @@ async fn run_trader(mut rx: mpsc::Receiver<TradeCmd>) {
 
 fn main() {
     init_crypto_provider();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    init_tracing(log_json_from_args(&args));
 
-    let base_dir = PathBuf::from("data");
+    let base_dir = data_dir().to_path_buf();
     let tickers = vec![
         "ETH-USD".to_string(),
         "BTC-USD".to_string(),
@@ -2033,7 +2383,7 @@ fn main() {
 
     let (trade_tx, trade_rx) = mpsc::channel::<TradeCmd>(64);
 
-    rt.spawn(run_trader(trade_rx));
+    rt.spawn(run_trader(trade_rx).instrument(info_span!("trader")));
 
     let native_options = eframe::NativeOptions::default();
 
@@ -2044,6 +2394,6 @@ fn main() {
         native_options,
         Box::new(|_cc| Box::new(app)),
     ) {
-        eprintln!("eframe error: {e}");
+        error!(error = %e, "eframe exited with an error");
     }
 }
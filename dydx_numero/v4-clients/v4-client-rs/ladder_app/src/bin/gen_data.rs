@@ -0,0 +1,153 @@
+// ladder_app/src/bin/gen_data.rs
+//
+// Writes a synthetic orderbook_<TICKER> / trades_<TICKER> pair from a seeded
+// random walk, so the replay and GUI apps can be tried without a network or a
+// day of collection, and as test fixtures; the same arguments always give the
+// same files.
+//
+//   cargo run -p ladder_app --bin gen_data -- --ticker ETH-USD --duration 3600 \
+//       --rate 5 --tick 0.1 --walk-vol 0.3 --seed 7 --data-dir data
+//
+//   --duration <secs>      length of the recording
+//   --rate <n>             book events per second
+//   --trade-prob <p>       chance an event also prints a trade
+//   --seed, --walk-vol, --walk-drift   the walk (vol is per event)
+//   --start-ts, --start-price, --tick, --levels   shape the book
+//   --data-format csv|jsonl
+//   --help                 print the flags and exit without writing
+//
+// Defaults are in ladder_app::synth::SynthMarket. Existing files are replaced.
+// Unknown flags are an error, so a typo can't quietly fall back to a default.
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use ladder_app::csv_io::{arg_value, data_dir, data_format, DataFormat};
+use ladder_app::synth::SynthMarket;
+
+const USAGE: &str = "\
+usage: gen_data [--ticker <T>] [--duration <secs>] [--rate <n>] [--trade-prob <p>]
+                [--seed <n>] [--walk-vol <v>] [--walk-drift <d>]
+                [--start-ts <ts>] [--start-price <p>] [--tick <t>] [--levels <n>]
+                [--data-dir <path>] [--data-format csv|jsonl]";
+
+// every flag gen_data reads; each takes a value, as `--flag v` or `--flag=v`
+const FLAGS: &[&str] = &[
+    "--ticker",
+    "--start-ts",
+    "--duration",
+    "--rate",
+    "--start-price",
+    "--tick",
+    "--levels",
+    "--trade-prob",
+    "--seed",
+    "--walk-vol",
+    "--walk-drift",
+    "--data-dir",
+    "--data-format",
+];
+
+// Err on the first argument that isn't one of FLAGS or is missing its value
+fn check_args(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    while let Some(a) = args.next() {
+        let flag = a.split_once('=').map_or(a.as_str(), |(f, _)| f);
+        if !FLAGS.contains(&flag) {
+            return Err(format!("unknown argument {a:?}"));
+        }
+        if flag == a && args.next().is_none() {
+            return Err(format!("{a}: missing value"));
+        }
+    }
+    Ok(())
+}
+
+fn parse_arg<T: FromStr>(args: &[String], flag: &str, default: T) -> Result<T, String> {
+    match arg_value(args.iter().cloned(), flag) {
+        Some(v) => v.parse().map_err(|_| format!("{flag}: bad value {v:?}")),
+        None => Ok(default),
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    check_args(args)?;
+    if let Some(v) = arg_value(args.iter().cloned(), "--data-format") {
+        if DataFormat::parse(&v).is_none() {
+            return Err(format!("--data-format: bad value {v:?}"));
+        }
+    }
+    let d = SynthMarket::default();
+    let synth = SynthMarket {
+        ticker: parse_arg(args, "--ticker", d.ticker)?,
+        start_ts: parse_arg(args, "--start-ts", d.start_ts)?,
+        duration: parse_arg(args, "--duration", d.duration)?,
+        events_per_sec: parse_arg(args, "--rate", d.events_per_sec)?,
+        start_price: parse_arg(args, "--start-price", d.start_price)?,
+        tick: parse_arg(args, "--tick", d.tick)?,
+        levels: parse_arg(args, "--levels", d.levels)?,
+        trade_prob: parse_arg(args, "--trade-prob", d.trade_prob)?,
+        walk: d.walk.with_args(args.iter().cloned()),
+    };
+    if synth.events_per_sec == 0 {
+        return Err("--rate: must be at least 1".to_string());
+    }
+    if synth.tick.is_nan() || synth.tick <= 0.0 {
+        return Err("--tick: must be positive".to_string());
+    }
+
+    let dir = data_dir();
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let (rows, trades) = synth
+        .write(dir, data_format())
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    println!(
+        "wrote {rows} book rows and {trades} trades for {} to {} \
+         ({}s at {}/s, seed {}, vol {}, drift {})",
+        synth.ticker,
+        dir.display(),
+        synth.duration,
+        synth.events_per_sec,
+        synth.walk.seed,
+        synth.walk.volatility,
+        synth.walk.drift
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("gen_data: {e}\n{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn unknown_or_valueless_flags_are_rejected() {
+        assert!(check_args(&args(&["--ticker", "BTC-USD", "--seed=7"])).is_ok());
+        assert!(check_args(&args(&[])).is_ok());
+
+        let err = check_args(&args(&["--durration", "60"])).unwrap_err();
+        assert!(err.contains("--durration"), "{err}");
+        assert!(check_args(&args(&["--ticker=ETH-USD", "extra"])).is_err());
+        assert!(check_args(&args(&["--rate"])).is_err());
+    }
+}
@@ -3,20 +3,30 @@
 // Offline REPLAY app.
 // Replays orderbook + candles + "fills" from CSV files produced by gui_app27:
 //
-//   data/orderbook_ethusd.csv  (book events)
+//   data/orderbook_ethusd.csv  (book events; --data-dir <path> replaces data/)
 //   data/trades.csv            (real trades placed from gui_app27)
 //
 // Features:
 //   - No network, no wallet, pure offline
-//   - Reconstructs orderbook over time
-//   - Candles + volume + RSI (same style as live app, filled bodies)
+//   - Reconstructs orderbook over time (ladder_app::replay::ReplayEngine)
+//   - Candles + volume + oscillator panel (RSI / Stochastic / Williams %R)
 //   - Orderbook + depth view
 //   - Time toggle: Unix vs Local
 //   - Theme selector (5 palettes)
-//   - Replay controls: Play/Pause, Speed slider, Restart
-//   - Data tab with detailed views of current book + trades + events
+//   - Replay controls: Play/Pause, Speed slider, Restart,
+//     real-time or event-paced playback
+//   - Data tab with detailed views of current book + trades + events,
+//     plus time-weighted spread / top-of-book size / uptime for the whole file
 //   - Adjustable windows for trades/events
 //   - Snapshot export of current state to data/replay_snapshot_<ts>.txt
+//   - Export of the Data tab's trade window to data/replay_trades_<ts>_<window>s.csv
+//   - Bookmarks (label + ts) with Prev/Next seek, saved to data/bookmarks_<ticker>.json
+//   - Jump to the next trade / next candle moving more than a threshold
+//   - Without data, candles follow a seeded random walk
+//     (--seed, --walk-vol, --walk-drift; see ladder_app::synth)
+//   - Repaints every 33ms while playing; while paused it slows down after a
+//     few idle seconds and then waits for input
+//     (--repaint-ms, --idle-repaint-ms; see ladder_app::repaint)
 //
 // Usage:
 //   1. Run gui_app27 for a while to collect data in data/*.csv
@@ -26,45 +36,76 @@
 //
 // NOTE: This only replays ETH-USD currently (matches orderbook_ethusd.csv).
 
-mod candle_agg;
-
-use candle_agg::{Candle, CandleAgg};
-
 use eframe::egui;
-use egui::{Color32, Stroke};
-use egui_plot::{GridMark, HLine, Line, Plot, PlotBounds, PlotPoints, Polygon, VLine};
+use egui::Color32;
+use egui_plot::{
+    GridMark, HLine, Line, LineStyle, Plot, PlotBounds, PlotPoint, PlotPoints, Text, VLine,
+};
 
-use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use chrono::{Local, TimeZone};
 
-use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as FmtWrite;
 use std::fs;
 use std::fs::File;
-use std::fmt::Write as FmtWrite;
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
+use ladder_app::candle_agg::{resample, Candle, CandleAgg};
+use ladder_app::candle_render::{draw_candles, CandleBody, CandleColorBasis, CandleRenderStyle};
+use ladder_app::csv_io::{sort_dedup_trades, BookSide, TradeSide};
+use ladder_app::pnl_hud::{self, HudCorner, PnlHud};
+use ladder_app::repaint::RepaintThrottle;
+use ladder_app::replay::ReplayEngine;
+use ladder_app::sim::{Depth, ExitReason, PositionSide, TradingState};
+use ladder_app::synth::{RandomWalk, WalkParams};
+
 // ---- price key quantization (for BTreeMap) ----
 // Store prices as i64 with 1e-4 precision
 type PriceKey = i64;
 const PRICE_SCALE: i64 = 10_000;
 
-fn price_to_key(price: f64) -> PriceKey {
-    (price * PRICE_SCALE as f64).round() as i64
-}
 fn key_to_price(k: PriceKey) -> f64 {
     k as f64 / PRICE_SCALE as f64
 }
 
+// ---- data dir ----
+
+const DEFAULT_DATA_DIR: &str = "data";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// `--data-dir <path>` or `--data-dir=<path>`; `data` when absent
+fn data_dir_from_args(args: impl IntoIterator<Item = String>) -> PathBuf {
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == "--data-dir" {
+            if let Some(v) = args.next() {
+                return PathBuf::from(v);
+            }
+        } else if let Some(v) = a.strip_prefix("--data-dir=") {
+            return PathBuf::from(v);
+        }
+    }
+    PathBuf::from(DEFAULT_DATA_DIR)
+}
+
+/// Base dir for every CSV read and write, taken from the command line once.
+fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| data_dir_from_args(env::args().skip(1)))
+}
+
 // ---- orderbook + trades CSV types ----
 
 #[derive(Debug, Clone)]
 struct OrderbookCsvEvent {
     ts: u64,
     msg_type: String,
-    side: String, // "bid" or "ask"
+    side: BookSide,
     price: f64,
     size: f64,
 }
@@ -73,45 +114,21 @@ struct OrderbookCsvEvent {
 struct TradeCsvEvent {
     ts: u64,
     ticker: String,
-    side: String,
+    side: TradeSide,
     size: f64,
 }
 
-// simple in-memory orderbook (price stored as quantized i64)
-#[derive(Default, Clone, Debug)]
-struct LiveBook {
-    pub bids: BTreeMap<PriceKey, f64>, // price_key -> size
-    pub asks: BTreeMap<PriceKey, f64>,
+// engine fed row by row from ob_events, which gui_replay4 loads itself
+fn new_replay() -> ReplayEngine<'static> {
+    ReplayEngine::over(&[], &[], PRICE_SCALE as f64)
 }
 
-impl LiveBook {
-    fn apply_level(&mut self, side: &str, price: f64, size: f64) {
-        let key = price_to_key(price);
-        let map = if side == "bid" {
-            &mut self.bids
-        } else {
-            &mut self.asks
-        };
-
-        if size == 0.0 {
-            map.remove(&key);
-        } else {
-            map.insert(key, size);
-        }
-    }
-
-    fn best_bid_ask(&self) -> (Option<(f64, f64)>, Option<(f64, f64)>) {
-        let bid = self
-            .bids
-            .iter()
-            .next_back()
-            .map(|(k, s)| (key_to_price(*k), *s));
-        let ask = self
-            .asks
-            .iter()
-            .next()
-            .map(|(k, s)| (key_to_price(*k), *s));
-        (bid, ask)
+// what the trading sim fills against
+fn depth<'a>(replay: &'a ReplayEngine) -> Depth<'a> {
+    Depth {
+        bids: replay.bids(),
+        asks: replay.asks(),
+        scale: PRICE_SCALE as f64,
     }
 }
 
@@ -142,286 +159,316 @@ struct ChartSettings {
     y_max: f64,
     show_candles: usize,
     auto_scale: bool,
+    show_divergences: bool,
+    div_lookback: usize,
+    oscillator: OscillatorKind,
+    osc_period: usize,
+    // sim position box over the candles
+    show_pnl_hud: bool,
+    pnl_hud_corner: HudCorner,
 }
 
-// fake trading sim (same as live, but no real orders)
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum PositionSide {
-    Flat,
-    Long,
-    Short,
-}
+// RSI
+fn compute_rsi(closes: &[f64], period: usize) -> Vec<(f64, f64)> {
+    if closes.len() < period + 1 {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
 
-impl PositionSide {
-    fn label(&self) -> &'static str {
-        match self {
-            PositionSide::Flat => "FLAT",
-            PositionSide::Long => "LONG",
-            PositionSide::Short => "SHORT",
+    for i in period..closes.len() {
+        let window = &closes[i - period..=i];
+        let mut gains = 0.0;
+        let mut losses = 0.0;
+
+        for w in 1..window.len() {
+            let diff = window[w] - window[w - 1];
+            if diff >= 0.0 {
+                gains += diff;
+            } else {
+                losses -= diff;
+            }
         }
+
+        let avg_gain = gains / period as f64;
+        let avg_loss = losses / period as f64;
+        let rsi = if avg_loss == 0.0 {
+            100.0
+        } else {
+            let rs = avg_gain / avg_loss;
+            100.0 - (100.0 / (1.0 + rs))
+        };
+
+        out.push((i as f64, rsi));
     }
-}
 
-#[derive(Clone, Debug)]
-struct TradingState {
-    wallet_usdc: f64,
-    margin: f64,
-    deposit_amount: f64,
-    withdraw_amount: f64,
-    leverage: f64,
-    position: f64,
-    side: PositionSide,
-    entry_price: Option<f64>,
-    realized_pnl: f64,
-    take_profit: Option<f64>,
-    stop_loss: Option<f64>,
-    maint_rate: f64,
-    last_liq_price: Option<f64>,
-    last_liq_time: Option<u64>,
-    liquidated_flag: bool,
+    out
 }
 
-impl TradingState {
-    fn new() -> Self {
-        Self {
-            wallet_usdc: 5_000.0,
-            margin: 100.0,
-            deposit_amount: 100.0,
-            withdraw_amount: 100.0,
-            leverage: 5.0,
-            position: 0.0,
-            side: PositionSide::Flat,
-            entry_price: None,
-            realized_pnl: 0.0,
-            take_profit: None,
-            stop_loss: None,
-            maint_rate: 0.005,
-            last_liq_price: None,
-            last_liq_time: None,
-            liquidated_flag: false,
-        }
-    }
-
-    fn deposit_to_margin(&mut self, amount: f64) {
-        if amount <= 0.0 {
-            return;
-        }
-        let amt = amount.min(self.wallet_usdc);
-        if amt <= 0.0 {
-            return;
-        }
-        self.wallet_usdc -= amt;
-        self.margin += amt;
+// oscillator output: (candle idx, value), same shape as compute_rsi
+type OscPoints = Vec<(f64, f64)>;
+// one named panel line
+type OscLine = (&'static str, Color32, OscPoints);
+
+// Stochastic %K over `period` candles, %D = 3-candle SMA of %K
+fn compute_stochastic(candles: &[Candle], period: usize) -> (OscPoints, OscPoints) {
+    let period = period.max(1);
+    let mut k = Vec::new();
+    if candles.len() < period {
+        return (k, Vec::new());
     }
 
-    fn withdraw_from_margin(&mut self, amount: f64) {
-        if amount <= 0.0 {
-            return;
-        }
-        let amt = amount.min(self.margin);
-        if amt <= 0.0 {
-            return;
-        }
-        self.margin -= amt;
-        self.wallet_usdc += amt;
+    for i in period - 1..candles.len() {
+        let window = &candles[i + 1 - period..=i];
+        let hh = window.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+        let ll = window.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+        let range = hh - ll;
+        let v = if range <= 0.0 {
+            50.0
+        } else {
+            100.0 * (candles[i].close - ll) / range
+        };
+        k.push((i as f64, v));
     }
 
-    fn notional(&self) -> f64 {
-        self.margin * self.leverage
-    }
+    let d = k
+        .windows(3)
+        .map(|w| (w[2].0, (w[0].1 + w[1].1 + w[2].1) / 3.0))
+        .collect();
 
-    fn max_position_units(&self, mark: f64) -> f64 {
-        if mark <= 0.0 {
-            return 0.0;
-        }
-        (self.margin * self.leverage / mark).max(0.0)
-    }
+    (k, d)
+}
 
-    fn is_open(&self) -> bool {
-        self.entry_price.is_some()
-            && self.position > 0.0
-            && !matches!(self.side, PositionSide::Flat)
+// Williams %R over `period` candles (-100..0)
+fn compute_williams_r(candles: &[Candle], period: usize) -> OscPoints {
+    let period = period.max(1);
+    let mut out = Vec::new();
+    if candles.len() < period {
+        return out;
     }
 
-    fn unrealized_pnl(&self, mark: f64) -> f64 {
-        if let Some(entry) = self.entry_price {
-            match self.side {
-                PositionSide::Long => (mark - entry) * self.position,
-                PositionSide::Short => (entry - mark) * self.position,
-                PositionSide::Flat => 0.0,
-            }
+    for i in period - 1..candles.len() {
+        let window = &candles[i + 1 - period..=i];
+        let hh = window.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+        let ll = window.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+        let range = hh - ll;
+        let v = if range <= 0.0 {
+            -50.0
         } else {
-            0.0
-        }
+            -100.0 * (hh - candles[i].close) / range
+        };
+        out.push((i as f64, v));
     }
 
-    fn equity(&self, mark: f64) -> f64 {
-        self.margin + self.realized_pnl + self.unrealized_pnl(mark)
+    out
+}
+
+// oscillator panel under the volume plot
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OscillatorKind {
+    Rsi,
+    Stochastic,
+    WilliamsR,
+}
+
+impl OscillatorKind {
+    fn label(&self) -> &'static str {
+        match self {
+            OscillatorKind::Rsi => "RSI",
+            OscillatorKind::Stochastic => "Stochastic %K/%D",
+            OscillatorKind::WilliamsR => "Williams %R",
+        }
     }
 
-    fn maintenance_margin(&self, mark: f64) -> f64 {
-        let notional = self.position * mark;
-        notional * self.maint_rate
+    fn all() -> &'static [OscillatorKind] {
+        &[
+            OscillatorKind::Rsi,
+            OscillatorKind::Stochastic,
+            OscillatorKind::WilliamsR,
+        ]
     }
 
-    fn open_at(&mut self, mark: f64) {
-        if self.is_open() || self.side == PositionSide::Flat {
-            return;
-        }
-        if self.margin <= 0.0 || self.leverage <= 0.0 || mark <= 0.0 {
-            return;
+    fn y_range(&self) -> (f64, f64) {
+        match self {
+            OscillatorKind::Rsi | OscillatorKind::Stochastic => (0.0, 100.0),
+            OscillatorKind::WilliamsR => (-100.0, 0.0),
         }
+    }
 
-        if self.position <= 0.0 {
-            self.position = self.max_position_units(mark);
-        } else {
-            let maxu = self.max_position_units(mark);
-            if self.position > maxu {
-                self.position = maxu;
-            }
+    /// (overbought, oversold) guide levels
+    fn guides(&self) -> (f64, f64) {
+        match self {
+            OscillatorKind::Rsi => (70.0, 30.0),
+            OscillatorKind::Stochastic => (80.0, 20.0),
+            OscillatorKind::WilliamsR => (-20.0, -80.0),
         }
-
-        self.entry_price = Some(mark);
-        self.liquidated_flag = false;
     }
 
-    fn close_at(&mut self, mark: f64) {
-        if !self.is_open() {
-            return;
+    /// Named lines for the panel, each as (candle idx, value) like compute_rsi.
+    fn compute(&self, candles: &[Candle], period: usize, pal: &ThemePalette) -> Vec<OscLine> {
+        match self {
+            OscillatorKind::Rsi => {
+                let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+                vec![("RSI", pal.rsi_line, compute_rsi(&closes, period))]
+            }
+            OscillatorKind::Stochastic => {
+                let (k, d) = compute_stochastic(candles, period);
+                vec![("%K", pal.rsi_line, k), ("%D", pal.accent, d)]
+            }
+            OscillatorKind::WilliamsR => {
+                vec![("%R", pal.rsi_line, compute_williams_r(candles, period))]
+            }
         }
+    }
+}
 
-        let upnl = self.unrealized_pnl(mark);
-
-        self.margin += upnl;
-        self.realized_pnl += upnl;
-        if self.margin < 0.0 {
-            self.margin = 0.0;
-        }
+/// Draw an oscillator panel: its lines (x already in unix secs), the
+/// overbought/oversold guides and extra overlay segments.
+fn draw_oscillator(
+    ui: &mut egui::Ui,
+    id: &str,
+    height: f32,
+    mode: TimeDisplayMode,
+    kind: OscillatorKind,
+    lines: &[OscLine],
+    overlays: &[(Vec<[f64; 2]>, Color32)],
+) {
+    let (y_lo, y_hi) = kind.y_range();
+    let (upper, lower) = kind.guides();
+
+    Plot::new(id)
+        .height(height)
+        .include_y(y_lo)
+        .include_y(y_hi)
+        .allow_drag(true)
+        .allow_zoom(true)
+        .allow_scroll(true)
+        .allow_boxed_zoom(true)
+        .y_axis_label(kind.label())
+        .x_axis_formatter(move |mark: GridMark, _range, _transform| {
+            format_ts_common(mode, mark.value as u64)
+        })
+        .show(ui, |plot_ui| {
+            let mut any = false;
+            for (name, color, values) in lines {
+                if values.is_empty() {
+                    continue;
+                }
+                any = true;
+                let pts: PlotPoints = values.iter().map(|(t, v)| [*t, *v]).collect();
+                plot_ui.line(Line::new(pts).name(*name).color(*color).width(2.0));
+            }
 
-        self.position = 0.0;
-        self.entry_price = None;
-        self.side = PositionSide::Flat;
-        self.take_profit = None;
-        self.stop_loss = None;
-    }
+            if any {
+                plot_ui.hline(HLine::new(upper));
+                plot_ui.hline(HLine::new(lower));
+            }
 
-    fn liquidate_at(&mut self, mark: f64, ts: u64) {
-        if !self.is_open() {
-            return;
-        }
+            for (seg, color) in overlays {
+                let pts: PlotPoints = seg.clone().into();
+                plot_ui.line(Line::new(pts).color(*color).width(2.0));
+            }
+        });
+}
 
-        let upnl = self.unrealized_pnl(mark);
+// RSI divergences
+// candles on each side a bar must beat to count as a pivot
+const PIVOT_WINDOW: usize = 3;
 
-        self.margin += upnl;
-        self.realized_pnl += upnl;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DivergenceKind {
+    /// price lower low, RSI higher low
+    Bullish,
+    /// price higher high, RSI lower high
+    Bearish,
+}
 
-        self.margin = 0.0;
+#[derive(Clone, Copy, Debug)]
+struct Divergence {
+    kind: DivergenceKind,
+    /// candle indices of the two pivots (first < second)
+    first: usize,
+    second: usize,
+}
 
-        self.position = 0.0;
-        self.entry_price = None;
-        self.side = PositionSide::Flat;
-        self.take_profit = None;
-        self.stop_loss = None;
+// RSI over the whole selected-TF series and every divergence found in it
+#[derive(Default)]
+struct Indicators {
+    rsi: Vec<(f64, f64)>,
+    divergences: Vec<Divergence>,
+}
 
-        self.last_liq_price = Some(mark);
-        self.last_liq_time = Some(ts);
-        self.liquidated_flag = true;
+fn is_pivot_low(candles: &[Candle], i: usize) -> bool {
+    if i < PIVOT_WINDOW || i + PIVOT_WINDOW >= candles.len() {
+        return false;
     }
+    let low = candles[i].low;
+    (i - PIVOT_WINDOW..=i + PIVOT_WINDOW).all(|j| j == i || candles[j].low >= low)
+}
 
-    fn bump_tp(&mut self, mark: f64, delta: f64) {
-        let base = self.take_profit.unwrap_or(mark);
-        self.take_profit = Some(base + delta);
+fn is_pivot_high(candles: &[Candle], i: usize) -> bool {
+    if i < PIVOT_WINDOW || i + PIVOT_WINDOW >= candles.len() {
+        return false;
     }
+    let high = candles[i].high;
+    (i - PIVOT_WINDOW..=i + PIVOT_WINDOW).all(|j| j == i || candles[j].high <= high)
+}
 
-    fn bump_sl(&mut self, mark: f64, delta: f64) {
-        let base = self.stop_loss.unwrap_or(mark);
-        self.stop_loss = Some(base + delta);
+/// Classic divergences between price pivots and RSI over the last
+/// `lookback` candles. `rsi` is the output of `compute_rsi`, indexed by candle.
+fn rsi_divergences(candles: &[Candle], rsi: &[(f64, f64)], lookback: usize) -> Vec<Divergence> {
+    let mut out = Vec::new();
+    if candles.len() < 2 * PIVOT_WINDOW + 1 || rsi.is_empty() {
+        return out;
     }
 
-    fn check_tp_sl(&mut self, mark: f64) {
-        if !self.is_open() {
-            return;
-        }
-        let tp = self.take_profit;
-        let sl = self.stop_loss;
-
-        match self.side {
-            PositionSide::Long => {
-                if let Some(tp) = tp {
-                    if mark >= tp {
-                        self.close_at(mark);
-                        return;
-                    }
-                }
-                if let Some(sl) = sl {
-                    if mark <= sl {
-                        self.close_at(mark);
-                        return;
-                    }
-                }
-            }
-            PositionSide::Short => {
-                if let Some(tp) = tp {
-                    if mark <= tp {
-                        self.close_at(mark);
-                        return;
-                    }
-                }
-                if let Some(sl) = sl {
-                    if mark >= sl {
-                        self.close_at(mark);
-                        return;
-                    }
-                }
-            }
-            PositionSide::Flat => {}
+    // candle index -> RSI value (RSI starts `period` candles in)
+    let mut rsi_at: Vec<Option<f64>> = vec![None; candles.len()];
+    for (idx_f, v) in rsi {
+        let idx = *idx_f as usize;
+        if idx < rsi_at.len() {
+            rsi_at[idx] = Some(*v);
         }
     }
 
-    fn check_liquidation(&mut self, mark: f64, ts: u64) {
-        if !self.is_open() {
-            return;
-        }
-        let equity = self.equity(mark);
-        let maint = self.maintenance_margin(mark);
+    // fewer candles than the lookback => just scan everything we have
+    let start = candles
+        .len()
+        .saturating_sub(lookback.max(2 * PIVOT_WINDOW + 1));
 
-        if equity <= maint {
-            self.liquidate_at(mark, ts);
-        }
-    }
-}
-
-// RSI
-fn compute_rsi(closes: &[f64], period: usize) -> Vec<(f64, f64)> {
-    if closes.len() < period + 1 {
-        return Vec::new();
-    }
-    let mut out = Vec::new();
+    let mut prev_low: Option<(usize, f64)> = None;
+    let mut prev_high: Option<(usize, f64)> = None;
 
-    for i in period..closes.len() {
-        let window = &closes[i - period..=i];
-        let mut gains = 0.0;
-        let mut losses = 0.0;
+    for i in start..candles.len() {
+        let r = match rsi_at[i] {
+            Some(r) => r,
+            None => continue,
+        };
 
-        for w in 1..window.len() {
-            let diff = window[w] - window[w - 1];
-            if diff >= 0.0 {
-                gains += diff;
-            } else {
-                losses -= diff;
+        if is_pivot_low(candles, i) {
+            if let Some((a, ra)) = prev_low {
+                if candles[i].low < candles[a].low && r > ra {
+                    out.push(Divergence {
+                        kind: DivergenceKind::Bullish,
+                        first: a,
+                        second: i,
+                    });
+                }
             }
+            prev_low = Some((i, r));
         }
 
-        let avg_gain = gains / period as f64;
-        let avg_loss = losses / period as f64;
-        let rsi = if avg_loss == 0.0 {
-            100.0
-        } else {
-            let rs = avg_gain / avg_loss;
-            100.0 - (100.0 / (1.0 + rs))
-        };
-
-        out.push((i as f64, rsi));
+        if is_pivot_high(candles, i) {
+            if let Some((a, ra)) = prev_high {
+                if candles[i].high > candles[a].high && r < ra {
+                    out.push(Divergence {
+                        kind: DivergenceKind::Bearish,
+                        first: a,
+                        second: i,
+                    });
+                }
+            }
+            prev_high = Some((i, r));
+        }
     }
 
     out
@@ -435,6 +482,29 @@ enum Tab {
     Data,
 }
 
+// how sim_ts moves while playing
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReplayPace {
+    // sim time advances with wall time * speed
+    RealTime,
+    // jump from one event ts to the next, one step per EVENT_PACE_SECS / speed
+    EventPaced,
+}
+
+impl ReplayPace {
+    fn label(&self) -> &'static str {
+        match self {
+            ReplayPace::RealTime => "Real-time",
+            ReplayPace::EventPaced => "Event-paced",
+        }
+    }
+}
+
+// wall seconds per event step at speed 1x
+const EVENT_PACE_SECS: f64 = 0.25;
+// at most this many event steps per frame, so a long stall doesn't fast-forward
+const EVENT_PACE_MAX_STEPS: usize = 50;
+
 // themes
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ThemeKind {
@@ -558,12 +628,39 @@ fn theme_palette(kind: ThemeKind) -> ThemePalette {
     }
 }
 
+// bookmarks: data/bookmarks_<ticker>.json, sorted by ts
+const REPLAY_TICKER: &str = "ETH-USD";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Bookmark {
+    ts: u64,
+    label: String,
+}
+
+fn bookmarks_path() -> PathBuf {
+    data_dir().join(format!("bookmarks_{REPLAY_TICKER}.json"))
+}
+
+fn load_bookmarks(path: &Path) -> Vec<Bookmark> {
+    let mut marks: Vec<Bookmark> = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    marks.sort_by_key(|b| b.ts);
+    marks
+}
+
+fn save_bookmarks(path: &Path, marks: &[Bookmark]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(marks).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
 // load CSV data
-fn load_orderbook_events(path: &str) -> Vec<OrderbookCsvEvent> {
+fn load_orderbook_events(path: &Path) -> Vec<OrderbookCsvEvent> {
     let file = match File::open(path) {
         Ok(f) => f,
         Err(e) => {
-            eprintln!("Replay: cannot open {path}: {e}");
+            eprintln!("Replay: cannot open {}: {e}", path.display());
             return Vec::new();
         }
     };
@@ -587,7 +684,9 @@ fn load_orderbook_events(path: &str) -> Vec<OrderbookCsvEvent> {
 
         let ts = parts[0].parse::<u64>().unwrap_or(0);
         let msg_type = parts[1].to_string();
-        let side = parts[2].to_string();
+        let Some(side) = BookSide::parse(parts[2]) else {
+            continue;
+        };
         let price = parts[3].parse::<f64>().unwrap_or(0.0);
         let size = parts[4].parse::<f64>().unwrap_or(0.0);
 
@@ -604,11 +703,11 @@ fn load_orderbook_events(path: &str) -> Vec<OrderbookCsvEvent> {
     out
 }
 
-fn load_trade_events(path: &str) -> Vec<TradeCsvEvent> {
+fn load_trade_events(path: &Path) -> Vec<TradeCsvEvent> {
     let file = match File::open(path) {
         Ok(f) => f,
         Err(e) => {
-            eprintln!("Replay: cannot open {path}: {e}");
+            eprintln!("Replay: cannot open {}: {e}", path.display());
             return Vec::new();
         }
     };
@@ -630,9 +729,13 @@ fn load_trade_events(path: &str) -> Vec<TradeCsvEvent> {
             continue;
         }
 
-        let ts = parts[0].parse::<u64>().unwrap_or(0);
+        let Ok(ts) = parts[0].parse::<u64>() else {
+            continue;
+        };
         let ticker = parts[2].to_string();
-        let side = parts[3].to_string();
+        let Some(side) = TradeSide::parse(parts[3]) else {
+            continue;
+        };
         let size = parts[4].parse::<f64>().unwrap_or(0.0);
 
         out.push(TradeCsvEvent {
@@ -643,15 +746,96 @@ fn load_trade_events(path: &str) -> Vec<TradeCsvEvent> {
         });
     }
 
-    out.sort_by_key(|e| e.ts);
+    // concurrent writers can leave rows out of order and the same fill twice;
+    // seek_to / step_sim walk this by index, so it has to be sorted and unique
+    let dropped = sort_dedup_trades(&mut out, |e| (e.ts, e.side, e.size));
+    if dropped > 0 {
+        eprintln!(
+            "Replay: dropped {dropped} duplicate trades from {}",
+            path.display()
+        );
+    }
     out
 }
 
+// time-weighted book quality over the whole loaded file
+#[derive(Clone, Copy, Debug, Default)]
+struct LiquidityStats {
+    // first to last event
+    span_secs: u64,
+    // fraction of span_secs with both sides populated
+    uptime: f64,
+    // the averages below only cover the two-sided time
+    avg_spread: f64,
+    avg_top_bid_size: f64,
+    avg_top_ask_size: f64,
+}
+
+// One pass: the book state after each event holds until the next event's ts
+// and is weighted by that gap; events sharing a ts collapse into the last one.
+fn session_liquidity_stats(events: &[OrderbookCsvEvent]) -> LiquidityStats {
+    let mut book = new_replay();
+    let mut two_sided = 0.0;
+    let (mut spread_sum, mut bid_sum, mut ask_sum) = (0.0, 0.0, 0.0);
+
+    for (i, ev) in events.iter().enumerate() {
+        book.apply_row(ev.ts, &ev.msg_type, ev.side, ev.price, ev.size, None);
+        let Some(next) = events.get(i + 1) else {
+            break;
+        };
+        let dt = next.ts.saturating_sub(ev.ts) as f64;
+        if dt == 0.0 {
+            continue;
+        }
+        // one-sided or empty book: counts against uptime only
+        if let (Some((bp, bs)), Some((ap, asz))) = (book.best_bid(), book.best_ask()) {
+            two_sided += dt;
+            spread_sum += (ap - bp) * dt;
+            bid_sum += bs * dt;
+            ask_sum += asz * dt;
+        }
+    }
+
+    let span_secs = match (events.first(), events.last()) {
+        (Some(a), Some(b)) => b.ts.saturating_sub(a.ts),
+        _ => 0,
+    };
+    if two_sided == 0.0 {
+        return LiquidityStats {
+            span_secs,
+            ..Default::default()
+        };
+    }
+    LiquidityStats {
+        span_secs,
+        uptime: two_sided / span_secs as f64,
+        avg_spread: spread_sum / two_sided,
+        avg_top_bid_size: bid_sum / two_sided,
+        avg_top_ask_size: ask_sum / two_sided,
+    }
+}
+
+// mid candles at MOVE_BASE_TF over the whole file, for forward searches
+// (the live aggs only reach sim_ts). Events before the book is two-sided are skipped.
+const MOVE_BASE_TF: u64 = 30;
+
+fn full_mid_candles(events: &[OrderbookCsvEvent]) -> Vec<Candle> {
+    let mut replay = new_replay();
+    for ev in events {
+        replay.apply_row(ev.ts, &ev.msg_type, ev.side, ev.price, ev.size, None);
+    }
+    resample(replay.candles(), MOVE_BASE_TF)
+}
+
 // main replay app
 struct ReplayApp {
     // data
     ob_events: Vec<OrderbookCsvEvent>,
     tr_events: Vec<TradeCsvEvent>,
+    liquidity: LiquidityStats,
+    full_candles: Vec<Candle>,
+    // |close / open - 1| in percent that counts as a large move
+    move_threshold_pct: f64,
 
     // replay time
     has_data: bool,
@@ -659,13 +843,17 @@ struct ReplayApp {
     end_ts: u64,
     sim_ts: u64,
     speed: f64,
+    pace: ReplayPace,
+    // wall seconds owed to event-paced stepping
+    pace_acc: f64,
     paused: bool,
     wall_last: Instant,
+    repaint: RepaintThrottle,
     ob_index: usize,
     tr_index: usize,
 
     // book + candles + trading sim
-    book: LiveBook,
+    replay: ReplayEngine<'static>,
     last_price: f64,
 
     tf_30s: CandleAgg,
@@ -674,12 +862,18 @@ struct ReplayApp {
     tf_5m: CandleAgg,
     selected_tf: u64,
     chart: ChartSettings,
+    // rebuilt only when indicators_dirty is set (candles, TF or indicator
+    // settings changed) instead of on every frame
+    indicators: Option<Indicators>,
+    indicators_dirty: bool,
     trading: TradingState,
 
     // UI state
     selected_tab: Tab,
     time_mode: TimeDisplayMode,
     current_theme: ThemeKind,
+    candle_body: CandleBody,
+    candle_color_basis: CandleColorBasis,
     candles_bounds: Option<PlotBounds>,
 
     // last trade for display
@@ -692,21 +886,28 @@ struct ReplayApp {
 
     // snapshot status
     snapshot_status: Option<String>,
+    report_status: Option<String>,
+    trades_export_status: Option<String>,
 
-    rng: StdRng,
+    // review bookmarks
+    bookmarks: Vec<Bookmark>,
+    bookmark_label: String,
+    bookmark_status: Option<String>,
+
+    // no-data fallback; --seed / --walk-vol / --walk-drift
+    walk: RandomWalk,
 }
 
 impl ReplayApp {
     fn new() -> Self {
-        let ob_events = load_orderbook_events("data/orderbook_ethusd.csv");
-        let tr_events = load_trade_events("data/trades.csv");
+        let ob_events = load_orderbook_events(&data_dir().join("orderbook_ethusd.csv"));
+        let tr_events = load_trade_events(&data_dir().join("trades.csv"));
 
         let has_data = !ob_events.is_empty();
+        let liquidity = session_liquidity_stats(&ob_events);
+        let full_candles = full_mid_candles(&ob_events);
         let (start_ts, end_ts) = if has_data {
-            (
-                ob_events.first().unwrap().ts,
-                ob_events.last().unwrap().ts,
-            )
+            (ob_events.first().unwrap().ts, ob_events.last().unwrap().ts)
         } else {
             (0, 0)
         };
@@ -714,16 +915,22 @@ impl ReplayApp {
         Self {
             ob_events,
             tr_events,
+            liquidity,
+            full_candles,
+            move_threshold_pct: 0.5,
             has_data,
             start_ts,
             end_ts,
             sim_ts: start_ts,
             speed: 1.0,
+            pace: ReplayPace::RealTime,
+            pace_acc: 0.0,
             paused: false,
             wall_last: Instant::now(),
+            repaint: RepaintThrottle::new(Duration::from_millis(33)).with_args(env::args().skip(1)),
             ob_index: 0,
             tr_index: 0,
-            book: LiveBook::default(),
+            replay: new_replay(),
             last_price: 3000.0,
             tf_30s: CandleAgg::new(30),
             tf_1m: CandleAgg::new(60),
@@ -735,18 +942,33 @@ impl ReplayApp {
                 y_max: 3050.0,
                 show_candles: 160,
                 auto_scale: true,
+                show_divergences: true,
+                div_lookback: 60,
+                oscillator: OscillatorKind::Rsi,
+                osc_period: 14,
+                show_pnl_hud: true,
+                pnl_hud_corner: HudCorner::TopLeft,
             },
+            indicators: None,
+            indicators_dirty: true,
             trading: TradingState::new(),
             selected_tab: Tab::Candles,
             time_mode: TimeDisplayMode::Local,
             current_theme: ThemeKind::ClassicDark,
+            candle_body: CandleBody::Filled,
+            candle_color_basis: CandleColorBasis::BodyOpenClose,
             candles_bounds: None,
             last_trade: None,
             trades_window_secs: 120,
             events_window_secs: 120,
             max_events_rows: 80,
             snapshot_status: None,
-            rng: StdRng::seed_from_u64(42),
+            report_status: None,
+            trades_export_status: None,
+            bookmarks: load_bookmarks(&bookmarks_path()),
+            bookmark_label: String::new(),
+            bookmark_status: None,
+            walk: RandomWalk::new(WalkParams::default().with_args(env::args().skip(1))),
         }
     }
 
@@ -774,17 +996,32 @@ impl ReplayApp {
         ctx.set_style(style);
     }
 
+    // the sim's open position at the replay price, for the chart HUD
+    fn sim_pnl_hud(&self) -> Option<PnlHud> {
+        let t = &self.trading;
+        if !t.is_open() {
+            return None;
+        }
+        Some(PnlHud {
+            long: t.side == PositionSide::Long,
+            size: t.position,
+            entry: t.entry_price?,
+            mark: self.last_price,
+            liquidation: t.liquidation_price(self.last_price),
+        })
+    }
+
     fn format_ts(&self, ts: u64) -> String {
         format_ts_common(self.time_mode, ts)
     }
 
     fn current_series_for_tf(&self, tf: u64) -> Vec<Candle> {
         match tf {
-            30 => self.tf_30s.get_series(),
-            60 => self.tf_1m.get_series(),
-            180 => self.tf_3m.get_series(),
-            300 => self.tf_5m.get_series(),
-            _ => self.tf_1m.get_series(),
+            30 => self.tf_30s.series().to_vec(),
+            60 => self.tf_1m.series().to_vec(),
+            180 => self.tf_3m.series().to_vec(),
+            300 => self.tf_5m.series().to_vec(),
+            _ => self.tf_1m.series().to_vec(),
         }
     }
 
@@ -795,10 +1032,30 @@ impl ReplayApp {
     fn switch_tf(&mut self, new_tf: u64) {
         self.selected_tf = new_tf;
         self.candles_bounds = None;
+        self.mark_indicators_dirty();
+    }
+
+    fn mark_indicators_dirty(&mut self) {
+        self.indicators_dirty = true;
+    }
+
+    fn recompute_indicators_if_dirty(&mut self, series: &[Candle]) {
+        if !self.indicators_dirty {
+            return;
+        }
+        let closes: Vec<f64> = series.iter().map(|c| c.close).collect();
+        let rsi = compute_rsi(&closes, self.chart.osc_period);
+        let divergences = if self.chart.show_divergences {
+            rsi_divergences(series, &rsi, self.chart.div_lookback)
+        } else {
+            Vec::new()
+        };
+        self.indicators = Some(Indicators { rsi, divergences });
+        self.indicators_dirty = false;
     }
 
     fn reset_replay(&mut self) {
-        self.book = LiveBook::default();
+        self.replay = new_replay();
         self.last_price = 3000.0;
         self.tf_30s = CandleAgg::new(30);
         self.tf_1m = CandleAgg::new(60);
@@ -811,6 +1068,34 @@ impl ReplayApp {
         self.last_trade = None;
         self.wall_last = Instant::now();
         self.candles_bounds = None;
+        self.mark_indicators_dirty();
+    }
+
+    // ob_events[i] into the book, then the mid into the TF candles
+    fn apply_book_event(&mut self, i: usize) {
+        let ev = &self.ob_events[i];
+        self.replay
+            .apply_row(ev.ts, &ev.msg_type, ev.side, ev.price, ev.size, None);
+
+        let (bid, ask) = self.best_bid_ask();
+        if let (Some((bp, _)), Some((ap, _))) = (bid, ask) {
+            let mid = (bp + ap) * 0.5;
+            if mid > 0.0 {
+                self.last_price = mid;
+            }
+        }
+
+        // use abs(size) as volume pulse
+        let volume = ev.size.abs().max(0.0);
+        self.tf_30s.update(ev.ts, self.last_price, volume);
+        self.tf_1m.update(ev.ts, self.last_price, volume);
+        self.tf_3m.update(ev.ts, self.last_price, volume);
+        self.tf_5m.update(ev.ts, self.last_price, volume);
+        self.mark_indicators_dirty();
+    }
+
+    fn best_bid_ask(&self) -> (Option<(f64, f64)>, Option<(f64, f64)>) {
+        (self.replay.best_bid(), self.replay.best_ask())
     }
 
     fn seek_to(&mut self, ts_target: u64) {
@@ -820,49 +1105,180 @@ impl ReplayApp {
         let target = ts_target.clamp(self.start_ts, self.end_ts);
         self.reset_replay();
 
-        while self.ob_index < self.ob_events.len()
-            && self.ob_events[self.ob_index].ts <= target
-        {
-            let ev = &self.ob_events[self.ob_index];
+        // events are sorted by ts: find both ends by binary search, then only
+        // fold the book slice (book state still has to be rebuilt from the start)
+        let ob_end = self.ob_events.partition_point(|e| e.ts <= target);
+        for i in 0..ob_end {
+            self.apply_book_event(i);
+        }
+        self.ob_index = ob_end;
 
-            self.book
-                .apply_level(ev.side.as_str(), ev.price, ev.size);
+        self.tr_index = self.tr_events.partition_point(|t| t.ts <= target);
+        self.last_trade = self
+            .tr_index
+            .checked_sub(1)
+            .map(|i| self.tr_events[i].clone());
 
-            let (bid, ask) = self.book.best_bid_ask();
-            if let (Some((bp, _)), Some((ap, _))) = (bid, ask) {
-                let mid = (bp + ap) * 0.5;
-                if mid > 0.0 {
-                    self.last_price = mid;
-                }
-            }
+        self.sim_ts = target;
+    }
 
-            let volume = ev.size.abs().max(0.0);
-            self.tf_30s.update(ev.ts, self.last_price, volume);
-            self.tf_1m.update(ev.ts, self.last_price, volume);
-            self.tf_3m.update(ev.ts, self.last_price, volume);
-            self.tf_5m.update(ev.ts, self.last_price, volume);
+    fn persist_bookmarks(&mut self) {
+        self.bookmark_status = save_bookmarks(&bookmarks_path(), &self.bookmarks)
+            .err()
+            .map(|e| format!("bookmarks: save failed: {e}"));
+    }
 
-            self.ob_index += 1;
+    // one bookmark per ts: re-adding at the same time just relabels it
+    fn add_bookmark(&mut self) {
+        let label = match self.bookmark_label.trim() {
+            "" => self.format_ts(self.sim_ts),
+            l => l.to_string(),
+        };
+        let ts = self.sim_ts;
+        match self.bookmarks.binary_search_by_key(&ts, |b| b.ts) {
+            Ok(i) => self.bookmarks[i].label = label,
+            Err(i) => self.bookmarks.insert(i, Bookmark { ts, label }),
+        }
+        self.bookmark_label.clear();
+        self.persist_bookmarks();
+    }
+
+    fn remove_bookmark(&mut self, idx: usize) {
+        if idx < self.bookmarks.len() {
+            self.bookmarks.remove(idx);
+            self.persist_bookmarks();
         }
+    }
 
-        while self.tr_index < self.tr_events.len()
-            && self.tr_events[self.tr_index].ts <= target
+    fn seek_next_bookmark(&mut self) {
+        if let Some(b) = self.bookmarks.iter().find(|b| b.ts > self.sim_ts) {
+            let ts = b.ts;
+            self.seek_to(ts);
+        }
+    }
+
+    fn next_trade_ts(&self) -> Option<u64> {
+        let i = self.tr_events.partition_point(|t| t.ts <= self.sim_ts);
+        self.tr_events.get(i).map(|t| t.ts)
+    }
+
+    // close ts of the next candle at the chart TF, opening after sim_ts, whose
+    // open-to-close return is at least move_threshold_pct
+    fn next_large_move_ts(&self) -> Option<u64> {
+        let tf = self.selected_tf;
+        let threshold = self.move_threshold_pct / 100.0;
+        resample(&self.full_candles, tf)
+            .into_iter()
+            .find(|c| {
+                c.t > self.sim_ts && c.open > 0.0 && (c.close / c.open - 1.0).abs() >= threshold
+            })
+            .map(|c| (c.t + tf - 1).min(self.end_ts))
+    }
+
+    fn ui_jumps(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Jump");
+        let next_trade = self.next_trade_ts();
+        if ui
+            .add_enabled(next_trade.is_some(), egui::Button::new("Next trade ▶"))
+            .clicked()
         {
-            self.last_trade = Some(self.tr_events[self.tr_index].clone());
-            self.tr_index += 1;
+            if let Some(ts) = next_trade {
+                self.seek_to(ts);
+            }
         }
 
-        self.sim_ts = target;
+        let next_move = self.next_large_move_ts();
+        if ui
+            .add_enabled(next_move.is_some(), egui::Button::new("Next large move ▶"))
+            .on_hover_text("Next candle at the chart TF with |return| over the threshold")
+            .clicked()
+        {
+            if let Some(ts) = next_move {
+                self.seek_to(ts);
+            }
+        }
+        ui.add(
+            egui::Slider::new(&mut self.move_threshold_pct, 0.05..=5.0)
+                .logarithmic(true)
+                .suffix("%")
+                .text("move"),
+        );
+    }
+
+    fn seek_prev_bookmark(&mut self) {
+        if let Some(b) = self.bookmarks.iter().rev().find(|b| b.ts < self.sim_ts) {
+            let ts = b.ts;
+            self.seek_to(ts);
+        }
+    }
+
+    fn ui_bookmarks(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Bookmarks");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.bookmark_label)
+                    .hint_text("label")
+                    .desired_width(110.0),
+            );
+            if ui.button("Add @ t").clicked() {
+                self.add_bookmark();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("◀ Prev").clicked() {
+                self.seek_prev_bookmark();
+            }
+            if ui.button("Next ▶").clicked() {
+                self.seek_next_bookmark();
+            }
+        });
+        if let Some(msg) = &self.bookmark_status {
+            ui.small(msg);
+        }
+        ui.separator();
+
+        if self.bookmarks.is_empty() {
+            ui.weak("No bookmarks yet.");
+            return;
+        }
+
+        let mut seek = None;
+        let mut remove = None;
+        egui::ScrollArea::vertical()
+            .id_source("bookmarks_scroll_replay")
+            .show(ui, |ui| {
+                for (i, b) in self.bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let at = b.ts == self.sim_ts;
+                        if ui
+                            .selectable_label(at, &b.label)
+                            .on_hover_text(self.format_ts(b.ts))
+                            .clicked()
+                        {
+                            seek = Some(b.ts);
+                        }
+                        if ui.small_button("✕").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+            });
+        if let Some(ts) = seek {
+            self.seek_to(ts);
+        }
+        if let Some(i) = remove {
+            self.remove_bookmark(i);
+        }
     }
 
     fn save_snapshot(&mut self) {
-        if let Err(e) = fs::create_dir_all("data") {
+        if let Err(e) = fs::create_dir_all(data_dir()) {
             self.snapshot_status = Some(format!("snapshot: failed to create data dir: {e}"));
             return;
         }
 
-        let path = format!("data/replay_snapshot_{}.txt", self.sim_ts);
-        let (bb, ba) = self.book.best_bid_ask();
+        let path = data_dir().join(format!("replay_snapshot_{}.txt", self.sim_ts));
+        let (bb, ba) = self.best_bid_ask();
 
         let mut out = String::new();
         let _ = writeln!(&mut out, "sim_ts: {}", self.sim_ts);
@@ -893,10 +1309,10 @@ impl ReplayApp {
 
         let mut total_bid_size = 0.0;
         let mut total_ask_size = 0.0;
-        for s in self.book.bids.values() {
+        for s in self.replay.bids().values() {
             total_bid_size += *s;
         }
-        for s in self.book.asks.values() {
+        for s in self.replay.asks().values() {
             total_ask_size += *s;
         }
         let _ = writeln!(
@@ -927,20 +1343,20 @@ impl ReplayApp {
         }
 
         let _ = writeln!(&mut out, "\n[BIDS]");
-        for (k, s) in self.book.bids.iter().rev() {
+        for (k, s) in self.replay.bids().iter().rev() {
             let p = key_to_price(*k);
             let _ = writeln!(&mut out, "{:.6}, {:.8}", p, s);
         }
 
         let _ = writeln!(&mut out, "\n[ASKS]");
-        for (k, s) in self.book.asks.iter() {
+        for (k, s) in self.replay.asks().iter() {
             let p = key_to_price(*k);
             let _ = writeln!(&mut out, "{:.6}, {:.8}", p, s);
         }
 
         match fs::write(&path, out) {
             Ok(_) => {
-                self.snapshot_status = Some(format!("snapshot saved to {}", path));
+                self.snapshot_status = Some(format!("snapshot saved to {}", path.display()));
             }
             Err(e) => {
                 self.snapshot_status = Some(format!("snapshot write error: {e}"));
@@ -948,16 +1364,129 @@ impl ReplayApp {
         }
     }
 
+    // trades the Data tab shows: the last `trades_window_secs` up to sim_ts
+    fn trades_in_window(&self) -> Vec<TradeCsvEvent> {
+        let window_secs = self.trades_window_secs.max(10);
+        let lower = self.sim_ts.saturating_sub(window_secs);
+
+        // tr_events is sorted on load
+        let start = self.tr_events.partition_point(|tr| tr.ts < lower);
+        let end = self.tr_events.partition_point(|tr| tr.ts <= self.sim_ts);
+        self.tr_events[start..end].to_vec()
+    }
+
+    fn export_trades_window(&mut self) {
+        if let Err(e) = fs::create_dir_all(data_dir()) {
+            self.trades_export_status = Some(format!("export: failed to create data dir: {e}"));
+            return;
+        }
+
+        let rows = self.trades_in_window();
+        let path = data_dir().join(format!(
+            "replay_trades_{}_{}s.csv",
+            self.sim_ts,
+            self.trades_window_secs.max(10)
+        ));
+
+        let mut out = String::new();
+        let _ = writeln!(&mut out, "ts,display_time,ticker,side,size");
+        for tr in &rows {
+            let _ = writeln!(
+                &mut out,
+                "{},{},{},{},{:.8}",
+                tr.ts,
+                self.format_ts(tr.ts),
+                tr.ticker,
+                tr.side,
+                tr.size
+            );
+        }
+
+        self.trades_export_status = Some(match fs::write(&path, out) {
+            Ok(_) => format!("{} trades saved to {}", rows.len(), path.display()),
+            Err(e) => format!("trades export error: {e}"),
+        });
+    }
+
+    fn save_report(&mut self) {
+        if let Err(e) = fs::create_dir_all(data_dir()) {
+            self.report_status = Some(format!("report: failed to create data dir: {e}"));
+            return;
+        }
+
+        let path = data_dir().join(format!("replay_report_{}.csv", self.sim_ts));
+        let t = &self.trading;
+        let (net, win_rate, max_dd) = t.log_totals();
+
+        let mut out = String::new();
+        let _ = writeln!(&mut out, "# replay sim report");
+        let _ = writeln!(&mut out, "# sim_ts: {}", self.sim_ts);
+        let _ = writeln!(&mut out, "# leverage: {:.2}", t.leverage);
+        let _ = writeln!(&mut out, "# maint_rate: {:.4}", t.maint_rate);
+        let _ = writeln!(&mut out, "# taker_fee: {:.5}", t.taker_fee);
+        let _ = writeln!(&mut out, "# maker_fee: {:.5}", t.maker_fee);
+        let fills = if t.fill_as_maker {
+            "maker (manual/tp), taker (sl/liquidation)"
+        } else {
+            "taker"
+        };
+        let _ = writeln!(&mut out, "# fills: {fills}");
+        let _ = writeln!(
+            &mut out,
+            "open_ts,close_ts,side,size,entry_price,exit_price,fees,pnl,exit_reason"
+        );
+        for e in &t.trade_log {
+            let _ = writeln!(
+                &mut out,
+                "{},{},{},{:.8},{:.6},{:.6},{:.6},{:.6},{}",
+                e.open_ts,
+                e.close_ts,
+                e.side.label(),
+                e.size,
+                e.entry_price,
+                e.exit_price,
+                e.fees,
+                e.pnl,
+                e.reason.label()
+            );
+        }
+        let _ = writeln!(&mut out, "# trades: {}", t.trade_log.len());
+        let _ = writeln!(&mut out, "# net_pnl: {:.6}", net);
+        let _ = writeln!(&mut out, "# fees_paid: {:.6}", t.fees_paid);
+        let _ = writeln!(&mut out, "# win_rate: {:.2}%", win_rate * 100.0);
+        let _ = writeln!(&mut out, "# max_drawdown: {:.6}", max_dd);
+
+        match fs::write(&path, out) {
+            Ok(()) => self.report_status = Some(format!("report saved: {}", path.display())),
+            Err(e) => self.report_status = Some(format!("report: write failed: {e}")),
+        }
+    }
+    // first book or trade event strictly after `ts` (end_ts when none is left);
+    // both event lists are sorted by ts
+    fn next_event_ts(&self, ts: u64) -> u64 {
+        let ob = &self.ob_events[self.ob_index..];
+        let tr = &self.tr_events[self.tr_index..];
+        let next_ob = ob.get(ob.partition_point(|e| e.ts <= ts)).map(|e| e.ts);
+        let next_tr = tr.get(tr.partition_point(|e| e.ts <= ts)).map(|e| e.ts);
+        next_ob
+            .into_iter()
+            .chain(next_tr)
+            .min()
+            .unwrap_or(self.end_ts)
+            .min(self.end_ts)
+    }
+
     fn step_sim(&mut self) {
         if !self.has_data {
             // fallback random just to keep candles alive
             let ts = self.start_ts.max(self.sim_ts).saturating_add(1);
-            let step: f64 = self.rng.random_range(-2.0..2.0);
+            let step = self.walk.step();
             self.last_price = (self.last_price + step).clamp(2950.0, 3050.0);
             self.tf_30s.update(ts, self.last_price, 1.0);
             self.tf_1m.update(ts, self.last_price, 1.0);
             self.tf_3m.update(ts, self.last_price, 1.0);
             self.tf_5m.update(ts, self.last_price, 1.0);
+            self.mark_indicators_dirty();
             self.sim_ts = ts;
             return;
         }
@@ -970,36 +1499,33 @@ impl ReplayApp {
         let dt = now.duration_since(self.wall_last).as_secs_f64();
         self.wall_last = now;
 
-        let sim_advance = (dt * self.speed).max(0.0);
-        let new_sim_ts = ((self.sim_ts as f64) + sim_advance).min(self.end_ts as f64) as u64;
-        self.sim_ts = new_sim_ts;
+        self.sim_ts = match self.pace {
+            ReplayPace::RealTime => {
+                let sim_advance = (dt * self.speed).max(0.0);
+                ((self.sim_ts as f64) + sim_advance).min(self.end_ts as f64) as u64
+            }
+            ReplayPace::EventPaced => {
+                let step = EVENT_PACE_SECS / self.speed.max(1e-6);
+                self.pace_acc += dt;
+                let mut ts = self.sim_ts;
+                let mut steps = 0;
+                while self.pace_acc >= step && steps < EVENT_PACE_MAX_STEPS {
+                    self.pace_acc -= step;
+                    ts = self.next_event_ts(ts);
+                    steps += 1;
+                }
+                if steps == EVENT_PACE_MAX_STEPS {
+                    self.pace_acc = 0.0;
+                }
+                ts
+            }
+        };
 
         // apply orderbook events up to sim_ts
         while self.ob_index < self.ob_events.len()
             && self.ob_events[self.ob_index].ts <= self.sim_ts
         {
-            let ev = &self.ob_events[self.ob_index];
-
-            self.book
-                .apply_level(ev.side.as_str(), ev.price, ev.size);
-
-            // recompute mid
-            let (bid, ask) = self.book.best_bid_ask();
-            if let (Some((bp, _)), Some((ap, _))) = (bid, ask) {
-                let mid = (bp + ap) * 0.5;
-                if mid > 0.0 {
-                    self.last_price = mid;
-                }
-            }
-
-            // use abs(size) as volume pulse
-            let volume = ev.size.abs().max(0.0);
-
-            self.tf_30s.update(ev.ts, self.last_price, volume);
-            self.tf_1m.update(ev.ts, self.last_price, volume);
-            self.tf_3m.update(ev.ts, self.last_price, volume);
-            self.tf_5m.update(ev.ts, self.last_price, volume);
-
+            self.apply_book_event(self.ob_index);
             self.ob_index += 1;
         }
 
@@ -1012,8 +1538,11 @@ impl ReplayApp {
         }
 
         // update trading sim
-        self.trading.check_tp_sl(self.last_price);
+        self.trading.accrue_funding_at(self.last_price, self.sim_ts);
+        self.trading
+            .check_tp_sl(&depth(&self.replay), self.last_price, self.sim_ts);
         self.trading.check_liquidation(self.last_price, self.sim_ts);
+        self.trading.record_equity(self.sim_ts, self.last_price);
     }
 
     // UI
@@ -1021,7 +1550,11 @@ impl ReplayApp {
     fn ui_top_bar(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.selectable_value(&mut self.selected_tab, Tab::Orderbook, "Orderbook + Depth");
-            ui.selectable_value(&mut self.selected_tab, Tab::Candles, "Candles + RSI");
+            ui.selectable_value(
+                &mut self.selected_tab,
+                Tab::Candles,
+                "Candles + Oscillators",
+            );
             ui.selectable_value(&mut self.selected_tab, Tab::Data, "Data");
             ui.separator();
 
@@ -1058,10 +1591,27 @@ impl ReplayApp {
                         ui.selectable_value(&mut self.current_theme, *theme, theme.label());
                     }
                 });
+            egui::ComboBox::from_id_source("candle_body_combo_replay")
+                .selected_text(self.candle_body.label())
+                .show_ui(ui, |ui| {
+                    for body in CandleBody::all() {
+                        ui.selectable_value(&mut self.candle_body, *body, body.label());
+                    }
+                });
+            egui::ComboBox::from_id_source("candle_color_basis_combo_replay")
+                .selected_text(self.candle_color_basis.label())
+                .show_ui(ui, |ui| {
+                    for basis in CandleColorBasis::all() {
+                        ui.selectable_value(&mut self.candle_color_basis, *basis, basis.label());
+                    }
+                });
 
             ui.separator();
             ui.label("Replay:");
-            if ui.button(if self.paused { "Play" } else { "Pause" }).clicked() {
+            if ui
+                .button(if self.paused { "Play" } else { "Pause" })
+                .clicked()
+            {
                 self.paused = !self.paused;
                 self.wall_last = Instant::now();
             }
@@ -1074,6 +1624,15 @@ impl ReplayApp {
                     .logarithmic(true)
                     .text("speed x"),
             );
+            for pace in [ReplayPace::RealTime, ReplayPace::EventPaced] {
+                if ui
+                    .selectable_value(&mut self.pace, pace, pace.label())
+                    .on_hover_text("Event-paced: one step per event ts, quiet gaps are skipped")
+                    .clicked()
+                {
+                    self.pace_acc = 0.0;
+                }
+            }
 
             ui.separator();
             ui.label(format!(
@@ -1137,6 +1696,61 @@ impl ReplayApp {
 
             ui.separator();
             ui.label(format!("Mark: {:.2}", self.last_price));
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.trading.accrue_funding, "Accrue funding");
+                let mut rate_pct = self.trading.funding_rate_1h * 100.0;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut rate_pct)
+                            .speed(0.0001)
+                            .clamp_range(-1.0..=1.0)
+                            .suffix(" % / 1h"),
+                    )
+                    .changed()
+                {
+                    self.trading.funding_rate_1h = rate_pct / 100.0;
+                }
+                ui.label(format!("paid: {:.4}", self.trading.funding_paid));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Fees:");
+                for (label, rate) in [
+                    ("taker", &mut self.trading.taker_fee),
+                    ("maker", &mut self.trading.maker_fee),
+                ] {
+                    let mut pct = *rate * 100.0;
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut pct)
+                                .speed(0.001)
+                                .clamp_range(-0.1..=1.0)
+                                .prefix(format!("{label} "))
+                                .suffix(" %"),
+                        )
+                        .changed()
+                    {
+                        *rate = pct / 100.0;
+                    }
+                }
+                ui.checkbox(&mut self.trading.fill_as_maker, "Fill as maker");
+                ui.label(format!("paid: {:.4}", self.trading.fees_paid));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Maintenance margin:");
+                let mut pct = self.trading.maint_rate * 100.0;
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut pct)
+                            .speed(0.01)
+                            .clamp_range(0.0..=50.0)
+                            .suffix(" %"),
+                    )
+                    .changed()
+                {
+                    self.trading.maint_rate = pct / 100.0;
+                }
+            });
             ui.separator();
 
             ui.horizontal(|ui| {
@@ -1151,10 +1765,7 @@ impl ReplayApp {
                 }
             });
 
-            ui.add(
-                egui::Slider::new(&mut self.trading.leverage, 1.0..=50.0)
-                    .text("Leverage (x)"),
-            );
+            ui.add(egui::Slider::new(&mut self.trading.leverage, 1.0..=50.0).text("Leverage (x)"));
 
             let max_units = self.trading.max_position_units(self.last_price);
             if self.trading.position > max_units {
@@ -1162,10 +1773,8 @@ impl ReplayApp {
             }
 
             ui.add(
-                egui::Slider::new(&mut self.trading.position, 0.0..=max_units).text(format!(
-                    "Position (units, max {:.4})",
-                    max_units
-                )),
+                egui::Slider::new(&mut self.trading.position, 0.0..=max_units)
+                    .text(format!("Position (units, max {:.4})", max_units)),
             );
 
             ui.separator();
@@ -1173,11 +1782,36 @@ impl ReplayApp {
             ui.horizontal(|ui| {
                 if ui.button("Open / Close (sim)").clicked() {
                     if self.trading.is_open() {
-                        self.trading.close_at(self.last_price);
+                        self.trading.close_at(
+                            &depth(&self.replay),
+                            self.last_price,
+                            self.sim_ts,
+                            ExitReason::Manual,
+                        );
                     } else {
-                        self.trading.open_at(self.last_price);
+                        self.trading
+                            .open_at(&depth(&self.replay), self.last_price, self.sim_ts);
                     }
                 }
+                let quarter = self.trading.position * 0.25;
+                if ui
+                    .add_enabled(self.trading.is_open(), egui::Button::new("Add 25%"))
+                    .clicked()
+                {
+                    self.trading
+                        .add_to_position(&depth(&self.replay), quarter, self.last_price);
+                }
+                if ui
+                    .add_enabled(self.trading.is_open(), egui::Button::new("Reduce 25%"))
+                    .clicked()
+                {
+                    self.trading.reduce_position(
+                        &depth(&self.replay),
+                        quarter,
+                        self.last_price,
+                        self.sim_ts,
+                    );
+                }
                 if ui.button("TP +1").clicked() {
                     self.trading.bump_tp(self.last_price, 1.0);
                 }
@@ -1224,6 +1858,9 @@ impl ReplayApp {
                     .map(|p| format!("{:.2}", p))
                     .unwrap_or("-".into()),
             ));
+            if let Some(note) = &self.trading.fill_note {
+                ui.label(format!("Last fill: {note}"));
+            }
 
             ui.separator();
             ui.heading("Replay fills (from trades.csv)");
@@ -1252,7 +1889,10 @@ impl ReplayApp {
         if !self.has_data {
             ui.colored_label(
                 pal.down,
-                "No orderbook data found in data/orderbook_ethusd.csv. Run gui_app27 first.",
+                format!(
+                    "No orderbook data found in {}. Run gui_app27 first.",
+                    data_dir().join("orderbook_ethusd.csv").display()
+                ),
             );
             return;
         }
@@ -1268,14 +1908,14 @@ impl ReplayApp {
                     let mut ask_points = Vec::new();
 
                     let mut cum = 0.0;
-                    for (k, s) in self.book.bids.iter().rev() {
+                    for (k, s) in self.replay.bids().iter().rev() {
                         let p = key_to_price(*k);
                         cum += *s;
                         bid_points.push((p, cum));
                     }
 
                     cum = 0.0;
-                    for (k, s) in self.book.asks.iter() {
+                    for (k, s) in self.replay.asks().iter() {
                         let p = key_to_price(*k);
                         cum += *s;
                         ask_points.push((p, cum));
@@ -1294,8 +1934,7 @@ impl ReplayApp {
                                     .map(|(x, y)| [*x, *y])
                                     .collect::<Vec<_>>()
                                     .into();
-                                plot_ui
-                                    .line(Line::new(pts).name("Bids").color(pal.depth_bid));
+                                plot_ui.line(Line::new(pts).name("Bids").color(pal.depth_bid));
                             }
                             if !ask_points.is_empty() {
                                 let pts: PlotPoints = ask_points
@@ -1303,8 +1942,7 @@ impl ReplayApp {
                                     .map(|(x, y)| [*x, *y])
                                     .collect::<Vec<_>>()
                                     .into();
-                                plot_ui
-                                    .line(Line::new(pts).name("Asks").color(pal.depth_ask));
+                                plot_ui.line(Line::new(pts).name("Asks").color(pal.depth_ask));
                             }
                         });
                 });
@@ -1317,34 +1955,36 @@ impl ReplayApp {
 
                     ui.columns(2, |cols| {
                         cols[0].label("Bids");
-                        egui::Grid::new("bids_grid_replay")
-                            .striped(true)
-                            .show(&mut cols[0], |ui| {
+                        egui::Grid::new("bids_grid_replay").striped(true).show(
+                            &mut cols[0],
+                            |ui| {
                                 ui.label("Price");
                                 ui.label("Size");
                                 ui.end_row();
-                                for (k, s) in self.book.bids.iter().rev().take(15) {
+                                for (k, s) in self.replay.bids().iter().rev().take(15) {
                                     let p = key_to_price(*k);
                                     ui.label(format!("{:>8.2}", p));
                                     ui.label(format!("{:>6.4}", s));
                                     ui.end_row();
                                 }
-                            });
+                            },
+                        );
 
                         cols[1].label("Asks");
-                        egui::Grid::new("asks_grid_replay")
-                            .striped(true)
-                            .show(&mut cols[1], |ui| {
+                        egui::Grid::new("asks_grid_replay").striped(true).show(
+                            &mut cols[1],
+                            |ui| {
                                 ui.label("Price");
                                 ui.label("Size");
                                 ui.end_row();
-                                for (k, s) in self.book.asks.iter().take(15) {
+                                for (k, s) in self.replay.asks().iter().take(15) {
                                     let p = key_to_price(*k);
                                     ui.label(format!("{:>8.2}", p));
                                     ui.label(format!("{:>6.4}", s));
                                     ui.end_row();
                                 }
-                            });
+                            },
+                        );
                     });
 
                     ui.separator();
@@ -1357,16 +1997,53 @@ impl ReplayApp {
     fn ui_candles(&mut self, ui: &mut egui::Ui) {
         let series_vec = self.current_series();
         if series_vec.is_empty() {
-            ui.label("No candles yet (need data/orderbook_ethusd.csv).");
+            ui.label(format!(
+                "No candles yet (need {}).",
+                data_dir().join("orderbook_ethusd.csv").display()
+            ));
             return;
         }
 
         ui.horizontal(|ui| {
             ui.label("History (candles):");
-            ui.add(
-                egui::Slider::new(&mut self.chart.show_candles, 20..=600)
-                    .logarithmic(true),
-            );
+            ui.add(egui::Slider::new(&mut self.chart.show_candles, 20..=600).logarithmic(true));
+
+            ui.separator();
+            ui.label("Oscillator:");
+            egui::ComboBox::from_id_source("osc_combo_replay")
+                .selected_text(self.chart.oscillator.label())
+                .show_ui(ui, |ui| {
+                    for kind in OscillatorKind::all() {
+                        ui.selectable_value(&mut self.chart.oscillator, *kind, kind.label());
+                    }
+                });
+            ui.label("Period:");
+            let period = ui.add(egui::Slider::new(&mut self.chart.osc_period, 2..=100));
+
+            ui.separator();
+            let show = ui.checkbox(&mut self.chart.show_divergences, "RSI divergences");
+            let mut lookback_changed = false;
+            if self.chart.show_divergences {
+                ui.label("Lookback:");
+                lookback_changed = ui
+                    .add(egui::Slider::new(&mut self.chart.div_lookback, 10..=500))
+                    .changed();
+            }
+            if period.changed() || show.changed() || lookback_changed {
+                self.indicators_dirty = true;
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.chart.show_pnl_hud, "PnL HUD");
+            if self.chart.show_pnl_hud {
+                egui::ComboBox::from_id_source("pnl_hud_corner_replay")
+                    .selected_text(self.chart.pnl_hud_corner.label())
+                    .show_ui(ui, |ui| {
+                        for c in HudCorner::all() {
+                            ui.selectable_value(&mut self.chart.pnl_hud_corner, *c, c.label());
+                        }
+                    });
+            }
 
             if !self.chart.auto_scale {
                 ui.separator();
@@ -1415,6 +2092,29 @@ impl ReplayApp {
         let tf = self.selected_tf as f64;
         let last = visible.last().unwrap();
         let pal = self.current_palette();
+        let candle_body = self.candle_body;
+        let color_basis = self.candle_color_basis;
+        let hud = self.sim_pnl_hud().filter(|_| self.chart.show_pnl_hud);
+
+        // taken out for the frame so the plots can borrow self mutably
+        self.recompute_indicators_if_dirty(&series_vec);
+        let indicators = self.indicators.take().unwrap_or_default();
+        let rsi_all = &indicators.rsi;
+
+        // only keep divergences whose pivots are both on screen
+        let start_idx = len - window_len;
+        let divergences: Vec<Divergence> = indicators
+            .divergences
+            .iter()
+            .filter(|d| d.first >= start_idx)
+            .copied()
+            .collect();
+        let rsi_value_at = |idx: usize| -> Option<f64> {
+            rsi_all
+                .iter()
+                .find(|(i, _)| *i as usize == idx)
+                .map(|(_, v)| *v)
+        };
 
         // candles plot (filled bodies, tuned wheel, time axis formatter)
         ui.allocate_ui(egui::vec2(avail_w, candles_h), |ui| {
@@ -1424,7 +2124,7 @@ impl ReplayApp {
 
             let mut new_bounds_out: Option<PlotBounds> = None;
 
-            Plot::new("candles_plot_replay")
+            let plot_resp = Plot::new("candles_plot_replay")
                 .height(candles_h)
                 .include_y(y_min)
                 .include_y(y_max)
@@ -1436,35 +2136,28 @@ impl ReplayApp {
                     format_ts_common(mode, mark.value as u64)
                 })
                 .show(ui, |plot_ui| {
-                    for c in visible {
-                        let mid = c.t as f64 + tf * 0.5;
-                        let body_half = tf * 0.35;
-                        let body_left = mid - body_half;
-                        let body_right = mid + body_half;
-
-                        let top = c.open.max(c.close);
-                        let bot = c.open.min(c.close);
-
-                        let color = if c.close >= c.open { pal.up } else { pal.down };
-
-                        // wick
-                        let wick_pts: PlotPoints =
-                            vec![[mid, c.low], [mid, c.high]].into();
-                        plot_ui.line(Line::new(wick_pts).color(color));
-
-                        // filled body
-                        let body_pts: PlotPoints = vec![
-                            [body_left, bot],
-                            [body_left, top],
-                            [body_right, top],
-                            [body_right, bot],
-                        ]
-                        .into();
-                        plot_ui.polygon(
-                            Polygon::new(body_pts)
-                                .fill_color(color)
-                                .stroke(Stroke::new(1.0, color)),
-                        );
+                    let style = CandleRenderStyle {
+                        body_width_factor: 0.7,
+                        wick_width: 1.5,
+                        up_color: pal.up,
+                        down_color: pal.down,
+                        body: candle_body,
+                        color_basis,
+                    };
+                    draw_candles(plot_ui, visible, tf, &style);
+
+                    for d in &divergences {
+                        let a = &series_vec[d.first];
+                        let b = &series_vec[d.second];
+                        let (ya, yb, color, label) = match d.kind {
+                            DivergenceKind::Bullish => (a.low, b.low, pal.up, "bull div"),
+                            DivergenceKind::Bearish => (a.high, b.high, pal.down, "bear div"),
+                        };
+                        let xa = a.t as f64 + tf * 0.5;
+                        let xb = b.t as f64 + tf * 0.5;
+                        let pts: PlotPoints = vec![[xa, ya], [xb, yb]].into();
+                        plot_ui.line(Line::new(pts).color(color).width(2.0));
+                        plot_ui.text(Text::new(PlotPoint::new(xb, yb), label).color(color));
                     }
 
                     let now_x = last.t as f64 + tf;
@@ -1472,6 +2165,15 @@ impl ReplayApp {
                     plot_ui.hline(HLine::new(now_px).name("now_px"));
                     plot_ui.vline(VLine::new(now_x).name("now_t"));
 
+                    for b in &self.bookmarks {
+                        plot_ui.vline(
+                            VLine::new(b.ts as f64)
+                                .color(pal.accent)
+                                .style(LineStyle::dashed_loose())
+                                .name(&b.label),
+                        );
+                    }
+
                     if let Some(entry) = self.trading.entry_price {
                         plot_ui.hline(HLine::new(entry).name("entry"));
                     }
@@ -1520,6 +2222,12 @@ impl ReplayApp {
                     new_bounds_out = Some(bounds);
                 });
 
+            if let Some(hud) = hud {
+                let rows = hud.rows(|p| format!("{p:.2}"), |s| format!("{s:.4}"));
+                let frame = *plot_resp.transform.frame();
+                pnl_hud::paint(ui, frame, self.chart.pnl_hud_corner, &rows);
+            }
+
             self.candles_bounds = new_bounds_out;
         });
 
@@ -1563,47 +2271,50 @@ impl ReplayApp {
 
         ui.separator();
 
-        // RSI plot
+        // oscillator plot
         ui.allocate_ui(egui::vec2(avail_w, rsi_h), |ui| {
-            let closes_all: Vec<f64> = series_vec.iter().map(|c| c.close).collect();
-            let rsi_all = compute_rsi(&closes_all, 14);
-
-            let start_idx = (len - window_len) as usize;
-            let mut rsi_visible = Vec::new();
-            for (idx_f, v) in rsi_all {
-                let idx = idx_f as usize;
-                if idx >= start_idx && idx < series_vec.len() {
-                    let t = series_vec[idx].t as f64;
-                    rsi_visible.push((t, v));
+            let kind = self.chart.oscillator;
+            // keep the visible part and map candle idx -> candle time
+            let lines: Vec<OscLine> = kind
+                .compute(&series_vec, self.chart.osc_period, &pal)
+                .into_iter()
+                .map(|(name, color, values)| {
+                    let values = values
+                        .into_iter()
+                        .filter(|(idx_f, _)| (*idx_f as usize) >= start_idx)
+                        .filter_map(|(idx_f, v)| {
+                            series_vec.get(idx_f as usize).map(|c| (c.t as f64, v))
+                        })
+                        .collect();
+                    (name, color, values)
+                })
+                .collect();
+
+            // divergence segments only make sense on the RSI panel
+            let mut overlays = Vec::new();
+            if kind == OscillatorKind::Rsi {
+                for d in &divergences {
+                    if let (Some(ra), Some(rb)) = (rsi_value_at(d.first), rsi_value_at(d.second)) {
+                        let color = match d.kind {
+                            DivergenceKind::Bullish => pal.up,
+                            DivergenceKind::Bearish => pal.down,
+                        };
+                        let xa = series_vec[d.first].t as f64;
+                        let xb = series_vec[d.second].t as f64;
+                        overlays.push((vec![[xa, ra], [xb, rb]], color));
+                    }
                 }
             }
 
-            let mode = self.time_mode;
-            let pal = self.current_palette();
-            Plot::new("rsi_plot_replay")
-                .height(rsi_h)
-                .include_y(0.0)
-                .include_y(100.0)
-                .allow_drag(true)
-                .allow_zoom(true)
-                .allow_scroll(true)
-                .allow_boxed_zoom(true)
-                .x_axis_formatter(move |mark: GridMark, _range, _transform| {
-                    format_ts_common(mode, mark.value as u64)
-                })
-                .show(ui, |plot_ui| {
-                    if !rsi_visible.is_empty() {
-                        let pts: PlotPoints = rsi_visible
-                            .iter()
-                            .map(|(t, v)| [*t, *v])
-                            .collect::<Vec<_>>()
-                            .into();
-                        plot_ui
-                            .line(Line::new(pts).name("RSI").color(pal.rsi_line).width(2.0));
-                        plot_ui.hline(HLine::new(70.0));
-                        plot_ui.hline(HLine::new(30.0));
-                    }
-                });
+            draw_oscillator(
+                ui,
+                "osc_plot_replay",
+                rsi_h,
+                self.time_mode,
+                kind,
+                &lines,
+                &overlays,
+            );
         });
 
         ui.separator();
@@ -1627,6 +2338,8 @@ impl ReplayApp {
                 self.ui_trading_panel(&mut cols[1]);
             });
         });
+
+        self.indicators = Some(indicators);
     }
 
     fn ui_data(&mut self, ui: &mut egui::Ui) {
@@ -1637,7 +2350,10 @@ impl ReplayApp {
         if !self.has_data {
             ui.colored_label(
                 pal.down,
-                "No data loaded. Need data/orderbook_ethusd.csv (and optionally data/trades.csv).",
+                format!(
+                    "No data loaded. Need orderbook_ethusd.csv (and optionally trades.csv) in {}.",
+                    data_dir().display()
+                ),
             );
             return;
         }
@@ -1655,18 +2371,25 @@ impl ReplayApp {
             if let Some(msg) = &self.snapshot_status {
                 ui.label(msg);
             }
+            ui.separator();
+            if ui.button("Download report").clicked() {
+                self.save_report();
+            }
+            if let Some(msg) = &self.report_status {
+                ui.label(msg);
+            }
         });
 
         ui.separator();
 
         // ---- Orderbook summary ----
-        let (bb, ba) = self.book.best_bid_ask();
+        let (bb, ba) = self.best_bid_ask();
         let mut total_bid_size = 0.0;
         let mut total_ask_size = 0.0;
-        for s in self.book.bids.values() {
+        for s in self.replay.bids().values() {
             total_bid_size += *s;
         }
-        for s in self.book.asks.values() {
+        for s in self.replay.asks().values() {
             total_ask_size += *s;
         }
 
@@ -1677,8 +2400,8 @@ impl ReplayApp {
 
         ui.group(|ui| {
             ui.heading("Orderbook snapshot (ETH-USD)");
-            ui.label(format!("# bid levels: {}", self.book.bids.len()));
-            ui.label(format!("# ask levels: {}", self.book.asks.len()));
+            ui.label(format!("# bid levels: {}", self.replay.bids().len()));
+            ui.label(format!("# ask levels: {}", self.replay.asks().len()));
             if let Some((bp, bs)) = bb {
                 ui.label(format!("Best bid: {:.4} (size {:.6})", bp, bs));
             } else {
@@ -1697,6 +2420,21 @@ impl ReplayApp {
             ));
         });
 
+        ui.group(|ui| {
+            let liq = &self.liquidity;
+            ui.heading("Session liquidity (whole file, time-weighted)");
+            ui.label(format!(
+                "Span: {}s | Two-sided uptime: {:.1}%",
+                liq.span_secs,
+                liq.uptime * 100.0
+            ));
+            ui.label(format!("Avg spread: {:.4}", liq.avg_spread));
+            ui.label(format!(
+                "Avg top-of-book size: bid {:.6} | ask {:.6}",
+                liq.avg_top_bid_size, liq.avg_top_ask_size
+            ));
+        });
+
         ui.separator();
 
         // ---- Full ladders / raw levels ----
@@ -1708,34 +2446,36 @@ impl ReplayApp {
                     .show(ui, |ui| {
                         ui.columns(2, |cols| {
                             cols[0].label("Bids (sorted descending)");
-                            egui::Grid::new("data_bids_grid")
-                                .striped(true)
-                                .show(&mut cols[0], |ui| {
+                            egui::Grid::new("data_bids_grid").striped(true).show(
+                                &mut cols[0],
+                                |ui| {
                                     ui.label("Price");
                                     ui.label("Size");
                                     ui.end_row();
-                                    for (k, s) in self.book.bids.iter().rev() {
+                                    for (k, s) in self.replay.bids().iter().rev() {
                                         let p = key_to_price(*k);
                                         ui.label(format!("{:>10.4}", p));
                                         ui.label(format!("{:>10.6}", s));
                                         ui.end_row();
                                     }
-                                });
+                                },
+                            );
 
                             cols[1].label("Asks (sorted ascending)");
-                            egui::Grid::new("data_asks_grid")
-                                .striped(true)
-                                .show(&mut cols[1], |ui| {
+                            egui::Grid::new("data_asks_grid").striped(true).show(
+                                &mut cols[1],
+                                |ui| {
                                     ui.label("Price");
                                     ui.label("Size");
                                     ui.end_row();
-                                    for (k, s) in self.book.asks.iter() {
+                                    for (k, s) in self.replay.asks().iter() {
                                         let p = key_to_price(*k);
                                         ui.label(format!("{:>10.4}", p));
                                         ui.label(format!("{:>10.6}", s));
                                         ui.end_row();
                                     }
-                                });
+                                },
+                            );
                         });
                     });
             });
@@ -1788,20 +2528,43 @@ impl ReplayApp {
 
         ui.separator();
 
-        // ---- Recent trades ----
-        egui::CollapsingHeader::new("Recent trades around current time")
+        // ---- Equity curve ----
+        egui::CollapsingHeader::new("Sim equity curve")
             .default_open(true)
             .show(ui, |ui| {
-                let window_secs = self.trades_window_secs.max(10);
-                let lower = self.sim_ts.saturating_sub(window_secs);
+                let curve = self.trading.equity_curve();
+                ui.label(format!(
+                    "Samples: {}   Max drawdown: {:.2} USDC",
+                    curve.len(),
+                    self.trading.max_drawdown()
+                ));
 
-                let mut rows: Vec<&TradeCsvEvent> = self
-                    .tr_events
-                    .iter()
-                    .filter(|tr| tr.ts >= lower && tr.ts <= self.sim_ts)
-                    .collect();
+                if curve.is_empty() {
+                    ui.label("No equity samples yet (start the replay).");
+                    return;
+                }
 
-                rows.sort_by_key(|tr| tr.ts);
+                let mode = self.time_mode;
+                let pts: PlotPoints = curve.iter().map(|(t, eq)| [*t as f64, *eq]).collect();
+                Plot::new("equity_curve_replay")
+                    .height(160.0)
+                    .allow_drag(true)
+                    .allow_zoom(true)
+                    .x_axis_formatter(move |mark: GridMark, _range, _transform| {
+                        format_ts_common(mode, mark.value as u64)
+                    })
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(pts).name("equity").color(pal.accent).width(2.0));
+                    });
+            });
+
+        ui.separator();
+
+        // ---- Recent trades ----
+        egui::CollapsingHeader::new("Recent trades around current time")
+            .default_open(true)
+            .show(ui, |ui| {
+                let rows = self.trades_in_window();
 
                 ui.horizontal(|ui| {
                     ui.label("Trade window (s):");
@@ -1816,6 +2579,12 @@ impl ReplayApp {
                                 self.seek_to(last.ts);
                             }
                         }
+                        if ui.button("Export trades in window").clicked() {
+                            self.export_trades_window();
+                        }
+                    }
+                    if let Some(msg) = &self.trades_export_status {
+                        ui.weak(msg);
                     }
                 });
 
@@ -1841,7 +2610,7 @@ impl ReplayApp {
                                     ui.label(format!("{}", tr.ts));
                                     ui.label(self.format_ts(tr.ts));
                                     ui.label(&tr.ticker);
-                                    ui.label(&tr.side);
+                                    ui.label(tr.side.as_str());
                                     ui.label(format!("{:.6}", tr.size));
                                     ui.end_row();
                                 }
@@ -1910,7 +2679,7 @@ impl ReplayApp {
                                     ui.label(format!("{}", ev.ts));
                                     ui.label(self.format_ts(ev.ts));
                                     ui.label(&ev.msg_type);
-                                    ui.label(&ev.side);
+                                    ui.label(ev.side.as_str());
                                     ui.label(format!("{:.4}", ev.price));
                                     ui.label(format!("{:.6}", ev.size));
                                     ui.end_row();
@@ -1930,6 +2699,15 @@ impl eframe::App for ReplayApp {
             self.ui_top_bar(ui);
         });
 
+        egui::SidePanel::right("bookmarks_panel_replay")
+            .resizable(true)
+            .default_width(200.0)
+            .show(ctx, |ui| {
+                self.ui_jumps(ui);
+                ui.separator();
+                self.ui_bookmarks(ui);
+            });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
@@ -1940,13 +2718,21 @@ impl eframe::App for ReplayApp {
                 });
         });
 
-        ctx.request_repaint_after(Duration::from_millis(33));
+        // without data the random walk never pauses
+        let now = Instant::now();
+        let paused = self.paused && self.has_data;
+        if !paused || ctx.input(|i| !i.events.is_empty()) {
+            self.repaint.touch(now);
+        }
+        if let Some(after) = self.repaint.next_repaint(now, paused) {
+            ctx.request_repaint_after(after);
+        }
     }
 }
 
 fn main() {
     // ensure data dir exists (just so writes from live app have a home)
-    let _ = fs::create_dir_all("data");
+    let _ = fs::create_dir_all(data_dir());
 
     let options = eframe::NativeOptions::default();
     if let Err(e) = eframe::run_native(
@@ -0,0 +1,294 @@
+// ladder_app/src/book.rs
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use dydx_client::indexer::OrderbookResponsePriceLevel;
+
+use crate::csv_io::BookSide;
+use crate::replay::MidFn;
+use crate::snapshot::{book_spread, simple_mid, SNAPSHOT_KIND};
+
+// integer keys so BTreeMap ordering is nice.
+// `scale` is keys per 1.0 of price, per ticker (see scale_from_tick).
+pub type PriceKey = i64;
+
+pub const DEFAULT_PRICE_SCALE: f64 = 10_000.0;
+
+pub fn price_to_key(price: f64, scale: f64) -> PriceKey {
+    (price * scale).round() as PriceKey
+}
+
+pub fn key_to_price(key: PriceKey, scale: f64) -> f64 {
+    key as f64 / scale
+}
+
+// smallest power of ten that makes the tick size a whole number of keys,
+// so every valid price level gets its own key without wasting precision
+pub fn scale_from_tick(tick: f64) -> f64 {
+    if tick <= 0.0 || !tick.is_finite() {
+        return DEFAULT_PRICE_SCALE;
+    }
+    let mut scale = 1.0;
+    for _ in 0..=12 {
+        let units = tick * scale;
+        if (units - units.round()).abs() < 1e-9 * units.max(1.0) {
+            return scale;
+        }
+        scale *= 10.0;
+    }
+    DEFAULT_PRICE_SCALE
+}
+
+// decimals implied by a tick or step size (0.01 -> 2, 5.0 -> 0);
+// None when the market hasn't told us one yet
+pub fn step_decimals(step: f64) -> Option<usize> {
+    if step <= 0.0 || !step.is_finite() {
+        return None;
+    }
+    Some(scale_from_tick(step).log10().round() as usize)
+}
+
+// price at the market's tick precision, so it reads like the exchange does
+pub fn fmt_price(price: f64, tick_size: f64) -> String {
+    format!("{:.*}", step_decimals(tick_size).unwrap_or(2), price)
+}
+
+// size at the market's step-size precision
+pub fn fmt_size(size: f64, step_size: f64) -> String {
+    format!("{:.*}", step_decimals(step_size).unwrap_or(4), size)
+}
+
+/// 1 right after an update, falling linearly to 0 at `fade`.
+pub fn flash_strength(age: Duration, fade: Duration) -> f32 {
+    if fade.is_zero() {
+        return 0.0;
+    }
+    1.0 - (age.as_secs_f32() / fade.as_secs_f32()).min(1.0)
+}
+
+/// One ticker's book as the orders feed builds it. `on_level` callbacks see
+/// every level applied (kind, side, price, size) so the caller can record it.
+#[derive(Clone, Debug)]
+pub struct LiveBook {
+    pub bids: BTreeMap<PriceKey, f64>,
+    pub asks: BTreeMap<PriceKey, f64>,
+    pub scale: f64,
+    /// market tick size in keys, the base width for binned ladders
+    pub tick_keys: PriceKey,
+    /// indexer message id of the last orders message applied
+    pub seq: Option<u64>,
+    /// holes in the feed's message ids so far, across resubscribes
+    pub seq_gaps: u64,
+    /// when each resting level last changed, for the ladder flash
+    pub touched: HashMap<PriceKey, Instant>,
+}
+
+impl Default for LiveBook {
+    fn default() -> Self {
+        Self::with_scale(DEFAULT_PRICE_SCALE)
+    }
+}
+
+impl LiveBook {
+    pub fn with_scale(scale: f64) -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            scale,
+            tick_keys: 1,
+            seq: None,
+            seq_gaps: 0,
+            touched: HashMap::new(),
+        }
+    }
+
+    /// Replace the book with a feed snapshot.
+    pub fn apply_initial(
+        &mut self,
+        bids: Vec<OrderbookResponsePriceLevel>,
+        asks: Vec<OrderbookResponsePriceLevel>,
+        seq: Option<u64>,
+        mut on_level: impl FnMut(&str, BookSide, f64, f64),
+    ) {
+        self.bids.clear();
+        self.asks.clear();
+        self.touched.clear();
+        self.seq = seq;
+
+        for (side, levels) in [(BookSide::Bid, bids), (BookSide::Ask, asks)] {
+            let map = match side {
+                BookSide::Bid => &mut self.bids,
+                BookSide::Ask => &mut self.asks,
+            };
+            for lvl in levels {
+                let (p, s) = level_f64(lvl);
+                if s != 0.0 {
+                    map.insert(price_to_key(p, self.scale), s);
+                }
+                on_level("book_init", side, p, s);
+            }
+        }
+    }
+
+    /// Apply a feed update; size 0 removes a level.
+    pub fn apply_update(
+        &mut self,
+        bids: Option<Vec<OrderbookResponsePriceLevel>>,
+        asks: Option<Vec<OrderbookResponsePriceLevel>>,
+        seq: Option<u64>,
+        mut on_level: impl FnMut(&str, BookSide, f64, f64),
+    ) {
+        if seq.is_some() {
+            self.seq = seq;
+        }
+        let now = Instant::now();
+        for (side, levels) in [(BookSide::Bid, bids), (BookSide::Ask, asks)] {
+            let map = match side {
+                BookSide::Bid => &mut self.bids,
+                BookSide::Ask => &mut self.asks,
+            };
+            for lvl in levels.into_iter().flatten() {
+                let (p, s) = level_f64(lvl);
+                let key = price_to_key(p, self.scale);
+                if s == 0.0 {
+                    map.remove(&key);
+                    self.touched.remove(&key);
+                } else {
+                    map.insert(key, s);
+                    self.touched.insert(key, now);
+                }
+                on_level("delta", side, p, s);
+            }
+        }
+    }
+
+    /// The whole book as one snapshot block (side, price, size, seq), best
+    /// levels first so replay has a mid again from the first ask row.
+    /// Nothing before the first message with an id.
+    pub fn write_snapshot(&self, mut on_level: impl FnMut(&str, BookSide, f64, f64, u64)) {
+        // nothing applied yet, nothing to vouch for
+        let Some(seq) = self.seq else {
+            return;
+        };
+        for (k, s) in self.bids.iter().rev() {
+            on_level(
+                SNAPSHOT_KIND,
+                BookSide::Bid,
+                key_to_price(*k, self.scale),
+                *s,
+                seq,
+            );
+        }
+        for (k, s) in &self.asks {
+            on_level(
+                SNAPSHOT_KIND,
+                BookSide::Ask,
+                key_to_price(*k, self.scale),
+                *s,
+                seq,
+            );
+        }
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        simple_mid(&self.bids, &self.asks, self.scale)
+    }
+
+    /// The mid as `mid` computes it, e.g. a size-weighted one.
+    pub fn mid_with(&self, mid: MidFn) -> Option<f64> {
+        mid(&self.bids, &self.asks, self.scale)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        book_spread(&self.bids, &self.asks, self.scale)
+    }
+
+    /// Flash strength for a level, 0 once `fade` has passed since its update.
+    pub fn flash(&self, key: &PriceKey, fade: Duration) -> f32 {
+        self.touched
+            .get(key)
+            .map_or(0.0, |t| flash_strength(t.elapsed(), fade))
+    }
+}
+
+fn level_f64(lvl: OrderbookResponsePriceLevel) -> (f64, f64) {
+    let p = lvl.price.0.to_string().parse::<f64>().unwrap_or(0.0);
+    let s = lvl.size.0.to_string().parse::<f64>().unwrap_or(0.0);
+    (p, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_flash_fades_out_over_the_fade_time() {
+        let fade = Duration::from_secs(1);
+        assert_eq!(flash_strength(Duration::ZERO, fade), 1.0);
+        assert_eq!(flash_strength(Duration::from_millis(250), fade), 0.75);
+        assert_eq!(flash_strength(Duration::from_secs(3), fade), 0.0);
+        // a zero fade is the toggle being off
+        assert_eq!(flash_strength(Duration::ZERO, Duration::ZERO), 0.0);
+
+        let mut book = LiveBook::default();
+        book.touched.insert(7, Instant::now());
+        assert!(book.flash(&7, fade) > 0.9);
+        assert_eq!(book.flash(&8, fade), 0.0);
+        assert_eq!(book.flash(&7, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn live_book_applies_and_reports_levels() {
+        let lvl = |p: i32, s: i32| OrderbookResponsePriceLevel {
+            price: p.into(),
+            size: s.into(),
+        };
+        let mut seen = Vec::new();
+        let mut book = LiveBook::with_scale(1.0);
+        book.apply_initial(
+            vec![lvl(99, 1), lvl(100, 2)],
+            vec![lvl(102, 3)],
+            Some(5),
+            |kind, side, p, s| seen.push((kind.to_string(), side, p, s)),
+        );
+        assert_eq!(book.mid(), Some(101.0));
+        assert_eq!(book.spread(), Some(2.0));
+        assert_eq!(seen.len(), 3);
+
+        book.apply_update(
+            Some(vec![lvl(100, 0)]),
+            None,
+            Some(6),
+            |kind, side, p, s| seen.push((kind.to_string(), side, p, s)),
+        );
+        assert_eq!(book.seq, Some(6));
+        assert_eq!(book.mid(), Some(100.5));
+        assert_eq!(seen[3], ("delta".to_string(), BookSide::Bid, 100.0, 0.0));
+
+        let mut rows = Vec::new();
+        book.write_snapshot(|kind, side, p, s, seq| {
+            rows.push((kind == SNAPSHOT_KIND, side, p, s, seq))
+        });
+        assert_eq!(
+            rows,
+            vec![
+                (true, BookSide::Bid, 99.0, 1.0, 6),
+                (true, BookSide::Ask, 102.0, 3.0, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_follow_tick_and_step_size() {
+        assert_eq!(fmt_price(3012.345, 0.1), "3012.3");
+        assert_eq!(fmt_price(142.1234, 0.001), "142.123");
+        assert_eq!(fmt_price(64012.7, 1.0), "64013");
+        assert_eq!(fmt_price(64012.7, 0.0), "64012.70");
+        assert_eq!(fmt_size(1.23456, 0.001), "1.235");
+        assert_eq!(fmt_size(10.0, 10.0), "10");
+        assert_eq!(fmt_size(0.5, f64::NAN), "0.5000");
+        assert_eq!(step_decimals(0.00001), Some(5));
+        assert_eq!(step_decimals(-1.0), None);
+    }
+}
@@ -0,0 +1,271 @@
+// ladder_app/src/bot.rs
+//
+// The Rhai bot's contract. Before each run the caller sets the inputs below
+// as scope variables; the script answers in bot_signal / bot_size /
+// bot_comment. full_gui_x15 and the backtest binary both go through
+// feed_scope and read_bot_from_scope, so a script sees the same values live,
+// in replay and in a backtest.
+//
+// Inputs:
+//   ticker: String, mode: "live" | "replay"
+//   best_bid, best_ask, mid, spread, spread_bps: f64
+//   rsi_14: f64 (RSI of the TF candles, 50 if not enough data)
+//   bid_liquidity_near, ask_liquidity_near: f64 (top 10 levels)
+//   bid_liquidity_bps, ask_liquidity_bps: f64 (size within liquidity_bps of mid)
+//   liquidity_bps: f64
+//   nearest_bid_wall, nearest_ask_wall: f64 (closest outsized level, 0 if none)
+//   tf_secs, history_candles: i64
+//   bot_signal, bot_size, bot_comment: the previous run's answer
+
+use std::collections::BTreeMap;
+
+use rhai::Scope;
+
+use crate::book::{key_to_price, PriceKey};
+
+/// Levels on each side a ladder level is compared against.
+pub const WALL_WINDOW: usize = 10;
+
+/// full_gui_x15's starting liquidity band and wall threshold.
+pub const DEFAULT_LIQUIDITY_BPS: f64 = 10.0;
+pub const DEFAULT_WALL_SIGMA: f64 = 2.0;
+
+/// Simple RSI over closes, value for the last candle only.
+pub fn last_rsi(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() < period + 1 {
+        return None;
+    }
+    let window = &closes[closes.len() - period - 1..];
+    let mut gains = 0.0;
+    let mut losses = 0.0;
+    for w in window.windows(2) {
+        let diff = w[1] - w[0];
+        if diff >= 0.0 {
+            gains += diff;
+        } else {
+            losses -= diff;
+        }
+    }
+    if losses == 0.0 {
+        return Some(100.0);
+    }
+    let rs = gains / losses;
+    Some(100.0 - 100.0 / (1.0 + rs))
+}
+
+/// Size resting within `bps` basis points of `mid` on each side. Unlike a
+/// top-N-levels sum this means the same thing whatever the market's tick size.
+pub fn liquidity_within_bps(
+    bids: &BTreeMap<PriceKey, f64>,
+    asks: &BTreeMap<PriceKey, f64>,
+    scale: f64,
+    mid: f64,
+    bps: f64,
+) -> (f64, f64) {
+    if mid <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let band = mid * bps.max(0.0) / 10_000.0;
+    let bid_liq = bids
+        .iter()
+        .rev()
+        .take_while(|(k, _)| key_to_price(**k, scale) >= mid - band)
+        .map(|(_, s)| *s)
+        .sum();
+    let ask_liq = asks
+        .iter()
+        .take_while(|(k, _)| key_to_price(**k, scale) <= mid + band)
+        .map(|(_, s)| *s)
+        .sum();
+    (bid_liq, ask_liq)
+}
+
+/// Keys whose size exceeds mean + `threshold`·σ of the `window` levels on
+/// either side of them (the level itself excluded). Sorted ascending.
+pub fn detect_walls(map: &BTreeMap<PriceKey, f64>, window: usize, threshold: f64) -> Vec<PriceKey> {
+    let levels: Vec<(PriceKey, f64)> = map.iter().map(|(k, s)| (*k, *s)).collect();
+    let mut walls = Vec::new();
+
+    for (i, (key, size)) in levels.iter().enumerate() {
+        let lo = i.saturating_sub(window);
+        let hi = (i + window + 1).min(levels.len());
+        let nearby: Vec<f64> = (lo..hi).filter(|j| *j != i).map(|j| levels[j].1).collect();
+        if nearby.len() < 2 {
+            continue;
+        }
+        let n = nearby.len() as f64;
+        let mean = nearby.iter().sum::<f64>() / n;
+        let var = nearby.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+        if *size > mean + threshold * var.sqrt() && *size > mean {
+            walls.push(*key);
+        }
+    }
+
+    walls
+}
+
+/// What one script run sees besides its previous answer.
+#[derive(Clone, Copy, Debug)]
+pub struct BotInputs<'a> {
+    pub ticker: &'a str,
+    pub mode: &'a str,
+    pub bids: &'a BTreeMap<PriceKey, f64>,
+    pub asks: &'a BTreeMap<PriceKey, f64>,
+    pub price_scale: f64,
+    /// closes of the selected TF's candles, oldest first
+    pub closes: &'a [f64],
+    pub tf_secs: u64,
+    pub history_candles: usize,
+    /// band for bid/ask_liquidity_bps, in basis points of mid
+    pub liquidity_bps: f64,
+    /// ladder levels this many stddevs above their neighbours count as walls
+    pub wall_sigma: f64,
+}
+
+/// The script's answer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BotOutput {
+    /// "none" | "buy" | "sell"
+    pub signal: String,
+    pub size: f64,
+    pub comment: String,
+}
+
+impl Default for BotOutput {
+    fn default() -> Self {
+        Self {
+            signal: "none".to_string(),
+            size: 0.0,
+            comment: String::new(),
+        }
+    }
+}
+
+/// Reset `scope` to the inputs for one run, with `last` as the bot_*
+/// variables.
+pub fn feed_scope(scope: &mut Scope, inputs: &BotInputs, last: &BotOutput) {
+    let scale = inputs.price_scale;
+    let best_bid = inputs
+        .bids
+        .iter()
+        .next_back()
+        .map(|(k, _)| key_to_price(*k, scale))
+        .unwrap_or(0.0);
+    let best_ask = inputs
+        .asks
+        .iter()
+        .next()
+        .map(|(k, _)| key_to_price(*k, scale))
+        .unwrap_or(0.0);
+    let mid = if best_bid > 0.0 && best_ask > 0.0 {
+        (best_bid + best_ask) * 0.5
+    } else {
+        0.0
+    };
+    let spread = if best_bid > 0.0 && best_ask > 0.0 {
+        best_ask - best_bid
+    } else {
+        0.0
+    };
+
+    let bid_liq: f64 = inputs.bids.values().rev().take(10).sum();
+    let ask_liq: f64 = inputs.asks.values().take(10).sum();
+
+    scope.clear();
+
+    scope.set_value("ticker", inputs.ticker.to_string());
+    scope.set_value("mode", inputs.mode.to_string());
+    scope.set_value("best_bid", best_bid);
+    scope.set_value("best_ask", best_ask);
+    scope.set_value("mid", mid);
+    scope.set_value("spread", spread);
+    scope.set_value(
+        "spread_bps",
+        if mid > 0.0 {
+            spread / mid * 10_000.0
+        } else {
+            0.0
+        },
+    );
+    scope.set_value("rsi_14", last_rsi(inputs.closes, 14).unwrap_or(50.0));
+    scope.set_value("bid_liquidity_near", bid_liq);
+    scope.set_value("ask_liquidity_near", ask_liq);
+    let (bid_liq_bps, ask_liq_bps) =
+        liquidity_within_bps(inputs.bids, inputs.asks, scale, mid, inputs.liquidity_bps);
+    scope.set_value("liquidity_bps", inputs.liquidity_bps);
+    scope.set_value("bid_liquidity_bps", bid_liq_bps);
+    scope.set_value("ask_liquidity_bps", ask_liq_bps);
+    // walls come back sorted by key: the best bid wall is the highest one
+    let nearest_bid_wall = detect_walls(inputs.bids, WALL_WINDOW, inputs.wall_sigma)
+        .last()
+        .map_or(0.0, |k| key_to_price(*k, scale));
+    let nearest_ask_wall = detect_walls(inputs.asks, WALL_WINDOW, inputs.wall_sigma)
+        .first()
+        .map_or(0.0, |k| key_to_price(*k, scale));
+    scope.set_value("nearest_bid_wall", nearest_bid_wall);
+    scope.set_value("nearest_ask_wall", nearest_ask_wall);
+    scope.set_value("tf_secs", inputs.tf_secs as i64);
+    scope.set_value("history_candles", inputs.history_candles as i64);
+
+    scope.set_value("bot_signal", last.signal.clone());
+    scope.set_value("bot_size", last.size);
+    scope.set_value("bot_comment", last.comment.clone());
+}
+
+/// The script's answer after a run; missing or mistyped variables read as
+/// "none" / 0 / empty.
+pub fn read_bot_from_scope(scope: &Scope) -> BotOutput {
+    BotOutput {
+        signal: scope
+            .get_value::<String>("bot_signal")
+            .unwrap_or_else(|| "none".to_string()),
+        size: scope
+            .get_value::<f64>("bot_size")
+            .map_or(0.0, |size| size.max(0.0)),
+        comment: scope.get_value::<String>("bot_comment").unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::price_to_key;
+    use rhai::Engine;
+
+    #[test]
+    fn script_reads_inputs_and_answers_through_scope() {
+        let scale = 10_000.0;
+        let bids = BTreeMap::from([(price_to_key(99.0, scale), 3.0)]);
+        let asks = BTreeMap::from([(price_to_key(101.0, scale), 1.0)]);
+        let inputs = BotInputs {
+            ticker: "ETH-USD",
+            mode: "replay",
+            bids: &bids,
+            asks: &asks,
+            price_scale: scale,
+            closes: &[],
+            tf_secs: 60,
+            history_candles: 0,
+            liquidity_bps: 10.0,
+            wall_sigma: 2.0,
+        };
+        let mut scope = Scope::new();
+        feed_scope(&mut scope, &inputs, &BotOutput::default());
+
+        let script = r#"
+            if mid == 100.0 && bid_liquidity_near > ask_liquidity_near {
+                bot_signal = "buy";
+                bot_size = 0.5;
+            }
+            bot_comment = ticker;
+        "#;
+        Engine::new()
+            .eval_with_scope::<()>(&mut scope, script)
+            .unwrap();
+
+        let out = read_bot_from_scope(&scope);
+        assert_eq!(out.signal, "buy");
+        assert_eq!(out.size, 0.5);
+        assert_eq!(out.comment, "ETH-USD");
+    }
+}
@@ -0,0 +1,460 @@
+// ladder_app/src/candle_agg.rs
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Candle {
+    /// Unix timestamp (seconds) of the bucket start
+    pub t: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct CandleAgg {
+    tf_secs: u64,
+    series: Vec<Candle>,
+    max_candles: Option<usize>,
+}
+
+impl CandleAgg {
+    pub fn new(tf_secs: u64) -> Self {
+        Self {
+            tf_secs,
+            series: Vec::new(),
+            max_candles: None,
+        }
+    }
+
+    /// Like `new`, but keeps at most `max_candles` (see `set_max_candles`).
+    pub fn with_capacity(tf_secs: u64, max_candles: usize) -> Self {
+        let mut agg = Self::new(tf_secs);
+        agg.set_max_candles(Some(max_candles));
+        agg
+    }
+
+    /// Cap the stored series; once it is exceeded the oldest candles are
+    /// dropped for good (re-read them from CSV if needed). The newest candle
+    /// is always kept. `None` keeps everything.
+    pub fn set_max_candles(&mut self, max_candles: Option<usize>) {
+        self.max_candles = max_candles.map(|m| m.max(1));
+        self.enforce_cap();
+    }
+
+    fn enforce_cap(&mut self) {
+        if let Some(max) = self.max_candles {
+            if self.series.len() > max {
+                let excess = self.series.len() - max;
+                self.series.drain(..excess);
+            }
+        }
+    }
+
+    pub fn tf(&self) -> u64 {
+        self.tf_secs
+    }
+
+    /// Update with a tick (ts, price, volume).
+    ///
+    /// Ticks normally arrive in non-decreasing `ts` order; any ts in the
+    /// current bucket folds into the last candle. A late tick for an earlier
+    /// bucket (clock skew, batched feeds) goes to that bucket's candle, see
+    /// `fold_late`, so `t` never goes backward along the series.
+    pub fn update(&mut self, ts: u64, price: f64, volume: f64) {
+        let bucket_start = (ts / self.tf_secs) * self.tf_secs;
+
+        if let Some(last) = self.series.last_mut() {
+            if bucket_start < last.t {
+                self.fold_late(bucket_start, price, volume);
+                return;
+            }
+            if last.t == bucket_start {
+                // update current candle
+                if price > last.high {
+                    last.high = price;
+                }
+                if price < last.low {
+                    last.low = price;
+                }
+                last.close = price;
+                last.volume += volume;
+                return;
+            }
+        }
+
+        // new candle
+        self.series.push(Candle {
+            t: bucket_start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+        });
+        self.enforce_cap();
+    }
+
+    // A closed bucket only takes the late tick's high/low/volume: its open and
+    // close stay with the ticks that arrived in order. A bucket with no candle
+    // yet gets one in place; below the cap's oldest candle it is dropped.
+    fn fold_late(&mut self, bucket_start: u64, price: f64, volume: f64) {
+        match self.series.binary_search_by_key(&bucket_start, |c| c.t) {
+            Ok(i) => {
+                let c = &mut self.series[i];
+                c.high = c.high.max(price);
+                c.low = c.low.min(price);
+                c.volume += volume;
+            }
+            Err(i) => {
+                self.series.insert(
+                    i,
+                    Candle {
+                        t: bucket_start,
+                        open: price,
+                        high: price,
+                        low: price,
+                        close: price,
+                        volume,
+                    },
+                );
+                self.enforce_cap();
+            }
+        }
+    }
+
+    /// Read-only access to internal series
+    pub fn series(&self) -> &[Candle] {
+        &self.series
+    }
+
+    /// Candles whose bucket starts at or after `ts` (the series is sorted by
+    /// `t`). Meant for sharing an agg behind `Arc<Mutex<CandleAgg>>`: the
+    /// feed task keeps calling `update` while a reader copies just the tail
+    /// it needs under the lock instead of the whole series.
+    pub fn series_since(&self, ts: u64) -> &[Candle] {
+        let start = self.series.partition_point(|c| c.t < ts);
+        &self.series[start..]
+    }
+
+    /// Mutable access if you really want to tweak
+    pub fn series_mut(&mut self) -> &mut Vec<Candle> {
+        &mut self.series
+    }
+
+    /// Append a fully-formed historical candle (for loading from disk).
+    pub fn push_candle(&mut self, c: Candle) {
+        self.series.push(c);
+        self.enforce_cap();
+    }
+
+    /// Load candles from a CSV file into this aggregator.
+    ///
+    /// Format:
+    ///   ts,tf_secs,open,high,low,close,volume
+    ///
+    /// Only lines where tf_secs == self.tf_secs are applied.
+    pub fn load_from_csv<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if !path.exists() {
+            return;
+        }
+
+        let file = match File::open(path) {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+        let reader = BufReader::new(file);
+
+        for (idx, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+
+            if idx == 0 && line.starts_with("ts,") {
+                // header
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() != 7 {
+                continue;
+            }
+
+            let ts: u64 = match parts[0].parse() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            let tf: u64 = match parts[1].parse() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            if tf != self.tf_secs {
+                continue;
+            }
+
+            let open: f64 = parts[2].parse().unwrap_or(0.0);
+            let high: f64 = parts[3].parse().unwrap_or(open);
+            let low: f64 = parts[4].parse().unwrap_or(open);
+            let close: f64 = parts[5].parse().unwrap_or(open);
+            let vol: f64 = parts[6].parse().unwrap_or(0.0);
+
+            self.series.push(Candle {
+                t: ts,
+                open,
+                high,
+                low,
+                close,
+                volume: vol,
+            });
+        }
+        self.enforce_cap();
+    }
+
+    /// Save the entire series to CSV.
+    /// We overwrite the file each time we flush.
+    pub fn save_to_csv<P: AsRef<Path>>(&self, path: P) {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        let mut file = match File::create(path) {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+
+        let _ = writeln!(file, "ts,tf_secs,open,high,low,close,volume");
+
+        for c in &self.series {
+            let _ = writeln!(
+                file,
+                "{},{},{:.8},{:.8},{:.8},{:.8},{:.8}",
+                c.t, self.tf_secs, c.open, c.high, c.low, c.close, c.volume
+            );
+        }
+    }
+}
+
+/// Merge finer base candles into `tf_secs` buckets.
+///
+/// Open is the first base open, close the last base close, high/low the
+/// extremes and volume the sum. Base candles must be sorted by `t` and
+/// `tf_secs` should be a multiple of the base TF. The trailing bucket is
+/// emitted as-is even if it only covers part of `tf_secs`, exactly like the
+/// still-open candle of a `CandleAgg` fed directly.
+pub fn resample(base: &[Candle], tf_secs: u64) -> Vec<Candle> {
+    let tf_secs = tf_secs.max(1);
+    let mut out: Vec<Candle> = Vec::new();
+
+    for c in base {
+        let bucket_start = (c.t / tf_secs) * tf_secs;
+
+        if let Some(last) = out.last_mut() {
+            if last.t == bucket_start {
+                if c.high > last.high {
+                    last.high = c.high;
+                }
+                if c.low < last.low {
+                    last.low = c.low;
+                }
+                last.close = c.close;
+                last.volume += c.volume;
+                continue;
+            }
+        }
+
+        out.push(Candle {
+            t: bucket_start,
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume: c.volume,
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn cap_drops_oldest_and_keeps_newest() {
+        let mut agg = CandleAgg::with_capacity(60, 3);
+        for i in 0..10u64 {
+            agg.update(i * 60, 100.0 + i as f64, 1.0);
+        }
+        let ts: Vec<u64> = agg.series().iter().map(|c| c.t).collect();
+        assert_eq!(ts, vec![420, 480, 540]);
+
+        // updating the open candle never evicts it
+        agg.update(545, 50.0, 1.0);
+        assert_eq!(agg.series().len(), 3);
+        assert_eq!(agg.series().last().map(|c| c.low), Some(50.0));
+
+        // shrinking the cap trims right away, a zero cap still keeps one
+        agg.set_max_candles(Some(0));
+        assert_eq!(agg.series().len(), 1);
+        assert_eq!(agg.series()[0].t, 540);
+    }
+
+    #[test]
+    fn series_since_reads_the_tail_while_shared() {
+        let agg = Arc::new(Mutex::new(CandleAgg::new(60)));
+        let writer = {
+            let agg = Arc::clone(&agg);
+            std::thread::spawn(move || {
+                for i in 0..100u64 {
+                    agg.lock().unwrap().update(i * 30, 100.0, 1.0);
+                }
+            })
+        };
+        writer.join().unwrap();
+
+        let agg = agg.lock().unwrap();
+        let tail: Vec<u64> = agg.series_since(2880).iter().map(|c| c.t).collect();
+        assert_eq!(tail, vec![2880, 2940]);
+        // mid-bucket cutoff starts at the next bucket, past the end is empty
+        assert_eq!(agg.series_since(2881).len(), 1);
+        assert!(agg.series_since(10_000).is_empty());
+        assert_eq!(agg.series_since(0).len(), agg.series().len());
+    }
+
+    // the per-candle invariants that hold whatever order ticks arrive in
+    fn assert_candle_shape(series: &[Candle], tf: u64) {
+        for c in series {
+            assert_eq!(c.t % tf, 0, "{c:?} not aligned to {tf}");
+            assert!(c.low <= c.open.min(c.close), "{c:?}");
+            assert!(c.high >= c.open.max(c.close), "{c:?}");
+            assert!(c.volume >= 0.0, "{c:?}");
+        }
+    }
+
+    fn tick_strategy() -> impl Strategy<Value = (u64, f64, f64)> {
+        // small offsets so duplicates and shared buckets are common
+        (0u64..600, 0.01f64..1e6, 0.0f64..1e3)
+    }
+
+    proptest! {
+        #[test]
+        fn in_order_ticks_give_one_candle_per_bucket(
+            tf in prop::sample::select(vec![1u64, 5, 60, 300]),
+            mut ticks in prop::collection::vec(tick_strategy(), 1..200),
+        ) {
+            ticks.sort_by_key(|t| t.0);
+            let t0 = 1_700_000_000;
+            let mut agg = CandleAgg::new(tf);
+            for &(dt, price, vol) in &ticks {
+                agg.update(t0 + dt, price, vol);
+            }
+            let series = agg.series();
+            assert_candle_shape(series, tf);
+            assert!(series.windows(2).all(|w| w[0].t < w[1].t));
+
+            let mut expected: Vec<Candle> = Vec::new();
+            for &(dt, price, vol) in &ticks {
+                let t = (t0 + dt) / tf * tf;
+                match expected.last_mut() {
+                    Some(c) if c.t == t => {
+                        c.high = c.high.max(price);
+                        c.low = c.low.min(price);
+                        c.close = price;
+                        c.volume += vol;
+                    }
+                    _ => expected.push(Candle {
+                        t,
+                        open: price,
+                        high: price,
+                        low: price,
+                        close: price,
+                        volume: vol,
+                    }),
+                }
+            }
+            let fields = |c: &Candle| (c.t, c.open, c.high, c.low, c.close, c.volume);
+            prop_assert_eq!(
+                series.iter().map(fields).collect::<Vec<_>>(),
+                expected.iter().map(fields).collect::<Vec<_>>()
+            );
+        }
+
+        // late ticks land in their own bucket's candle: the series stays
+        // sorted with one candle per bucket whatever the arrival order
+        #[test]
+        fn unordered_ticks_fold_into_their_bucket(
+            tf in prop::sample::select(vec![1u64, 5, 60, 300]),
+            ticks in prop::collection::vec(tick_strategy(), 1..200),
+        ) {
+            let t0 = 1_700_000_000;
+            let mut agg = CandleAgg::new(tf);
+            for &(dt, price, vol) in &ticks {
+                agg.update(t0 + dt, price, vol);
+            }
+            let series = agg.series();
+            assert_candle_shape(series, tf);
+            prop_assert!(series.windows(2).all(|w| w[0].t < w[1].t));
+
+            // bucket -> (high, low, volume)
+            let mut buckets: BTreeMap<u64, (f64, f64, f64)> = BTreeMap::new();
+            for &(dt, price, vol) in &ticks {
+                let b = buckets
+                    .entry((t0 + dt) / tf * tf)
+                    .or_insert((price, price, 0.0));
+                *b = (b.0.max(price), b.1.min(price), b.2 + vol);
+            }
+            prop_assert_eq!(series.len(), buckets.len());
+            for (c, (&t, &(high, low, vol))) in series.iter().zip(&buckets) {
+                prop_assert_eq!((c.t, c.high, c.low), (t, high, low));
+                prop_assert!((c.volume - vol).abs() <= 1e-9 * vol.max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn late_tick_folds_back_one_or_many_buckets() {
+        let mut agg = CandleAgg::new(60);
+        agg.update(0, 100.0, 1.0);
+        agg.update(120, 102.0, 1.0);
+        agg.update(600, 105.0, 1.0);
+
+        // one bucket back, into an empty bucket: a new candle in place
+        agg.update(545, 90.0, 2.0);
+        // many buckets back, into an existing candle: high/low/volume only
+        agg.update(30, 110.0, 3.0);
+        let got: Vec<_> = agg
+            .series()
+            .iter()
+            .map(|c| (c.t, c.open, c.high, c.low, c.close, c.volume))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (0, 100.0, 110.0, 100.0, 100.0, 4.0),
+                (120, 102.0, 102.0, 102.0, 102.0, 1.0),
+                (540, 90.0, 90.0, 90.0, 90.0, 2.0),
+                (600, 105.0, 105.0, 105.0, 105.0, 1.0),
+            ]
+        );
+
+        // under a cap, a tick older than every kept candle is dropped
+        let mut capped = CandleAgg::with_capacity(60, 2);
+        for t in [0, 60, 120] {
+            capped.update(t, 1.0, 1.0);
+        }
+        capped.update(10, 5.0, 1.0);
+        let ts: Vec<u64> = capped.series().iter().map(|c| c.t).collect();
+        assert_eq!(ts, vec![60, 120]);
+        assert_eq!(capped.series()[0].high, 1.0);
+    }
+}
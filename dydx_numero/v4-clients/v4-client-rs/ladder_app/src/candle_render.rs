@@ -0,0 +1,128 @@
+// ladder_app/src/candle_render.rs
+//
+// Candle drawing shared by the GUI bins (full_gui11, full_gui_x15, gui_replay4),
+// so body / wick changes land in every chart at once.
+
+use egui::{Color32, Stroke};
+use egui_plot::{Line, PlotPoints, PlotUi, Polygon};
+
+use crate::candle_agg::Candle;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CandleBody {
+    Filled,
+    Outline,
+    /// Up candles outlined, down candles filled
+    Hollow,
+}
+
+impl CandleBody {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CandleBody::Filled => "Filled",
+            CandleBody::Outline => "Outline",
+            CandleBody::Hollow => "Hollow",
+        }
+    }
+
+    pub fn all() -> &'static [CandleBody] {
+        &[CandleBody::Filled, CandleBody::Outline, CandleBody::Hollow]
+    }
+}
+
+/// What picks a candle's up or down color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CandleColorBasis {
+    /// close >= open
+    BodyOpenClose,
+    /// close >= the previous candle's close, like a line chart's up/down; the
+    /// first visible candle falls back to open/close
+    PrevClose,
+}
+
+impl CandleColorBasis {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CandleColorBasis::BodyOpenClose => "Open/close",
+            CandleColorBasis::PrevClose => "Prev close",
+        }
+    }
+
+    pub fn all() -> &'static [CandleColorBasis] {
+        &[CandleColorBasis::BodyOpenClose, CandleColorBasis::PrevClose]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CandleRenderStyle {
+    /// Body width as a fraction of the TF bucket (clamped to 0.1..=1.0)
+    pub body_width_factor: f32,
+    pub wick_width: f32,
+    pub up_color: Color32,
+    pub down_color: Color32,
+    /// Filled bodies are a polygon, outlined ones a 2px line
+    pub body: CandleBody,
+    /// Only the color; Hollow still fills by open/close
+    pub color_basis: CandleColorBasis,
+}
+
+/// Draws `visible` as OHLC candles, bucket `c.t .. c.t + tf` on the x axis.
+pub fn draw_candles(plot_ui: &mut PlotUi, visible: &[Candle], tf: f64, style: &CandleRenderStyle) {
+    let half_body = tf * 0.5 * style.body_width_factor.clamp(0.1, 1.0) as f64;
+
+    let mut prev_close = None;
+    for c in visible {
+        let mid = c.t as f64 + tf * 0.5;
+        let top = c.open.max(c.close);
+        let bot = c.open.min(c.close);
+
+        let up = c.close >= c.open;
+        let color_up = match (style.color_basis, prev_close) {
+            (CandleColorBasis::PrevClose, Some(prev)) => c.close >= prev,
+            _ => up,
+        };
+        prev_close = Some(c.close);
+        let color = if color_up {
+            style.up_color
+        } else {
+            style.down_color
+        };
+        let filled = match style.body {
+            CandleBody::Filled => true,
+            CandleBody::Outline => false,
+            CandleBody::Hollow => !up,
+        };
+
+        // wick
+        let wick_pts: PlotPoints = vec![[mid, c.low], [mid, c.high]].into();
+        plot_ui.line(Line::new(wick_pts).color(color).width(style.wick_width));
+
+        // body
+        let body_left = mid - half_body;
+        let body_right = mid + half_body;
+        if filled {
+            let body_pts: PlotPoints = vec![
+                [body_left, bot],
+                [body_left, top],
+                [body_right, top],
+                [body_right, bot],
+            ]
+            .into();
+            plot_ui.polygon(
+                Polygon::new(body_pts)
+                    .fill_color(color)
+                    .stroke(Stroke::new(1.0, color)),
+            );
+        } else {
+            let body_pts: PlotPoints = vec![
+                [body_left, bot],
+                [body_left, top],
+                [body_right, top],
+                [body_right, bot],
+                [body_left, bot],
+            ]
+            .into();
+            plot_ui.line(Line::new(body_pts).color(color).width(2.0));
+        }
+    }
+}
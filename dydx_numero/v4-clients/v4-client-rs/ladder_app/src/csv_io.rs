@@ -0,0 +1,904 @@
+// ladder_app/src/csv_io.rs
+//
+// The data dir layout every binary reads: orderbook_<TICKER>.csv rows
+// `ts,ticker,kind,side,price,size[,seq]` (kind `snapshot` rows form the
+// blocks described in snapshot.rs) and trades_<TICKER>.csv rows
+// `ts,ticker,source,side,size[,price]`. With `--data-format jsonl` the same
+// events go to .jsonl files instead, one JSON object per line with those
+// names as typed fields; loaders pick the parser from the file extension.
+// Loaders count malformed lines in LoadStats instead of dropping them
+// silently. Sides are written lowercase (bid/ask, buy/sell) and read in any
+// case, so files from older writers that used "Buy"/"BUY" still load. Dirs
+// from other venues in the same schema can be merged into one
+// stream (see merge_book_sources).
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::book::{price_to_key, PriceKey};
+use crate::snapshot::SNAPSHOT_KIND;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BookCsvEvent {
+    pub ts: u64,
+    pub ticker: String,
+    pub kind: String,
+    pub side: BookSide,
+    pub price: f64,
+    pub size: f64,
+    // indexer message id the row reflects, when the writer recorded one
+    #[serde(default)]
+    pub seq: Option<u64>,
+    // venue label when merged from several data dirs, empty otherwise
+    #[serde(skip)]
+    pub venue: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct TradeCsvEvent {
+    pub ts: u64,
+    pub ticker: String,
+    pub source: String,
+    pub side: TradeSide,
+    pub size_str: String,
+    pub venue: String,
+    /// None for rows written without the price column
+    pub price: Option<f64>,
+}
+
+// --- sides ---
+
+/// The side of the book a level rests on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+impl BookSide {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BookSide::Bid => "bid",
+            BookSide::Ask => "ask",
+        }
+    }
+
+    // any case
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("bid") {
+            Some(BookSide::Bid)
+        } else if s.eq_ignore_ascii_case("ask") {
+            Some(BookSide::Ask)
+        } else {
+            None
+        }
+    }
+}
+
+/// The taker's side of a trade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    pub fn from_buy(is_buy: bool) -> Self {
+        if is_buy {
+            TradeSide::Buy
+        } else {
+            TradeSide::Sell
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradeSide::Buy => "buy",
+            TradeSide::Sell => "sell",
+        }
+    }
+
+    // any case, so "Buy" from the old `{:?}` writers reads too
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("buy") {
+            Some(TradeSide::Buy)
+        } else if s.eq_ignore_ascii_case("sell") {
+            Some(TradeSide::Sell)
+        } else {
+            None
+        }
+    }
+
+    /// The book side the taker trades against.
+    pub fn hits(self) -> BookSide {
+        match self {
+            TradeSide::Buy => BookSide::Ask,
+            TradeSide::Sell => BookSide::Bid,
+        }
+    }
+}
+
+impl fmt::Display for BookSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for TradeSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for BookSide {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        Self::parse(&s).ok_or_else(|| format!("bad side {s:?}"))
+    }
+}
+
+impl TryFrom<String> for TradeSide {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        Self::parse(&s).ok_or_else(|| format!("bad side {s:?}"))
+    }
+}
+
+// --- data dir ---
+
+pub const DEFAULT_DATA_DIR: &str = "data";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// value of `<flag> <v>` or `<flag>=<v>`
+pub fn arg_value(args: impl IntoIterator<Item = String>, flag: &str) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == flag {
+            return args.next();
+        }
+        if let Some(v) = a.strip_prefix(flag).and_then(|r| r.strip_prefix('=')) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+// every value of a repeatable flag, in command-line order
+pub fn arg_values(args: impl IntoIterator<Item = String>, flag: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == flag {
+            out.extend(args.next());
+        } else if let Some(v) = a.strip_prefix(flag).and_then(|r| r.strip_prefix('=')) {
+            out.push(v.to_string());
+        }
+    }
+    out
+}
+
+// `--data-dir <path>` or `--data-dir=<path>`; `data` when absent
+pub fn data_dir_from_args(args: impl IntoIterator<Item = String>) -> PathBuf {
+    arg_value(args, "--data-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR))
+}
+
+/// Base dir for every CSV read and write, taken from the command line once.
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| data_dir_from_args(env::args().skip(1)))
+}
+
+// --- file format ---
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataFormat {
+    #[default]
+    Csv,
+    JsonLines,
+}
+
+impl DataFormat {
+    pub fn ext(self) -> &'static str {
+        match self {
+            DataFormat::Csv => "csv",
+            DataFormat::JsonLines => "jsonl",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(DataFormat::Csv),
+            "jsonl" | "json" => Some(DataFormat::JsonLines),
+            _ => None,
+        }
+    }
+
+    // by extension; anything that isn't .jsonl reads as CSV
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("jsonl") => DataFormat::JsonLines,
+            _ => DataFormat::Csv,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            DataFormat::Csv => DataFormat::JsonLines,
+            DataFormat::JsonLines => DataFormat::Csv,
+        }
+    }
+}
+
+static DATA_FORMAT: OnceLock<DataFormat> = OnceLock::new();
+
+// `--data-format csv|jsonl`; CSV when absent or unknown
+pub fn data_format_from_args(args: impl IntoIterator<Item = String>) -> DataFormat {
+    arg_value(args, "--data-format")
+        .and_then(|v| DataFormat::parse(&v))
+        .unwrap_or_default()
+}
+
+/// Format the writers use, taken from the command line once.
+pub fn data_format() -> DataFormat {
+    *DATA_FORMAT.get_or_init(|| data_format_from_args(env::args().skip(1)))
+}
+
+/// `<dir>/<stem>.<ext>` in the configured format, or in the other one when
+/// only that exists, so a dir written either way still loads.
+pub fn data_file(dir: &Path, stem: &str) -> PathBuf {
+    let fmt = data_format();
+    let preferred = dir.join(format!("{stem}.{}", fmt.ext()));
+    if preferred.exists() {
+        return preferred;
+    }
+    let other = dir.join(format!("{stem}.{}", fmt.other().ext()));
+    if other.exists() {
+        other
+    } else {
+        preferred
+    }
+}
+
+/// One book row as the writers emit it; `seq` is the optional 7th column,
+/// the indexer message id the row came in, which the loaders don't need.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BookRow<'a> {
+    pub ts: u64,
+    pub ticker: &'a str,
+    pub kind: &'a str,
+    pub side: BookSide,
+    pub price: f64,
+    pub size: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+impl BookRow<'_> {
+    pub fn encode(&self, format: DataFormat) -> String {
+        match format {
+            DataFormat::Csv => {
+                let row = format!(
+                    "{},{},{},{},{},{}",
+                    self.ts, self.ticker, self.kind, self.side, self.price, self.size
+                );
+                match self.seq {
+                    Some(seq) => format!("{row},{seq}"),
+                    None => row,
+                }
+            }
+            DataFormat::JsonLines => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
+
+/// One trade row as the writers emit it; `price` is the optional 6th column.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct TradeRow<'a> {
+    pub ts: u64,
+    pub ticker: &'a str,
+    pub source: &'a str,
+    pub side: TradeSide,
+    pub size: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+}
+
+impl TradeRow<'_> {
+    pub fn encode(&self, format: DataFormat) -> String {
+        match format {
+            DataFormat::Csv => {
+                let row = format!(
+                    "{},{},{},{},{}",
+                    self.ts, self.ticker, self.source, self.side, self.size
+                );
+                match self.price {
+                    Some(p) => format!("{row},{p}"),
+                    None => row,
+                }
+            }
+            DataFormat::JsonLines => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
+
+// owned TradeRow for the .jsonl loader
+#[derive(Deserialize)]
+struct TradeRecord {
+    ts: u64,
+    ticker: String,
+    source: String,
+    side: TradeSide,
+    size: f64,
+    #[serde(default)]
+    price: Option<f64>,
+}
+
+// --- loaders ---
+
+// how a CSV load went; malformed lines are counted, not silently dropped
+#[derive(Clone, Debug, Default)]
+pub struct LoadStats {
+    pub total_lines: usize,
+    pub parsed: usize,
+    pub skipped: usize,
+    // book rows dropped by full_gui11's dedup_book_events
+    pub collapsed: usize,
+    // first few parse errors, "line N: reason"
+    pub samples: Vec<String>,
+}
+
+pub const LOAD_STATS_MAX_SAMPLES: usize = 5;
+
+impl LoadStats {
+    pub fn skip(&mut self, line_no: usize, reason: String) {
+        self.skipped += 1;
+        if self.samples.len() < LOAD_STATS_MAX_SAMPLES {
+            self.samples.push(format!("line {line_no}: {reason}"));
+        }
+    }
+
+    pub fn merge(&mut self, other: &LoadStats) {
+        self.total_lines += other.total_lines;
+        self.parsed += other.parsed;
+        self.skipped += other.skipped;
+        self.collapsed += other.collapsed;
+        for s in &other.samples {
+            if self.samples.len() < LOAD_STATS_MAX_SAMPLES {
+                self.samples.push(s.clone());
+            }
+        }
+    }
+}
+
+// One parsed book line: ts, ticker, kind, side, price, size, seq.
+pub type BookFields<'a> = (u64, &'a str, &'a str, BookSide, f64, f64, Option<u64>);
+
+// ts,ticker,kind,side,price,size[,seq] -> borrowed fields, no per-line allocation
+pub fn parse_book_fields(line: &str) -> Result<BookFields<'_>, String> {
+    let mut it = line.split(',');
+    let mut next = || it.next();
+    let (ts, tk, kind, side, price, size) = match (next(), next(), next(), next(), next(), next()) {
+        (Some(a), Some(b), Some(c), Some(d), Some(e), Some(f)) => (a, b, c, d, e, f),
+        _ => return Err("expected 6 fields".to_string()),
+    };
+    let ts = ts.parse::<u64>().map_err(|_| format!("bad ts {ts:?}"))?;
+    let side = BookSide::parse(side).ok_or_else(|| format!("bad side {side:?}"))?;
+    let price = price
+        .parse::<f64>()
+        .map_err(|_| format!("bad price {price:?}"))?;
+    let size = size
+        .parse::<f64>()
+        .map_err(|_| format!("bad size {size:?}"))?;
+    let seq = match it.next() {
+        Some(seq) => Some(seq.parse::<u64>().map_err(|_| format!("bad seq {seq:?}"))?),
+        None => None,
+    };
+    Ok((ts, tk.trim_matches('"'), kind, side, price, size, seq))
+}
+
+// Feed every data line of `path` to `row` with its 1-based line number; a
+// missing file has none. Blank lines and a leading CSV header are passed over.
+fn for_each_line(
+    path: &Path,
+    stats: &mut LoadStats,
+    mut row: impl FnMut(&mut LoadStats, usize, &str),
+) {
+    if !path.exists() {
+        return;
+    }
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            stats.skip(0, format!("cannot open {}: {e}", path.display()));
+            return;
+        }
+    };
+    let reader = BufReader::new(f);
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line_no = idx + 1;
+        stats.total_lines += 1;
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                stats.skip(line_no, format!("read error: {e}"));
+                continue;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || (idx == 0 && line.starts_with("ts,")) {
+            continue;
+        }
+        row(stats, line_no, line);
+    }
+}
+
+/// Book events for `ticker`, sorted by ts; .jsonl files are read as JSON
+/// Lines, anything else as CSV.
+pub fn load_book_csv(path: &Path, ticker: &str) -> (Vec<BookCsvEvent>, LoadStats) {
+    let format = DataFormat::from_path(path);
+    let mut stats = LoadStats::default();
+    let mut out = Vec::new();
+
+    for_each_line(path, &mut stats, |stats, line_no, line| {
+        let e = match format {
+            DataFormat::Csv => match parse_book_fields(line) {
+                Ok((ts, tk, kind, side, price, size, seq)) => {
+                    // other tickers are not malformed, just not ours
+                    if tk != ticker {
+                        return;
+                    }
+                    BookCsvEvent {
+                        ts,
+                        ticker: tk.to_string(),
+                        kind: kind.to_string(),
+                        side,
+                        price,
+                        size,
+                        seq,
+                        venue: String::new(),
+                    }
+                }
+                Err(reason) => return stats.skip(line_no, reason),
+            },
+            DataFormat::JsonLines => match serde_json::from_str::<BookCsvEvent>(line) {
+                Ok(e) if e.ticker != ticker => return,
+                Ok(e) => e,
+                Err(e) => return stats.skip(line_no, e.to_string()),
+            },
+        };
+        stats.parsed += 1;
+        out.push(e);
+    });
+
+    out.sort_by_key(|e| e.ts);
+    (out, stats)
+}
+
+/// Trade events for `ticker`, sorted by ts; format by extension as for
+/// load_book_csv.
+pub fn load_trades_csv(path: &Path, ticker: &str) -> (Vec<TradeCsvEvent>, LoadStats) {
+    let format = DataFormat::from_path(path);
+    let mut stats = LoadStats::default();
+    let mut out = Vec::new();
+
+    for_each_line(path, &mut stats, |stats, line_no, line| {
+        let e = match format {
+            DataFormat::Csv => {
+                let parts: Vec<&str> = line.split(',').collect();
+                if parts.len() < 5 {
+                    return stats.skip(line_no, format!("expected 5 fields, got {}", parts.len()));
+                }
+                let Ok(ts) = parts[0].parse::<u64>() else {
+                    return stats.skip(line_no, format!("bad ts {:?}", parts[0]));
+                };
+                let Some(side) = TradeSide::parse(parts[3]) else {
+                    return stats.skip(line_no, format!("bad side {:?}", parts[3]));
+                };
+                let price = match parts.get(5).map(|p| p.trim()) {
+                    None | Some("") => None,
+                    Some(p) => match p.parse::<f64>() {
+                        Ok(v) => Some(v),
+                        Err(_) => return stats.skip(line_no, format!("bad price {p:?}")),
+                    },
+                };
+                TradeCsvEvent {
+                    ts,
+                    ticker: parts[1].trim_matches('"').to_string(),
+                    source: parts[2].to_string(),
+                    side,
+                    size_str: parts[4].to_string(),
+                    venue: String::new(),
+                    price,
+                }
+            }
+            DataFormat::JsonLines => match serde_json::from_str::<TradeRecord>(line) {
+                Ok(r) => TradeCsvEvent {
+                    ts: r.ts,
+                    ticker: r.ticker,
+                    source: r.source,
+                    side: r.side,
+                    size_str: r.size.to_string(),
+                    venue: String::new(),
+                    price: r.price,
+                },
+                Err(e) => return stats.skip(line_no, e.to_string()),
+            },
+        };
+        if e.ticker != ticker {
+            return;
+        }
+        stats.parsed += 1;
+        out.push(e);
+    });
+
+    out.sort_by_key(|e| e.ts);
+    (out, stats)
+}
+
+/// Sort trades by ts and drop repeats of the same (ts, side, size), which two
+/// writers appending the same fill to one file leave behind. The sort is
+/// stable, so distinct trades keep their file order within a second. Returns
+/// how many rows were dropped.
+pub fn sort_dedup_trades<T>(
+    events: &mut Vec<T>,
+    key: impl Fn(&T) -> (u64, TradeSide, f64),
+) -> usize {
+    events.sort_by_key(|e| key(e).0);
+    let before = events.len();
+    // sorted, so only the current second's keys can repeat
+    let mut seen = HashSet::new();
+    let mut second = None;
+    events.retain(|e| {
+        let (ts, side, size) = key(e);
+        if second != Some(ts) {
+            second = Some(ts);
+            seen.clear();
+        }
+        seen.insert((side, size.to_bits()))
+    });
+    before - events.len()
+}
+
+// --- multi-venue merge ---
+
+/// A secondary venue's data dir in the same CSV schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataSource {
+    pub label: String,
+    pub dir: PathBuf,
+}
+
+impl DataSource {
+    // `label=path`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (label, dir) = spec.split_once('=')?;
+        if label.is_empty() || dir.is_empty() {
+            return None;
+        }
+        Some(Self {
+            label: label.to_string(),
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+// `--merge-source <label>=<dir>`, repeatable; malformed specs are dropped
+pub fn merge_sources_from_args(args: impl IntoIterator<Item = String>) -> Vec<DataSource> {
+    arg_values(args, "--merge-source")
+        .iter()
+        .filter_map(|s| DataSource::parse(s))
+        .collect()
+}
+
+/// Merge per-venue book streams by ts into one combined book. Each row is a
+/// venue's absolute size at a level, so the venues' sizes at the same price
+/// are kept apart and every output row carries their sum: a venue pulling
+/// its level only removes its own share. Same-ts rows keep `sources` order.
+/// A venue's snapshot block would clear the other venues' levels too, so
+/// its rows come out as plain deltas.
+pub fn merge_book_sources(
+    sources: Vec<(String, Vec<BookCsvEvent>)>,
+    scale: f64,
+) -> Vec<BookCsvEvent> {
+    let n = sources.len();
+    let mut tagged: Vec<(usize, BookCsvEvent)> = Vec::new();
+    for (i, (label, events)) in sources.into_iter().enumerate() {
+        tagged.extend(events.into_iter().map(|mut e| {
+            e.venue = label.clone();
+            (i, e)
+        }));
+    }
+    tagged.sort_by_key(|(_, e)| e.ts);
+
+    // (is_bid, key) -> size per venue
+    let mut levels: HashMap<(bool, PriceKey), Vec<f64>> = HashMap::new();
+    tagged
+        .into_iter()
+        .map(|(i, mut e)| {
+            let key = (e.side == BookSide::Bid, price_to_key(e.price, scale));
+            let per_venue = levels.entry(key).or_insert_with(|| vec![0.0; n]);
+            per_venue[i] = e.size;
+            e.size = per_venue.iter().sum();
+            if e.kind == SNAPSHOT_KIND {
+                e.kind = "delta".to_string();
+            }
+            e
+        })
+        .collect()
+}
+
+/// Merge per-venue trade streams by ts, tagging each with its venue.
+pub fn merge_trade_sources(sources: Vec<(String, Vec<TradeCsvEvent>)>) -> Vec<TradeCsvEvent> {
+    let mut out: Vec<TradeCsvEvent> = sources
+        .into_iter()
+        .flat_map(|(label, events)| {
+            events.into_iter().map(move |mut e| {
+                e.venue = label.clone();
+                e
+            })
+        })
+        .collect();
+    out.sort_by_key(|e| e.ts);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_dir_flag_forms() {
+        let args = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(data_dir_from_args(args(&[])), PathBuf::from("data"));
+        assert_eq!(
+            data_dir_from_args(args(&["--data-dir", "/tmp/a"])),
+            PathBuf::from("/tmp/a")
+        );
+        assert_eq!(
+            data_dir_from_args(args(&["--x", "--data-dir=archive"])),
+            PathBuf::from("archive")
+        );
+        // dangling flag falls back to the default
+        assert_eq!(
+            data_dir_from_args(args(&["--data-dir"])),
+            PathBuf::from("data")
+        );
+    }
+
+    #[test]
+    fn rows_load_back_from_either_format() {
+        let dir = std::env::temp_dir().join(format!("csv_io_formats_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let book = [
+            BookRow {
+                ts: 2,
+                ticker: "ETH-USD",
+                kind: "delta",
+                side: BookSide::Ask,
+                price: 3000.5,
+                size: 0.0,
+                seq: Some(42),
+            },
+            BookRow {
+                ts: 1,
+                ticker: "ETH-USD",
+                kind: "book_init",
+                side: BookSide::Bid,
+                price: 3000.25,
+                size: 1.5,
+                seq: None,
+            },
+            BookRow {
+                ts: 1,
+                ticker: "BTC-USD",
+                kind: "book_init",
+                side: BookSide::Bid,
+                price: 60000.0,
+                size: 1.0,
+                seq: None,
+            },
+        ];
+        let trade = TradeRow {
+            ts: 3,
+            ticker: "ETH-USD",
+            source: "market",
+            side: TradeSide::Buy,
+            size: 0.25,
+            price: Some(3000.5),
+        };
+
+        for format in [DataFormat::Csv, DataFormat::JsonLines] {
+            let write = |stem: &str, rows: Vec<String>| {
+                let path = dir.join(format!("{stem}.{}", format.ext()));
+                std::fs::write(&path, rows.join("\n") + "\n").unwrap();
+                path
+            };
+            let ob = write("orderbook", book.iter().map(|r| r.encode(format)).collect());
+            let tr = write("trades", vec![trade.encode(format), "garbage".into()]);
+
+            let (events, stats) = load_book_csv(&ob, "ETH-USD");
+            let got: Vec<_> = events
+                .iter()
+                .map(|e| (e.ts, e.kind.as_str(), e.side.as_str(), e.price, e.size))
+                .collect();
+            assert_eq!(
+                got,
+                vec![
+                    (1, "book_init", "bid", 3000.25, 1.5),
+                    (2, "delta", "ask", 3000.5, 0.0)
+                ],
+                "{format:?}"
+            );
+            assert_eq!((stats.parsed, stats.skipped), (2, 0), "{format:?}");
+            assert_eq!((events[0].seq, events[1].seq), (None, Some(42)));
+
+            let (trades, stats) = load_trades_csv(&tr, "ETH-USD");
+            assert_eq!(trades.len(), 1, "{format:?}");
+            let t = &trades[0];
+            assert_eq!(
+                (t.ts, t.side.as_str(), t.size_str.as_str(), t.price),
+                (3, "buy", "0.25", Some(3000.5))
+            );
+            assert_eq!(stats.skipped, 1, "{format:?}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sides_round_trip_and_read_legacy_casings() {
+        for side in [BookSide::Bid, BookSide::Ask] {
+            let json = serde_json::to_string(&side).unwrap();
+            assert_eq!(json, format!("\"{side}\""));
+            assert_eq!(serde_json::from_str::<BookSide>(&json).unwrap(), side);
+            assert_eq!(BookSide::parse(side.as_str()), Some(side));
+            let upper = side.as_str().to_uppercase();
+            assert_eq!(BookSide::parse(&upper), Some(side));
+        }
+        for side in [TradeSide::Buy, TradeSide::Sell] {
+            let json = serde_json::to_string(&side).unwrap();
+            assert_eq!(json, format!("\"{side}\""));
+            assert_eq!(serde_json::from_str::<TradeSide>(&json).unwrap(), side);
+            assert_eq!(TradeSide::parse(side.as_str()), Some(side));
+            // what `format!("{:?}", OrderSide)` used to write
+            assert_eq!(TradeSide::parse(&format!("{side:?}")), Some(side));
+            let upper = side.as_str().to_uppercase();
+            assert_eq!(TradeSide::parse(&upper), Some(side));
+        }
+        assert_eq!(BookSide::parse("buy"), None);
+        assert_eq!(TradeSide::parse("bid"), None);
+        assert!(serde_json::from_str::<TradeSide>("\"Buy\"").is_ok());
+        assert!(serde_json::from_str::<BookSide>("\"middle\"").is_err());
+
+        // legacy CSV rows load; a side that is neither is a skipped line
+        let dir = std::env::temp_dir().join(format!("csv_io_sides_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ob = dir.join("orderbook.csv");
+        std::fs::write(
+            &ob,
+            "1,ETH-USD,delta,BID,10,1\n2,ETH-USD,delta,Ask,11,1\n3,ETH-USD,delta,mid,10,1\n",
+        )
+        .unwrap();
+        let (events, stats) = load_book_csv(&ob, "ETH-USD");
+        let sides: Vec<_> = events.iter().map(|e| e.side).collect();
+        assert_eq!(sides, [BookSide::Bid, BookSide::Ask]);
+        assert_eq!(stats.skipped, 1);
+        let tr = dir.join("trades.csv");
+        std::fs::write(
+            &tr,
+            "1,ETH-USD,gui_live,Buy,1\n2,ETH-USD,market,SELL,1\n3,ETH-USD,market,?,1\n",
+        )
+        .unwrap();
+        let (trades, stats) = load_trades_csv(&tr, "ETH-USD");
+        let sides: Vec<_> = trades.iter().map(|t| t.side).collect();
+        assert_eq!(sides, [TradeSide::Buy, TradeSide::Sell]);
+        assert_eq!(stats.skipped, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn interleaved_writers_sort_and_dedup() {
+        // two writers appending to one file, flushed out of order, and both
+        // logging the 0.5 buy at ts 12
+        let rows = [
+            "12,ETH-USD,gui_live,buy,0.5",
+            "10,ETH-USD,gui_live,buy,1",
+            "12,ETH-USD,market,Buy,0.5",
+            "11,ETH-USD,market,sell,2",
+            "12,ETH-USD,market,sell,0.5",
+            "10,ETH-USD,market,buy,1",
+            "13,ETH-USD,gui_live,buy,0.5",
+        ];
+        let path = std::env::temp_dir().join(format!("csv_io_dedup_{}.csv", std::process::id()));
+        std::fs::write(&path, rows.join("\n")).unwrap();
+        let (mut trades, _) = load_trades_csv(&path, "ETH-USD");
+        let _ = std::fs::remove_file(&path);
+
+        let dropped = sort_dedup_trades(&mut trades, |t| {
+            (t.ts, t.side, t.size_str.parse().unwrap_or(0.0))
+        });
+        assert_eq!(dropped, 2);
+        let got: Vec<_> = trades
+            .iter()
+            .map(|t| (t.ts, t.side, t.size_str.as_str()))
+            .collect();
+        use TradeSide::{Buy, Sell};
+        assert_eq!(
+            got,
+            [
+                (10, Buy, "1"),
+                (11, Sell, "2"),
+                (12, Buy, "0.5"),
+                (12, Sell, "0.5"),
+                (13, Buy, "0.5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_source_specs() {
+        let args = [
+            "--merge-source",
+            "binance=/tmp/b",
+            "--merge-source=okx=o",
+            "--merge-source",
+            "bad",
+        ]
+        .map(String::from);
+        let sources = merge_sources_from_args(args);
+        assert_eq!(
+            sources,
+            vec![
+                DataSource {
+                    label: "binance".into(),
+                    dir: PathBuf::from("/tmp/b")
+                },
+                DataSource {
+                    label: "okx".into(),
+                    dir: PathBuf::from("o")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn merged_book_sums_levels_across_venues() {
+        let row = |ts: u64, side: BookSide, price: f64, size: f64| BookCsvEvent {
+            ts,
+            ticker: "ETH-USD".into(),
+            kind: "update".into(),
+            side,
+            price,
+            size,
+            seq: None,
+            venue: String::new(),
+        };
+        let (bid, ask) = (BookSide::Bid, BookSide::Ask);
+        let a = vec![row(1, bid, 100.0, 2.0), row(4, bid, 100.0, 0.0)];
+        let b = vec![row(2, bid, 100.0, 3.0), row(3, ask, 101.0, 1.0)];
+        let merged = merge_book_sources(vec![("a".into(), a), ("b".into(), b)], 100.0);
+
+        let got: Vec<_> = merged
+            .iter()
+            .map(|e| (e.ts, e.venue.as_str(), e.size))
+            .collect();
+        // b's 3.0 stacks on a's 2.0, and a pulling its level leaves b's share
+        assert_eq!(
+            got,
+            vec![(1, "a", 2.0), (2, "b", 5.0), (3, "b", 1.0), (4, "a", 3.0)]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// ladder_app/src/csv_sink.rs
+//
+// Append-only CSV files kept open between rows, shared by the collectors
+// (full_gui11, data_daemon02). A path gets a BufWriter on its first row; rows
+// reach the disk when that buffer fills or at the next `flush_all`, which the
+// owning binary runs every FLUSH_INTERVAL (`flush_periodically`) and once more
+// on shutdown, so tail / replay readers trail by about a second.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use tracing::warn;
+
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// per file; a busy book fills this in well under FLUSH_INTERVAL
+const BUF_BYTES: usize = 64 * 1024;
+
+static FILES: OnceLock<Mutex<HashMap<PathBuf, BufWriter<File>>>> = OnceLock::new();
+
+fn files() -> MutexGuard<'static, HashMap<PathBuf, BufWriter<File>>> {
+    // a panic mid-write leaves at worst a partial buffer; keep going
+    FILES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Append `row` and a newline to `path`, creating the file and its parent
+/// dir on first use.
+pub fn append_row(path: &Path, row: &str) -> io::Result<()> {
+    let mut files = files();
+    let w = match files.entry(path.to_path_buf()) {
+        Entry::Occupied(o) => o.into_mut(),
+        Entry::Vacant(v) => {
+            if let Some(dir) = path.parent() {
+                create_dir_all(dir)?;
+            }
+            let f = OpenOptions::new().create(true).append(true).open(path)?;
+            v.insert(BufWriter::with_capacity(BUF_BYTES, f))
+        }
+    };
+    // one write per row, so the buffer only ever spills whole lines
+    w.write_all(format!("{row}\n").as_bytes())
+}
+
+/// Write out every buffered row.
+pub fn flush_all() {
+    for (path, w) in files().iter_mut() {
+        if let Err(e) = w.flush() {
+            warn!(path = %path.display(), error = %e, "csv flush failed");
+        }
+    }
+}
+
+/// `flush_all` every FLUSH_INTERVAL; spawn once per process.
+pub async fn flush_periodically() {
+    let mut every = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        every.tick().await;
+        flush_all();
+    }
+}
@@ -0,0 +1,123 @@
+// ladder_app/src/footprint.rs
+//
+// Footprint candles: a candle's trades split into price rows of taker buy vs
+// taker sell volume. Candles are built from the mid while trades print at
+// the bid / ask, so a trade priced past the candle's high / low counts in
+// its top / bottom row instead of being dropped.
+
+use crate::candle_agg::Candle;
+use crate::csv_io::{TradeCsvEvent, TradeSide};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FootprintRow {
+    /// Bottom of the row; it spans `price .. price + row height`
+    pub price: f64,
+    pub buy: f64,
+    pub sell: f64,
+}
+
+impl FootprintRow {
+    pub fn total(&self) -> f64 {
+        self.buy + self.sell
+    }
+
+    /// Buy minus sell volume
+    pub fn delta(&self) -> f64 {
+        self.buy - self.sell
+    }
+}
+
+/// Rows of `row_ticks` ticks covering `candle`'s low..=high, bottom first,
+/// holding the trades in `candle.t .. candle.t + tf`. `trades` must be sorted
+/// by ts, as load_trades_csv leaves them; trades recorded without a price are
+/// skipped. Empty for a bad tick or candle.
+pub fn footprint(
+    trades: &[TradeCsvEvent],
+    candle: &Candle,
+    tf: u64,
+    tick: f64,
+    row_ticks: i64,
+) -> Vec<FootprintRow> {
+    let row_h = tick * row_ticks.max(1) as f64;
+    if row_h <= 0.0 || !row_h.is_finite() || candle.low > candle.high {
+        return Vec::new();
+    }
+    // the epsilon keeps a price sitting on a row boundary in the upper row
+    let row_of = |price: f64| (price / row_h + 1e-9).floor() as i64;
+    let (lo, hi) = (row_of(candle.low), row_of(candle.high));
+    let mut rows: Vec<FootprintRow> = (lo..=hi)
+        .map(|i| FootprintRow {
+            price: i as f64 * row_h,
+            ..FootprintRow::default()
+        })
+        .collect();
+
+    let start = trades.partition_point(|t| t.ts < candle.t);
+    let end = trades.partition_point(|t| t.ts < candle.t + tf);
+    for t in &trades[start..end] {
+        let (Some(price), Ok(size)) = (t.price, t.size_str.trim().parse::<f64>()) else {
+            continue;
+        };
+        let row = &mut rows[(row_of(price).clamp(lo, hi) - lo) as usize];
+        match t.side {
+            TradeSide::Buy => row.buy += size.abs(),
+            TradeSide::Sell => row.sell += size.abs(),
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(ts: u64, side: TradeSide, price: Option<f64>, size: &str) -> TradeCsvEvent {
+        TradeCsvEvent {
+            ts,
+            ticker: "ETH-USD".to_string(),
+            source: "market".to_string(),
+            side,
+            size_str: size.to_string(),
+            venue: String::new(),
+            price,
+        }
+    }
+
+    #[test]
+    fn footprint_buckets_trades_by_row_and_side() {
+        let candle = Candle {
+            t: 60,
+            open: 100.0,
+            high: 101.9,
+            low: 99.5,
+            close: 101.0,
+            volume: 0.0,
+        };
+        let trades = [
+            // before the candle
+            trade(59, TradeSide::Buy, Some(100.0), "9"),
+            trade(60, TradeSide::Buy, Some(100.0), "1"),
+            trade(61, TradeSide::Sell, Some(100.4), "2"),
+            trade(70, TradeSide::Buy, Some(101.5), "0.5"),
+            // past the high: top row
+            trade(80, TradeSide::Buy, Some(103.0), "3"),
+            // below the low: bottom row
+            trade(90, TradeSide::Sell, Some(98.0), "4"),
+            // no price, then the next candle
+            trade(100, TradeSide::Sell, None, "7"),
+            trade(120, TradeSide::Sell, Some(100.0), "9"),
+        ];
+
+        // 0.1 tick x 10 = 1.0 rows: 99, 100, 101
+        let rows = footprint(&trades, &candle, 60, 0.1, 10);
+        let got: Vec<_> = rows.iter().map(|r| (r.price, r.buy, r.sell)).collect();
+        assert_eq!(
+            got,
+            vec![(99.0, 0.0, 4.0), (100.0, 1.0, 2.0), (101.0, 3.5, 0.0)]
+        );
+        assert_eq!(rows[1].delta(), -1.0);
+        assert_eq!(rows.iter().map(FootprintRow::total).sum::<f64>(), 10.5);
+
+        assert!(footprint(&trades, &candle, 60, 0.0, 10).is_empty());
+    }
+}
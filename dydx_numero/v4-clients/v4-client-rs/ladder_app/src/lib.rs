@@ -0,0 +1,39 @@
+// ladder_app/src/lib.rs
+//
+// Pieces the GUI binaries (full_gui11, full_gui12, full_gui_x15) used to carry
+// their own copies of. Each binary still owns its UI, layout and app state.
+//
+//   book        price keys (integer BTreeMap keys per ticker scale), LiveBook
+//   bot         Rhai bot inputs/outputs (scope variables), walls, RSI
+//   candle_agg  Candle, CandleAgg and resample (bins that `mod candle_agg;`
+//               include the same file)
+//   candle_render  candle bodies / wicks on an egui_plot chart
+//   csv_io      CSV row types, loaders with LoadStats, --data-dir
+//   csv_sink    append-only data files kept open behind buffered writers
+//   footprint   a candle's trades as buy / sell volume per price row
+//   pairs       two tickers' candles as an aligned ratio/spread, z-score
+//   pnl_hud     position / PnL box painted over a candle chart
+//   repaint     RepaintThrottle: repaint cadence that backs off when idle
+//   replay      ReplayEngine: book, candles and trades as of any ts, no GUI
+//   sim         paper-trading TradingState that fills against a replayed book
+//   snapshot    replaying book rows into bid/ask maps, per-TF replay snapshots
+//   snapshot_worker  replay snapshots rebuilt on a thread, newest request wins
+//   synth       seeded random walks, synthetic book/trade files
+//   time_fmt    now_unix + Unix/Local timestamp display
+
+pub mod book;
+pub mod bot;
+pub mod candle_agg;
+pub mod candle_render;
+pub mod csv_io;
+pub mod csv_sink;
+pub mod footprint;
+pub mod pairs;
+pub mod pnl_hud;
+pub mod repaint;
+pub mod replay;
+pub mod sim;
+pub mod snapshot;
+pub mod snapshot_worker;
+pub mod synth;
+pub mod time_fmt;
@@ -7,21 +7,23 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use ratatui::symbols::Marker;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Chart, Axis, Dataset},
+    widgets::{Axis, Block, Borders, Chart, Dataset, Paragraph},
     Terminal,
 };
-use ratatui::symbols::Marker;
 use tokio::sync::watch;
 use tracing_subscriber;
 
 // prove the crate links; we won't use it yet
 use dydx_client as _;
 
+use ladder_app::synth::{RandomWalk, WalkParams};
+
 #[derive(Clone, Default)]
 struct SideBook {
     // (price, size)
@@ -153,15 +155,25 @@ async fn main() -> Result<()> {
 
     let (tx, rx) = watch::channel(OrderBook::fake());
 
+    // a steady +0.1 per tick unless --seed / --walk-vol / --walk-drift say otherwise
+    let walk_params = WalkParams {
+        seed: 42,
+        volatility: 0.0,
+        drift: 0.1,
+    }
+    .with_args(std::env::args().skip(1));
+
     // background task to slowly nudge prices so you see movement
     tokio::spawn(async move {
+        let mut walk = RandomWalk::new(walk_params);
         let mut book = OrderBook::fake();
         loop {
+            let step = walk.step();
             for (p, _) in &mut book.bids.levels {
-                *p += 0.1;
+                *p += step;
             }
             for (p, _) in &mut book.asks.levels {
-                *p += 0.1;
+                *p += step;
             }
             let _ = tx.send(book.clone());
             tokio::time::sleep(StdDuration::from_millis(500)).await;
@@ -210,10 +222,7 @@ fn ui_loop<B: ratatui::backend::Backend>(
             // top: chart, bottom: ladder
             let vertical = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Percentage(40),
-                    Constraint::Percentage(60),
-                ])
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
                 .split(area);
 
             let chart_area = vertical[0];
@@ -222,10 +231,7 @@ fn ui_loop<B: ratatui::backend::Backend>(
             // bottom split into bids/asks
             let ladder_chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Percentage(50),
-                    Constraint::Percentage(50),
-                ])
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
                 .split(ladder_area);
 
             // ---- depth chart ----
@@ -246,9 +252,7 @@ fn ui_loop<B: ratatui::backend::Backend>(
                     .title("Price")
                     .bounds([min_price, max_price]);
 
-                let y_axis = Axis::default()
-                    .title("Cum Size")
-                    .bounds([0.0, max_size]);
+                let y_axis = Axis::default().title("Cum Size").bounds([0.0, max_size]);
 
                 let chart = Chart::new(vec![bids_dataset, asks_dataset])
                     .block(
@@ -265,9 +269,7 @@ fn ui_loop<B: ratatui::backend::Backend>(
                 f.render_widget(chart, chart_area);
             } else {
                 // if no data, just show an empty block
-                let block = Block::default()
-                    .title(" Depth ")
-                    .borders(Borders::ALL);
+                let block = Block::default().title(" Depth ").borders(Borders::ALL);
                 f.render_widget(block, chart_area);
             }
 
@@ -0,0 +1,140 @@
+// ladder_app/src/pairs.rs
+//
+// Two tickers' candles joined on bucket start, as a ratio or a hedged spread,
+// and a rolling z-score of the result to judge how stretched the pair is.
+
+use crate::candle_agg::Candle;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PairMetric {
+    /// a.close / b.close
+    Ratio,
+    /// a.close - hedge * b.close
+    Spread { hedge: f64 },
+}
+
+impl PairMetric {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PairMetric::Ratio => "Ratio",
+            PairMetric::Spread { .. } => "Spread",
+        }
+    }
+
+    fn apply(&self, a: f64, b: f64) -> Option<f64> {
+        match self {
+            PairMetric::Ratio if b != 0.0 => Some(a / b),
+            PairMetric::Ratio => None,
+            PairMetric::Spread { hedge } => Some(a - hedge * b),
+        }
+    }
+}
+
+/// (t, a.close / b.close) for every bucket both series have. Both must be
+/// sorted by `t`, as CandleAgg keeps them.
+pub fn aligned_ratio(a: &[Candle], b: &[Candle]) -> Vec<(u64, f64)> {
+    aligned(a, b, PairMetric::Ratio)
+}
+
+/// (t, metric of the two closes) for every bucket both series have.
+pub fn aligned(a: &[Candle], b: &[Candle], metric: PairMetric) -> Vec<(u64, f64)> {
+    let mut out = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (ca, cb) = (&a[i], &b[j]);
+        if ca.t < cb.t {
+            i += 1;
+        } else if cb.t < ca.t {
+            j += 1;
+        } else {
+            if let Some(v) = metric.apply(ca.close, cb.close) {
+                out.push((ca.t, v));
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Each point's z-score against the `window` points ending at it (itself
+/// included). The first `window - 1` points and flat windows are skipped.
+pub fn rolling_zscore(points: &[(u64, f64)], window: usize) -> Vec<(u64, f64)> {
+    if window < 2 || points.len() < window {
+        return Vec::new();
+    }
+    let n = window as f64;
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    let mut out = Vec::with_capacity(points.len() + 1 - window);
+    for (k, &(t, v)) in points.iter().enumerate() {
+        sum += v;
+        sum_sq += v * v;
+        if k >= window {
+            let old = points[k - window].1;
+            sum -= old;
+            sum_sq -= old * old;
+        }
+        if k + 1 < window {
+            continue;
+        }
+        let mean = sum / n;
+        let var = (sum_sq / n - mean * mean).max(0.0);
+        let sd = var.sqrt();
+        if sd > 1e-12 * mean.abs().max(1.0) {
+            out.push((t, (v - mean) / sd));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(t: u64, close: f64) -> Candle {
+        Candle {
+            t,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 0.0,
+        }
+    }
+
+    #[test]
+    fn pairs_join_on_bucket_start() {
+        let a = [c(0, 10.0), c(60, 12.0), c(120, 9.0), c(240, 8.0)];
+        // b is missing 120 and has an extra 180
+        let b = [c(0, 5.0), c(60, 4.0), c(180, 3.0), c(240, 2.0)];
+
+        assert_eq!(aligned_ratio(&a, &b), vec![(0, 2.0), (60, 3.0), (240, 4.0)]);
+        assert_eq!(
+            aligned(&a, &b, PairMetric::Spread { hedge: 2.0 }),
+            vec![(0, 0.0), (60, 4.0), (240, 4.0)]
+        );
+
+        // a zero close can't be divided by
+        assert_eq!(aligned_ratio(&a[..1], &[c(0, 0.0)]), vec![]);
+    }
+
+    #[test]
+    fn zscore_measures_the_trailing_window() {
+        let pts: Vec<_> = [1.0, 3.0, 1.0, 3.0, 7.0]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i as u64, *v))
+            .collect();
+        let z = rolling_zscore(&pts, 2);
+        // every 2-point window has sd = |a - b| / 2
+        assert_eq!(z.len(), 4);
+        assert_eq!(z[0], (1, 1.0));
+        assert_eq!(z[1], (2, -1.0));
+        assert_eq!(z[3], (4, 1.0));
+
+        // a flat window has no spread to measure against
+        let flat = [(0, 2.0), (1, 2.0), (2, 2.0)];
+        assert!(rolling_zscore(&flat, 3).is_empty());
+        assert!(rolling_zscore(&pts, 10).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+// ladder_app/src/pnl_hud.rs
+//
+// Position / PnL box in a corner of the candle chart, shared by full_gui11
+// (the live subaccount position) and gui_replay4 (the paper TradingState).
+// It is painted over the plot's screen rect, not into plot space, so it stays
+// put while the chart pans and zooms.
+
+use egui::{Align2, Color32, FontId, Rect, Rounding, Stroke, Ui, Vec2};
+
+// liquidation closer than this (percent of mark) is red, then amber
+const LIQ_DANGER_PCT: f64 = 5.0;
+const LIQ_WARN_PCT: f64 = 15.0;
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HudCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl HudCorner {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HudCorner::TopLeft => "Top left",
+            HudCorner::TopRight => "Top right",
+            HudCorner::BottomLeft => "Bottom left",
+            HudCorner::BottomRight => "Bottom right",
+        }
+    }
+
+    pub fn all() -> &'static [HudCorner] {
+        &[
+            HudCorner::TopLeft,
+            HudCorner::TopRight,
+            HudCorner::BottomLeft,
+            HudCorner::BottomRight,
+        ]
+    }
+
+    fn align(&self) -> Align2 {
+        match self {
+            HudCorner::TopLeft => Align2::LEFT_TOP,
+            HudCorner::TopRight => Align2::RIGHT_TOP,
+            HudCorner::BottomLeft => Align2::LEFT_BOTTOM,
+            HudCorner::BottomRight => Align2::RIGHT_BOTTOM,
+        }
+    }
+}
+
+/// One open position as the HUD shows it, valued at `mark`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PnlHud {
+    pub long: bool,
+    pub size: f64,
+    pub entry: f64,
+    pub mark: f64,
+    pub liquidation: Option<f64>,
+}
+
+impl PnlHud {
+    pub fn unrealized(&self) -> f64 {
+        let per_unit = self.mark - self.entry;
+        if self.long {
+            per_unit * self.size
+        } else {
+            -per_unit * self.size
+        }
+    }
+
+    /// Unrealized PnL in percent of the entry notional.
+    pub fn unrealized_pct(&self) -> f64 {
+        let notional = self.entry * self.size;
+        if notional > 0.0 {
+            self.unrealized() / notional * 100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// How far the mark can still move against the position before it is
+    /// liquidated, in percent of the mark; negative once past it.
+    pub fn liq_distance_pct(&self) -> Option<f64> {
+        let liq = self.liquidation?;
+        if self.mark <= 0.0 {
+            return None;
+        }
+        let against = if self.long {
+            self.mark - liq
+        } else {
+            liq - self.mark
+        };
+        Some(against / self.mark * 100.0)
+    }
+
+    /// Text rows top to bottom with their colors; `price` / `size` format
+    /// numbers at the market's precision.
+    pub fn rows(
+        &self,
+        price: impl Fn(f64) -> String,
+        size: impl Fn(f64) -> String,
+    ) -> Vec<(String, Color32)> {
+        let (side, side_color) = if self.long {
+            ("LONG", Color32::from_rgb(0, 200, 120))
+        } else {
+            ("SHORT", Color32::from_rgb(230, 80, 80))
+        };
+        let pnl = self.unrealized();
+        let pnl_color = if pnl >= 0.0 {
+            Color32::from_rgb(0, 200, 120)
+        } else {
+            Color32::from_rgb(230, 80, 80)
+        };
+        let liq = match (self.liquidation, self.liq_distance_pct()) {
+            (Some(liq), Some(dist)) => {
+                let color = if dist < LIQ_DANGER_PCT {
+                    Color32::from_rgb(255, 60, 60)
+                } else if dist < LIQ_WARN_PCT {
+                    Color32::from_rgb(255, 180, 0)
+                } else {
+                    Color32::LIGHT_GRAY
+                };
+                (format!("Liq {} ({dist:.1}% away)", price(liq)), color)
+            }
+            _ => ("Liq n/a".to_string(), Color32::GRAY),
+        };
+        vec![
+            (
+                format!("{side} {} @ {}", size(self.size), price(self.entry)),
+                side_color,
+            ),
+            (
+                format!("uPnL {pnl:+.2} ({:+.2}%)", self.unrealized_pct()),
+                pnl_color,
+            ),
+            liq,
+        ]
+    }
+}
+
+/// Paints `rows` in a box at `corner` of `frame`, the plot's screen rect.
+pub fn paint(ui: &Ui, frame: Rect, corner: HudCorner, rows: &[(String, Color32)]) {
+    if rows.is_empty() {
+        return;
+    }
+    let painter = ui.painter().with_clip_rect(frame);
+    let font = FontId::monospace(12.0);
+    let galleys: Vec<_> = rows
+        .iter()
+        .map(|(text, color)| painter.layout_no_wrap(text.clone(), font.clone(), *color))
+        .collect();
+    let width = galleys.iter().map(|g| g.size().x).fold(0.0, f32::max);
+    let height: f32 = galleys.iter().map(|g| g.size().y).sum();
+    let size = Vec2::new(width, height) + Vec2::splat(2.0 * PADDING);
+
+    let area = corner
+        .align()
+        .align_size_within_rect(size, frame.shrink(MARGIN));
+    painter.rect(
+        area,
+        Rounding::same(4.0),
+        Color32::from_black_alpha(170),
+        Stroke::new(1.0, Color32::from_gray(90)),
+    );
+    let mut pos = area.min + Vec2::splat(PADDING);
+    for g in galleys {
+        let h = g.size().y;
+        painter.galley(pos, g, Color32::WHITE);
+        pos.y += h;
+    }
+}
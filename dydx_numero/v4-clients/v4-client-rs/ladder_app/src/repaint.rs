@@ -0,0 +1,109 @@
+// ladder_app/src/repaint.rs
+//
+// How often the eframe GUIs ask for a repaint. They poll their feeds from
+// update(), so they can't simply wait for input, but redrawing at full rate
+// while nothing changes burns CPU/GPU for nothing. RepaintThrottle keeps the
+// fast cadence while data or input is arriving, backs off to a slow one once
+// both have been quiet for IDLE_AFTER, and stops asking altogether when the
+// caller says nothing can change without input (a paused replay); egui still
+// repaints on input by itself.
+//
+//   --repaint-ms <ms>       cadence while active
+//   --idle-repaint-ms <ms>  cadence once idle; 0 keeps the fast one throughout
+
+use std::time::{Duration, Instant};
+
+use crate::csv_io::arg_value;
+
+pub const DEFAULT_IDLE_INTERVAL: Duration = Duration::from_millis(500);
+
+// quiet this long (no new data, no input) before throttling
+pub const IDLE_AFTER: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug)]
+pub struct RepaintThrottle {
+    pub interval: Duration,
+    // None: never throttle
+    pub idle_interval: Option<Duration>,
+    last_activity: Instant,
+}
+
+impl RepaintThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            idle_interval: Some(DEFAULT_IDLE_INTERVAL),
+            last_activity: Instant::now(),
+        }
+    }
+
+    /// This throttle with whichever of --repaint-ms / --idle-repaint-ms are
+    /// given; values that don't parse are ignored.
+    pub fn with_args(self, args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        let get = |flag: &str| arg_value(args.iter().cloned(), flag)?.parse::<u64>().ok();
+        Self {
+            interval: get("--repaint-ms")
+                .map(|ms| Duration::from_millis(ms.max(1)))
+                .unwrap_or(self.interval),
+            idle_interval: match get("--idle-repaint-ms") {
+                Some(0) => None,
+                Some(ms) => Some(Duration::from_millis(ms)),
+                None => self.idle_interval,
+            },
+            ..self
+        }
+    }
+
+    /// New data arrived or the user did something: back to the fast cadence.
+    pub fn touch(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn is_idle(&self, now: Instant) -> bool {
+        self.idle_interval.is_some()
+            && now.saturating_duration_since(self.last_activity) >= IDLE_AFTER
+    }
+
+    /// Delay for the next request_repaint_after, or None to wait for input.
+    /// `paused`: nothing on screen can change until the user acts.
+    pub fn next_repaint(&self, now: Instant, paused: bool) -> Option<Duration> {
+        match self.idle_interval {
+            Some(_) if self.is_idle(now) && paused => None,
+            Some(idle) if self.is_idle(now) => Some(idle.max(self.interval)),
+            _ => Some(self.interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_when_quiet_and_snaps_back_on_activity() {
+        let fast = Duration::from_millis(50);
+        let mut t = RepaintThrottle::new(fast);
+        let start = Instant::now();
+        t.touch(start);
+
+        let busy = start + Duration::from_secs(1);
+        assert_eq!(t.next_repaint(busy, false), Some(fast));
+        // a paused replay keeps painting until it has been quiet a while too
+        assert_eq!(t.next_repaint(busy, true), Some(fast));
+
+        let quiet = start + IDLE_AFTER;
+        assert_eq!(t.next_repaint(quiet, false), Some(DEFAULT_IDLE_INTERVAL));
+        assert_eq!(t.next_repaint(quiet, true), None);
+
+        t.touch(quiet);
+        assert_eq!(t.next_repaint(quiet, true), Some(fast));
+
+        // throttle off: always the fast cadence, paused or not
+        let args = ["--repaint-ms", "33", "--idle-repaint-ms=0"].map(String::from);
+        let off = RepaintThrottle::new(fast).with_args(args);
+        let later = Instant::now() + IDLE_AFTER * 2;
+        assert_eq!(off.interval, Duration::from_millis(33));
+        assert_eq!(off.next_repaint(later, true), Some(off.interval));
+    }
+}
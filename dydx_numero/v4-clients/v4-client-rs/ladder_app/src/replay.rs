@@ -0,0 +1,303 @@
+// ladder_app/src/replay.rs
+//
+// Replay without a GUI. ReplayEngine walks one ticker's recorded book rows in
+// ts order and rebuilds the book, a 1s mid candle series and the recent
+// trades as of any ts; the GUIs draw its Snapshot, and backtests or scripts
+// can drive the same engine directly.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::book::{key_to_price, PriceKey, DEFAULT_PRICE_SCALE};
+use crate::candle_agg::{Candle, CandleAgg};
+use crate::csv_io::{
+    data_file, load_book_csv, load_trades_csv, BookCsvEvent, BookSide, LoadStats, TradeCsvEvent,
+};
+use crate::snapshot::{apply_level, simple_mid, SnapshotBlocks, SNAPSHOT_KIND};
+
+/// Most recent trades a Snapshot carries.
+pub const SNAPSHOT_TRADES: usize = 200;
+
+/// The price candles are built from, given bids, asks and the price scale.
+pub type MidFn = fn(&BTreeMap<PriceKey, f64>, &BTreeMap<PriceKey, f64>, f64) -> Option<f64>;
+
+/// One ticker's recorded events, held in memory.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayData {
+    pub ticker: String,
+    pub book_events: Vec<BookCsvEvent>,
+    pub trade_events: Vec<TradeCsvEvent>,
+    pub price_scale: f64,
+    pub load_stats: LoadStats,
+}
+
+impl ReplayData {
+    /// orderbook_<ticker> and trades_<ticker> from `dir`, in either format
+    /// (see data_file). None when neither has a row for the ticker.
+    pub fn load(dir: &Path, ticker: &str) -> Option<Self> {
+        let ob_path = data_file(dir, &format!("orderbook_{ticker}"));
+        let tr_path = data_file(dir, &format!("trades_{ticker}"));
+        let (book_events, mut load_stats) = load_book_csv(&ob_path, ticker);
+        let (trade_events, trade_stats) = load_trades_csv(&tr_path, ticker);
+        load_stats.merge(&trade_stats);
+        if book_events.is_empty() && trade_events.is_empty() {
+            return None;
+        }
+        Some(Self {
+            ticker: ticker.to_string(),
+            book_events,
+            trade_events,
+            // files carry no tick size
+            price_scale: DEFAULT_PRICE_SCALE,
+            load_stats,
+        })
+    }
+}
+
+/// Replayed state as of `ts`.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub ts: u64,
+    pub bids: BTreeMap<PriceKey, f64>,
+    pub asks: BTreeMap<PriceKey, f64>,
+    pub price_scale: f64,
+    /// 1s candles of the mid up to `ts`; resample for coarser TFs
+    pub candles: Vec<Candle>,
+    /// up to SNAPSHOT_TRADES trades with ts <= `ts`, oldest first
+    pub trades: Vec<TradeCsvEvent>,
+}
+
+impl Snapshot {
+    /// (price, size) of the highest bid.
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        let (k, s) = self.bids.iter().next_back()?;
+        Some((key_to_price(*k, self.price_scale), *s))
+    }
+
+    /// (price, size) of the lowest ask.
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        let (k, s) = self.asks.iter().next()?;
+        Some((key_to_price(*k, self.price_scale), *s))
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        simple_mid(&self.bids, &self.asks, self.price_scale)
+    }
+}
+
+/// Cursor over one ticker's events. Seeking forward applies only the rows in
+/// between; seeking backward replays from the first row.
+///
+/// ```
+/// use std::path::Path;
+/// use ladder_app::replay::{ReplayData, ReplayEngine};
+///
+/// let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay");
+/// let data = ReplayData::load(&dir, "ETH-USD").unwrap();
+/// let mut engine = ReplayEngine::new(&data);
+/// engine.seek(1_700_000_010);
+/// let snap = engine.snapshot();
+/// assert_eq!(snap.best_bid(), Some((100.0, 2.0)));
+/// assert_eq!(snap.best_ask(), Some((101.0, 1.5)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplayEngine<'a> {
+    book_events: &'a [BookCsvEvent],
+    trade_events: &'a [TradeCsvEvent],
+    scale: f64,
+    mid: MidFn,
+    // index of the next book event to apply
+    next: usize,
+    ts: u64,
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    blocks: SnapshotBlocks,
+    base: CandleAgg,
+}
+
+impl<'a> ReplayEngine<'a> {
+    pub fn new(data: &'a ReplayData) -> Self {
+        Self::over(&data.book_events, &data.trade_events, data.price_scale)
+    }
+
+    /// An engine over events kept elsewhere; both slices sorted by ts.
+    pub fn over(
+        book_events: &'a [BookCsvEvent],
+        trade_events: &'a [TradeCsvEvent],
+        scale: f64,
+    ) -> Self {
+        Self {
+            book_events,
+            trade_events,
+            scale,
+            mid: simple_mid,
+            next: 0,
+            ts: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            blocks: SnapshotBlocks::default(),
+            base: CandleAgg::new(1),
+        }
+    }
+
+    /// Build candles from `mid` instead of simple_mid.
+    pub fn with_mid(mut self, mid: MidFn) -> Self {
+        self.mid = mid;
+        self
+    }
+
+    pub fn ts(&self) -> u64 {
+        self.ts
+    }
+
+    pub fn bids(&self) -> &BTreeMap<PriceKey, f64> {
+        &self.bids
+    }
+
+    pub fn asks(&self) -> &BTreeMap<PriceKey, f64> {
+        &self.asks
+    }
+
+    /// (price, size) of the highest bid, as Snapshot::best_bid.
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        let (k, s) = self.bids.iter().next_back()?;
+        Some((key_to_price(*k, self.scale), *s))
+    }
+
+    /// (price, size) of the lowest ask, as Snapshot::best_ask.
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        let (k, s) = self.asks.iter().next()?;
+        Some((key_to_price(*k, self.scale), *s))
+    }
+
+    /// 1s candles so far, without copying them into a Snapshot.
+    pub fn candles(&self) -> &[Candle] {
+        self.base.series()
+    }
+
+    /// Apply every book event with ts <= `ts` and stand at `ts`.
+    pub fn seek(&mut self, ts: u64) {
+        if ts < self.ts {
+            self.rewind();
+        }
+        while let Some(e) = self.book_events.get(self.next) {
+            if e.ts > ts {
+                break;
+            }
+            self.next += 1;
+            self.apply_row(e.ts, &e.kind, e.side, e.price, e.size, e.seq);
+        }
+        self.ts = ts;
+    }
+
+    /// Move to the ts of the next book event and apply every event at it.
+    /// None once all events are applied.
+    pub fn step_to_next_event(&mut self) -> Option<u64> {
+        let ts = self.book_events.get(self.next)?.ts;
+        self.seek(ts);
+        Some(ts)
+    }
+
+    /// Apply one row that isn't in the engine's slice, e.g. streamed straight
+    /// from a file into an engine built over no book events. Rows must come in
+    /// ts order; snapshot rows restate levels, so they add no volume.
+    pub fn apply_row(
+        &mut self,
+        ts: u64,
+        kind: &str,
+        side: BookSide,
+        price: f64,
+        size: f64,
+        seq: Option<u64>,
+    ) {
+        if self.blocks.starts_block(kind, seq) {
+            self.bids.clear();
+            self.asks.clear();
+        }
+        apply_level(
+            &mut self.bids,
+            &mut self.asks,
+            side,
+            price,
+            size,
+            self.scale,
+        );
+        self.ts = self.ts.max(ts);
+
+        if let Some(mid) = (self.mid)(&self.bids, &self.asks, self.scale) {
+            let vol = if kind == SNAPSHOT_KIND {
+                0.0
+            } else {
+                size.abs()
+            };
+            self.base.update(ts, mid, vol);
+        }
+    }
+
+    /// Book, candles and the last SNAPSHOT_TRADES trades as of ts().
+    pub fn snapshot(&self) -> Snapshot {
+        let end = self.trade_events.partition_point(|t| t.ts <= self.ts);
+        let start = end.saturating_sub(SNAPSHOT_TRADES);
+        Snapshot {
+            ts: self.ts,
+            bids: self.bids.clone(),
+            asks: self.asks.clone(),
+            price_scale: self.scale,
+            candles: self.base.series().to_vec(),
+            trades: self.trade_events[start..end].to_vec(),
+        }
+    }
+
+    fn rewind(&mut self) {
+        self.next = 0;
+        self.ts = 0;
+        self.bids.clear();
+        self.asks.clear();
+        self.blocks = SnapshotBlocks::default();
+        self.base = CandleAgg::new(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> ReplayData {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay");
+        ReplayData::load(&dir, "ETH-USD").expect("fixture loads")
+    }
+
+    #[test]
+    fn seeking_back_matches_a_fresh_replay() {
+        let data = data();
+        let mut fresh = ReplayEngine::new(&data);
+        fresh.seek(1_700_000_006);
+
+        let mut engine = ReplayEngine::new(&data);
+        engine.seek(1_700_000_020);
+        assert_eq!(engine.snapshot().best_ask(), Some((100.5, 3.0)));
+        assert_eq!(engine.best_ask(), engine.snapshot().best_ask());
+        engine.seek(1_700_000_006);
+
+        let (a, b) = (engine.snapshot(), fresh.snapshot());
+        assert_eq!(a.bids, b.bids);
+        assert_eq!(a.asks, b.asks);
+        assert_eq!(a.candles.len(), b.candles.len());
+        assert_eq!(a.trades.len(), 1);
+    }
+
+    #[test]
+    fn steps_visit_each_event_ts_once() {
+        let data = data();
+        let mut engine = ReplayEngine::new(&data);
+        let mut seen = Vec::new();
+        while let Some(ts) = engine.step_to_next_event() {
+            seen.push(ts);
+        }
+        assert_eq!(
+            seen,
+            [1_700_000_000, 1_700_000_005, 1_700_000_008, 1_700_000_012]
+        );
+        assert_eq!(engine.snapshot().mid(), Some(100.25));
+    }
+}
@@ -0,0 +1,731 @@
+// ladder_app/src/sim.rs
+//
+// The paper-trading sim: one isolated position against a replayed book,
+// with fees, TP/SL, funding and liquidation, plus a log of closed round
+// trips. gui_replay4 drives it from its trading panel and the backtest
+// binary from a Rhai script, so both fill the same way.
+
+use std::collections::BTreeMap;
+
+use crate::book::{key_to_price, PriceKey};
+
+/// The book a sim fill walks: bid/ask maps keyed at `scale`.
+#[derive(Clone, Copy, Debug)]
+pub struct Depth<'a> {
+    pub bids: &'a BTreeMap<PriceKey, f64>,
+    pub asks: &'a BTreeMap<PriceKey, f64>,
+    pub scale: f64,
+}
+
+/// dYdX v4 maintenance margin fraction for BTC/ETH.
+pub const DEFAULT_MAINT_RATE: f64 = 0.03;
+
+/// Mark at which a position's equity falls to its maintenance margin, the
+/// level `TradingState::check_liquidation` acts on. `equity` is valued at
+/// `mark` and only this position's PnL is assumed to move it from there.
+/// None when flat, or for a long whose equity covers the whole notional.
+pub fn liquidation_price(
+    long: bool,
+    size: f64,
+    equity: f64,
+    mark: f64,
+    maint_rate: f64,
+) -> Option<f64> {
+    if !(size > 0.0 && mark > 0.0 && equity.is_finite() && (0.0..1.0).contains(&maint_rate)) {
+        return None;
+    }
+    // equity + size * (p - mark) = size * p * maint_rate, and mirrored for shorts
+    let per_unit = equity / size;
+    let p = if long {
+        (mark - per_unit) / (1.0 - maint_rate)
+    } else {
+        (mark + per_unit) / (1.0 + maint_rate)
+    };
+    (p > 0.0).then_some(p)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionSide {
+    Flat,
+    Long,
+    Short,
+}
+
+impl PositionSide {
+    // side of the order that exits this position
+    pub fn opposite(&self) -> PositionSide {
+        match self {
+            PositionSide::Flat => PositionSide::Flat,
+            PositionSide::Long => PositionSide::Short,
+            PositionSide::Short => PositionSide::Long,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PositionSide::Flat => "FLAT",
+            PositionSide::Long => "LONG",
+            PositionSide::Short => "SHORT",
+        }
+    }
+}
+
+/// Walk the book for a market order of `size`: Long buys up the asks,
+/// Short sells down the bids. Returns (avg price, filled size); filled is
+/// short of `size` when the book runs out, and 0 for Flat or an empty side.
+pub fn fill_price(book: &Depth, side: PositionSide, size: f64) -> (f64, f64) {
+    let levels: Box<dyn Iterator<Item = (&PriceKey, &f64)>> = match side {
+        PositionSide::Long => Box::new(book.asks.iter()),
+        PositionSide::Short => Box::new(book.bids.iter().rev()),
+        PositionSide::Flat => return (0.0, 0.0),
+    };
+    let mut filled = 0.0;
+    let mut cost = 0.0;
+    for (k, s) in levels {
+        let want = size - filled;
+        if want <= 0.0 {
+            break;
+        }
+        let take = s.min(want);
+        cost += take * key_to_price(*k, book.scale);
+        // land exactly on `size` once a level covers the rest
+        filled = if take < want { filled + take } else { size };
+    }
+    if filled <= 0.0 {
+        return (0.0, 0.0);
+    }
+    (cost / filled, filled)
+}
+
+// equity samples kept for the curve; oldest are dropped past this
+pub const EQUITY_CURVE_MAX: usize = 20_000;
+
+// why a sim position was closed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    Manual,
+    TakeProfit,
+    StopLoss,
+    Liquidation,
+}
+
+impl ExitReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExitReason::Manual => "manual",
+            ExitReason::TakeProfit => "tp",
+            ExitReason::StopLoss => "sl",
+            ExitReason::Liquidation => "liquidation",
+        }
+    }
+}
+
+// one closed round trip of the sim
+#[derive(Clone, Debug)]
+pub struct TradeLogEntry {
+    pub open_ts: u64,
+    pub close_ts: u64,
+    pub side: PositionSide,
+    pub size: f64,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    // open + close fees; `pnl` is already net of them
+    pub fees: f64,
+    pub pnl: f64,
+    pub reason: ExitReason,
+}
+
+#[derive(Clone, Debug)]
+pub struct TradingState {
+    pub wallet_usdc: f64,
+    pub margin: f64,
+    pub deposit_amount: f64,
+    pub withdraw_amount: f64,
+    pub leverage: f64,
+    pub position: f64,
+    pub side: PositionSide,
+    pub entry_price: Option<f64>,
+    pub realized_pnl: f64,
+    pub take_profit: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub maint_rate: f64,
+    // fee rates as fractions of fill notional
+    pub taker_fee: f64,
+    pub maker_fee: f64,
+    // manual and TP fills rest as limits (maker); SL and liquidation always take
+    pub fill_as_maker: bool,
+    pub entry_fee: f64,
+    pub fees_paid: f64,
+    // outcome of the last taker fill (slippage / shortfall), for the panel
+    pub fill_note: Option<String>,
+    pub last_liq_price: Option<f64>,
+    pub last_liq_time: Option<u64>,
+    pub liquidated_flag: bool,
+    pub open_ts: Option<u64>,
+    pub trade_log: Vec<TradeLogEntry>,
+    pub equity_curve: Vec<(u64, f64)>,
+    // funding (no indexer offline, so the rate is entered by hand)
+    pub accrue_funding: bool,
+    pub funding_rate_1h: f64, // fraction per hour, longs pay when > 0
+    pub funding_paid: f64,
+    pub last_funding_ts: Option<u64>,
+}
+
+impl Default for TradingState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradingState {
+    pub fn new() -> Self {
+        Self {
+            wallet_usdc: 5_000.0,
+            margin: 100.0,
+            deposit_amount: 100.0,
+            withdraw_amount: 100.0,
+            leverage: 5.0,
+            position: 0.0,
+            side: PositionSide::Flat,
+            entry_price: None,
+            realized_pnl: 0.0,
+            take_profit: None,
+            stop_loss: None,
+            // dYdX v4: BTC/ETH maintenance fraction, tier-1 taker/maker fees
+            maint_rate: DEFAULT_MAINT_RATE,
+            taker_fee: 0.0005,
+            maker_fee: 0.0001,
+            fill_as_maker: false,
+            entry_fee: 0.0,
+            fees_paid: 0.0,
+            fill_note: None,
+            last_liq_price: None,
+            last_liq_time: None,
+            liquidated_flag: false,
+            open_ts: None,
+            trade_log: Vec::new(),
+            equity_curve: Vec::new(),
+            accrue_funding: false,
+            funding_rate_1h: 0.0,
+            funding_paid: 0.0,
+            last_funding_ts: None,
+        }
+    }
+
+    pub fn deposit_to_margin(&mut self, amount: f64) {
+        if amount <= 0.0 {
+            return;
+        }
+        let amt = amount.min(self.wallet_usdc);
+        if amt <= 0.0 {
+            return;
+        }
+        self.wallet_usdc -= amt;
+        self.margin += amt;
+    }
+
+    pub fn withdraw_from_margin(&mut self, amount: f64) {
+        if amount <= 0.0 {
+            return;
+        }
+        let amt = amount.min(self.margin);
+        if amt <= 0.0 {
+            return;
+        }
+        self.margin -= amt;
+        self.wallet_usdc += amt;
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.margin * self.leverage
+    }
+
+    pub fn max_position_units(&self, mark: f64) -> f64 {
+        if mark <= 0.0 {
+            return 0.0;
+        }
+        (self.margin * self.leverage / mark).max(0.0)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.entry_price.is_some()
+            && self.position > 0.0
+            && !matches!(self.side, PositionSide::Flat)
+    }
+
+    pub fn unrealized_pnl(&self, mark: f64) -> f64 {
+        if let Some(entry) = self.entry_price {
+            match self.side {
+                PositionSide::Long => (mark - entry) * self.position,
+                PositionSide::Short => (entry - mark) * self.position,
+                PositionSide::Flat => 0.0,
+            }
+        } else {
+            0.0
+        }
+    }
+
+    pub fn equity(&self, mark: f64) -> f64 {
+        self.margin + self.realized_pnl + self.unrealized_pnl(mark)
+    }
+
+    pub fn maintenance_margin(&self, mark: f64) -> f64 {
+        let notional = self.position * mark;
+        notional * self.maint_rate
+    }
+
+    fn fee_rate(&self, maker_ok: bool) -> f64 {
+        if maker_ok && self.fill_as_maker {
+            self.maker_fee
+        } else {
+            self.taker_fee
+        }
+    }
+
+    // fee on a fill of `units` at `mark`, taken from margin
+    fn charge_fee(&mut self, units: f64, mark: f64, maker_ok: bool) -> f64 {
+        let fee = units * mark * self.fee_rate(maker_ok);
+        self.margin -= fee;
+        self.realized_pnl -= fee;
+        self.fees_paid += fee;
+        fee
+    }
+
+    // Price and size for a fill of `units` on `side`. Maker fills rest at
+    // `mark`; taker fills walk the book (see fill_price), may come up short
+    // of `units` and leave a note for the panel.
+    fn fill(
+        &mut self,
+        book: &Depth,
+        side: PositionSide,
+        units: f64,
+        mark: f64,
+        maker_ok: bool,
+    ) -> Option<(f64, f64)> {
+        if maker_ok && self.fill_as_maker {
+            return Some((mark, units));
+        }
+        let (avg, filled) = fill_price(book, side, units);
+        if filled <= 0.0 {
+            let book_side = match side {
+                PositionSide::Long => "ask",
+                _ => "bid",
+            };
+            self.fill_note = Some(format!("no {book_side} depth, nothing filled"));
+            return None;
+        }
+        self.fill_note = Some(if filled < units {
+            format!(
+                "filled {filled:.4} of {units:.4} @ {avg:.2}, book short by {:.4}",
+                units - filled
+            )
+        } else {
+            format!("filled {filled:.4} @ {avg:.2} (mark {mark:.2})")
+        });
+        Some((avg, filled))
+    }
+
+    pub fn open_at(&mut self, book: &Depth, mark: f64, ts: u64) {
+        if self.is_open() || self.side == PositionSide::Flat {
+            return;
+        }
+        if self.margin <= 0.0 || self.leverage <= 0.0 || mark <= 0.0 {
+            return;
+        }
+
+        if self.position <= 0.0 {
+            self.position = self.max_position_units(mark);
+        } else {
+            let maxu = self.max_position_units(mark);
+            if self.position > maxu {
+                self.position = maxu;
+            }
+        }
+
+        let Some((price, filled)) = self.fill(book, self.side, self.position, mark, true) else {
+            return;
+        };
+        self.position = filled;
+        self.entry_price = Some(price);
+        self.open_ts = Some(ts);
+        self.liquidated_flag = false;
+        self.entry_fee = self.charge_fee(filled, price, true);
+    }
+
+    /// Scale into the open position; the entry becomes the size-weighted
+    /// average. Capped at max_position_units.
+    pub fn add_to_position(&mut self, book: &Depth, units: f64, mark: f64) {
+        if !self.is_open() || mark <= 0.0 {
+            return;
+        }
+        let units = units.min(self.max_position_units(mark) - self.position);
+        if units <= 0.0 {
+            return;
+        }
+        let Some((price, filled)) = self.fill(book, self.side, units, mark, true) else {
+            return;
+        };
+        let entry = self.entry_price.unwrap_or(price);
+        let total = self.position + filled;
+        self.entry_price = Some((entry * self.position + price * filled) / total);
+        self.entry_fee += self.charge_fee(filled, price, true);
+        self.position = total;
+    }
+
+    /// Close `units` of the open position and realize their PnL; the rest
+    /// keeps its entry. Reducing by the whole position is a full close.
+    pub fn reduce_position(&mut self, book: &Depth, units: f64, mark: f64, ts: u64) {
+        if !self.is_open() || units <= 0.0 {
+            return;
+        }
+        let units = units.min(self.position);
+        let exit_side = self.side.opposite();
+        if let Some((price, filled)) = self.fill(book, exit_side, units, mark, true) {
+            self.exit_units(filled, price, ts, ExitReason::Manual);
+        }
+    }
+
+    fn record_exit(&mut self, mark: f64, ts: u64, upnl: f64, exit_fee: f64, reason: ExitReason) {
+        let fees = self.entry_fee + exit_fee;
+        self.trade_log.push(TradeLogEntry {
+            open_ts: self.open_ts.take().unwrap_or(ts),
+            close_ts: ts,
+            side: self.side,
+            size: self.position,
+            entry_price: self.entry_price.unwrap_or(mark),
+            exit_price: mark,
+            fees,
+            pnl: upnl - fees,
+            reason,
+        });
+    }
+
+    /// Exit through the book; a fill short of the whole position leaves the
+    /// rest open.
+    pub fn close_at(&mut self, book: &Depth, mark: f64, ts: u64, reason: ExitReason) {
+        if !self.is_open() {
+            return;
+        }
+        let maker_ok = matches!(reason, ExitReason::Manual | ExitReason::TakeProfit);
+        let exit_side = self.side.opposite();
+        if let Some((price, filled)) = self.fill(book, exit_side, self.position, mark, maker_ok) {
+            self.exit_units(filled, price, ts, reason);
+        }
+    }
+
+    // realize `units` filled at `price`; all of them closes the position
+    fn exit_units(&mut self, units: f64, price: f64, ts: u64, reason: ExitReason) {
+        let maker_ok = matches!(reason, ExitReason::Manual | ExitReason::TakeProfit);
+        if units < self.position {
+            let upnl = self.unrealized_pnl(price) * units / self.position;
+            let entry_share = self.entry_fee * units / self.position;
+            let fees = entry_share + self.charge_fee(units, price, maker_ok);
+            self.trade_log.push(TradeLogEntry {
+                open_ts: self.open_ts.unwrap_or(ts),
+                close_ts: ts,
+                side: self.side,
+                size: units,
+                entry_price: self.entry_price.unwrap_or(price),
+                exit_price: price,
+                fees,
+                pnl: upnl - fees,
+                reason,
+            });
+
+            self.margin += upnl;
+            self.realized_pnl += upnl;
+            self.entry_fee -= entry_share;
+            self.position -= units;
+            return;
+        }
+
+        let upnl = self.unrealized_pnl(price);
+        let exit_fee = self.charge_fee(self.position, price, maker_ok);
+        self.record_exit(price, ts, upnl, exit_fee, reason);
+
+        self.margin += upnl;
+        self.realized_pnl += upnl;
+        if self.margin < 0.0 {
+            self.margin = 0.0;
+        }
+
+        self.position = 0.0;
+        self.entry_price = None;
+        self.side = PositionSide::Flat;
+        self.take_profit = None;
+        self.stop_loss = None;
+    }
+
+    pub fn liquidate_at(&mut self, mark: f64, ts: u64) {
+        if !self.is_open() {
+            return;
+        }
+
+        let upnl = self.unrealized_pnl(mark);
+        let exit_fee = self.charge_fee(self.position, mark, false);
+        self.record_exit(mark, ts, upnl, exit_fee, ExitReason::Liquidation);
+
+        self.margin += upnl;
+        self.realized_pnl += upnl;
+
+        self.margin = 0.0;
+
+        self.position = 0.0;
+        self.entry_price = None;
+        self.side = PositionSide::Flat;
+        self.take_profit = None;
+        self.stop_loss = None;
+
+        self.last_liq_price = Some(mark);
+        self.last_liq_time = Some(ts);
+        self.liquidated_flag = true;
+    }
+
+    pub fn bump_tp(&mut self, mark: f64, delta: f64) {
+        let base = self.take_profit.unwrap_or(mark);
+        self.take_profit = Some(base + delta);
+    }
+
+    pub fn bump_sl(&mut self, mark: f64, delta: f64) {
+        let base = self.stop_loss.unwrap_or(mark);
+        self.stop_loss = Some(base + delta);
+    }
+
+    pub fn check_tp_sl(&mut self, book: &Depth, mark: f64, ts: u64) {
+        if !self.is_open() {
+            return;
+        }
+        let tp = self.take_profit;
+        let sl = self.stop_loss;
+
+        match self.side {
+            PositionSide::Long => {
+                if let Some(tp) = tp {
+                    if mark >= tp {
+                        self.close_at(book, mark, ts, ExitReason::TakeProfit);
+                        return;
+                    }
+                }
+                if let Some(sl) = sl {
+                    if mark <= sl {
+                        self.close_at(book, mark, ts, ExitReason::StopLoss);
+                    }
+                }
+            }
+            PositionSide::Short => {
+                if let Some(tp) = tp {
+                    if mark <= tp {
+                        self.close_at(book, mark, ts, ExitReason::TakeProfit);
+                        return;
+                    }
+                }
+                if let Some(sl) = sl {
+                    if mark >= sl {
+                        self.close_at(book, mark, ts, ExitReason::StopLoss);
+                    }
+                }
+            }
+            PositionSide::Flat => {}
+        }
+    }
+
+    /// Accrue funding on the open position for the sim time since the last call.
+    pub fn accrue_funding_at(&mut self, mark: f64, ts: u64) {
+        let last = self.last_funding_ts.replace(ts);
+        if !self.accrue_funding || !self.is_open() {
+            return;
+        }
+        let dt = match last {
+            Some(prev) if ts > prev => (ts - prev) as f64,
+            _ => return,
+        };
+
+        let payment = self.position * mark * self.funding_rate_1h * dt / 3600.0;
+        let pnl = match self.side {
+            PositionSide::Long => -payment,
+            PositionSide::Short => payment,
+            PositionSide::Flat => 0.0,
+        };
+
+        self.margin += pnl;
+        self.realized_pnl += pnl;
+        self.funding_paid -= pnl;
+    }
+
+    /// Append (ts, equity) for this sim step; same-ts samples overwrite.
+    pub fn record_equity(&mut self, ts: u64, mark: f64) {
+        let eq = self.equity(mark);
+        if let Some(last) = self.equity_curve.last_mut() {
+            if last.0 == ts {
+                last.1 = eq;
+                return;
+            }
+        }
+        self.equity_curve.push((ts, eq));
+
+        // drop a chunk at a time instead of shifting on every push
+        if self.equity_curve.len() > EQUITY_CURVE_MAX {
+            let excess = self.equity_curve.len() - EQUITY_CURVE_MAX + EQUITY_CURVE_MAX / 10;
+            self.equity_curve.drain(..excess);
+        }
+    }
+
+    pub fn equity_curve(&self) -> &[(u64, f64)] {
+        &self.equity_curve
+    }
+
+    /// Largest peak-to-trough equity decline over the recorded curve.
+    pub fn max_drawdown(&self) -> f64 {
+        let mut peak = f64::MIN;
+        let mut max_dd = 0.0_f64;
+        for (_, eq) in &self.equity_curve {
+            peak = peak.max(*eq);
+            max_dd = max_dd.max(peak - eq);
+        }
+        max_dd
+    }
+
+    /// Net PnL, win rate (0..1) and max drawdown of cumulative realized PnL
+    /// over the trade log.
+    pub fn log_totals(&self) -> (f64, f64, f64) {
+        let mut net = 0.0;
+        let mut peak = 0.0_f64;
+        let mut max_dd = 0.0_f64;
+        let mut wins = 0usize;
+
+        for t in &self.trade_log {
+            net += t.pnl;
+            if t.pnl > 0.0 {
+                wins += 1;
+            }
+            peak = peak.max(net);
+            max_dd = max_dd.max(peak - net);
+        }
+
+        let win_rate = if self.trade_log.is_empty() {
+            0.0
+        } else {
+            wins as f64 / self.trade_log.len() as f64
+        };
+        (net, win_rate, max_dd)
+    }
+
+    /// A market order of `units` on `side`, the way a bot sends one: opens
+    /// when flat, adds on the same side and reduces (never flips) on the
+    /// other.
+    pub fn market_order(
+        &mut self,
+        book: &Depth,
+        side: PositionSide,
+        units: f64,
+        mark: f64,
+        ts: u64,
+    ) {
+        if units <= 0.0 || side == PositionSide::Flat {
+            return;
+        }
+        if !self.is_open() {
+            self.side = side;
+            self.position = units;
+            self.open_at(book, mark, ts);
+        } else if self.side == side {
+            self.add_to_position(book, units, mark);
+        } else {
+            self.reduce_position(book, units, mark, ts);
+        }
+    }
+
+    /// Where check_liquidation would close the open position.
+    pub fn liquidation_price(&self, mark: f64) -> Option<f64> {
+        if !self.is_open() {
+            return None;
+        }
+        let long = self.side == PositionSide::Long;
+        let equity = self.equity(mark);
+        liquidation_price(long, self.position, equity, mark, self.maint_rate)
+    }
+
+    pub fn check_liquidation(&mut self, mark: f64, ts: u64) {
+        if !self.is_open() {
+            return;
+        }
+        let equity = self.equity(mark);
+        let maint = self.maintenance_margin(mark);
+
+        if equity <= maint {
+            self.liquidate_at(mark, ts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::price_to_key;
+
+    #[test]
+    fn market_orders_open_add_and_reduce() {
+        let scale = 10_000.0;
+        let bids = BTreeMap::from([(price_to_key(99.0, scale), 10.0)]);
+        let asks = BTreeMap::from([
+            (price_to_key(101.0, scale), 1.0),
+            (price_to_key(102.0, scale), 10.0),
+        ]);
+        let book = Depth {
+            bids: &bids,
+            asks: &asks,
+            scale,
+        };
+
+        // 2 units walk two ask levels
+        assert_eq!(fill_price(&book, PositionSide::Long, 2.0), (101.5, 2.0));
+
+        let mut sim = TradingState::new();
+        sim.market_order(&book, PositionSide::Long, 1.0, 100.0, 1);
+        assert_eq!(sim.side, PositionSide::Long);
+        assert_eq!(sim.entry_price, Some(101.0));
+
+        sim.market_order(&book, PositionSide::Long, 1.0, 100.0, 2);
+        assert_eq!(sim.position, 2.0);
+
+        // a sell reduces, it doesn't flip
+        sim.market_order(&book, PositionSide::Short, 5.0, 100.0, 3);
+        assert!(!sim.is_open());
+        assert_eq!(sim.trade_log.len(), 1);
+        assert!(sim.trade_log[0].pnl < 0.0);
+    }
+
+    #[test]
+    fn liquidation_price_is_where_equity_meets_maintenance() {
+        let scale = 10_000.0;
+        let bids = BTreeMap::from([(price_to_key(100.0, scale), 100.0)]);
+        let asks = BTreeMap::from([(price_to_key(100.0, scale), 100.0)]);
+        let book = Depth {
+            bids: &bids,
+            asks: &asks,
+            scale,
+        };
+
+        for side in [PositionSide::Long, PositionSide::Short] {
+            let mut sim = TradingState::new();
+            sim.market_order(&book, side, 5.0, 100.0, 1);
+            let liq = sim.liquidation_price(100.0).unwrap();
+            let gap = sim.equity(liq) - sim.maintenance_margin(liq);
+            assert!(gap.abs() < 1e-6, "{side:?}: gap {gap} at {liq}");
+            // the same answer from any mark the equity is valued at
+            assert!((sim.liquidation_price(95.0).unwrap() - liq).abs() < 1e-6);
+
+            let beyond = if side == PositionSide::Long {
+                liq - 0.01
+            } else {
+                liq + 0.01
+            };
+            sim.check_liquidation(beyond, 2);
+            assert!(sim.liquidated_flag, "{side:?} not liquidated past {liq}");
+        }
+
+        assert_eq!(liquidation_price(true, 0.0, 100.0, 100.0, 0.03), None);
+        // a long backed by more than its notional can't be liquidated
+        assert_eq!(liquidation_price(true, 1.0, 150.0, 100.0, 0.03), None);
+    }
+}
@@ -0,0 +1,198 @@
+// ladder_app/src/snapshot.rs
+//
+// The step every replay shares: one CSV book row applied to the bid/ask maps.
+// What gets built on top (candles, trades, the Snapshot struct) is per binary.
+//
+// Collectors also write the whole book now and then as a snapshot block:
+// consecutive rows with kind SNAPSHOT_KIND, one per level, best prices first,
+// all with the same ts and the same seq column (the message id the book was
+// at). Replaying a block replaces the book instead of adding to it, so a
+// dropped delta only corrupts the book until the next block.
+//
+// compute_snapshot_for is the replay view's snapshot: a ReplayEngine seeked to
+// a ts, with its 1s candles resampled to every timeframe the view offers.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::book::{key_to_price, price_to_key, PriceKey};
+use crate::candle_agg::{resample, Candle};
+use crate::csv_io::{BookSide, TradeCsvEvent};
+use crate::replay::ReplayEngine;
+
+/// Set `side`'s level at `price` to `size`; size 0 removes it.
+pub fn apply_level(
+    bids: &mut BTreeMap<PriceKey, f64>,
+    asks: &mut BTreeMap<PriceKey, f64>,
+    side: BookSide,
+    price: f64,
+    size: f64,
+    scale: f64,
+) {
+    let map = match side {
+        BookSide::Bid => bids,
+        BookSide::Ask => asks,
+    };
+    let key = price_to_key(price, scale);
+    if size == 0.0 {
+        map.remove(&key);
+    } else {
+        map.insert(key, size);
+    }
+}
+
+/// (best bid + best ask) / 2, None while either side is empty.
+pub fn simple_mid(
+    bids: &BTreeMap<PriceKey, f64>,
+    asks: &BTreeMap<PriceKey, f64>,
+    scale: f64,
+) -> Option<f64> {
+    let (bk, _) = bids.iter().next_back()?;
+    let (ak, _) = asks.iter().next()?;
+    Some((key_to_price(*bk, scale) + key_to_price(*ak, scale)) * 0.5)
+}
+
+/// Best ask - best bid, None while either side is empty.
+pub fn book_spread(
+    bids: &BTreeMap<PriceKey, f64>,
+    asks: &BTreeMap<PriceKey, f64>,
+    scale: f64,
+) -> Option<f64> {
+    let (bk, _) = bids.iter().next_back()?;
+    let (ak, _) = asks.iter().next()?;
+    Some(key_to_price(*ak, scale) - key_to_price(*bk, scale))
+}
+
+pub const SNAPSHOT_KIND: &str = "snapshot";
+
+/// Spots snapshot blocks in a row stream replayed in order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SnapshotBlocks {
+    // seq of the block the previous row belonged to, None after a delta
+    current: Option<Option<u64>>,
+}
+
+impl SnapshotBlocks {
+    /// True for the first row of a snapshot block: clear the book, then
+    /// apply the row as usual.
+    pub fn starts_block(&mut self, kind: &str, seq: Option<u64>) -> bool {
+        if kind != SNAPSHOT_KIND {
+            self.current = None;
+            return false;
+        }
+        let starts = self.current != Some(seq);
+        self.current = Some(seq);
+        starts
+    }
+}
+
+/// Replayed state as of `ts`, with candles per timeframe.
+#[derive(Clone, Debug, Default)]
+pub struct TfSnapshot {
+    pub ts: u64,
+    pub bids: BTreeMap<PriceKey, f64>,
+    pub asks: BTreeMap<PriceKey, f64>,
+    pub price_scale: f64,
+    /// tf seconds -> candles up to `ts`
+    pub candles_by_tf: HashMap<u64, Vec<Candle>>,
+    /// close and volume of the last `last_tf` candle, 0 without one
+    pub last_mid: f64,
+    pub last_vol: f64,
+    pub trades: Vec<TradeCsvEvent>,
+}
+
+impl TfSnapshot {
+    /// Candles for `tf`, empty when it wasn't one of the snapshot's tfs.
+    pub fn candles(&self, tf: u64) -> &[Candle] {
+        self.candles_by_tf.get(&tf).map_or(&[], |c| c.as_slice())
+    }
+}
+
+/// Seek `engine` to `target_ts` and resample its 1s candles to each of
+/// `tfs`; last_mid/last_vol come from the `last_tf` series.
+pub fn compute_snapshot_for(
+    mut engine: ReplayEngine,
+    target_ts: u64,
+    tfs: &[u64],
+    last_tf: u64,
+) -> TfSnapshot {
+    engine.seek(target_ts);
+
+    let candles_by_tf: HashMap<u64, Vec<Candle>> = tfs
+        .iter()
+        .map(|tf| (*tf, resample(engine.candles(), *tf)))
+        .collect();
+    let snap = engine.snapshot();
+
+    let (last_mid, last_vol) = candles_by_tf
+        .get(&last_tf)
+        .and_then(|series| series.last())
+        .map_or((0.0, 0.0), |c| (c.close, c.volume));
+
+    TfSnapshot {
+        ts: snap.ts,
+        bids: snap.bids,
+        asks: snap.asks,
+        price_scale: snap.price_scale,
+        candles_by_tf,
+        last_mid,
+        last_vol,
+        trades: snap.trades,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::ReplayData;
+
+    #[test]
+    fn snapshot_resamples_to_each_tf() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/replay");
+        let data = ReplayData::load(&dir, "ETH-USD").unwrap();
+        let t0 = 1_700_000_000;
+
+        let snap = compute_snapshot_for(ReplayEngine::new(&data), t0 + 10, &[1, 60], 60);
+        assert_eq!(snap.ts, t0 + 10);
+        assert_eq!(snap.candles(1).len(), 3);
+        assert_eq!(snap.candles(60).len(), 1);
+        // not asked for
+        assert!(snap.candles(5).is_empty());
+        assert_eq!((snap.last_mid, snap.last_vol), (100.5, 4.5));
+        assert_eq!(snap.trades.len(), 1);
+
+        let empty = compute_snapshot_for(ReplayEngine::new(&data), t0 - 1, &[1, 60], 60);
+        assert!(empty.candles(60).is_empty());
+        assert_eq!((empty.last_mid, empty.last_vol), (0.0, 0.0));
+    }
+
+    #[test]
+    fn snapshot_block_replaces_the_book() {
+        let rows = [
+            ("delta", BookSide::Bid, 100.0, 1.0, Some(1)),
+            ("delta", BookSide::Bid, 99.0, 2.0, Some(2)),
+            // 99.0 was pulled in a delta we never saw
+            (SNAPSHOT_KIND, BookSide::Bid, 100.0, 1.5, Some(5)),
+            (SNAPSHOT_KIND, BookSide::Ask, 101.0, 3.0, Some(5)),
+            ("delta", BookSide::Ask, 102.0, 1.0, Some(6)),
+            // back-to-back blocks still split on the id
+            (SNAPSHOT_KIND, BookSide::Ask, 101.5, 2.0, Some(7)),
+        ];
+        let (mut bids, mut asks) = (BTreeMap::new(), BTreeMap::new());
+        let mut blocks = SnapshotBlocks::default();
+        let mut book_after = Vec::new();
+        for (kind, side, price, size, seq) in rows {
+            if blocks.starts_block(kind, seq) {
+                bids.clear();
+                asks.clear();
+            }
+            apply_level(&mut bids, &mut asks, side, price, size, 10.0);
+            book_after.push((bids.len(), asks.len()));
+        }
+        assert_eq!(
+            book_after,
+            vec![(1, 0), (2, 0), (1, 0), (1, 1), (1, 2), (0, 1)]
+        );
+        assert_eq!(bids.get(&1000), None);
+        assert_eq!(asks.get(&1015), Some(&2.0));
+    }
+}
@@ -0,0 +1,109 @@
+// ladder_app/src/snapshot_worker.rs
+//
+// Rebuilds replay snapshots off the UI thread. Requests that pile up while a
+// reconstruction is running are coalesced: only the newest one is computed.
+// The key says what to rebuild (ticker, ts, and whatever else the binary's
+// snapshot depends on); the data is shared with the UI behind an Arc.
+
+use std::sync::mpsc;
+use std::sync::Arc;
+
+pub struct SnapshotWorker<K, D, S> {
+    req_tx: mpsc::Sender<(K, Arc<D>)>,
+    res_rx: mpsc::Receiver<(K, S)>,
+    // newest request not answered yet
+    pending: Option<K>,
+    /// Most recent completed snapshot. Views take it out while drawing and
+    /// put it back afterwards.
+    pub latest: Option<(K, S)>,
+}
+
+impl<K, D, S> SnapshotWorker<K, D, S>
+where
+    K: Clone + PartialEq + Send + 'static,
+    D: Send + Sync + 'static,
+    S: Send + 'static,
+{
+    /// Start the worker thread; `compute` builds the snapshot for a key.
+    pub fn spawn(compute: impl Fn(&D, &K) -> S + Send + 'static) -> Self {
+        let (req_tx, req_rx) = mpsc::channel::<(K, Arc<D>)>();
+        let (res_tx, res_rx) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("snapshot-worker".to_string())
+            .spawn(move || {
+                while let Ok(mut req) = req_rx.recv() {
+                    // slider drags queue many targets; skip to the last one
+                    while let Ok(newer) = req_rx.try_recv() {
+                        req = newer;
+                    }
+                    let (key, data) = req;
+                    let snap = compute(&data, &key);
+                    if res_tx.send((key, snap)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("spawn snapshot worker");
+
+        Self {
+            req_tx,
+            res_rx,
+            pending: None,
+            latest: None,
+        }
+    }
+
+    /// Ask for `key` unless it is already shown or in flight.
+    pub fn request(&mut self, key: K, data: &Arc<D>) {
+        let shown = self.latest.as_ref().map(|(k, _)| k) == Some(&key);
+        if shown || self.pending.as_ref() == Some(&key) {
+            return;
+        }
+        if self.req_tx.send((key.clone(), Arc::clone(data))).is_ok() {
+            self.pending = Some(key);
+        }
+    }
+
+    /// Pick up finished snapshots, keeping the newest.
+    pub fn poll(&mut self) {
+        while let Ok((key, snap)) = self.res_rx.try_recv() {
+            if self.pending.as_ref() == Some(&key) {
+                self.pending = None;
+            }
+            self.latest = Some((key, snap));
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn worker_answers_and_skips_repeat_requests() {
+        let data = Arc::new(10u64);
+        let mut worker = SnapshotWorker::spawn(|d: &u64, k: &u64| d + k);
+
+        worker.request(1, &data);
+        // same key while in flight: not queued again
+        worker.request(1, &data);
+        assert!(worker.is_pending());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while worker.is_pending() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+            worker.poll();
+        }
+        assert_eq!(worker.latest, Some((1, 11)));
+
+        // already shown: nothing to do
+        worker.request(1, &data);
+        assert!(!worker.is_pending());
+    }
+}
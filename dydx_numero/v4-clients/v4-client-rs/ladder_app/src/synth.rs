@@ -0,0 +1,347 @@
+// ladder_app/src/synth.rs
+//
+// Seeded random walks for the places that fake a market (gui_replay4's
+// no-data fallback, the ratatui demo feed) and for writing synthetic
+// orderbook_<TICKER>.csv / trades_<TICKER>.csv pairs to test and demo
+// against (the gen_data binary). The same seed and parameters always give the
+// same walk, so generated files are reproducible.
+//
+//   --seed <u64>          RNG seed
+//   --walk-vol <f64>      each step moves by a uniform draw in ±vol ...
+//   --walk-drift <f64>    ... plus this much
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::csv_io::{arg_value, BookRow, BookSide, DataFormat, TradeRow, TradeSide};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WalkParams {
+    pub seed: u64,
+    pub volatility: f64,
+    pub drift: f64,
+}
+
+impl Default for WalkParams {
+    /// gui_replay4's fallback walk: seed 42, ±2 per step, no drift.
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            volatility: 2.0,
+            drift: 0.0,
+        }
+    }
+}
+
+impl WalkParams {
+    /// These params with whichever of --seed / --walk-vol / --walk-drift are
+    /// given; values that don't parse are ignored.
+    pub fn with_args(self, args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        let get = |flag: &str| arg_value(args.iter().cloned(), flag);
+        Self {
+            seed: get("--seed")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(self.seed),
+            volatility: get("--walk-vol")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(self.volatility),
+            drift: get("--walk-drift")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(self.drift),
+        }
+    }
+}
+
+/// Step generator for a walk; callers apply the steps to their own price.
+#[derive(Clone, Debug)]
+pub struct RandomWalk {
+    params: WalkParams,
+    rng: StdRng,
+}
+
+impl RandomWalk {
+    pub fn new(params: WalkParams) -> Self {
+        Self {
+            params,
+            rng: StdRng::seed_from_u64(params.seed),
+        }
+    }
+
+    pub fn params(&self) -> WalkParams {
+        self.params
+    }
+
+    /// The next price move.
+    pub fn step(&mut self) -> f64 {
+        let vol = self.params.volatility.abs();
+        let noise = if vol > 0.0 {
+            self.rng.random_range(-vol..vol)
+        } else {
+            0.0
+        };
+        self.params.drift + noise
+    }
+
+    /// A uniform draw in `lo..hi` from the walk's RNG (level sizes etc.).
+    pub fn uniform(&mut self, lo: f64, hi: f64) -> f64 {
+        if hi > lo {
+            self.rng.random_range(lo..hi)
+        } else {
+            lo
+        }
+    }
+}
+
+// chance per event that a resting level other than the touched ones is
+// resized, so the book churns without every level moving every event
+const LEVEL_CHURN: f64 = 0.25;
+
+const TRADE_SOURCE: &str = "market";
+
+/// A synthetic market recording: `levels` price levels a side, `tick` apart,
+/// around a mid that takes one `walk` step per event, at `events_per_sec`
+/// events a second for `duration` seconds. Each event may also print a
+/// trade against the touch with probability `trade_prob`.
+#[derive(Clone, Debug)]
+pub struct SynthMarket {
+    pub ticker: String,
+    pub start_ts: u64,
+    pub duration: u64,
+    pub events_per_sec: u32,
+    pub start_price: f64,
+    pub tick: f64,
+    pub levels: usize,
+    pub trade_prob: f64,
+    pub walk: WalkParams,
+}
+
+impl Default for SynthMarket {
+    fn default() -> Self {
+        Self {
+            ticker: "ETH-USD".to_string(),
+            start_ts: 1_700_000_000,
+            duration: 600,
+            events_per_sec: 1,
+            start_price: 3000.0,
+            tick: 0.5,
+            levels: 10,
+            trade_prob: 0.3,
+            walk: WalkParams::default(),
+        }
+    }
+}
+
+/// Encoded rows (no header) for the two files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SynthData {
+    pub book: Vec<String>,
+    pub trades: Vec<String>,
+}
+
+// a resting level: side, price in ticks, size
+type Level = (BookSide, i64, f64);
+
+impl SynthMarket {
+    fn tick(&self) -> f64 {
+        if self.tick > 0.0 {
+            self.tick
+        } else {
+            1.0
+        }
+    }
+
+    fn price(&self, ticks: i64) -> f64 {
+        // round off the float noise of ticks * 0.1 and the like
+        (ticks as f64 * self.tick() * 1e8).round() / 1e8
+    }
+
+    /// Rows in the collectors' format: a book_init block, then per event the
+    /// delta rows that move the book to the new mid (removed levels as size
+    /// 0, plus some resized ones), and any trade with the delta for the level
+    /// it hit. Bids stay strictly below asks throughout. seq counts events.
+    pub fn generate(&self, format: DataFormat) -> SynthData {
+        let mut walk = RandomWalk::new(self.walk);
+        let tick = self.tick();
+        let rate = self.events_per_sec.max(1) as u64;
+        let floor = tick * (self.levels as f64 + 1.0);
+        let mut mid = self.start_price.max(floor);
+        let mut book: Vec<Level> = Vec::new();
+        let mut out = SynthData::default();
+
+        for event in 0..=self.duration * rate {
+            let ts = self.start_ts + event / rate;
+            let seq = Some(event + 1);
+            if event > 0 {
+                mid = (mid + walk.step()).max(floor);
+            }
+            let kind = if event == 0 { "book_init" } else { "delta" };
+            let mut row = |side: BookSide, ticks: i64, size: f64| {
+                let r = BookRow {
+                    ts,
+                    ticker: &self.ticker,
+                    kind,
+                    side,
+                    price: self.price(ticks),
+                    size,
+                    seq,
+                };
+                out.book.push(r.encode(format));
+            };
+
+            // best bid on the tick grid just under mid, best ask one tick up;
+            // levels that fall off go first so no prefix of the rows crosses
+            let best_bid = (mid / tick).floor() as i64;
+            let depth = self.levels as i64;
+            for old in &book {
+                let kept = match old.0 {
+                    BookSide::Bid => old.1 > best_bid - depth && old.1 <= best_bid,
+                    BookSide::Ask => old.1 > best_bid && old.1 <= best_bid + depth,
+                };
+                if !kept {
+                    row(old.0, old.1, 0.0);
+                }
+            }
+            let mut next: Vec<Level> = Vec::with_capacity(self.levels * 2);
+            for i in 0..depth {
+                for (side, ticks) in [
+                    (BookSide::Bid, best_bid - i),
+                    (BookSide::Ask, best_bid + 1 + i),
+                ] {
+                    let old = book.iter().find(|l| l.0 == side && l.1 == ticks);
+                    let size = match old {
+                        Some(l) if event > 0 && walk.uniform(0.0, 1.0) >= LEVEL_CHURN => l.2,
+                        _ => {
+                            let size = round_size(walk.uniform(0.1, 2.0));
+                            row(side, ticks, size);
+                            size
+                        }
+                    };
+                    next.push((side, ticks, size));
+                }
+            }
+
+            if event > 0 && self.levels > 0 && walk.uniform(0.0, 1.0) < self.trade_prob {
+                // a taker buy lifts the best ask, a sell hits the best bid,
+                // taking part of the level so it stays in the book
+                let taker = TradeSide::from_buy(walk.uniform(0.0, 1.0) < 0.5);
+                let maker = taker.hits();
+                let level = next.iter_mut().find(|l| l.0 == maker).expect("touch");
+                let size = round_size(level.2 * walk.uniform(0.05, 0.9));
+                level.2 = round_size(level.2 - size);
+                let trade = TradeRow {
+                    ts,
+                    ticker: &self.ticker,
+                    source: TRADE_SOURCE,
+                    side: taker,
+                    size,
+                    price: Some(self.price(level.1)),
+                };
+                out.trades.push(trade.encode(format));
+                row(maker, level.1, level.2);
+            }
+            book = next;
+        }
+        out
+    }
+
+    /// Book rows only, as CSV.
+    pub fn rows(&self) -> Vec<String> {
+        self.generate(DataFormat::Csv).book
+    }
+
+    /// Write orderbook_<TICKER> and trades_<TICKER> in `format` under `dir`,
+    /// replacing them; returns the (book, trade) row counts.
+    pub fn write(&self, dir: &Path, format: DataFormat) -> io::Result<(usize, usize)> {
+        let data = self.generate(format);
+        let write = |stem: &str, rows: &[String]| {
+            let mut text = rows.join("\n");
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            fs::write(
+                dir.join(format!("{stem}_{}.{}", self.ticker, format.ext())),
+                text,
+            )
+        };
+        write("orderbook", &data.book)?;
+        write("trades", &data.trades)?;
+        Ok((data.book.len(), data.trades.len()))
+    }
+}
+
+// sizes to 4 dp, never rounded away to nothing
+fn round_size(size: f64) -> f64 {
+    ((size * 1e4).round() / 1e4).max(0.0001)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_io::{load_book_csv, load_trades_csv};
+    use crate::replay::{ReplayData, ReplayEngine};
+
+    #[test]
+    fn same_seed_writes_the_same_market() {
+        let synth = SynthMarket {
+            duration: 50,
+            ..SynthMarket::default()
+        };
+        assert_eq!(
+            synth.generate(DataFormat::Csv),
+            synth.generate(DataFormat::Csv)
+        );
+
+        let other = SynthMarket {
+            walk: WalkParams {
+                seed: 7,
+                ..synth.walk
+            },
+            ..synth.clone()
+        };
+        assert_ne!(synth.rows(), other.rows());
+
+        let args = ["--seed", "7", "--walk-drift=0.5"].map(String::from);
+        let params = WalkParams::default().with_args(args);
+        assert_eq!(
+            (params.seed, params.volatility, params.drift),
+            (7, 2.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn synthetic_files_replay_to_an_uncrossed_book() {
+        let dir = std::env::temp_dir().join(format!("ladder_synth_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let synth = SynthMarket {
+            duration: 30,
+            events_per_sec: 4,
+            tick: 0.1,
+            trade_prob: 0.5,
+            ..SynthMarket::default()
+        };
+        let (rows, trades) = synth.write(&dir, DataFormat::Csv).unwrap();
+        assert!(trades > 0);
+
+        let (events, stats) = load_book_csv(&dir.join("orderbook_ETH-USD.csv"), "ETH-USD");
+        assert_eq!((events.len(), stats.skipped), (rows, 0));
+        let (prints, stats) = load_trades_csv(&dir.join("trades_ETH-USD.csv"), "ETH-USD");
+        assert_eq!((prints.len(), stats.skipped), (trades, 0));
+
+        let data = ReplayData::load(&dir, "ETH-USD").unwrap();
+        let mut engine = ReplayEngine::new(&data);
+        while engine.step_to_next_event().is_some() {
+            let snap = engine.snapshot();
+            assert_eq!((snap.bids.len(), snap.asks.len()), (10, 10));
+            let (bid, ask) = (snap.best_bid().unwrap().0, snap.best_ask().unwrap().0);
+            assert!((ask - bid - synth.tick).abs() < 1e-9, "{bid} / {ask}");
+        }
+        // one 1s candle per second
+        assert_eq!(engine.snapshot().candles.len() as u64, synth.duration + 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+// ladder_app/src/time_fmt.rs
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, TimeZone};
+
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeDisplayMode {
+    Unix,
+    Local,
+}
+
+impl TimeDisplayMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeDisplayMode::Unix => "Unix",
+            TimeDisplayMode::Local => "Local",
+        }
+    }
+}
+
+pub fn format_ts(mode: TimeDisplayMode, ts: u64) -> String {
+    match mode {
+        TimeDisplayMode::Unix => format!("{ts}"),
+        TimeDisplayMode::Local => {
+            let dt = Local
+                .timestamp_opt(ts as i64, 0)
+                .single()
+                .unwrap_or_else(|| Local.timestamp_opt(0, 0).single().unwrap());
+            dt.format("%Y-%m-%d %H:%M:%S").to_string()
+        }
+    }
+}
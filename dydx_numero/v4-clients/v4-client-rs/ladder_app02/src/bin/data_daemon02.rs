@@ -2,7 +2,7 @@
 //
 // Synthetic market data daemon for ladder_app02.
 //
-// - Writes CSV files into ./data:
+// - Writes CSV files into ./data (or --data-dir <path>):
 //
 //     data/orderbook_ETH-USD.csv
 //     data/orderbook_BTC-USD.csv
@@ -21,6 +21,10 @@
 //     1710000001,ETH-USD,sim,buy,0.01234567
 //
 // This does NOT talk to dYdX yet. It's just a random-walk simulator.
+//
+// - `--seed <u64>` makes the walk and the trades repeatable (entropy when
+//   absent), `--walk-vol <x>` scales every ticker's volatility and
+//   `--walk-drift <f>` adds a per-step move of f * mid.
 
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
@@ -45,10 +49,7 @@ struct TickerState {
 }
 
 fn open_append(path: &Path) -> std::io::Result<std::fs::File> {
-    OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
+    OpenOptions::new().create(true).append(true).open(path)
 }
 
 fn write_orderbook_snapshot(
@@ -66,18 +67,14 @@ fn write_orderbook_snapshot(
     for i in 0..levels {
         let price = mid - (i as f64) * tick;
         let size: f64 = rng.gen_range(0.01..0.5);
-        let line = format!(
-            "{ts},{ticker},orderbook,bid,{price:.2},{size:.6}\n"
-        );
+        let line = format!("{ts},{ticker},orderbook,bid,{price:.2},{size:.6}\n");
         f.write_all(line.as_bytes())?;
     }
 
     for i in 0..levels {
         let price = mid + (i as f64) * tick;
         let size: f64 = rng.gen_range(0.01..0.5);
-        let line = format!(
-            "{ts},{ticker},orderbook,ask,{price:.2},{size:.6}\n"
-        );
+        let line = format!("{ts},{ticker},orderbook,ask,{price:.2},{size:.6}\n");
         f.write_all(line.as_bytes())?;
     }
 
@@ -96,7 +93,11 @@ fn maybe_write_trade(
         return Ok(());
     }
 
-    let side = if rng.gen::<f64>() < 0.5 { "buy" } else { "sell" };
+    let side = if rng.gen::<f64>() < 0.5 {
+        "buy"
+    } else {
+        "sell"
+    };
     let size: f64 = rng.gen_range(0.001..0.05);
     let size_str = format!("{:.8}", size);
     let source = "sim";
@@ -104,16 +105,43 @@ fn maybe_write_trade(
     let _price_jitter = rng.gen_range(-0.0005..0.0005) * mid;
 
     let mut f = open_append(tr_path)?;
-    let line = format!(
-        "{ts},{ticker},{source},{side},{size_str}\n"
-    );
+    let line = format!("{ts},{ticker},{source},{side},{size_str}\n");
     f.write_all(line.as_bytes())?;
 
     Ok(())
 }
 
+// value of `<flag> <v>` or `<flag>=<v>`
+fn arg_value(args: impl IntoIterator<Item = String>, flag: &str) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == flag {
+            return args.next();
+        }
+        if let Some(v) = a.strip_prefix(flag).and_then(|r| r.strip_prefix('=')) {
+            return Some(v.to_string());
+        }
+    }
+    None
+}
+
+// `--data-dir <path>` or `--data-dir=<path>`; `data` when absent
+fn data_dir_from_args(args: impl IntoIterator<Item = String>) -> PathBuf {
+    arg_value(args, "--data-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("data"))
+}
+
+// numeric flag, `default` when absent or unparsable
+fn arg_f64(args: &[String], flag: &str, default: f64) -> f64 {
+    arg_value(args.iter().cloned(), flag)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
 fn main() {
-    let base_dir = PathBuf::from("data");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let base_dir = data_dir_from_args(args.iter().cloned());
     println!(
         "[data_daemon02] Starting synthetic data daemon. Writing to: {}",
         base_dir.display()
@@ -124,7 +152,12 @@ fn main() {
         return;
     }
 
-    let mut rng = StdRng::from_entropy();
+    let mut rng = match arg_value(args.iter().cloned(), "--seed").and_then(|v| v.parse().ok()) {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let vol_mult = arg_f64(&args, "--walk-vol", 1.0);
+    let drift = arg_f64(&args, "--walk-drift", 0.0);
 
     let mut tickers = vec![
         TickerState {
@@ -148,7 +181,7 @@ fn main() {
         let ts = now_unix();
 
         for tk in &mut tickers {
-            let step = rng.gen_range(-1.0..1.0) * tk.mid * tk.vol_scale;
+            let step = rng.gen_range(-1.0..1.0) * tk.mid * tk.vol_scale * vol_mult + tk.mid * drift;
             tk.mid = (tk.mid + step).max(1.0);
 
             let ob_path = base_dir.join(format!("orderbook_{}.csv", tk.name));
@@ -162,10 +195,7 @@ fn main() {
             }
 
             if let Err(e) = maybe_write_trade(&tr_path, ts, &tk.name, tk.mid, &mut rng) {
-                eprintln!(
-                    "[data_daemon02] error writing trade for {}: {e}",
-                    tk.name
-                );
+                eprintln!("[data_daemon02] error writing trade for {}: {e}", tk.name);
             }
         }
 
@@ -354,7 +354,11 @@ fn compute_bubble_metrics(snap: &Snapshot) -> BubbleMetrics {
         ask_liq += *s;
     }
 
-    let imbalance = if ask_liq > 0.0 { bid_liq / ask_liq } else { 0.0 };
+    let imbalance = if ask_liq > 0.0 {
+        bid_liq / ask_liq
+    } else {
+        0.0
+    };
 
     BubbleMetrics {
         best_bid,
@@ -502,7 +506,9 @@ impl AppCore {
     }
 
     fn ticker_range(&self, ticker: &str) -> Option<(u64, u64)> {
-        self.ticker_data.get(ticker).map(|td| (td.min_ts, td.max_ts))
+        self.ticker_data
+            .get(ticker)
+            .map(|td| (td.min_ts, td.max_ts))
     }
 
     fn reload_current_ticker(&mut self) {
@@ -593,11 +599,10 @@ impl AppCore {
 
         self.scope.set_value("bot_signal", self.bot_signal.clone());
         self.scope.set_value("bot_size", self.bot_size);
-        self.scope.set_value("bot_comment", self.bot_comment.clone());
+        self.scope
+            .set_value("bot_comment", self.bot_comment.clone());
 
-        let res = self
-            .engine
-            .eval_with_scope::<()>(&mut self.scope, &script);
+        let res = self.engine.eval_with_scope::<()>(&mut self.scope, &script);
 
         match res {
             Ok(()) => {
@@ -689,7 +694,12 @@ impl AppCore {
 
 // ---- UI wiring -------------------------------------------------------------
 
-fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetrics, dom_depth_levels: usize) {
+fn apply_snapshot_to_ui(
+    app: &AppWindow,
+    snap: &Snapshot,
+    metrics: &BubbleMetrics,
+    dom_depth_levels: usize,
+) {
     app.set_mid_price(metrics.mid as f32);
     app.set_best_bid(metrics.best_bid as f32);
     app.set_best_ask(metrics.best_ask as f32);
@@ -698,10 +708,19 @@ fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetric
 
     let depth = dom_depth_levels.max(1).min(50);
 
-    let mut bid_levels_raw: Vec<(PriceKey, f64)> =
-        snap.bids.iter().rev().take(depth).map(|(k, s)| (*k, *s)).collect();
-    let mut ask_levels_raw: Vec<(PriceKey, f64)> =
-        snap.asks.iter().take(depth).map(|(k, s)| (*k, *s)).collect();
+    let mut bid_levels_raw: Vec<(PriceKey, f64)> = snap
+        .bids
+        .iter()
+        .rev()
+        .take(depth)
+        .map(|(k, s)| (*k, *s))
+        .collect();
+    let mut ask_levels_raw: Vec<(PriceKey, f64)> = snap
+        .asks
+        .iter()
+        .take(depth)
+        .map(|(k, s)| (*k, *s))
+        .collect();
 
     let max_bid = bid_levels_raw
         .iter()
@@ -714,7 +733,11 @@ fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetric
     let bids: Vec<BookLevel> = bid_levels_raw
         .drain(..)
         .map(|(k, s)| {
-            let ratio = if max_bid > 0.0 { (s.abs() / max_bid) as f32 } else { 0.0 };
+            let ratio = if max_bid > 0.0 {
+                (s.abs() / max_bid) as f32
+            } else {
+                0.0
+            };
             let is_best = first_bid;
             if first_bid {
                 first_bid = false;
@@ -733,7 +756,11 @@ fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetric
     let asks: Vec<BookLevel> = ask_levels_raw
         .drain(..)
         .map(|(k, s)| {
-            let ratio = if max_ask > 0.0 { (s.abs() / max_ask) as f32 } else { 0.0 };
+            let ratio = if max_ask > 0.0 {
+                (s.abs() / max_ask) as f32
+            } else {
+                0.0
+            };
             let is_best = first_ask;
             if first_ask {
                 first_ask = false;
@@ -826,8 +853,16 @@ fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetric
 
         let n = slice.len();
         for (i, c) in slice.iter().enumerate() {
-            let x_center = if n <= 1 { 0.5f32 } else { (i as f32 + 0.5) / n as f32 };
-            let w = if n == 0 { 1.0f32 } else { (1.0f32 / n as f32) * 0.7 };
+            let x_center = if n <= 1 {
+                0.5f32
+            } else {
+                (i as f32 + 0.5) / n as f32
+            };
+            let w = if n == 0 {
+                1.0f32
+            } else {
+                (1.0f32 / n as f32) * 0.7
+            };
 
             let open_n = norm_price(c.open);
             let high_n = norm_price(c.high);
@@ -835,7 +870,11 @@ fn apply_snapshot_to_ui(app: &AppWindow, snap: &Snapshot, metrics: &BubbleMetric
             let close_n = norm_price(c.close);
             let is_up = c.close >= c.open;
 
-            let volume_n = if max_vol > 0.0 { (c.volume / max_vol) as f32 } else { 0.0 };
+            let volume_n = if max_vol > 0.0 {
+                (c.volume / max_vol) as f32
+            } else {
+                0.0
+            };
 
             candle_points_vec.push(CandlePoint {
                 x: x_center,
@@ -896,9 +935,28 @@ fn debug_print_candle_meta(label: &str, candles: &[Candle]) {
     );
 }
 
+// `--data-dir <path>` or `--data-dir=<path>`; `data` when absent
+fn data_dir_from_args(args: impl IntoIterator<Item = String>) -> PathBuf {
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        if a == "--data-dir" {
+            if let Some(v) = args.next() {
+                return PathBuf::from(v);
+            }
+        } else if let Some(v) = a.strip_prefix("--data-dir=") {
+            return PathBuf::from(v);
+        }
+    }
+    PathBuf::from("data")
+}
+
 fn main() {
-    let base_dir = PathBuf::from("data");
-    let tickers = vec!["ETH-USD".to_string(), "BTC-USD".to_string(), "SOL-USD".to_string()];
+    let base_dir = data_dir_from_args(std::env::args().skip(1));
+    let tickers = vec![
+        "ETH-USD".to_string(),
+        "BTC-USD".to_string(),
+        "SOL-USD".to_string(),
+    ];
 
     let core = AppCore::new(base_dir.clone(), tickers.clone());
     let core_rc = Rc::new(RefCell::new(core));
@@ -1116,7 +1174,10 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
                 core.push_receipt(&app, receipt);
 
                 if let Some((_, metrics)) = core.snapshot_for_ui() {
-                    println!("[ORDER] {} (mid {:.2}, spread {:.5})", msg, metrics.mid, metrics.spread);
+                    println!(
+                        "[ORDER] {} (mid {:.2}, spread {:.5})",
+                        msg, metrics.mid, metrics.spread
+                    );
                 } else {
                     println!("[ORDER] {}", msg);
                 }
@@ -1224,8 +1285,13 @@ if imbalance > 2.5 && spread < mid * 0.0005 {
             if let Some(app) = app_weak_timer.upgrade() {
                 let mut core = core_rc_timer.borrow_mut();
 
+                // UI handlers push their own changes; the tick only re-pushes
+                // the models when the cached snapshot was recomputed
+                let fresh = core.snapshot_dirty;
                 if let Some((snap, metrics)) = core.snapshot_for_ui() {
-                    apply_snapshot_to_ui(&app, &snap, &metrics, core.dom_depth_levels());
+                    if fresh {
+                        apply_snapshot_to_ui(&app, &snap, &metrics, core.dom_depth_levels());
+                    }
 
                     if app.get_bot_auto_trade() {
                         core.run_bot_script(&app, &metrics);